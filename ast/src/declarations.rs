@@ -17,6 +17,14 @@ impl Declaration {
     }
 }
 
+/// `_Static_assert(constant-expression, string-literal);` (C11 6.7.10)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticAssert {
+    pub expression: ConstantExpression,
+    /// The contents of the string literal, as it appears in the source
+    pub message: String
+}
+
 pub type InitDeclaratorList = Vec<InitDeclarator>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,11 +39,56 @@ pub struct Declarator {
     pub direct_declarator: DirectDeclarator
 }
 
+impl Declarator {
+    /// The identifier declared by this declarator
+    pub fn identifier(&self) -> Option<Symbol> {
+        self.direct_declarator.iter().filter_map(|part| match part {
+            DirectDeclaratorPart::Identifier(identifier) => Some(*identifier),
+            DirectDeclaratorPart::Parens(declarator) => declarator.identifier(),
+            _ => None
+        }).next()
+    }
+
+    /// The identifiers of the parameters of the function that this declarator declares, which are in scope
+    /// in the body of a function definition
+    pub fn parameter_names(&self) -> Vec<Symbol> {
+        match self.parameters() {
+            Some(DirectDeclaratorPart::ParameterTypeList(list)) => list.parameter_list.iter().filter_map(|parameter| match parameter.declarator {
+                ParameterDeclarator::Declarator(ref declarator) => declarator.identifier(),
+                ParameterDeclarator::AbstractDeclarator(_) => None
+            }).collect(),
+            Some(DirectDeclaratorPart::IdentifierList(list)) => list.clone(),
+            _ => Vec::new()
+        }
+    }
+
+    /// The parameter type list of the function that this declarator declares, if it has one
+    pub fn parameter_type_list(&self) -> Option<&ParameterTypeList> {
+        match self.parameters() {
+            Some(DirectDeclaratorPart::ParameterTypeList(list)) => Some(list),
+            _ => None
+        }
+    }
+
+    /// The part of the declarator that holds the parameters of the function it declares
+    fn parameters(&self) -> Option<&DirectDeclaratorPart> {
+        let mut parts = self.direct_declarator.iter();
+        match parts.next() {
+            // the parameters follow the identifier, which may be in parentheses
+            Some(DirectDeclaratorPart::Identifier(_)) => (),
+            Some(DirectDeclaratorPart::Parens(declarator)) if declarator.pointer.is_none() && declarator.direct_declarator.len() == 1 => (),
+            Some(DirectDeclaratorPart::Parens(declarator)) => return declarator.parameters(),
+            _ => return None
+        }
+        parts.next()
+    }
+}
+
 pub type DirectDeclarator = Vec<DirectDeclaratorPart>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DirectDeclaratorPart {
-    Identifier(String),
+    Identifier(Symbol),
     Parens(Box<Declarator>),
     Array(Option<AssignmentExpression>), /* discards static keyword and type qualifier list preceeding the optional assignment expression */
     VLA, /* [*] */
@@ -113,7 +166,7 @@ pub enum TypeSpecifier {
     //Complex, // not implemented
     StructOrUnionSpecifier(StructOrUnionSpecifier),
     EnumSpecifier(EnumSpecifier),
-    Typedef(String)
+    Typedef(Symbol)
 }
 
 impl Display for TypeSpecifier {
@@ -146,8 +199,8 @@ impl Display for TypeSpecifier {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StructOrUnionSpecifier {
-    Partial { kind: StructOrUnion, identifier: String },
-    Complete { kind: StructOrUnion, identifier: Option<String>, declaration_list: StructDeclarationList }
+    Partial { kind: StructOrUnion, identifier: Symbol },
+    Complete { kind: StructOrUnion, identifier: Option<Symbol>, declaration_list: StructDeclarationList }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -168,14 +221,14 @@ impl Display for StructOrUnion {
 pub type StructDeclarationList = Vec<StructDeclaration>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct StructDeclaration {
-    pub specification_qualifier_list: SpecifierQualifierList,
-    pub struct_declaration_list: StructDeclaratorList
+pub enum StructDeclaration {
+    Members { specification_qualifier_list: SpecifierQualifierList, struct_declaration_list: StructDeclaratorList },
+    StaticAssert(Node<StaticAssert>)
 }
 
 impl StructDeclaration {
     pub fn new(specification_qualifier_list: SpecifierQualifierList, struct_declaration_list: StructDeclaratorList) -> StructDeclaration {
-        StructDeclaration {
+        StructDeclaration::Members {
             specification_qualifier_list,
             struct_declaration_list
         }
@@ -192,8 +245,8 @@ pub enum SpecifierQualifier {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TypeName {
-    specifier_qualifier_list: SpecifierQualifierList,
-    abstract_declarator: Option<AbstractDeclarator>
+    pub specifier_qualifier_list: SpecifierQualifierList,
+    pub abstract_declarator: Option<AbstractDeclarator>
 }
 
 impl TypeName {
@@ -215,15 +268,15 @@ pub enum StructDeclarator {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EnumSpecifier {
-    Complete { identifier: Option<String>, enumerator_list: EnumeratorList },
-    Partial { identifier: String }
+    Complete { identifier: Option<Symbol>, enumerator_list: EnumeratorList },
+    Partial { identifier: Symbol }
 }
 
 pub type EnumeratorList = Vec<Enumerator>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Enumerator {
-    pub identifier: String,
+    pub identifier: Symbol,
     pub value: Option<ConstantExpression>
 }
 
@@ -266,7 +319,7 @@ pub enum AbstractDeclarator {
 
 pub type DirectAbstractDeclarator = Vec<DirectAbstractDeclaratorPart>;
 
-pub type IdentifierList = Vec<String>;
+pub type IdentifierList = Vec<Symbol>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DirectAbstractDeclaratorPart {
@@ -295,5 +348,5 @@ pub type DesignatorList = Vec<Designator>;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Designator {
     Index(ConstantExpression),
-    Field(String)
+    Field(Symbol)
 }
\ No newline at end of file
@@ -1,51 +1,301 @@
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
-use crate::{Node, TypeName, InitializerList};
+use crate::{Node, TypeName, InitializerList, Symbol, TargetInfo, DataModel};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Constant {
-    Integer(Integer)
+    Integer(Integer),
+    /// A character constant. Plain character constants have type `int`, and wide character constants
+    /// (`L'x'`) have type `wchar_t`; the type is the type of `value`.
+    Character{ wide: bool, value: Integer },
+    Float(Float)
 }
 
+/// The value of an integer constant, and its type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Integer {
-    U8(u8),
-    I8(i8),
-    U16(u16),
-    I16(i16),
-    U32(u32),
-    I32(i32),
-    U64(u64),
-    I64(i64),
-    U128(u128),
-    I128(i128)
+pub struct Integer {
+    pub type_: IntegerType,
+    /// The value, which can be represented by `type_`
+    pub value: i128
+}
+
+impl Integer {
+    pub fn new(type_: IntegerType, value: i128) -> Integer {
+        Integer { type_, value }
+    }
 }
 
 impl Display for Integer {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        use Integer::*;
+        write!(f, "{}", self.value)
+    }
+}
+
+/// The integer types. Types that are equally wide are still distinct (`char` and `signed char`, or `long` and
+/// `long long` on LP64), which matters for type compatibility and the usual arithmetic conversions.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum IntegerType {
+    Bool,
+    /// Plain `char`, which is signed
+    Char,
+    SignedChar,
+    UnsignedChar,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    /// `long` is as wide as `int` or `long long` depending on the data model, `bits` is its width on the target
+    Long { bits: usize },
+    UnsignedLong { bits: usize },
+    LongLong,
+    UnsignedLongLong
+}
+
+impl IntegerType {
+    /// `long` on the target
+    pub fn long(target: TargetInfo) -> IntegerType {
+        IntegerType::Long { bits: target.long_bits() }
+    }
+
+    /// `unsigned long` on the target
+    pub fn unsigned_long(target: TargetInfo) -> IntegerType {
+        IntegerType::UnsignedLong { bits: target.long_bits() }
+    }
+
+    /// The type of `size_t`, the unsigned type that is as wide as a pointer
+    pub fn size_type(target: TargetInfo) -> IntegerType {
+        match target.data_model {
+            DataModel::ILP32 => IntegerType::UnsignedInt,
+            DataModel::LP64 => IntegerType::unsigned_long(target),
+            DataModel::LLP64 => IntegerType::UnsignedLongLong
+        }
+    }
+
+    /// The type of `wchar_t`, which is what gcc uses on Linux for 32 bit and 64 bit targets, and what
+    /// msvc uses on 64 bit Windows
+    pub fn wide_char(target: TargetInfo) -> IntegerType {
+        match target.data_model {
+            DataModel::ILP32 => IntegerType::long(target),
+            DataModel::LP64 => IntegerType::Int,
+            DataModel::LLP64 => IntegerType::UnsignedShort
+        }
+    }
+
+    /// Converts `value` to this type, keeping only as many of its low bits as the type has
+    pub fn wrap(&self, value: i128) -> i128 {
+        let bits = self.bits() as u32;
+        let value = value & ((1i128 << bits) - 1);
+        if self.is_signed() && value >> (bits - 1) != 0 { value - (1i128 << bits) } else { value }
+    }
+
+    pub fn bits(&self) -> usize {
+        use IntegerType::*;
         match self {
-            U8(v) => write!(f, "{}", v),
-            I8(v) => write!(f, "{}", v),
-            U16(v) => write!(f, "{}", v),
-            I16(v) => write!(f, "{}", v),
-            U32(v) => write!(f, "{}", v),
-            I32(v) => write!(f, "{}", v),
-            U64(v) => write!(f, "{}", v),
-            I64(v) => write!(f, "{}", v),
-            U128(v) => write!(f, "{}", v),
-            I128(v) => write!(f, "{}", v),
+            Bool => 1,
+            Char | SignedChar | UnsignedChar => 8,
+            Short | UnsignedShort => 16,
+            Int | UnsignedInt => 32,
+            Long { bits } | UnsignedLong { bits } => *bits,
+            LongLong | UnsignedLongLong => 64
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        use IntegerType::*;
+        match self {
+            Char | SignedChar | Short | Int | Long { .. } | LongLong => true,
+            Bool | UnsignedChar | UnsignedShort | UnsignedInt | UnsignedLong { .. } | UnsignedLongLong => false
+        }
+    }
+
+    /// The integer conversion rank (C99 6.3.1.1p1), which orders the types by their kind rather than their width
+    pub fn rank(&self) -> u8 {
+        use IntegerType::*;
+        match self {
+            Bool => 0,
+            Char | SignedChar | UnsignedChar => 1,
+            Short | UnsignedShort => 2,
+            Int | UnsignedInt => 3,
+            Long { .. } | UnsignedLong { .. } => 4,
+            LongLong | UnsignedLongLong => 5
+        }
+    }
+
+    /// The corresponding unsigned type
+    pub fn to_unsigned(&self) -> IntegerType {
+        use IntegerType::*;
+        match self {
+            Char | SignedChar => UnsignedChar,
+            Short => UnsignedShort,
+            Int => UnsignedInt,
+            Long { bits } => UnsignedLong { bits: *bits },
+            LongLong => UnsignedLongLong,
+            unsigned => *unsigned
+        }
+    }
+
+    /// The integer promotions (C99 6.3.1.1p2), every type with a lower rank than int is promoted to int
+    pub fn promote(&self) -> IntegerType {
+        if self.rank() < IntegerType::Int.rank() { IntegerType::Int } else { *self }
+    }
+
+    /// The type that the operands of a binary operator are converted to by the
+    /// usual arithmetic conversions (C99 6.3.1.8)
+    pub fn common_type(&self, other: &IntegerType) -> IntegerType {
+        let (a, b) = (self.promote(), other.promote());
+        if a == b {
+            a
+        }
+        else if a.is_signed() == b.is_signed() {
+            if a.rank() > b.rank() { a } else { b }
+        }
+        else {
+            let (signed, unsigned) = if a.is_signed() { (a, b) } else { (b, a) };
+            if unsigned.rank() >= signed.rank() {
+                unsigned
+            }
+            else if signed.bits() > unsigned.bits() {
+                // the signed type can represent every value of the unsigned type
+                signed
+            }
+            else {
+                signed.to_unsigned()
+            }
         }
     }
 }
 
+impl Display for IntegerType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use IntegerType::*;
+        let s = match self {
+            Bool => "_Bool",
+            Char => "char",
+            SignedChar => "signed char",
+            UnsignedChar => "unsigned char",
+            Short => "short int",
+            UnsignedShort => "unsigned short int",
+            Int => "int",
+            UnsignedInt => "unsigned int",
+            Long { .. } => "long int",
+            UnsignedLong { .. } => "unsigned long int",
+            LongLong => "long long int",
+            UnsignedLongLong => "unsigned long long int"
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum FloatType {
+    Float,
+    Double,
+    LongDouble // x87 80 bit extended precision
+}
+
+impl FloatType {
+    /// The number of bits in the significand (including the explicit or implicit leading bit).
+    pub fn precision(&self) -> u32 {
+        match self {
+            FloatType::Float => 24,
+            FloatType::Double => 53,
+            FloatType::LongDouble => 64
+        }
+    }
+
+    /// The exponent of the smallest normalized value, as a power of two.
+    pub fn min_exponent(&self) -> i32 {
+        match self {
+            FloatType::Float => -126,
+            FloatType::Double => -1022,
+            FloatType::LongDouble => -16382
+        }
+    }
+
+    /// Every finite value of this type is less than 2 raised to this power.
+    pub fn max_exponent(&self) -> i32 {
+        match self {
+            FloatType::Float => 128,
+            FloatType::Double => 1024,
+            FloatType::LongDouble => 16384
+        }
+    }
+}
+
+impl Display for FloatType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use FloatType::*;
+        match self {
+            Float => write!(f, "float"),
+            Double => write!(f, "double"),
+            LongDouble => write!(f, "long double")
+        }
+    }
+}
+
+/// The value of a floating constant, already rounded to the precision of its type.
+///
+/// The value is `mantissa * 2^exponent`. The mantissa is always odd (or zero, in which case
+/// the exponent is zero too), so every value has exactly one representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Float {
+    pub type_: FloatType,
+    pub mantissa: u64,
+    pub exponent: i32
+}
+
+impl Float {
+    pub fn new(type_: FloatType, mantissa: u64, exponent: i32) -> Float {
+        if mantissa == 0 {
+            return Float { type_, mantissa: 0, exponent: 0 };
+        }
+        let zeros = mantissa.trailing_zeros();
+        Float {
+            type_,
+            mantissa: mantissa >> zeros,
+            exponent: exponent + zeros as i32
+        }
+    }
+
+    /// Converts the value to a double, rounding it if it has more precision than a double can hold
+    pub fn to_f64(&self) -> f64 {
+        // scale in two steps so that neither factor overflows or underflows on its own
+        let half = self.exponent / 2;
+        self.mantissa as f64 * 2f64.powi(half) * 2f64.powi(self.exponent - half)
+    }
+}
+
+impl Display for Float {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let suffix = match self.type_ {
+            FloatType::Float => "f",
+            FloatType::Double => "",
+            FloatType::LongDouble => "L"
+        };
+        write!(f, "{:?}{}", self.to_f64(), suffix)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PrimaryExpression {
-    Identifier(String),
+    Identifier(Symbol),
     Constant(Constant),
-    StringLiteral{ wide: bool, contents: String },
-    Parens(Box<Expression>)
+    /// One or more adjacent string literals, concatenated in translation phase 6. `contents` is the spelling
+    /// of each literal (without the quotes), and `value` holds the code units of the array that they
+    /// initialize, without the terminating null character: bytes for narrow literals, and `wchar_t`
+    /// values if any of the literals is wide.
+    StringLiteral{ wide: bool, contents: Vec<String>, value: Vec<u32> },
+    Parens(Box<Expression>),
+    /// `_Generic(controlling, type-name: expression, ..., default: expression)` (C11 6.5.1.1)
+    GenericSelection{ controlling: Box<AssignmentExpression>, associations: Vec<GenericAssociation> }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenericAssociation {
+    /// `None` for the `default` association
+    pub type_name: Option<TypeName>,
+    pub expression: AssignmentExpression
 }
 
 pub type PostfixExpression = Vec<PostfixExpressionPart>;
@@ -55,8 +305,8 @@ pub enum PostfixExpressionPart {
     PrimaryExpression(PrimaryExpression),
     ArrayAccess(Box<Expression>),
     ArgumentExpressionList(ArgumentExpressionList),
-    MemberAccess(String), // member access with dot operator
-    PointerMemberAccess(String), // member access with arrow operator
+    MemberAccess(Symbol), // member access with dot operator
+    PointerMemberAccess(Symbol), // member access with arrow operator
     Increment, // postfix ++
     Decrement, // postfix --
     TypeInitializerList{type_name: TypeName, initializer_list: InitializerList}
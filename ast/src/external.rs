@@ -5,7 +5,8 @@ pub type TranslationUnit = Vec<Node<ExternalDeclaration>>;
 #[derive(Debug, Clone)]
 pub enum ExternalDeclaration {
     FunctionDefinition(FunctionDefinition),
-    Declaration(Declaration)
+    Declaration(Declaration),
+    StaticAssert(StaticAssert)
 }
 
 #[derive(Debug, Clone)]
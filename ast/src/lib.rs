@@ -4,10 +4,14 @@ mod expressions;
 mod external;
 mod location;
 mod node;
+mod target;
+mod symbol;
 
 pub use self::declarations::*;
 pub use self::statements::*;
 pub use self::expressions::*;
 pub use self::external::*;
 pub use self::location::Location;
-pub use self::node::Node;
\ No newline at end of file
+pub use self::node::Node;
+pub use self::target::{TargetInfo, DataModel};
+pub use self::symbol::Symbol;
\ No newline at end of file
@@ -9,7 +9,12 @@ pub struct Location {
     pub filename: Rc<OsString>,
     pub line: usize,
     pub column: usize,
-    pub byte_offset: usize
+    pub byte_offset: usize,
+
+    /// For tokens produced by a macro expansion, the location of the macro invocation that produced them.
+    /// The other fields then hold the location the token was spelled at (in the macro definition, or
+    /// in one of the arguments of the invocation).
+    pub expanded_from: Option<Rc<Location>>
 }
 
 impl Location {
@@ -18,8 +23,19 @@ impl Location {
             filename,
             line,
             column,
-            byte_offset
+            byte_offset,
+            expanded_from: None
+        }
+    }
+
+    /// The location in the source file of the outermost macro invocation this location was expanded from,
+    /// or this location itself if it isn't part of a macro expansion.
+    pub fn expansion_location(&self) -> &Location {
+        let mut location = self;
+        while let Some(ref parent) = location.expanded_from {
+            location = parent;
         }
+        location
     }
 }
 
@@ -35,7 +51,8 @@ impl Default for Location {
             filename: Rc::new(OsString::default()),
             line: 0,
             column: 0,
-            byte_offset: 0
+            byte_offset: 0,
+            expanded_from: None
         }
     }
 }
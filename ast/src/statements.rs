@@ -5,6 +5,7 @@ pub type CompoundStatement = Vec<Node<BlockItem>>;
 #[derive(Debug, Clone)]
 pub enum BlockItem {
     Declaration(Declaration),
+    StaticAssert(StaticAssert),
     Statement(Box<Statement>)
 }
 
@@ -23,7 +24,7 @@ pub enum Statement {
 
 #[derive(Debug, Clone)]
 pub enum LabeledStatement {
-    Statement{ label: String, statement: Box<Node<Statement>> },
+    Statement{ label: Symbol, statement: Box<Node<Statement>> },
     Case { value: Node<ConstantExpression>, body: Box<Node<Statement>> },
     DefaultCase { body: Box<Node<Statement>> }
 }
@@ -44,7 +45,7 @@ pub enum IterationStatement {
 
 #[derive(Debug, Clone)]
 pub enum JumpStatement {
-    Goto(String),
+    Goto(Symbol),
     Continue,
     Break,
     Return(Option<Box<Expression>>)
@@ -0,0 +1,156 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::{Mutex, OnceLock};
+
+/// An interned identifier. Each distinct name is stored once, so comparing and hashing symbols only looks
+/// at an integer, and `as_str` gives back the name.
+///
+/// Symbols are interned in a table that is shared by every thread and is never freed, which is fine
+/// because a program only has so many distinct identifiers. Interning a name takes a lock, but looking up
+/// the name of a symbol doesn't.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+/// The number of names in the first chunk of `Names`, each chunk is twice as large as the previous one
+const FIRST_CHUNK: usize = 256;
+
+/// Enough chunks for every `u32` index
+const CHUNKS: usize = 25;
+
+/// The name of each symbol, indexed by symbol. The table is append-only and its chunks never move once they
+/// are allocated, so it can be read without taking the interner's lock.
+struct Names {
+    chunks: [OnceLock<Box<[OnceLock<&'static str>]>>; CHUNKS]
+}
+
+static NAMES: Names = Names { chunks: [const { OnceLock::new() }; CHUNKS] };
+
+impl Names {
+    /// The chunk that holds the name of the symbol `index`, and the position of the name in the chunk
+    fn position(index: u32) -> (usize, usize) {
+        let n = index as usize + FIRST_CHUNK;
+        let chunk = (n.ilog2() - FIRST_CHUNK.ilog2()) as usize;
+        (chunk, n - (FIRST_CHUNK << chunk))
+    }
+
+    fn get(&self, index: u32) -> &'static str {
+        let (chunk, position) = Names::position(index);
+        self.chunks[chunk].get().and_then(|names| names[position].get()).expect("symbol without a name")
+    }
+
+    /// Only called by the interner, which gives out each index once
+    fn set(&self, index: u32, name: &'static str) {
+        let (chunk, position) = Names::position(index);
+        let names = self.chunks[chunk].get_or_init(|| (0..FIRST_CHUNK << chunk).map(|_| OnceLock::new()).collect());
+        names[position].set(name).expect("symbol named twice");
+    }
+}
+
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>
+}
+
+fn interner() -> &'static Mutex<Interner> {
+    static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(|| Mutex::new(Interner::default()))
+}
+
+impl Symbol {
+    /// The symbol for `name`, which is the same every time the same name is interned
+    pub fn intern(name: &str) -> Symbol {
+        let mut interner = interner().lock().unwrap();
+        if let Some(&symbol) = interner.symbols.get(name) {
+            return symbol;
+        }
+        let symbol = Symbol(interner.symbols.len() as u32);
+        let name: &'static str = Box::leak(name.into());
+        NAMES.set(symbol.0, name);
+        interner.symbols.insert(name, symbol);
+        symbol
+    }
+
+    pub fn as_str(self) -> &'static str {
+        NAMES.get(self.0)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Symbol {
+        Symbol::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Symbol {
+        Symbol::intern(&name)
+    }
+}
+
+impl From<Cow<'_, str>> for Symbol {
+    fn from(name: Cow<str>) -> Symbol {
+        Symbol::intern(&name)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interned_names_are_shared() {
+        let a = Symbol::intern("counter");
+        assert_eq!(a, Symbol::from(String::from("counter")));
+        assert_ne!(a, Symbol::intern("Counter"));
+        assert_eq!(a.as_str(), "counter");
+        assert_eq!(a, "counter");
+        assert_eq!(format!("{} {:?}", a, a), "counter \"counter\"");
+    }
+
+    #[test]
+    fn interning_from_many_threads() {
+        let threads: Vec<_> = (0..4).map(|_| std::thread::spawn(|| {
+            (0..100).map(|i| Symbol::intern(&format!("name_{}", i))).collect::<Vec<_>>()
+        })).collect();
+        let symbols: Vec<_> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+        assert!(symbols.iter().all(|s| *s == symbols[0]));
+        assert_eq!(symbols[0][42].as_str(), "name_42");
+    }
+
+    #[test]
+    fn names_span_several_chunks() {
+        let symbols: Vec<_> = (0..3 * FIRST_CHUNK).map(|i| Symbol::intern(&format!("chunk_{}", i))).collect();
+        assert!(symbols.iter().enumerate().all(|(i, symbol)| symbol.as_str() == format!("chunk_{}", i)));
+        assert_eq!(Names::position(0), (0, 0));
+        assert_eq!(Names::position(FIRST_CHUNK as u32 - 1), (0, FIRST_CHUNK - 1));
+        assert_eq!(Names::position(FIRST_CHUNK as u32), (1, 0));
+        assert_eq!(Names::position(3 * FIRST_CHUNK as u32), (2, 0));
+        assert_eq!(Names::position(u32::MAX).0, CHUNKS - 1);
+    }
+}
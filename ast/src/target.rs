@@ -0,0 +1,52 @@
+/// The widths of `int`, `long`, `long long` and pointers, which differ between platforms.
+/// `char` is always 8 bits wide and `short` 16 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataModel {
+    /// 32 bit `int`, `long` and pointers, and a 64 bit `long long` (32 bit Linux and Windows)
+    ILP32,
+    /// 32 bit `int`, and 64 bit `long`, `long long` and pointers (64 bit Linux and macOS)
+    LP64,
+    /// 32 bit `int` and `long`, and 64 bit `long long` and pointers (64 bit Windows)
+    LLP64
+}
+
+/// Describes the platform that is being compiled for, as far as it affects the types of the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetInfo {
+    pub data_model: DataModel
+}
+
+impl TargetInfo {
+    pub fn new(data_model: DataModel) -> TargetInfo {
+        TargetInfo { data_model }
+    }
+
+    pub fn int_bits(&self) -> usize {
+        32
+    }
+
+    pub fn long_bits(&self) -> usize {
+        match self.data_model {
+            DataModel::ILP32 | DataModel::LLP64 => 32,
+            DataModel::LP64 => 64
+        }
+    }
+
+    pub fn long_long_bits(&self) -> usize {
+        64
+    }
+
+    pub fn pointer_bits(&self) -> usize {
+        match self.data_model {
+            DataModel::ILP32 => 32,
+            DataModel::LP64 | DataModel::LLP64 => 64
+        }
+    }
+}
+
+impl Default for TargetInfo {
+    /// 64 bit Linux
+    fn default() -> Self {
+        TargetInfo::new(DataModel::LP64)
+    }
+}
@@ -1,5 +1,6 @@
 extern crate ast;
 extern crate lexer;
+extern crate preprocessor;
 extern crate lalrpop_util;
 
 use std::fmt;
@@ -10,38 +11,89 @@ use std::convert::From;
 use lalrpop_util::ParseError;
 
 use ast::{Node, Location};
+use preprocessor::PreprocessorError;
+
+mod parse_error;
 
 #[derive(Debug)]
 pub enum CompilationError<'a> {
-    ParseError(ParseError<Location, lexer::Token<'a>, lexer::InvalidToken>),
+    Preprocessor(PreprocessorError),
+    ParseError(ParseError<Location, lexer::Token<'a>, lexer::LexicalError>),
     TypedefRedefinition(TypedefRedefinitionError),
+    VariableRedefinition(VariableRedefinitionError),
     InvalidStorageClassSpecifierCombination(InvalidStorageClassSpecifierCombination),
     InvalidTypeSpecifierCombination(InvalidTypeSpecifierCombination),
     BitFieldSizeExceedsTypeWidth(BitFieldSizeExceedsTypeWidth),
     DuplicateStructMember(DuplicateStructMember),
     NonIntegralBitfieldType(NonIntegralBitfieldType),
-    EnumVariantRedefinition(EnumVariantRedefinition)
+    NamedZeroWidthBitField(NamedZeroWidthBitField),
+    IncompleteStructMember(IncompleteStructMember),
+    FlexibleArrayMemberNotAtEnd(FlexibleArrayMemberNotAtEnd),
+    InvalidBitFieldWidth(InvalidBitFieldWidth),
+    TagKindMismatch(TagKindMismatch),
+    TagRedefinition(TagRedefinition),
+    InvalidConstantExpression(InvalidConstantExpression),
+    EnumeratorValueOutOfRange(EnumeratorValueOutOfRange),
+    InvalidArraySize(InvalidArraySize),
+    SymbolKindMismatch(SymbolKindMismatch),
+    ConflictingTypes(ConflictingTypes),
+    LabelRedefinition(LabelRedefinition),
+    UndefinedLabel(UndefinedLabel),
+    EnumVariantRedefinition(EnumVariantRedefinition),
+    StaticAssertionFailed(StaticAssertionFailed),
+    CompatibleGenericAssociations(CompatibleGenericAssociations),
+    DuplicateGenericDefault(DuplicateGenericDefault),
+    NoMatchingGenericAssociation(NoMatchingGenericAssociation),
+    UnknownTypeName(UnknownTypeName),
+    UnexpectedEndOfFile(UnexpectedEndOfFile)
 }
 
 impl<'a> Display for CompilationError<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            CompilationError::ParseError(inner) => inner.fmt(f),
+            CompilationError::Preprocessor(inner) => inner.fmt(f),
+            CompilationError::ParseError(inner) => parse_error::fmt_parse_error(inner, f),
             CompilationError::TypedefRedefinition(inner) => inner.fmt(f),
+            CompilationError::VariableRedefinition(inner) => inner.fmt(f),
             CompilationError::InvalidStorageClassSpecifierCombination(inner) => inner.fmt(f),
             CompilationError::InvalidTypeSpecifierCombination(inner) => inner.fmt(f),
             CompilationError::BitFieldSizeExceedsTypeWidth(inner) => inner.fmt(f),
             CompilationError::DuplicateStructMember(inner) => inner.fmt(f),
             CompilationError::NonIntegralBitfieldType(inner) => inner.fmt(f),
-            CompilationError::EnumVariantRedefinition(inner) => inner.fmt(f)
+            CompilationError::NamedZeroWidthBitField(inner) => inner.fmt(f),
+            CompilationError::IncompleteStructMember(inner) => inner.fmt(f),
+            CompilationError::FlexibleArrayMemberNotAtEnd(inner) => inner.fmt(f),
+            CompilationError::InvalidBitFieldWidth(inner) => inner.fmt(f),
+            CompilationError::TagKindMismatch(inner) => inner.fmt(f),
+            CompilationError::TagRedefinition(inner) => inner.fmt(f),
+            CompilationError::InvalidConstantExpression(inner) => inner.fmt(f),
+            CompilationError::EnumeratorValueOutOfRange(inner) => inner.fmt(f),
+            CompilationError::InvalidArraySize(inner) => inner.fmt(f),
+            CompilationError::SymbolKindMismatch(inner) => inner.fmt(f),
+            CompilationError::ConflictingTypes(inner) => inner.fmt(f),
+            CompilationError::LabelRedefinition(inner) => inner.fmt(f),
+            CompilationError::UndefinedLabel(inner) => inner.fmt(f),
+            CompilationError::EnumVariantRedefinition(inner) => inner.fmt(f),
+            CompilationError::StaticAssertionFailed(inner) => inner.fmt(f),
+            CompilationError::CompatibleGenericAssociations(inner) => inner.fmt(f),
+            CompilationError::DuplicateGenericDefault(inner) => inner.fmt(f),
+            CompilationError::NoMatchingGenericAssociation(inner) => inner.fmt(f),
+            CompilationError::UnknownTypeName(inner) => inner.fmt(f),
+            CompilationError::UnexpectedEndOfFile(inner) => inner.fmt(f)
         }
     }
 }
 
 impl<'a> Error for CompilationError<'a> {}
 
-impl<'a> From<ParseError<Location, lexer::Token<'a>, lexer::InvalidToken>> for CompilationError<'a> {
-    fn from(error: ParseError<Location, lexer::Token<'a>, lexer::InvalidToken>) -> Self {
+impl<'a> From<PreprocessorError> for CompilationError<'a> {
+    fn from(error: PreprocessorError) -> Self {
+        CompilationError::Preprocessor(error)
+    }
+}
+
+impl<'a> From<ParseError<Location, lexer::Token<'a>, lexer::LexicalError>> for CompilationError<'a> {
+    fn from(error: ParseError<Location, lexer::Token<'a>, lexer::LexicalError>) -> Self {
         CompilationError::ParseError(error)
     }
 }
@@ -52,6 +104,12 @@ impl<'a> From<TypedefRedefinitionError> for CompilationError<'a> {
     }
 }
 
+impl<'a> From<VariableRedefinitionError> for CompilationError<'a> {
+    fn from(error: VariableRedefinitionError) -> Self {
+        CompilationError::VariableRedefinition(error)
+    }
+}
+
 impl<'a> From<InvalidStorageClassSpecifierCombination> for CompilationError<'a> {
     fn from(error: InvalidStorageClassSpecifierCombination) -> Self {
         CompilationError::InvalidStorageClassSpecifierCombination(error)
@@ -82,12 +140,126 @@ impl<'a> From<NonIntegralBitfieldType> for CompilationError<'a> {
     }
 }
 
+impl<'a> From<NamedZeroWidthBitField> for CompilationError<'a> {
+    fn from(error: NamedZeroWidthBitField) -> Self {
+        CompilationError::NamedZeroWidthBitField(error)
+    }
+}
+
+impl<'a> From<IncompleteStructMember> for CompilationError<'a> {
+    fn from(error: IncompleteStructMember) -> Self {
+        CompilationError::IncompleteStructMember(error)
+    }
+}
+
+impl<'a> From<FlexibleArrayMemberNotAtEnd> for CompilationError<'a> {
+    fn from(error: FlexibleArrayMemberNotAtEnd) -> Self {
+        CompilationError::FlexibleArrayMemberNotAtEnd(error)
+    }
+}
+
+impl<'a> From<InvalidBitFieldWidth> for CompilationError<'a> {
+    fn from(error: InvalidBitFieldWidth) -> Self {
+        CompilationError::InvalidBitFieldWidth(error)
+    }
+}
+
+impl<'a> From<TagKindMismatch> for CompilationError<'a> {
+    fn from(error: TagKindMismatch) -> Self {
+        CompilationError::TagKindMismatch(error)
+    }
+}
+
+impl<'a> From<TagRedefinition> for CompilationError<'a> {
+    fn from(error: TagRedefinition) -> Self {
+        CompilationError::TagRedefinition(error)
+    }
+}
+
+impl<'a> From<InvalidConstantExpression> for CompilationError<'a> {
+    fn from(error: InvalidConstantExpression) -> Self {
+        CompilationError::InvalidConstantExpression(error)
+    }
+}
+
+impl<'a> From<EnumeratorValueOutOfRange> for CompilationError<'a> {
+    fn from(error: EnumeratorValueOutOfRange) -> Self {
+        CompilationError::EnumeratorValueOutOfRange(error)
+    }
+}
+
+impl<'a> From<InvalidArraySize> for CompilationError<'a> {
+    fn from(error: InvalidArraySize) -> Self {
+        CompilationError::InvalidArraySize(error)
+    }
+}
+
+impl<'a> From<SymbolKindMismatch> for CompilationError<'a> {
+    fn from(error: SymbolKindMismatch) -> Self {
+        CompilationError::SymbolKindMismatch(error)
+    }
+}
+
+impl<'a> From<ConflictingTypes> for CompilationError<'a> {
+    fn from(error: ConflictingTypes) -> Self {
+        CompilationError::ConflictingTypes(error)
+    }
+}
+
+impl<'a> From<LabelRedefinition> for CompilationError<'a> {
+    fn from(error: LabelRedefinition) -> Self {
+        CompilationError::LabelRedefinition(error)
+    }
+}
+
+impl<'a> From<UndefinedLabel> for CompilationError<'a> {
+    fn from(error: UndefinedLabel) -> Self {
+        CompilationError::UndefinedLabel(error)
+    }
+}
+
 impl<'a> From<EnumVariantRedefinition> for CompilationError<'a> {
     fn from(error: EnumVariantRedefinition) -> Self {
         CompilationError::EnumVariantRedefinition(error)
     }
 }
 
+impl<'a> From<StaticAssertionFailed> for CompilationError<'a> {
+    fn from(error: StaticAssertionFailed) -> Self {
+        CompilationError::StaticAssertionFailed(error)
+    }
+}
+
+impl<'a> From<CompatibleGenericAssociations> for CompilationError<'a> {
+    fn from(error: CompatibleGenericAssociations) -> Self {
+        CompilationError::CompatibleGenericAssociations(error)
+    }
+}
+
+impl<'a> From<DuplicateGenericDefault> for CompilationError<'a> {
+    fn from(error: DuplicateGenericDefault) -> Self {
+        CompilationError::DuplicateGenericDefault(error)
+    }
+}
+
+impl<'a> From<NoMatchingGenericAssociation> for CompilationError<'a> {
+    fn from(error: NoMatchingGenericAssociation) -> Self {
+        CompilationError::NoMatchingGenericAssociation(error)
+    }
+}
+
+impl<'a> From<UnknownTypeName> for CompilationError<'a> {
+    fn from(error: UnknownTypeName) -> Self {
+        CompilationError::UnknownTypeName(error)
+    }
+}
+
+impl<'a> From<UnexpectedEndOfFile> for CompilationError<'a> {
+    fn from(error: UnexpectedEndOfFile) -> Self {
+        CompilationError::UnexpectedEndOfFile(error)
+    }
+}
+
 #[derive(Debug)]
 pub struct TypedefRedefinitionError {
     identifier: String,
@@ -113,6 +285,7 @@ impl Display for TypedefRedefinitionError {
 
 impl Error for TypedefRedefinitionError {}
 
+#[derive(Debug)]
 pub struct VariableRedefinitionError {
     identifier: String,
     location: Location,
@@ -136,6 +309,8 @@ impl Display for VariableRedefinitionError {
     }
 }
 
+impl Error for VariableRedefinitionError {}
+
 #[derive(Debug)]
 pub struct MultipleStorageClassSpecifiersInDeclarationError {
     declaration: Node<ast::Declaration>
@@ -269,6 +444,75 @@ impl Display for NonIntegralBitfieldType {
 
 impl Error for NonIntegralBitfieldType {}
 
+#[derive(Debug)]
+pub struct NamedZeroWidthBitField {
+    location: (Location, Location),
+    field_name: String
+}
+
+impl NamedZeroWidthBitField {
+    pub fn new(location: (Location, Location), field_name: String) -> NamedZeroWidthBitField {
+        NamedZeroWidthBitField {
+            location,
+            field_name
+        }
+    }
+}
+
+impl Display for NamedZeroWidthBitField {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: named bit field '{}' has zero width", self.location.0, self.field_name)
+    }
+}
+
+impl Error for NamedZeroWidthBitField {}
+
+#[derive(Debug)]
+pub struct IncompleteStructMember {
+    location: (Location, Location),
+    field_name: String
+}
+
+impl IncompleteStructMember {
+    pub fn new(location: (Location, Location), field_name: String) -> IncompleteStructMember {
+        IncompleteStructMember {
+            location,
+            field_name
+        }
+    }
+}
+
+impl Display for IncompleteStructMember {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: field '{}' has incomplete type", self.location.0, self.field_name)
+    }
+}
+
+impl Error for IncompleteStructMember {}
+
+#[derive(Debug)]
+pub struct FlexibleArrayMemberNotAtEnd {
+    location: (Location, Location),
+    field_name: String
+}
+
+impl FlexibleArrayMemberNotAtEnd {
+    pub fn new(location: (Location, Location), field_name: String) -> FlexibleArrayMemberNotAtEnd {
+        FlexibleArrayMemberNotAtEnd {
+            location,
+            field_name
+        }
+    }
+}
+
+impl Display for FlexibleArrayMemberNotAtEnd {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: flexible array member '{}' is not at the end of the struct", self.location.0, self.field_name)
+    }
+}
+
+impl Error for FlexibleArrayMemberNotAtEnd {}
+
 #[derive(Debug)]
 pub struct EnumVariantRedefinition {
     location: (Location, Location),
@@ -290,4 +534,487 @@ impl Display for EnumVariantRedefinition {
     }
 }
 
-impl Error for EnumVariantRedefinition {}
\ No newline at end of file
+impl Error for EnumVariantRedefinition {}
+
+#[derive(Debug)]
+pub struct InvalidBitFieldWidth {
+    location: (Location, Location),
+    field_name: String
+}
+
+impl InvalidBitFieldWidth {
+    pub fn new(location: (Location, Location), field_name: String) -> InvalidBitFieldWidth {
+        InvalidBitFieldWidth {
+            location,
+            field_name
+        }
+    }
+}
+
+impl Display for InvalidBitFieldWidth {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: width of bit field '{}' is not a non-negative integer constant", self.location.0, self.field_name)
+    }
+}
+
+impl Error for InvalidBitFieldWidth {}
+
+#[derive(Debug)]
+pub struct TagKindMismatch {
+    location: (Location, Location),
+    name: String
+}
+
+impl TagKindMismatch {
+    pub fn new(location: (Location, Location), name: String) -> TagKindMismatch {
+        TagKindMismatch {
+            location,
+            name
+        }
+    }
+}
+
+impl Display for TagKindMismatch {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: '{}' defined as the wrong kind of tag", self.location.0, self.name)
+    }
+}
+
+impl Error for TagKindMismatch {}
+
+#[derive(Debug)]
+pub struct TagRedefinition {
+    location: (Location, Location),
+    name: String
+}
+
+impl TagRedefinition {
+    pub fn new(location: (Location, Location), name: String) -> TagRedefinition {
+        TagRedefinition {
+            location,
+            name
+        }
+    }
+}
+
+impl Display for TagRedefinition {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: redefinition of '{}'", self.location.0, self.name)
+    }
+}
+
+impl Error for TagRedefinition {}
+
+#[derive(Debug)]
+pub struct InvalidConstantExpression {
+    location: (Location, Location),
+    reason: String,
+    non_constant_operand: bool
+}
+
+impl InvalidConstantExpression {
+    pub fn new(location: (Location, Location), reason: String) -> InvalidConstantExpression {
+        InvalidConstantExpression {
+            location,
+            reason,
+            non_constant_operand: false
+        }
+    }
+
+    /// An expression that can only be evaluated at runtime (ex: it reads a variable), which is valid where
+    /// a constant expression isn't required, such as the size of a variable length array
+    pub fn non_constant_operand(location: (Location, Location), reason: String) -> InvalidConstantExpression {
+        InvalidConstantExpression {
+            location,
+            reason,
+            non_constant_operand: true
+        }
+    }
+
+    pub fn has_non_constant_operand(&self) -> bool {
+        self.non_constant_operand
+    }
+}
+
+impl Display for InvalidConstantExpression {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: invalid constant expression: {}", self.location.0, self.reason)
+    }
+}
+
+impl Error for InvalidConstantExpression {}
+
+#[derive(Debug)]
+pub struct EnumeratorValueOutOfRange {
+    location: (Location, Location),
+    name: String
+}
+
+impl EnumeratorValueOutOfRange {
+    pub fn new(location: (Location, Location), name: String) -> EnumeratorValueOutOfRange {
+        EnumeratorValueOutOfRange {
+            location,
+            name
+        }
+    }
+}
+
+impl Display for EnumeratorValueOutOfRange {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: value of enumerator '{}' is out of range", self.location.0, self.name)
+    }
+}
+
+impl Error for EnumeratorValueOutOfRange {}
+
+/// Warnings don't stop compilation, they are collected so that they can be reported along with any errors.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    warnings: Vec<Warning>
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics {
+            warnings: Vec::new()
+        }
+    }
+
+    pub fn warn<W: Into<Warning>>(&mut self, warning: W) {
+        self.warnings.push(warning.into());
+    }
+
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+}
+
+#[derive(Debug)]
+pub enum Warning {
+    DuplicateEnumeratorValue(DuplicateEnumeratorValue),
+    IntegerOverflow(IntegerOverflow)
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Warning::DuplicateEnumeratorValue(inner) => inner.fmt(f),
+            Warning::IntegerOverflow(inner) => inner.fmt(f)
+        }
+    }
+}
+
+impl From<DuplicateEnumeratorValue> for Warning {
+    fn from(warning: DuplicateEnumeratorValue) -> Self {
+        Warning::DuplicateEnumeratorValue(warning)
+    }
+}
+
+impl From<IntegerOverflow> for Warning {
+    fn from(warning: IntegerOverflow) -> Self {
+        Warning::IntegerOverflow(warning)
+    }
+}
+
+#[derive(Debug)]
+pub struct DuplicateEnumeratorValue {
+    location: (Location, Location),
+    enum_name: String,
+    name: String,
+    previous: String
+}
+
+impl DuplicateEnumeratorValue {
+    pub fn new(location: (Location, Location), enum_name: String, name: String, previous: String) -> DuplicateEnumeratorValue {
+        DuplicateEnumeratorValue {
+            location,
+            enum_name,
+            name,
+            previous
+        }
+    }
+}
+
+impl Display for DuplicateEnumeratorValue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: warning: in enum {}, enumerators '{}' and '{}' share the same value", self.location.0, self.enum_name, self.previous, self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct IntegerOverflow {
+    location: (Location, Location)
+}
+
+impl IntegerOverflow {
+    pub fn new(location: (Location, Location)) -> IntegerOverflow {
+        IntegerOverflow {
+            location
+        }
+    }
+}
+
+impl Display for IntegerOverflow {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: warning: integer overflow in constant expression", self.location.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidArraySize {
+    location: (Location, Location),
+    name: String
+}
+
+impl InvalidArraySize {
+    pub fn new(location: (Location, Location), name: String) -> InvalidArraySize {
+        InvalidArraySize {
+            location,
+            name
+        }
+    }
+}
+
+impl Display for InvalidArraySize {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: size of array '{}' is negative", self.location.0, self.name)
+    }
+}
+
+impl Error for InvalidArraySize {}
+
+#[derive(Debug)]
+pub struct SymbolKindMismatch {
+    location: (Location, Location),
+    name: String
+}
+
+impl SymbolKindMismatch {
+    pub fn new(location: (Location, Location), name: String) -> SymbolKindMismatch {
+        SymbolKindMismatch {
+            location,
+            name
+        }
+    }
+}
+
+impl Display for SymbolKindMismatch {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: '{}' redeclared as a different kind of symbol", self.location.0, self.name)
+    }
+}
+
+impl Error for SymbolKindMismatch {}
+
+#[derive(Debug)]
+pub struct ConflictingTypes {
+    location: (Location, Location),
+    name: String
+}
+
+impl ConflictingTypes {
+    pub fn new(location: (Location, Location), name: String) -> ConflictingTypes {
+        ConflictingTypes {
+            location,
+            name
+        }
+    }
+}
+
+impl Display for ConflictingTypes {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: conflicting types for '{}'", self.location.0, self.name)
+    }
+}
+
+impl Error for ConflictingTypes {}
+
+#[derive(Debug)]
+pub struct LabelRedefinition {
+    location: (Location, Location),
+    name: String
+}
+
+impl LabelRedefinition {
+    pub fn new(location: (Location, Location), name: String) -> LabelRedefinition {
+        LabelRedefinition {
+            location,
+            name
+        }
+    }
+}
+
+impl Display for LabelRedefinition {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: duplicate label '{}'", self.location.0, self.name)
+    }
+}
+
+impl Error for LabelRedefinition {}
+
+#[derive(Debug)]
+pub struct UndefinedLabel {
+    location: (Location, Location),
+    name: String
+}
+
+impl UndefinedLabel {
+    pub fn new(location: (Location, Location), name: String) -> UndefinedLabel {
+        UndefinedLabel {
+            location,
+            name
+        }
+    }
+}
+
+impl Display for UndefinedLabel {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: label '{}' used but not defined", self.location.0, self.name)
+    }
+}
+
+impl Error for UndefinedLabel {}
+
+#[derive(Debug)]
+pub struct StaticAssertionFailed {
+    location: (Location, Location),
+    message: String
+}
+
+impl StaticAssertionFailed {
+    pub fn new(location: (Location, Location), message: String) -> StaticAssertionFailed {
+        StaticAssertionFailed {
+            location,
+            message
+        }
+    }
+}
+
+impl Display for StaticAssertionFailed {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: static assertion failed: \"{}\"", self.location.0, self.message)
+    }
+}
+
+impl Error for StaticAssertionFailed {}
+
+#[derive(Debug)]
+pub struct CompatibleGenericAssociations {
+    location: (Location, Location),
+    type_name: String,
+    previous_type_name: String
+}
+
+impl CompatibleGenericAssociations {
+    pub fn new(location: (Location, Location), type_name: String, previous_type_name: String) -> CompatibleGenericAssociations {
+        CompatibleGenericAssociations {
+            location,
+            type_name,
+            previous_type_name
+        }
+    }
+}
+
+impl Display for CompatibleGenericAssociations {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: type '{}' in generic association is compatible with previously specified type '{}'",
+               self.location.0, self.type_name, self.previous_type_name)
+    }
+}
+
+impl Error for CompatibleGenericAssociations {}
+
+#[derive(Debug)]
+pub struct DuplicateGenericDefault {
+    location: (Location, Location)
+}
+
+impl DuplicateGenericDefault {
+    pub fn new(location: (Location, Location)) -> DuplicateGenericDefault {
+        DuplicateGenericDefault {
+            location
+        }
+    }
+}
+
+impl Display for DuplicateGenericDefault {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: duplicate default association in generic selection", self.location.0)
+    }
+}
+
+impl Error for DuplicateGenericDefault {}
+
+#[derive(Debug)]
+pub struct NoMatchingGenericAssociation {
+    location: (Location, Location),
+    controlling_type: String
+}
+
+impl NoMatchingGenericAssociation {
+    pub fn new(location: (Location, Location), controlling_type: String) -> NoMatchingGenericAssociation {
+        NoMatchingGenericAssociation {
+            location,
+            controlling_type
+        }
+    }
+}
+
+impl Display for NoMatchingGenericAssociation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: controlling expression type '{}' is not compatible with any generic association type",
+               self.location.0, self.controlling_type)
+    }
+}
+
+impl Error for NoMatchingGenericAssociation {}
+
+#[derive(Debug)]
+pub struct UnknownTypeName {
+    location: (Location, Location),
+    name: String
+}
+
+impl UnknownTypeName {
+    pub fn new(location: (Location, Location), name: String) -> UnknownTypeName {
+        UnknownTypeName {
+            location,
+            name
+        }
+    }
+}
+
+impl Display for UnknownTypeName {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: error: unknown type name '{}'", self.location.0, self.name)
+    }
+}
+
+impl Error for UnknownTypeName {}
+
+#[derive(Debug)]
+pub struct UnexpectedEndOfFile {
+    location: Location,
+    expected: Vec<String>
+}
+
+impl UnexpectedEndOfFile {
+    pub fn new(location: Location, expected: Vec<String>) -> UnexpectedEndOfFile {
+        UnexpectedEndOfFile {
+            location,
+            expected
+        }
+    }
+}
+
+impl Display for UnexpectedEndOfFile {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        parse_error::fmt_unrecognized_token(Some(&self.location), None, &self.expected, f)
+    }
+}
+
+impl Error for UnexpectedEndOfFile {}
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+use lalrpop_util::ParseError;
+
+use ast::Location;
+use lexer::{Token, LexicalError};
+
+/// The operators that can follow an expression. When they are expected, the parser is in the middle of an
+/// expression, and the error is reported as a token that is missing after it.
+const OPERATORS: &[&str] = &[
+    "=", "*=", "/=", "%=", "+=", "-=", "<<=", ">>=", "&=", "^=", "|=", "?", "||", "&&", "|", "^", "&", "==", "!=",
+    "<", ">", "<=", ">=", "<<", ">>", "+", "-", "*", "/", "%", "++", "--", "[", "(", ".", "->"
+];
+
+const STATEMENT_KEYWORDS: &[&str] = &["if", "switch", "while", "do", "for", "goto", "continue", "break", "return", "case", "default"];
+
+const EXPRESSION_START: &[&str] = &[
+    "Ident", "IntLiteral", "FloatLiteral", "CharLiteral", "StringLiteral", "(", "sizeof", "_Generic", "&", "*", "+", "-", "~", "!", "++", "--"
+];
+
+const STORAGE_CLASS_SPECIFIERS: &[&str] = &["typedef", "extern", "static", "auto", "register", "inline"];
+
+const TYPE_SPECIFIERS: &[&str] = &[
+    "void", "char", "short", "int", "long", "float", "double", "signed", "unsigned", "_Bool", "struct", "union", "enum",
+    "TypedefName", "const", "restrict", "volatile"
+];
+
+/// A summary of the tokens that the parser expected
+struct Expectation {
+    /// Grammar categories and tokens, in the order they are listed
+    items: Vec<String>,
+    /// What the expected tokens would follow
+    context: Option<&'static str>
+}
+
+/// Turns the names of the expected terminals (`"\";\""`, `"Ident"`...) into a short list: the tokens that can
+/// continue an expression or declarator are left out in favor of the token that ends it, and the tokens that
+/// start a statement, declaration, type or expression are replaced by the name of that category.
+///
+/// The parser reports every token that is valid in any of the contexts that share its current state, so after
+/// an expression the tokens that close parentheses, brackets and initializer lists are also expected, even once the
+/// expression has been reduced to a statement. A missing ';' is the most common mistake, so it's the one that gets
+/// reported.
+fn summarize(expected: &[String]) -> Expectation {
+    let mut names: Vec<&str> = expected.iter().map(|name| name.trim_matches('"')).collect();
+    let has = |names: &[&str], name: &str| names.contains(&name);
+    let mut context = None;
+
+    if has(&names, "==") {
+        names.retain(|name| !OPERATORS.contains(name));
+        context = Some("after expression");
+        if has(&names, ";") {
+            names = vec![";"];
+        }
+    }
+    else if has(&names, "=") && has(&names, ";") {
+        names = vec![";"];
+        context = Some("after declaration");
+    }
+    else if has(&names, ";") && has(&names, "Ident") && has(&names, "*") && !has(&names, "IntLiteral") {
+        return Expectation { items: vec!["declarator".to_string(), "';'".to_string()], context: None };
+    }
+    else if has(&names, ";") && !has(&names, "if") && (has(&names, ")") || has(&names, "]")) {
+        names = vec![";"];
+        context = Some("after expression");
+    }
+
+    let mut items = Vec::new();
+    let mut category = |names: &mut Vec<&str>, marker: &str, members: &[&[&str]], name: &str| {
+        if has(names, marker) {
+            names.retain(|token| !members.iter().any(|members| members.contains(token)));
+            items.push(name.to_string());
+        }
+    };
+    category(&mut names, "if", &[STATEMENT_KEYWORDS, EXPRESSION_START, &["{", ";"]], "statement");
+    category(&mut names, "typedef", &[STORAGE_CLASS_SPECIFIERS, TYPE_SPECIFIERS, &["_Static_assert"]], "declaration");
+    category(&mut names, "int", &[TYPE_SPECIFIERS, &["_Static_assert"]], "type specifier");
+    category(&mut names, "IntLiteral", &[EXPRESSION_START], "expression");
+    category(&mut names, "Ident", &[&["Ident"]], "identifier");
+    category(&mut names, "TypedefName", &[&["TypedefName"]], "type name");
+    items.extend(names.iter().map(|name| format!("'{}'", name)));
+
+    Expectation { items, context }
+}
+
+/// "a", "a or b", "a, b or c"...
+fn one_of(items: &[String]) -> String {
+    match items.split_last() {
+        None => String::new(),
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last)
+    }
+}
+
+fn describe_token(token: &Token) -> String {
+    match token {
+        Token::Identifier(_) => format!("identifier '{}'", token),
+        Token::TypedefType(_) => format!("type name '{}'", token),
+        Token::IntLiteral(_) | Token::FloatLiteral(_) => format!("constant '{}'", token),
+        Token::CharLiteral { .. } => format!("character constant {}", token),
+        Token::StringLiteral { .. } => format!("string literal {}", token),
+        _ => format!("'{}'", token)
+    }
+}
+
+/// A hint about the likely cause of a syntax error, for the mistakes that are easy to recognize
+fn hint(token: Option<&Token>, expected: &[String]) -> Option<String> {
+    let expects = |name: &str| expected.iter().any(|expected| expected.trim_matches('"') == name);
+    match token {
+        Some(Token::RBrace) if expects(";") && !expects("if") => Some("a ';' is probably missing before the '}'".to_string()),
+        Some(Token::Identifier(name)) if expects("TypedefName") && !expects("Ident") && !expects(";") => Some(format!("unknown type name '{}'", name)),
+        None if expects("}") => Some("a '}' is probably missing at the end of the file".to_string()),
+        _ => None
+    }
+}
+
+pub(crate) fn fmt_unrecognized_token(location: Option<&Location>, token: Option<&Token>, expected: &[String], f: &mut Formatter) -> fmt::Result {
+    match location {
+        Some(location) => write!(f, "{}: error: ", location)?,
+        None => write!(f, "error: ")?
+    }
+    let found = token.map(describe_token).unwrap_or_else(|| "end of file".to_string());
+    let expectation = summarize(expected);
+    if expectation.items.is_empty() {
+        write!(f, "unexpected {}", found)?;
+    }
+    else {
+        write!(f, "expected {}", one_of(&expectation.items))?;
+        if let Some(context) = expectation.context {
+            write!(f, " {}", context)?;
+        }
+        write!(f, ", found {}", found)?;
+    }
+    if let Some(hint) = hint(token, expected) {
+        write!(f, "\nnote: {}", hint)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn fmt_parse_error(error: &ParseError<Location, Token, LexicalError>, f: &mut Formatter) -> fmt::Result {
+    match error {
+        ParseError::InvalidToken { location } => write!(f, "{}: error: invalid token", location),
+        ParseError::UnrecognizedToken { token: Some((location, token, _)), expected } => {
+            fmt_unrecognized_token(Some(location), Some(token), expected, f)
+        },
+        ParseError::UnrecognizedToken { token: None, expected } => fmt_unrecognized_token(None, None, expected, f),
+        ParseError::ExtraToken { token: (location, token, _) } => write!(f, "{}: error: unexpected {}", location, describe_token(token)),
+        ParseError::User { error } => write!(f, "{}", error)
+    }
+}
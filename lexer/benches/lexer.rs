@@ -0,0 +1,59 @@
+//! Compares the speed of the scanner with the original nom based implementation, on a large translation unit
+//! that looks like the output of the preprocessor.
+//!
+//! Run with `cargo bench -p lexer --features reference`. The number of lines can be changed with the `LEXER_BENCH_LINES`
+//! environment variable.
+
+use std::ffi::OsString;
+use std::time::{Duration, Instant};
+
+use lexer::Lexer;
+
+const FUNCTION: &str = r#"
+typedef struct node_7 { struct node_7 *next; unsigned long key; double weight; char name[32]; } node_7;
+static const char *names_7[] = { "alpha", "beta\tgamma", L"wide", "escaped \"quote\"\n" };
+/* walks the list and sums the weights of the nodes whose key matches */
+static inline double sum_weights_7(const node_7 *head, unsigned long key, int *count) {
+    double total = 0.0;
+    for (const node_7 *n = head; n != (void *) 0; n = n->next) {
+        if ((n->key & 0xff00UL) == (key << 8) && n->name[0] != '\0') {
+            total += n->weight * 1.5e-3 + 0x1.8p-4;
+            ++*count;
+        } else if (n->key >= 1000000000000ULL || n->weight <= -2.5f) {
+            total -= sizeof(node_7) % 16 ? 1.0L : .5; // adjust for padding
+        }
+    }
+    return total / (*count ? *count : 1);
+}
+"#;
+
+fn source(lines: usize) -> String {
+    let function_lines = FUNCTION.lines().count();
+    (0..lines / function_lines).map(|i| FUNCTION.replace("_7", &format!("_{}", i))).collect()
+}
+
+/// Lexes `source` a few times, returning the fastest time and the number of tokens
+fn measure<'a>(lexer: impl Fn() -> Lexer<'a>) -> (Duration, usize) {
+    (0..5).map(|_| {
+        let start = Instant::now();
+        let tokens = lexer().filter_map(Result::ok).count();
+        (start.elapsed(), tokens)
+    }).min().unwrap()
+}
+
+fn main() {
+    let lines = std::env::var("LEXER_BENCH_LINES").ok().and_then(|lines| lines.parse().ok()).unwrap_or(50_000);
+    let source = source(lines);
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+    println!("lexing {} lines ({:.1} MiB)", source.lines().count(), megabytes);
+
+    let (scanner, tokens) = measure(|| Lexer::new(OsString::from("bench.c"), &source));
+    let (reference, reference_tokens) = measure(|| Lexer::new(OsString::from("bench.c"), &source).with_reference_scanner());
+    assert_eq!(tokens, reference_tokens);
+
+    for (name, time) in &[("scanner", scanner), ("nom (reference)", reference)] {
+        println!("{:>16}: {:>8.2?} {:>8.1} MiB/s {:>10.0} tokens/s", name, time,
+            megabytes / time.as_secs_f64(), tokens as f64 / time.as_secs_f64());
+    }
+    println!("speedup: {:.1}x", reference.as_secs_f64() / scanner.as_secs_f64());
+}
@@ -0,0 +1,340 @@
+use crate::Token;
+use crate::identifiers::{universal_character_name, universal_character_name_length};
+use ast::{Integer, IntegerType, TargetInfo};
+
+/// Scans the character constant at the start of `s`, which begins with `'` (or `L'` if `wide` is set),
+/// returning the token and its length in bytes.
+pub(crate) fn scan_character_constant(s: &str, wide: bool, target: TargetInfo) -> Option<(Token<'_>, usize)> {
+    let start = if wide { 2 } else { 1 };
+    let len = quoted_length(s, start, b'\'', code_unit_bits(wide, target))?;
+    if len == start + 1 {
+        return None;
+    }
+    let contents = &s[start..len - 1];
+    Some((Token::CharLiteral{ wide, contents, value: character_value(contents, wide, target) }, len))
+}
+
+/// Scans the string literal at the start of `s`, which begins with `"` (or `L"` if `wide` is set),
+/// returning the token and its length in bytes.
+pub(crate) fn scan_string_literal(s: &str, wide: bool, target: TargetInfo) -> Option<(Token<'_>, usize)> {
+    let start = if wide { 2 } else { 1 };
+    let len = quoted_length(s, start, b'"', code_unit_bits(wide, target))?;
+    Some((Token::StringLiteral{ wide, contents: &s[start..len - 1] }, len))
+}
+
+/// The length of a string literal or character constant whose contents begin at `start`, including the
+/// closing quote. Fails if the line (or the input) ends before the closing quote, or if there is an invalid
+/// escape sequence, including octal and hexadecimal escape sequences that don't fit in a `unit_bits` wide code unit.
+fn quoted_length(s: &str, start: usize, quote: u8, unit_bits: usize) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = start;
+    loop {
+        match *bytes.get(i)? {
+            c if c == quote => return Some(i + 1),
+            b'\n' => return None,
+            b'\\' => {
+                let len = escape_sequence_length(&s[i..])?;
+                if !escape_sequence_fits(&s[i..i + len], unit_bits) {
+                    return None;
+                }
+                i += len;
+            },
+            _ => i += 1
+        }
+    }
+}
+
+/// The width of the code units of a character constant or string literal: a `char` or, if it is wide, a `wchar_t`
+pub(crate) fn code_unit_bits(wide: bool, target: TargetInfo) -> usize {
+    if wide { IntegerType::wide_char(target).bits() } else { 8 }
+}
+
+/// Whether the value of a (valid) escape sequence fits in a code unit that is `unit_bits` wide. The value of an
+/// octal or hexadecimal escape sequence must be in the range of `unsigned char`, or of the unsigned type that
+/// corresponds to `wchar_t` for wide literals (C99 6.4.4.4p9).
+pub(crate) fn escape_sequence_fits(escape: &str, unit_bits: usize) -> bool {
+    let (digits, radix) = match escape.as_bytes()[1] {
+        b'x' => (&escape[2..], 16),
+        b'0'..=b'7' => (&escape[1..], 8),
+        _ => return true
+    };
+    digits.chars()
+        .try_fold(0u64, |value, digit| value.checked_mul(radix)?.checked_add(u64::from(digit.to_digit(radix as u32).unwrap())))
+        .is_some_and(|value| value >> unit_bits == 0)
+}
+
+/// Computes the value of a character constant from its (already validated) contents, following
+/// section 6.4.4.4 of ISO/IEC 9899:1999.
+///
+/// An integer character constant has type `int`. Each character is converted to a `char` (which is signed
+/// on the targets we support) before being widened to `int`, so `'\xff'` has the value -1. The value of a
+/// constant containing more than one character is implementation-defined; like gcc, each character
+/// is shifted in from the right.
+///
+/// A wide character constant has type `wchar_t`, which depends on the target, and its value is the code
+/// point of the character converted to `wchar_t`. If it contains more than one character, the last one is used.
+pub(crate) fn character_value(contents: &str, wide: bool, target: TargetInfo) -> Integer {
+    let chars = decode_characters(contents);
+    if wide {
+        let value = match chars.last() {
+            Some(DecodedCharacter::CodeUnit(v)) => *v,
+            Some(DecodedCharacter::Character(c)) => *c as u32,
+            None => 0
+        };
+        let type_ = IntegerType::wide_char(target);
+        Integer::new(type_, type_.wrap(value.into()))
+    }
+    else {
+        let mut bytes = Vec::new();
+        for c in chars {
+            match c {
+                // the lexer only accepts octal and hex escape sequences that fit in an unsigned char
+                DecodedCharacter::CodeUnit(v) => bytes.push(v as u8),
+                // universal character names and non-ascii source characters are stored as utf-8
+                DecodedCharacter::Character(c) => {
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+        let value = match bytes.as_slice() {
+            [single] => i32::from(*single as i8),
+            bytes => bytes.iter().fold(0u32, |acc, b| (acc << 8) | u32::from(*b)) as i32
+        };
+        Integer::new(IntegerType::Int, value.into())
+    }
+}
+
+/// Decodes the contents of a string literal into the code units of the array it initializes, without
+/// the terminating null character.
+///
+/// The code units of a narrow string literal are bytes, and universal character names and non-ascii
+/// source characters are encoded as utf-8. The code units of a wide string literal are `wchar_t` values
+/// (32 bits), one per character. A narrow literal that is concatenated with a wide one is decoded as a
+/// wide literal (C99 6.4.5p4).
+pub fn decode_string_literal(contents: &str, wide: bool) -> Vec<u32> {
+    let mut units = Vec::new();
+    for c in decode_characters(contents) {
+        match c {
+            DecodedCharacter::CodeUnit(v) if wide => units.push(v),
+            // octal and hex escape sequences must fit in an unsigned char, extra bits are discarded
+            DecodedCharacter::CodeUnit(v) => units.push(v & 0xff),
+            DecodedCharacter::Character(c) if wide => units.push(c as u32),
+            DecodedCharacter::Character(c) => {
+                let mut buf = [0; 4];
+                units.extend(c.encode_utf8(&mut buf).bytes().map(u32::from));
+            }
+        }
+    }
+    units
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DecodedCharacter {
+    /// A code unit given directly by a simple, octal or hexadecimal escape sequence
+    CodeUnit(u32),
+    /// A source character or universal character name, which may need to be encoded
+    /// as multiple code units in a narrow literal
+    Character(char)
+}
+
+/// Splits the contents of a character constant or string literal into each of its characters,
+/// resolving escape sequences along the way.
+pub(crate) fn decode_characters(contents: &str) -> Vec<DecodedCharacter> {
+    let mut values = Vec::new();
+    let mut rest = contents;
+    while let Some(c) = rest.chars().next() {
+        if c == '\\' {
+            let (value, len) = decode_escape_sequence(rest);
+            let value = match rest.as_bytes()[1] {
+                b'u' | b'U' => DecodedCharacter::Character(std::char::from_u32(value).unwrap_or(std::char::REPLACEMENT_CHARACTER)),
+                _ => DecodedCharacter::CodeUnit(value)
+            };
+            values.push(value);
+            rest = &rest[len..];
+        }
+        else {
+            values.push(DecodedCharacter::Character(c));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    values
+}
+
+/// The length of the escape sequence at the start of `s` (which begins with a backslash), if it is valid
+pub(crate) fn escape_sequence_length(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let count = |from: usize, max: usize, is_digit: fn(&u8) -> bool| bytes[from..].iter().take(max).take_while(|c| is_digit(c)).count();
+    match *bytes.get(1)? {
+        b'\\' | b'\'' | b'"' | b'?' | b'a' | b'b' | b'f' | b'n' | b'r' | b't' | b'v' => Some(2),
+        b'0'..=b'7' => Some(1 + count(1, 3, |c| (b'0'..=b'7').contains(c))),
+        b'x' => match count(2, usize::MAX, u8::is_ascii_hexdigit) {
+            0 => None,
+            digits => Some(2 + digits)
+        },
+        b'u' | b'U' => universal_character_name(s).ok().map(|(_, len)| len),
+        _ => None
+    }
+}
+
+/// Decodes the escape sequence at the start of `s`, returning its value and its length in bytes.
+///
+/// `s` must begin with an escape sequence accepted by `escape_sequence`. Octal and hexadecimal escape
+/// sequences that don't fit in 32 bits are truncated.
+fn decode_escape_sequence(s: &str) -> (u32, usize) {
+    let bytes = s.as_bytes();
+    debug_assert_eq!(bytes[0], b'\\');
+    match bytes[1] {
+        b'u' | b'U' => match universal_character_name(s) {
+            Ok((c, len)) => (c as u32, len),
+            // the lexer only accepts literals with valid universal character names
+            Err(_) => (std::char::REPLACEMENT_CHARACTER as u32, universal_character_name_length(s))
+        },
+        b'x' => {
+            let digits = s[2..].chars().take_while(|c| c.is_ascii_hexdigit()).count();
+            let value = s[2..2 + digits].chars()
+                .fold(0u32, |acc, c| acc.wrapping_shl(4) | c.to_digit(16).unwrap());
+            (value, 2 + digits)
+        },
+        b'0'..=b'7' => {
+            let digits = s[1..].chars().take(3).take_while(|c| c.is_digit(8)).count();
+            let value = s[1..1 + digits].chars()
+                .fold(0u32, |acc, c| (acc << 3) | c.to_digit(8).unwrap());
+            (value, 1 + digits)
+        },
+        c => {
+            let value = match c {
+                b'a' => 0x07,
+                b'b' => 0x08,
+                b'f' => 0x0C,
+                b'n' => 0x0A,
+                b'r' => 0x0D,
+                b't' => 0x09,
+                b'v' => 0x0B,
+                c => u32::from(c) // \' \" \? and \\ stand for themselves
+            };
+            (value, 2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Lexer, Token};
+    use ast::{Integer, IntegerType, TargetInfo, DataModel};
+
+    fn lex_value(source: &str) -> Integer {
+        let mut input = Lexer::new("test.c".into(), source);
+        let token = input.next().unwrap().unwrap().1;
+        assert_eq!(input.next(), None);
+        match token {
+            Token::CharLiteral{ value, .. } => value,
+            other => panic!("expected a character constant, found {:?}", other)
+        }
+    }
+
+    #[test]
+    fn simple_character_constant() {
+        let mut input = Lexer::new("test.c".into(), "'a'");
+        let token = input.next().unwrap().unwrap().1;
+        assert_eq!(token, Token::CharLiteral{ wide: false, contents: "a", value: Integer::new(IntegerType::Int, 97) });
+    }
+
+    #[test]
+    fn simple_escape_sequences() {
+        assert_eq!(lex_value(r"'\n'"), Integer::new(IntegerType::Int, 10));
+        assert_eq!(lex_value(r"'\t'"), Integer::new(IntegerType::Int, 9));
+        assert_eq!(lex_value(r"'\''"), Integer::new(IntegerType::Int, 39));
+        assert_eq!(lex_value(r#"'\"'"#), Integer::new(IntegerType::Int, 34));
+        assert_eq!(lex_value(r"'\\'"), Integer::new(IntegerType::Int, 92));
+        assert_eq!(lex_value(r"'\?'"), Integer::new(IntegerType::Int, 63));
+        assert_eq!(lex_value(r"'\a'"), Integer::new(IntegerType::Int, 7));
+    }
+
+    #[test]
+    fn unescaped_double_quote() {
+        assert_eq!(lex_value(r#"'"'"#), Integer::new(IntegerType::Int, 34));
+    }
+
+    #[test]
+    fn octal_escape_sequences() {
+        assert_eq!(lex_value(r"'\0'"), Integer::new(IntegerType::Int, 0));
+        assert_eq!(lex_value(r"'\101'"), Integer::new(IntegerType::Int, 65));
+        // an octal escape sequence is at most 3 digits long, so this is the multi-character constant '\101' '1'
+        assert_eq!(lex_value(r"'\1011'"), Integer::new(IntegerType::Int, (65 << 8) | 49));
+    }
+
+    #[test]
+    fn hex_escape_sequence() {
+        assert_eq!(lex_value(r"'\x41'"), Integer::new(IntegerType::Int, 65));
+    }
+
+    #[test]
+    fn plain_char_is_signed() {
+        assert_eq!(lex_value(r"'\xff'"), Integer::new(IntegerType::Int, -1));
+        assert_eq!(lex_value(r"'\377'"), Integer::new(IntegerType::Int, -1));
+    }
+
+    #[test]
+    fn multi_character_constant() {
+        assert_eq!(lex_value("'ab'"), Integer::new(IntegerType::Int, 0x6162));
+        assert_eq!(lex_value("'abcd'"), Integer::new(IntegerType::Int, 0x61626364));
+    }
+
+    #[test]
+    fn narrow_universal_character_name_is_utf8() {
+        assert_eq!(lex_value(r"'\u00e9'"), Integer::new(IntegerType::Int, 0xC3A9));
+    }
+
+    #[test]
+    fn wide_character_constant() {
+        let mut input = Lexer::new("test.c".into(), "L'x'");
+        let token = input.next().unwrap().unwrap().1;
+        assert_eq!(token, Token::CharLiteral{ wide: true, contents: "x", value: Integer::new(IntegerType::Int, 120) });
+    }
+
+    #[test]
+    fn wide_character_constant_type_depends_on_target() {
+        let lex_for = |data_model: DataModel, source: &str| {
+            let mut input = Lexer::new("test.c".into(), source);
+            input.set_target(TargetInfo::new(data_model));
+            match input.next().unwrap().unwrap().1 {
+                Token::CharLiteral{ value, .. } => value,
+                other => panic!("expected a character constant, found {:?}", other)
+            }
+        };
+        assert_eq!(lex_for(DataModel::ILP32, "L'x'"), Integer::new(IntegerType::Long { bits: 32 }, 120));
+        assert_eq!(lex_for(DataModel::LLP64, "L'x'"), Integer::new(IntegerType::UnsignedShort, 120));
+        assert_eq!(lex_for(DataModel::LP64, r"L'\xffffffff'"), Integer::new(IntegerType::Int, -1));
+        assert_eq!(lex_for(DataModel::LLP64, r"L'\xffff'"), Integer::new(IntegerType::UnsignedShort, 0xffff));
+    }
+
+    #[test]
+    fn wide_character_constant_non_ascii() {
+        assert_eq!(lex_value(r"L'\u20AC'"), Integer::new(IntegerType::Int, 0x20AC));
+        assert_eq!(lex_value(r"L'€'"), Integer::new(IntegerType::Int, 0x20AC));
+    }
+
+    #[test]
+    fn empty_character_constant_is_invalid() {
+        let mut input = Lexer::new("test.c".into(), "''");
+        assert!(input.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn decoded_string_literals() {
+        use super::decode_string_literal;
+        assert_eq!(decode_string_literal(r"a\n\0", false), vec![97, 10, 0]);
+        assert_eq!(decode_string_literal(r"\x1234\377", false), vec![0x34, 0xff]);
+        assert_eq!(decode_string_literal(r"é\u20AC", false), vec![0xC3, 0xA9, 0xE2, 0x82, 0xAC]);
+        assert_eq!(decode_string_literal(r"é\u20AC\x1234", true), vec![0xE9, 0x20AC, 0x1234]);
+        assert_eq!(decode_string_literal("", true), vec![]);
+    }
+
+    #[test]
+    fn identifier_starting_with_l() {
+        let mut input = Lexer::new("test.c".into(), "L");
+        let token = input.next().unwrap().unwrap().1;
+        assert_eq!(token, Token::Identifier("L".into()));
+    }
+}
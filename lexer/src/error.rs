@@ -1,31 +1,93 @@
-use std::ffi::OsString;
 use std::error::Error;
 use std::fmt::{Display, Formatter, Error as FmtError};
-use std::rc::Rc;
 
 use ast::Location;
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct InvalidToken {
-    pub location: Location,
+/// The reason why the lexer couldn't form a token
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LexicalErrorKind {
+    /// An integer constant followed by something that isn't a valid suffix (ex: `10uu` or `123abc`)
+    InvalidIntegerSuffix,
+    /// An integer constant that doesn't fit in any integer type
+    IntegerLiteralTooLarge,
+    /// A floating constant that is too large to be represented by its type
+    FloatConstantOutOfRange,
+    /// A string literal that is missing its closing `"` before the end of the line
+    UnterminatedString,
+    /// A character constant that is missing its closing `'` before the end of the line
+    UnterminatedCharacterConstant,
+    /// A character constant without any characters (`''`)
+    EmptyCharacterConstant,
+    /// A block comment (`/* ... */`) that is missing its closing `*/`
+    UnterminatedComment,
+    /// A backslash in a string literal or character constant that doesn't start a valid escape sequence
+    InvalidEscapeSequence,
+    /// A `\u` that isn't followed by 4 hex digits, or a `\U` that isn't followed by 8
+    IncompleteUniversalCharacterName,
+    /// A universal character name that names a surrogate, a value past the end of unicode, or a character of
+    /// the basic character set (C99 6.4.3p2)
+    InvalidUniversalCharacterName(u32),
+    /// A universal character name for a character that isn't allowed in identifiers (C99 Annex D), or that
+    /// can't start one
+    CharacterNotAllowedInIdentifier(char),
+    /// A character that can't start any token (ex: `@` or `` ` ``)
+    StrayCharacter(char)
+}
+
+/// An error encountered while lexing, which covers the bytes from `start` up to (but not including) `end`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LexicalError {
+    pub kind: LexicalErrorKind,
+    pub start: Location,
+    pub end: Location,
+    /// The text of the line that the error starts on
     pub line_text: String
 }
 
-impl InvalidToken {
-    pub fn from_offset(location: Location, source: &str) -> InvalidToken {
-        let line_text = source.lines().skip(location.line - 1).next().unwrap_or("").to_string();
-        InvalidToken {
-            location,
+impl LexicalError {
+    pub fn new(kind: LexicalErrorKind, start: Location, end: Location, source: &str) -> LexicalError {
+        let line_start = source[..start.byte_offset.min(source.len())].rfind('\n').map_or(0, |i| i + 1);
+        let line_text = source[line_start..].lines().next().unwrap_or("").to_string();
+        LexicalError {
+            kind,
+            start,
+            end,
             line_text
         }
     }
 }
 
-impl Display for InvalidToken {
+impl Display for LexicalError {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
-        write!(f, "{}: invalid token", self.location)
+        write!(f, "{}: error: ", self.start)?;
+        match self.kind {
+            LexicalErrorKind::InvalidIntegerSuffix => write!(f, "invalid suffix on integer constant"),
+            LexicalErrorKind::IntegerLiteralTooLarge => write!(f, "integer constant is too large for any integer type"),
+            LexicalErrorKind::FloatConstantOutOfRange => write!(f, "floating constant exceeds the range of its type"),
+            LexicalErrorKind::UnterminatedString => write!(f, "missing terminating '\"' character"),
+            LexicalErrorKind::UnterminatedCharacterConstant => write!(f, "missing terminating ' character"),
+            LexicalErrorKind::EmptyCharacterConstant => write!(f, "empty character constant"),
+            LexicalErrorKind::UnterminatedComment => write!(f, "unterminated comment"),
+            LexicalErrorKind::InvalidEscapeSequence => write!(f, "invalid escape sequence"),
+            LexicalErrorKind::IncompleteUniversalCharacterName => {
+                write!(f, "incomplete universal character name, \\u must be followed by 4 hex digits and \\U by 8")
+            },
+            LexicalErrorKind::InvalidUniversalCharacterName(value) => match value {
+                0xD800..=0xDFFF => write!(f, "universal character name \\u{:04X} is a surrogate code point", value),
+                0..=0x9F => write!(f, "universal character name \\u{:04X} names a character of the basic character set", value),
+                _ => write!(f, "universal character name \\U{:08X} is outside the range of unicode", value)
+            },
+            LexicalErrorKind::CharacterNotAllowedInIdentifier(c) => {
+                if crate::identifiers::is_identifier_continue(c) {
+                    write!(f, "U+{:04X} ('{}') is not allowed at the start of an identifier", c as u32, c)
+                }
+                else {
+                    write!(f, "U+{:04X} ('{}') is not allowed in an identifier", c as u32, c)
+                }
+            },
+            LexicalErrorKind::StrayCharacter(c) => write!(f, "stray '{}' in program", c.escape_default())
+        }
     }
 }
 
-//TODO: add cause for invalid token error (ex: invalid int literal suffix)
-impl Error for InvalidToken {}
+impl Error for LexicalError {}
@@ -0,0 +1,394 @@
+use std::cmp::Ordering;
+
+use crate::{Token, LexicalErrorKind};
+use ast::{Float, FloatType};
+
+/// Scans the floating constant at the start of `s`, returning the token and its length in bytes, or `None`
+/// if `s` doesn't start with a floating constant (ex: it starts with an integer constant).
+pub(crate) fn scan_float(s: &str) -> Option<Result<(Token<'static>, usize), LexicalErrorKind>> {
+    let bytes = s.as_bytes();
+    let hex = bytes.len() > 2 && bytes[0] == b'0' && (bytes[1] == b'x' || bytes[1] == b'X');
+    let (radix, exponent_chars, start): (u32, &[u8], usize) = if hex { (16, b"pP", 2) } else { (10, b"eE", 0) };
+    let is_digit = |c: &u8| if hex { c.is_ascii_hexdigit() } else { c.is_ascii_digit() };
+
+    let whole_len = bytes[start..].iter().take_while(|c| is_digit(c)).count();
+    let mut end = start + whole_len;
+    let mut fraction_len = 0;
+    let has_point = bytes.get(end) == Some(&b'.');
+    if has_point {
+        fraction_len = bytes[end + 1..].iter().take_while(|c| is_digit(c)).count();
+        end += 1 + fraction_len;
+    }
+    if whole_len == 0 && fraction_len == 0 {
+        return None;
+    }
+
+    // the exponent is optional for decimal constants with a decimal point, and required otherwise
+    let exponent = match scan_exponent(&bytes[end..], exponent_chars) {
+        Some((exponent, len)) => {
+            end += len;
+            exponent
+        },
+        None if has_point && !hex => 0,
+        None => return None
+    };
+
+    let type_ = match bytes.get(end) {
+        Some(b'f') | Some(b'F') => FloatType::Float,
+        Some(b'l') | Some(b'L') => FloatType::LongDouble,
+        _ => FloatType::Double
+    };
+    let suffix_len = if type_ == FloatType::Double { 0 } else { 1 };
+
+    let whole = &s[start..start + whole_len];
+    let fraction = if has_point { &s[start + whole_len + 1..start + whole_len + 1 + fraction_len] } else { "" };
+    let digits = format!("{}{}", whole, fraction);
+    let digit_exponent = if hex { 4 } else { 1 };
+    match convert(&digits, radix, exponent.saturating_sub(digit_exponent * fraction_len as i64), type_) {
+        Some(value) => Some(Ok((Token::FloatLiteral(value), end + suffix_len))),
+        None => Some(Err(LexicalErrorKind::FloatConstantOutOfRange))
+    }
+}
+
+/// Scans an exponent part (ex: `e+10`) starting with one of `exponent_chars`, returning its value and length
+fn scan_exponent(bytes: &[u8], exponent_chars: &[u8]) -> Option<(i64, usize)> {
+    if !exponent_chars.contains(bytes.first()?) {
+        return None;
+    }
+    let sign_len = match bytes.get(1) {
+        Some(b'+') | Some(b'-') => 1,
+        _ => 0
+    };
+    let digits = bytes[1 + sign_len..].iter().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    // exponents this large are far outside the range of every floating type, so saturating is harmless
+    let value = bytes[1 + sign_len..1 + sign_len + digits].iter()
+        .fold(0i64, |acc, c| acc.saturating_mul(10).saturating_add(i64::from(c - b'0')));
+    let value = if bytes[1] == b'-' { -value } else { value };
+    Some((value, 1 + sign_len + digits))
+}
+
+/// Converts the significant digits of a floating constant (with the decimal point removed) and its
+/// exponent to the nearest value representable in the given type, following section 6.4.4.2 of
+/// ISO/IEC 9899:1999 with round-to-nearest-even.
+///
+/// For decimal constants the value is `digits * 10^exponent`, and for hexadecimal constants it is
+/// `digits * 2^exponent`. Values too small to be represented round to zero, values too large to be
+/// represented return `None`.
+pub(crate) fn convert(digits: &str, radix: u32, exponent: i64, type_: FloatType) -> Option<Float> {
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Some(Float::new(type_, 0, 0));
+    }
+
+    // rough bounds on the magnitude of the value, so that we don't build enormous numbers for
+    // constants that are certainly zero or certainly too large (the limits of long double are
+    // roughly 10^-4951 and 10^4932)
+    let magnitude = match radix {
+        16 => exponent.saturating_add(4 * digits.len() as i64),
+        _ => exponent.saturating_add(digits.len() as i64).saturating_mul(4)
+    };
+    if magnitude > 4 * 5000 {
+        return None;
+    }
+    if magnitude < -4 * 5000 {
+        return Some(Float::new(type_, 0, 0));
+    }
+
+    let mut numerator = BigUint::from_digits(digits, radix);
+    let mut denominator = BigUint::from_u32(1);
+    match radix {
+        16 if exponent >= 0 => numerator.shl(exponent as usize),
+        16 => denominator.shl(-exponent as usize),
+        _ if exponent >= 0 => numerator.mul_pow10(exponent as usize),
+        _ => denominator.mul_pow10(-exponent as usize)
+    }
+
+    round(&numerator, &denominator, type_)
+}
+
+/// Rounds `numerator / denominator` to the nearest value of the given type (ties to even).
+fn round(numerator: &BigUint, denominator: &BigUint, type_: FloatType) -> Option<Float> {
+    let precision = i64::from(type_.precision());
+    // the exponent of the smallest subnormal value
+    let min_exponent = i64::from(type_.min_exponent()) - (precision - 1);
+
+    // numerator / denominator lies in [2^(bits - 1), 2^(bits + 1)), so scaling it by 2^-exponent
+    // gives a quotient with either `precision` or `precision + 1` bits
+    let bits = numerator.bit_len() as i64 - denominator.bit_len() as i64;
+    let mut exponent = std::cmp::max(bits - precision, min_exponent);
+    let (mut quotient, mut remainder) = divide(numerator, denominator, exponent, precision as usize);
+    if quotient >> precision != 0 {
+        exponent += 1;
+        let (q, r) = divide(numerator, denominator, exponent, precision as usize);
+        quotient = q;
+        remainder = r;
+    }
+
+    if remainder == Ordering::Greater || (remainder == Ordering::Equal && quotient & 1 == 1) {
+        quotient += 1;
+        if quotient >> precision != 0 {
+            quotient >>= 1;
+            exponent += 1;
+        }
+    }
+
+    let value_bits = 128 - i64::from(quotient.leading_zeros());
+    if quotient != 0 && value_bits + exponent > i64::from(type_.max_exponent()) {
+        return None;
+    }
+    Some(Float::new(type_, quotient as u64, exponent as i32))
+}
+
+/// Computes `numerator / (denominator * 2^exponent)`, which must be less than `2^(bits + 1)`.
+///
+/// Returns the integer part of the quotient, and how twice the remainder compares to the divisor
+/// (`Less` if the fractional part is less than one half, `Equal` if it is exactly one half, and `Greater` if
+/// it's larger).
+fn divide(numerator: &BigUint, denominator: &BigUint, exponent: i64, bits: usize) -> (u128, Ordering) {
+    let mut remainder = numerator.clone();
+    let mut divisor = denominator.clone();
+    if exponent >= 0 {
+        divisor.shl(exponent as usize);
+    }
+    else {
+        remainder.shl(-exponent as usize);
+    }
+
+    let mut quotient = 0u128;
+    for i in (0..=bits).rev() {
+        let mut shifted = divisor.clone();
+        shifted.shl(i);
+        if remainder.cmp(&shifted) != Ordering::Less {
+            remainder.sub(&shifted);
+            quotient |= 1 << i;
+        }
+    }
+
+    remainder.shl(1);
+    (quotient, remainder.cmp(&divisor))
+}
+
+/// A minimal arbitrary precision unsigned integer, just enough to convert floating constants exactly.
+#[derive(Clone, Debug)]
+struct BigUint {
+    /// 32 bit limbs, least significant first, without any trailing zero limbs
+    limbs: Vec<u32>
+}
+
+impl BigUint {
+    fn from_u32(value: u32) -> BigUint {
+        let mut n = BigUint { limbs: vec![value] };
+        n.normalize();
+        n
+    }
+
+    fn from_digits(digits: &str, radix: u32) -> BigUint {
+        let mut n = BigUint::from_u32(0);
+        for c in digits.chars() {
+            n.mul_add(radix, c.to_digit(radix).unwrap());
+        }
+        n
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+
+    /// self = self * multiplier + addend
+    fn mul_add(&mut self, multiplier: u32, addend: u32) {
+        let mut carry = u64::from(addend);
+        for limb in self.limbs.iter_mut() {
+            let v = u64::from(*limb) * u64::from(multiplier) + carry;
+            *limb = v as u32;
+            carry = v >> 32;
+        }
+        if carry != 0 {
+            self.limbs.push(carry as u32);
+        }
+        self.normalize();
+    }
+
+    fn mul_pow10(&mut self, mut exponent: usize) {
+        while exponent >= 9 {
+            self.mul_add(1_000_000_000, 0);
+            exponent -= 9;
+        }
+        self.mul_add(10u32.pow(exponent as u32), 0);
+    }
+
+    fn shl(&mut self, bits: usize) {
+        if self.limbs.is_empty() {
+            return;
+        }
+        let (limbs, bits) = (bits / 32, bits % 32);
+        if bits != 0 {
+            let mut carry = 0;
+            for limb in self.limbs.iter_mut() {
+                let v = (*limb << bits) | carry;
+                carry = *limb >> (32 - bits);
+                *limb = v;
+            }
+            if carry != 0 {
+                self.limbs.push(carry);
+            }
+        }
+        if limbs != 0 {
+            self.limbs.splice(0..0, std::iter::repeat_n(0, limbs));
+        }
+    }
+
+    /// self = self - other, other must not be greater than self
+    fn sub(&mut self, other: &BigUint) {
+        let mut borrow = 0i64;
+        for i in 0..self.limbs.len() {
+            let rhs = i64::from(other.limbs.get(i).cloned().unwrap_or(0));
+            let mut v = i64::from(self.limbs[i]) - rhs - borrow;
+            borrow = 0;
+            if v < 0 {
+                v += 1 << 32;
+                borrow = 1;
+            }
+            self.limbs[i] = v as u32;
+        }
+        debug_assert_eq!(borrow, 0);
+        self.normalize();
+    }
+
+    fn bit_len(&self) -> usize {
+        match self.limbs.last() {
+            Some(last) => self.limbs.len() * 32 - last.leading_zeros() as usize,
+            None => 0
+        }
+    }
+
+    fn cmp(&self, other: &BigUint) -> Ordering {
+        self.limbs.len().cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Lexer, Token, LexicalErrorKind};
+    use ast::{Float, FloatType};
+
+    fn lex_float(source: &str) -> Float {
+        let mut input = Lexer::new("test.c".into(), source);
+        let token = input.next().unwrap().unwrap().1;
+        assert_eq!(input.next(), None);
+        match token {
+            Token::FloatLiteral(value) => value,
+            other => panic!("expected a floating constant, found {:?}", other)
+        }
+    }
+
+    fn double(value: f64) -> Float {
+        let bits = value.to_bits();
+        let exponent = ((bits >> 52) & 0x7FF) as i32;
+        let fraction = bits & ((1 << 52) - 1);
+        if exponent == 0 {
+            Float::new(FloatType::Double, fraction, -1074)
+        }
+        else {
+            Float::new(FloatType::Double, fraction | (1 << 52), exponent - 1075)
+        }
+    }
+
+    fn float(value: f32) -> Float {
+        let bits = value.to_bits();
+        let exponent = ((bits >> 23) & 0xFF) as i32;
+        let fraction = u64::from(bits & ((1 << 23) - 1));
+        if exponent == 0 {
+            Float::new(FloatType::Float, fraction, -149)
+        }
+        else {
+            Float::new(FloatType::Float, fraction | (1 << 23), exponent - 150)
+        }
+    }
+
+    #[test]
+    fn simple_decimal_floats() {
+        assert_eq!(lex_float("1.5"), Float::new(FloatType::Double, 3, -1));
+        assert_eq!(lex_float("1."), Float::new(FloatType::Double, 1, 0));
+        assert_eq!(lex_float(".5"), Float::new(FloatType::Double, 1, -1));
+        assert_eq!(lex_float("0.0"), Float::new(FloatType::Double, 0, 0));
+        assert_eq!(lex_float("1e3"), Float::new(FloatType::Double, 125, 3));
+    }
+
+    #[test]
+    fn decimal_floats_are_correctly_rounded() {
+        for source in &["0.1", "1.5e-3", "3.141592653589793238462643383279", "2.2250738585072014e-308",
+                        "4.9406564584124654e-324", "1.7976931348623157e308", "9007199254740993.0",
+                        "123456789012345678901234567890e-10", "0.30000000000000004"] {
+            assert_eq!(lex_float(source), double(source.parse().unwrap()), "{}", source);
+        }
+    }
+
+    #[test]
+    fn float_suffixes() {
+        assert_eq!(lex_float(".5f"), Float::new(FloatType::Float, 1, -1));
+        assert_eq!(lex_float("0.1F"), float(0.1));
+        assert_eq!(lex_float("1e-45f"), float(1e-45));
+        assert_eq!(lex_float("2.5l").type_, FloatType::LongDouble);
+    }
+
+    #[test]
+    fn long_double_has_64_bits_of_precision() {
+        // 0.1 rounded to a 64 bit significand
+        assert_eq!(lex_float("0.1L"), Float::new(FloatType::LongDouble, 0xCCCCCCCCCCCCCCCD, -67));
+        // 2^64 + 1 is a tie between 2^64 and 2^64 + 2, which rounds to the even significand
+        assert_eq!(lex_float("36893488147419103233.0L"), Float::new(FloatType::LongDouble, 1, 65));
+    }
+
+    #[test]
+    fn hex_floats() {
+        assert_eq!(lex_float("0x1.8p3L"), Float::new(FloatType::LongDouble, 3, 2));
+        assert_eq!(lex_float("0x1p-2"), Float::new(FloatType::Double, 1, -2));
+        assert_eq!(lex_float("0X.8P1"), Float::new(FloatType::Double, 1, 0));
+        assert_eq!(lex_float("0xA.p0f"), Float::new(FloatType::Float, 5, 1));
+        assert_eq!(lex_float("0x1.fffffffffffffp1023"), double(std::f64::MAX));
+    }
+
+    #[test]
+    fn hex_float_rounding() {
+        // 1 + 2^-53 is exactly between 1 and the next double, so it rounds down to the even value
+        assert_eq!(lex_float("0x1.00000000000008p0"), Float::new(FloatType::Double, 1, 0));
+        // 1 + 3 * 2^-53 is exactly between two doubles, and rounds up to the even value
+        assert_eq!(lex_float("0x1.00000000000018p0"), double(1.0 + 2.0 * std::f64::EPSILON));
+    }
+
+    #[test]
+    fn tiny_float_rounds_to_zero() {
+        assert_eq!(lex_float("1e-400"), Float::new(FloatType::Double, 0, 0));
+        assert_eq!(lex_float("1e-99999999999999999999"), Float::new(FloatType::Double, 0, 0));
+    }
+
+    #[test]
+    fn float_out_of_range() {
+        for source in &["1e309", "3.5e38f", "0x1p1024", "1e99999999999999999999L"] {
+            let mut input = Lexer::new("test.c".into(), source);
+            let err = input.next().unwrap().unwrap_err();
+            assert_eq!(err.kind, LexicalErrorKind::FloatConstantOutOfRange, "{}", source);
+        }
+    }
+
+    #[test]
+    fn integers_are_not_floats() {
+        let mut input = Lexer::new("test.c".into(), "10 0x1f");
+        assert!(match input.next().unwrap().unwrap().1 { Token::IntLiteral(_) => true, _ => false });
+        assert!(match input.next().unwrap().unwrap().1 { Token::IntLiteral(_) => true, _ => false });
+    }
+
+    #[test]
+    fn member_access_is_not_a_float() {
+        let mut input = Lexer::new("test.c".into(), "a.b");
+        assert_eq!(input.next().unwrap().unwrap().1, Token::Identifier("a".into()));
+        assert_eq!(input.next().unwrap().unwrap().1, Token::Dot);
+        assert_eq!(input.next().unwrap().unwrap().1, Token::Identifier("b".into()));
+    }
+}
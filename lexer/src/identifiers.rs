@@ -0,0 +1,302 @@
+//! Extended identifiers: identifiers may contain the letters of many scripts (C99 Annex D), either directly
+//! as utf-8 or spelled as universal character names (`\u00e9` or `\U000000e9`, C99 6.4.3).
+
+use std::borrow::Cow;
+
+use crate::LexicalErrorKind;
+
+/// The ranges of characters that are allowed in identifiers (C99 Annex D), sorted and non-overlapping
+const ANNEX_D: &[(u32, u32)] = &[
+    /* Latin (1) */
+    (0x00AA, 0x00AA),
+    /* Special characters (1) */
+    (0x00B5, 0x00B5), (0x00B7, 0x00B7),
+    /* Latin (2) */
+    (0x00BA, 0x00BA), (0x00C0, 0x00D6), (0x00D8, 0x00F6), (0x00F8, 0x01F5), (0x01FA, 0x0217),
+    (0x0250, 0x02A8),
+    /* Special characters (2) */
+    (0x02B0, 0x02B8), (0x02BB, 0x02BB), (0x02BD, 0x02C1), (0x02D0, 0x02D1), (0x02E0, 0x02E4),
+    (0x037A, 0x037A),
+    /* Greek (1) */
+    (0x0386, 0x0386), (0x0388, 0x038A), (0x038C, 0x038C), (0x038E, 0x03A1), (0x03A3, 0x03CE),
+    (0x03D0, 0x03D6), (0x03DA, 0x03DA), (0x03DC, 0x03DC), (0x03DE, 0x03DE), (0x03E0, 0x03E0),
+    (0x03E2, 0x03F3),
+    /* Cyrillic */
+    (0x0401, 0x040C), (0x040E, 0x044F), (0x0451, 0x045C), (0x045E, 0x0481), (0x0490, 0x04C4),
+    (0x04C7, 0x04C8), (0x04CB, 0x04CC), (0x04D0, 0x04EB), (0x04EE, 0x04F5), (0x04F8, 0x04F9),
+    /* Armenian, and a special character */
+    (0x0531, 0x0556), (0x0559, 0x0559), (0x0561, 0x0587),
+    /* Hebrew */
+    (0x05B0, 0x05B9), (0x05BB, 0x05BD), (0x05BF, 0x05BF), (0x05C1, 0x05C2), (0x05D0, 0x05EA),
+    (0x05F0, 0x05F2),
+    /* Arabic, and Arabic digits */
+    (0x0621, 0x063A), (0x0640, 0x0652), (0x0660, 0x0669), (0x0670, 0x06B7), (0x06BA, 0x06BE),
+    (0x06C0, 0x06CE), (0x06D0, 0x06DC), (0x06E5, 0x06E8), (0x06EA, 0x06ED), (0x06F0, 0x06F9),
+    /* Devanagari, a special character, and Devanagari digits */
+    (0x0901, 0x0903), (0x0905, 0x0939), (0x093D, 0x094D), (0x0950, 0x0952), (0x0958, 0x0963),
+    (0x0966, 0x096F),
+    /* Bengali, and Bengali digits */
+    (0x0981, 0x0983), (0x0985, 0x098C), (0x098F, 0x0990), (0x0993, 0x09A8), (0x09AA, 0x09B0),
+    (0x09B2, 0x09B2), (0x09B6, 0x09B9), (0x09BE, 0x09C4), (0x09C7, 0x09C8), (0x09CB, 0x09CD),
+    (0x09DC, 0x09DD), (0x09DF, 0x09E3), (0x09E6, 0x09EF), (0x09F0, 0x09F1),
+    /* Gurmukhi, and Gurmukhi digits */
+    (0x0A02, 0x0A02), (0x0A05, 0x0A0A), (0x0A0F, 0x0A10), (0x0A13, 0x0A28), (0x0A2A, 0x0A30),
+    (0x0A32, 0x0A33), (0x0A35, 0x0A36), (0x0A38, 0x0A39), (0x0A3E, 0x0A42), (0x0A47, 0x0A48),
+    (0x0A4B, 0x0A4D), (0x0A59, 0x0A5C), (0x0A5E, 0x0A5E), (0x0A66, 0x0A6F), (0x0A74, 0x0A74),
+    /* Gujarati, and Gujarati digits */
+    (0x0A81, 0x0A83), (0x0A85, 0x0A8B), (0x0A8D, 0x0A8D), (0x0A8F, 0x0A91), (0x0A93, 0x0AA8),
+    (0x0AAA, 0x0AB0), (0x0AB2, 0x0AB3), (0x0AB5, 0x0AB9), (0x0ABD, 0x0AC5), (0x0AC7, 0x0AC9),
+    (0x0ACB, 0x0ACD), (0x0AD0, 0x0AD0), (0x0AE0, 0x0AE0), (0x0AE6, 0x0AEF),
+    /* Oriya, a special character, and Oriya digits */
+    (0x0B01, 0x0B03), (0x0B05, 0x0B0C), (0x0B0F, 0x0B10), (0x0B13, 0x0B28), (0x0B2A, 0x0B30),
+    (0x0B32, 0x0B33), (0x0B36, 0x0B39), (0x0B3D, 0x0B43), (0x0B47, 0x0B48), (0x0B4B, 0x0B4D),
+    (0x0B5C, 0x0B5D), (0x0B5F, 0x0B61), (0x0B66, 0x0B6F),
+    /* Tamil, and Tamil digits */
+    (0x0B82, 0x0B83), (0x0B85, 0x0B8A), (0x0B8E, 0x0B90), (0x0B92, 0x0B95), (0x0B99, 0x0B9A),
+    (0x0B9C, 0x0B9C), (0x0B9E, 0x0B9F), (0x0BA3, 0x0BA4), (0x0BA8, 0x0BAA), (0x0BAE, 0x0BB5),
+    (0x0BB7, 0x0BB9), (0x0BBE, 0x0BC2), (0x0BC6, 0x0BC8), (0x0BCA, 0x0BCD), (0x0BE7, 0x0BEF),
+    /* Telugu, and Telugu digits */
+    (0x0C01, 0x0C03), (0x0C05, 0x0C0C), (0x0C0E, 0x0C10), (0x0C12, 0x0C28), (0x0C2A, 0x0C33),
+    (0x0C35, 0x0C39), (0x0C3E, 0x0C44), (0x0C46, 0x0C48), (0x0C4A, 0x0C4D), (0x0C60, 0x0C61),
+    (0x0C66, 0x0C6F),
+    /* Kannada, and Kannada digits */
+    (0x0C82, 0x0C83), (0x0C85, 0x0C8C), (0x0C8E, 0x0C90), (0x0C92, 0x0CA8), (0x0CAA, 0x0CB3),
+    (0x0CB5, 0x0CB9), (0x0CBE, 0x0CC4), (0x0CC6, 0x0CC8), (0x0CCA, 0x0CCD), (0x0CDE, 0x0CDE),
+    (0x0CE0, 0x0CE1), (0x0CE6, 0x0CEF),
+    /* Malayalam, and Malayalam digits */
+    (0x0D02, 0x0D03), (0x0D05, 0x0D0C), (0x0D0E, 0x0D10), (0x0D12, 0x0D28), (0x0D2A, 0x0D39),
+    (0x0D3E, 0x0D43), (0x0D46, 0x0D48), (0x0D4A, 0x0D4D), (0x0D60, 0x0D61), (0x0D66, 0x0D6F),
+    /* Thai, including Thai digits */
+    (0x0E01, 0x0E3A), (0x0E40, 0x0E5B),
+    /* Lao, and Lao digits */
+    (0x0E81, 0x0E82), (0x0E84, 0x0E84), (0x0E87, 0x0E88), (0x0E8A, 0x0E8A), (0x0E8D, 0x0E8D),
+    (0x0E94, 0x0E97), (0x0E99, 0x0E9F), (0x0EA1, 0x0EA3), (0x0EA5, 0x0EA5), (0x0EA7, 0x0EA7),
+    (0x0EAA, 0x0EAB), (0x0EAD, 0x0EAE), (0x0EB0, 0x0EB9), (0x0EBB, 0x0EBD), (0x0EC0, 0x0EC4),
+    (0x0EC6, 0x0EC6), (0x0EC8, 0x0ECD), (0x0ED0, 0x0ED9), (0x0EDC, 0x0EDD),
+    /* Tibetan, and Tibetan digits */
+    (0x0F00, 0x0F00), (0x0F18, 0x0F19), (0x0F20, 0x0F33), (0x0F35, 0x0F35), (0x0F37, 0x0F37),
+    (0x0F39, 0x0F39), (0x0F3E, 0x0F47), (0x0F49, 0x0F69), (0x0F71, 0x0F84), (0x0F86, 0x0F8B),
+    (0x0F90, 0x0F95), (0x0F97, 0x0F97), (0x0F99, 0x0FAD), (0x0FB1, 0x0FB7), (0x0FB9, 0x0FB9),
+    /* Georgian */
+    (0x10A0, 0x10C5), (0x10D0, 0x10F6),
+    /* Latin (3) */
+    (0x1E00, 0x1E9B), (0x1EA0, 0x1EF9),
+    /* Greek (2), and a special character */
+    (0x1F00, 0x1F15), (0x1F18, 0x1F1D), (0x1F20, 0x1F45), (0x1F48, 0x1F4D), (0x1F50, 0x1F57),
+    (0x1F59, 0x1F59), (0x1F5B, 0x1F5B), (0x1F5D, 0x1F5D), (0x1F5F, 0x1F7D), (0x1F80, 0x1FB4),
+    (0x1FB6, 0x1FBC), (0x1FBE, 0x1FBE), (0x1FC2, 0x1FC4), (0x1FC6, 0x1FCC), (0x1FD0, 0x1FD3),
+    (0x1FD6, 0x1FDB), (0x1FE0, 0x1FEC), (0x1FF2, 0x1FF4), (0x1FF6, 0x1FFC),
+    /* Special characters (3), and Latin (4) */
+    (0x203F, 0x2040), (0x207F, 0x207F), (0x2102, 0x2102), (0x2107, 0x2107), (0x210A, 0x2113),
+    (0x2115, 0x2115), (0x2118, 0x211D), (0x2124, 0x2124), (0x2126, 0x2126), (0x2128, 0x2128),
+    (0x212A, 0x2131), (0x2133, 0x2138), (0x2160, 0x2182), (0x3005, 0x3007), (0x3021, 0x3029),
+    /* Hiragana */
+    (0x3041, 0x3093), (0x309B, 0x309C),
+    /* Katakana */
+    (0x30A1, 0x30F6), (0x30FB, 0x30FC),
+    /* Bopomofo */
+    (0x3105, 0x312C),
+    /* CJK Unified Ideographs */
+    (0x4E00, 0x9FA5),
+    /* Hangul */
+    (0xAC00, 0xD7A3)
+];
+
+/// The digits of Annex D, which can't be the first character of an identifier (C99 6.4.2.1p3)
+const ANNEX_D_DIGITS: &[(u32, u32)] = &[
+    (0x0660, 0x0669), (0x06F0, 0x06F9), (0x0966, 0x096F), (0x09E6, 0x09EF), (0x0A66, 0x0A6F),
+    (0x0AE6, 0x0AEF), (0x0B66, 0x0B6F), (0x0BE7, 0x0BEF), (0x0C66, 0x0C6F), (0x0CE6, 0x0CEF),
+    (0x0D66, 0x0D6F), (0x0E50, 0x0E59), (0x0ED0, 0x0ED9), (0x0F20, 0x0F33)
+];
+
+fn in_ranges(c: char, ranges: &[(u32, u32)]) -> bool {
+    let c = c as u32;
+    ranges.binary_search_by(|&(first, last)| {
+        if last < c {
+            std::cmp::Ordering::Less
+        }
+        else if first > c {
+            std::cmp::Ordering::Greater
+        }
+        else {
+            std::cmp::Ordering::Equal
+        }
+    }).is_ok()
+}
+
+/// Whether `c` can be the first character of an identifier
+pub fn is_identifier_start(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_alphabetic() || c == '_'
+    }
+    else {
+        in_ranges(c, ANNEX_D) && !in_ranges(c, ANNEX_D_DIGITS)
+    }
+}
+
+/// Whether `c` can appear in an identifier after the first character
+pub fn is_identifier_continue(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_alphanumeric() || c == '_'
+    }
+    else {
+        in_ranges(c, ANNEX_D)
+    }
+}
+
+/// Decodes the universal character name at the start of `s`, which begins with `\u` or `\U`, returning the
+/// character and the length of the universal character name in bytes.
+///
+/// `\u` must be followed by exactly 4 hex digits and `\U` by exactly 8. The character may not be a
+/// surrogate, and may only be in the basic character set if it is `$`, `@` or `` ` `` (C99 6.4.3p2).
+pub(crate) fn universal_character_name(s: &str) -> Result<(char, usize), LexicalErrorKind> {
+    let digits = if s.as_bytes()[1] == b'u' { 4 } else { 8 };
+    let hex = s.get(2..2 + digits)
+        .filter(|hex| hex.bytes().all(|c| c.is_ascii_hexdigit()))
+        .ok_or(LexicalErrorKind::IncompleteUniversalCharacterName)?;
+    let value = u32::from_str_radix(hex, 16).unwrap();
+    match std::char::from_u32(value) {
+        Some(c) if value >= 0xA0 || c == '$' || c == '@' || c == '`' => Ok((c, 2 + digits)),
+        _ => Err(LexicalErrorKind::InvalidUniversalCharacterName(value))
+    }
+}
+
+/// The length of the (possibly incomplete) universal character name at the start of `s`, which is used
+/// as the span of an error about it
+pub(crate) fn universal_character_name_length(s: &str) -> usize {
+    let digits = if s.as_bytes()[1] == b'u' { 4 } else { 8 };
+    2 + s[2..].bytes().take(digits).take_while(u8::is_ascii_hexdigit).count()
+}
+
+/// The length in bytes of the identifier at the start of `s`, or 0 if `s` doesn't start with one.
+///
+/// The identifier ends before the first character that isn't allowed in it, including a universal character
+/// name that isn't valid or that names such a character.
+pub(crate) fn identifier_length(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut len = 0;
+    while len < bytes.len() {
+        let (c, n) = if bytes[len] == b'\\' {
+            match bytes.get(len + 1) {
+                Some(b'u') | Some(b'U') => match universal_character_name(&s[len..]) {
+                    Ok(ucn) => ucn,
+                    Err(_) => break
+                },
+                _ => break
+            }
+        }
+        else {
+            let c = s[len..].chars().next().unwrap();
+            (c, c.len_utf8())
+        };
+        let allowed = if len == 0 { is_identifier_start(c) } else { is_identifier_continue(c) };
+        if !allowed {
+            break;
+        }
+        len += n;
+    }
+    len
+}
+
+/// The length in bytes of the preprocessing token that starts with an identifier at the start of `s`.
+///
+/// Unlike `identifier_length`, this includes anything that looks like a universal character name, so that
+/// an invalid one is reported once the token is lexed rather than splitting the identifier in two.
+pub fn preprocessing_identifier_length(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut len = 0;
+    while len < bytes.len() {
+        if bytes[len] == b'\\' && matches!(bytes.get(len + 1), Some(b'u') | Some(b'U')) {
+            len += universal_character_name_length(&s[len..]);
+            continue;
+        }
+        match s[len..].chars().next() {
+            Some(c) if (len == 0 && is_identifier_start(c)) || (len > 0 && is_identifier_continue(c)) => len += c.len_utf8(),
+            _ => break
+        }
+    }
+    len
+}
+
+/// Replaces the universal character names in the spelling of an identifier with the characters that they
+/// name, so that `caf\u00e9` and `café` are the same identifier. Anything that isn't a valid universal
+/// character name is left as it is.
+pub fn decode_identifier(spelling: &str) -> Cow<'_, str> {
+    if !spelling.contains('\\') {
+        return Cow::Borrowed(spelling);
+    }
+    let mut decoded = String::with_capacity(spelling.len());
+    let mut rest = spelling;
+    while let Some(i) = rest.find('\\') {
+        decoded.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let ucn = match rest.as_bytes().get(1) {
+            Some(b'u') | Some(b'U') => universal_character_name(rest).ok(),
+            _ => None
+        };
+        match ucn {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            },
+            None => {
+                decoded.push('\\');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn annex_d_ranges_are_sorted() {
+        for ranges in &[ANNEX_D, ANNEX_D_DIGITS] {
+            assert!(ranges.iter().all(|(first, last)| first <= last));
+            assert!(ranges.windows(2).all(|pair| pair[0].1 < pair[1].0));
+        }
+        assert!(ANNEX_D_DIGITS.iter().all(|&(first, last)| {
+            in_ranges(std::char::from_u32(first).unwrap(), ANNEX_D) && in_ranges(std::char::from_u32(last).unwrap(), ANNEX_D)
+        }));
+    }
+
+    #[test]
+    fn extended_identifier_characters() {
+        assert!(is_identifier_start('é'));
+        assert!(is_identifier_start('λ'));
+        assert!(is_identifier_start('日'));
+        assert!(!is_identifier_start('€'));
+        assert!(!is_identifier_start('٣'));
+        assert!(is_identifier_continue('٣'));
+        assert!(!is_identifier_continue('😀'));
+    }
+
+    #[test]
+    fn universal_character_names() {
+        assert_eq!(universal_character_name(r"\u00e9"), Ok(('é', 6)));
+        assert_eq!(universal_character_name(r"\U0001F600x"), Ok(('😀', 10)));
+        assert_eq!(universal_character_name(r"\u0024"), Ok(('$', 6)));
+        assert_eq!(universal_character_name(r"\u00e"), Err(LexicalErrorKind::IncompleteUniversalCharacterName));
+        assert_eq!(universal_character_name(r"\U00e9"), Err(LexicalErrorKind::IncompleteUniversalCharacterName));
+        assert_eq!(universal_character_name(r"\u0041"), Err(LexicalErrorKind::InvalidUniversalCharacterName(0x41)));
+        assert_eq!(universal_character_name(r"\uD800"), Err(LexicalErrorKind::InvalidUniversalCharacterName(0xD800)));
+        assert_eq!(universal_character_name(r"\U00110000"), Err(LexicalErrorKind::InvalidUniversalCharacterName(0x110000)));
+        assert_eq!(universal_character_name_length(r"\u12;"), 4);
+    }
+
+    #[test]
+    fn decoded_identifiers() {
+        assert_eq!(decode_identifier("plain"), Cow::Borrowed("plain"));
+        assert_eq!(decode_identifier(r"caf\u00e9"), "café");
+        assert_eq!(decode_identifier(r"\U000065E5\u672c"), "日本");
+        assert_eq!(decode_identifier(r"a\u12"), r"a\u12");
+        assert_eq!(identifier_length(r"caf\u00e9 = 1"), 9);
+        assert_eq!(identifier_length(r"a\u0024"), 1);
+        assert_eq!(identifier_length(r"\u0660a"), 0);
+        assert_eq!(preprocessing_identifier_length(r"a\u12+"), 5);
+    }
+}
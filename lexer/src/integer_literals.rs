@@ -1,11 +1,8 @@
-use nom::IResult;
-use nom::types::CompleteStr;
-
-use crate::Token;
-use ast::Integer;
+use crate::{Token, LexicalErrorKind};
+use ast::{Integer, IntegerType, TargetInfo};
 
 #[derive(PartialEq, Eq)]
-enum Base {
+pub(crate) enum Base {
     Binary,
     Octal,
     Decimal,
@@ -21,44 +18,68 @@ impl Base {
             Base::Hex => 16
         }
     }
-
-    pub fn prefix(&self) -> &'static str {
-        match self {
-            Base::Binary => "0b",
-            Base::Octal => "0",
-            Base::Decimal => "",
-            Base::Hex => "0x"
-        }
-    }
 }
 
-#[derive(PartialEq, Eq)]
-enum ConstIntSize {
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ConstIntSize {
     Unspecified,
     Long,
     LongLong
 }
 
 #[derive(PartialEq, Eq)]
-enum ConstIntSign {
+pub(crate) enum ConstIntSign {
     Unspecified,
     Unsigned
 }
 
-pub fn integer_literal(input: CompleteStr) -> IResult<CompleteStr, Token, u32> {
-    let (input, (literal, base)) = literal(input)?;
-    let (input, (sign, size)) = integer_suffix(input)?;
-    let sign = match sign {
-        Some(_) => ConstIntSign::Unsigned,
-        None => ConstIntSign::Unspecified
+/// Scans the integer constant at the start of `s`, which begins with a digit, returning the token and its
+/// length in bytes.
+pub(crate) fn scan_integer(s: &str, target: TargetInfo) -> Result<(Token<'static>, usize), LexicalErrorKind> {
+    let bytes = s.as_bytes();
+    let digits = |from: usize, is_digit: fn(&u8) -> bool| bytes[from..].iter().take_while(|c| is_digit(c)).count();
+    let is_octal = |c: &u8| (b'0'..=b'7').contains(c);
+    let is_binary = |c: &u8| *c == b'0' || *c == b'1';
+
+    let prefix = if bytes[0] == b'0' { bytes.get(1).copied() } else { None };
+    let (base, start, len) = match prefix {
+        Some(b'x') | Some(b'X') if digits(2, u8::is_ascii_hexdigit) > 0 => (Base::Hex, 2, digits(2, u8::is_ascii_hexdigit)),
+        Some(_) if digits(1, is_octal) > 0 => (Base::Octal, 1, digits(1, is_octal)),
+        Some(b'b') | Some(b'B') if digits(2, is_binary) > 0 => (Base::Binary, 2, digits(2, is_binary)),
+        _ => (Base::Decimal, 0, digits(0, u8::is_ascii_digit))
     };
-    let size = match size.map(|v| v.0) {
-        Some("ll") | Some("LL") => ConstIntSize::LongLong,
-        Some("l") | Some("L") => ConstIntSize::Long,
+    let literal = &s[start..start + len];
+
+    let rest = &s[start + len..];
+    // an unsigned suffix and a size suffix, in either order
+    let suffix = rest.as_bytes();
+    let unsigned_at = |i: usize| matches!(suffix.get(i), Some(b'u') | Some(b'U'));
+    let long_at = |i: usize| match suffix.get(i..i + 2) {
+        Some(b"ll") | Some(b"LL") => 2,
+        _ if matches!(suffix.get(i), Some(b'l') | Some(b'L')) => 1,
+        _ => 0
+    };
+    let (unsigned, long) = if unsigned_at(0) {
+        (true, long_at(1))
+    }
+    else {
+        let long = long_at(0);
+        (unsigned_at(long), long)
+    };
+    let suffix_len = long + unsigned as usize;
+    let sign = if unsigned { ConstIntSign::Unsigned } else { ConstIntSign::Unspecified };
+    let size = match long {
+        2 => ConstIntSize::LongLong,
+        1 => ConstIntSize::Long,
         _ => ConstIntSize::Unspecified
     };
-    let value = convert(&literal, base, sign, size);
-    Ok((input, Token::IntLiteral(value)))
+    if rest[suffix_len..].starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        return Err(LexicalErrorKind::InvalidIntegerSuffix);
+    }
+    match convert(literal, base, sign, size, target) {
+        Some(value) => Ok((Token::IntLiteral(value), start + len + suffix_len)),
+        None => Err(LexicalErrorKind::IntegerLiteralTooLarge)
+    }
 }
 
 /// Converts a hex, decimal, octal, or binary integer literal with an optional unsigned specifier and
@@ -77,116 +98,40 @@ pub fn integer_literal(input: CompleteStr) -> IResult<CompleteStr, Token, u32> {
 ///  5. The type of the integer is the smallest type that can hold the value while following the previous 4 rules.
 ///
 /// Refer to section 6.4.4.1 of ISO/IEC 9899:1999 for a helpful chart.
-/// 
-/// The current implementation of this relies on the fact that invalid literals are rejected before being passed to this function,
-/// and the fact that the standard library from_str_radix functions will return an error on overflow and underflow.
-fn convert<'a>(value: &'a str, base: Base, sign: ConstIntSign, size: ConstIntSize) -> Integer {
-    if sign != ConstIntSign::Unsigned && size == ConstIntSize::Unspecified {
-        if let Ok(v) = i32::from_str_radix(value, base.radix()) {
-            return Integer::I32(v);
-        }
-    }
-    if (base != Base::Decimal || sign == ConstIntSign::Unsigned) && size == ConstIntSize::Unspecified {
-        if let Ok(v) = u32::from_str_radix(value, base.radix()) {
-            return Integer::U32(v);
-        }
-    }
-    if sign != ConstIntSign::Unsigned && size != ConstIntSize::LongLong {
-        if let Ok(v) = i64::from_str_radix(value, base.radix()) {
-            return Integer::I64(v);
-        }
-    }
-    if (base != Base::Decimal || sign == ConstIntSign::Unsigned) && size != ConstIntSize::LongLong {
-        if let Ok(v) = u64::from_str_radix(value, base.radix()) {
-            return Integer::U64(v);
-        }
-    }
-    if sign != ConstIntSign::Unsigned {
-        if let Ok(v) = i128::from_str_radix(value, base.radix()) {
-            return Integer::I128(v);
+///
+/// Returns `None` if the value is too large to fit into any of the available types.
+///
+/// The widths of ints, long ints and long long ints are given by the target's data model.
+pub(crate) fn convert(value: &str, base: Base, sign: ConstIntSign, size: ConstIntSize, target: TargetInfo) -> Option<Integer> {
+    let value = u128::from_str_radix(value, base.radix()).ok()?;
+    let types = [
+        (ConstIntSize::Unspecified, IntegerType::Int),
+        (ConstIntSize::Long, IntegerType::long(target)),
+        (ConstIntSize::LongLong, IntegerType::LongLong)
+    ];
+    for (_, type_) in types.iter().filter(|(type_size, _)| *type_size >= size) {
+        let unsigned_max = u128::MAX >> (128 - type_.bits());
+        if sign != ConstIntSign::Unsigned && value <= unsigned_max >> 1 {
+            return Some(Integer::new(*type_, value as i128));
         }
-    }
-    if base != Base::Decimal || sign == ConstIntSign::Unsigned {
-        if let Ok(v) = u128::from_str_radix(value, base.radix()) {
-            return Integer::U128(v);
+        if (base != Base::Decimal || sign == ConstIntSign::Unsigned) && value <= unsigned_max {
+            return Some(Integer::new(type_.to_unsigned(), value as i128));
         }
     }
-
-    // the value is too large to fit into any of the available types
-    // if it is a decimal int, truncate to a i128, otherwise truncate to a u128
-    // TODO: better warning (with source name & line:col numbers) & propogate warning up instead of printing to console here
-    println!("warning: constant integer literal {}{} truncated", base.prefix(), value);
-
-    if base == Base::Decimal {
-        Integer::I128(i128::max_value())
-    }
-    else {
-        Integer::U128(u128::max_value())
-    }
+    None
 }
 
-named!(literal(CompleteStr) -> (CompleteStr, Base), alt!(
-    hex_integer_literal | octal_integer_literal |
-    binary_integer_literal | decimal_integer_literal
-));
-
-named!(hex_integer_literal(CompleteStr) -> (CompleteStr, Base), do_parse!(
-    alt!(tag!("0x") | tag!("0X")) >>
-    literal: take_while1!(|c: char| c.is_digit(16)) >>
-    (literal, Base::Hex)
-));
-
-named!(decimal_integer_literal(CompleteStr) -> (CompleteStr, Base), do_parse!(
-    literal: take_while1!(|c: char| c.is_digit(10)) >>
-    ((literal, Base::Decimal))
-));
-
-named!(octal_integer_literal(CompleteStr) -> (CompleteStr, Base), do_parse!(
-    char!('0') >>
-    literal: take_while1!(|c: char| c.is_digit(8)) >>
-    (literal, Base::Octal)
-));
-
-// not part of c99 (or c11 for that matter),
-// but accepted by gcc as an extension
-named!(binary_integer_literal(CompleteStr) -> (CompleteStr, Base), do_parse!(
-    alt!(tag!("0b") | tag!("0B")) >>
-    literal: take_while1!(|c: char| c.is_digit(2)) >>
-    (literal, Base::Binary)
-));
-
-/// The first element in the returned tuple is the unsigned_suffix, and the
-/// second element is the size suffix (long or long long).
-named!(integer_suffix(CompleteStr) -> (Option<CompleteStr>,Option<CompleteStr>), alt!(
-    // sign before size
-    do_parse!(
-        sign: opt!(unsigned_suffix) >>
-        size: opt!(alt!(long_long_suffix | long_suffix)) >>
-        (sign, size)
-    ) |
-    // size before sign
-    do_parse!(
-        size: opt!(alt!(long_long_suffix | long_suffix)) >>
-        sign: opt!(unsigned_suffix) >>
-        (sign, size)
-    )
-));
-
-named!(unsigned_suffix(CompleteStr) -> CompleteStr, alt!(tag!("u") | tag!("U")));
-named!(long_suffix(CompleteStr) -> CompleteStr, alt!(tag!("l") | tag!("L")));
-named!(long_long_suffix(CompleteStr) -> CompleteStr, alt!(tag!("ll") | tag!("LL")));
-
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::Lexer;
-    use ast::Integer;
+    use ast::{Integer, IntegerType, DataModel};
 
     #[test]
     fn small_decimal_integer_literal() {
         let mut input = Lexer::new("test.c".into(), "254");
         let token = input.next().unwrap().unwrap().1;
-        assert_eq!(token, Token::IntLiteral(Integer::I32(254)));
+        assert_eq!(token, Token::IntLiteral(Integer::new(IntegerType::Int, 254)));
         assert_eq!(input.next(), None);
     }
 
@@ -194,7 +139,7 @@ mod test {
     fn big_decimal_integer_literal() {
         let mut input = Lexer::new("test.c".into(), "1000000000000000"); // too big to fit into i32
         let token = input.next().unwrap().unwrap().1;
-        assert_eq!(token, Token::IntLiteral(Integer::I64(1000000000000000)));
+        assert_eq!(token, Token::IntLiteral(Integer::new(IntegerType::Long { bits: 64 }, 1000000000000000)));
         assert_eq!(input.next(), None);
     }
 
@@ -202,44 +147,54 @@ mod test {
     fn unsigned_long_decimal_integer_literal() {
         let mut input = Lexer::new("test.c".into(), "17ul");
         let token = input.next().unwrap().unwrap().1;
-        assert_eq!(token, Token::IntLiteral(Integer::U64(17)));
+        assert_eq!(token, Token::IntLiteral(Integer::new(IntegerType::UnsignedLong { bits: 64 }, 17)));
+        assert_eq!(input.next(), None);
+    }
+
+    #[test]
+    fn size_suffix_before_unsigned_suffix() {
+        let mut input = Lexer::new("test.c".into(), "17lu 17LLU");
+        assert_eq!(input.next().unwrap().unwrap().1, Token::IntLiteral(Integer::new(IntegerType::UnsignedLong { bits: 64 }, 17)));
+        assert_eq!(input.next().unwrap().unwrap().1, Token::IntLiteral(Integer::new(IntegerType::UnsignedLongLong, 17)));
         assert_eq!(input.next(), None);
     }
 
     #[test]
     fn decimal_u32_max() {
         // this is i32::max_value() + 1, and since it as a decimal int without the unsigned specifier,
-        // it turns into an i64 (long int).
+        // it turns into a long int.
         let source = "2147483648"; 
         let mut input = Lexer::new("test.c".into(), source);
         let token = input.next().unwrap().unwrap().1;
-        assert_eq!(token, Token::IntLiteral(Integer::I64(2147483648)));
+        assert_eq!(token, Token::IntLiteral(Integer::new(IntegerType::Long { bits: 64 }, 2147483648)));
         assert_eq!(input.next(), None);
     }
 
     #[test]
     fn decimal_u32_max_unsigned_specifier() {
-        // this is i32::max_value() + 1, since we specify that it is unsigned, the concrete type should be u32.
+        // this is i32::max_value() + 1, since we specify that it is unsigned, the concrete type should be unsigned int.
         let source = "2147483648u"; 
         let mut input = Lexer::new("test.c".into(), source);
         let token = input.next().unwrap().unwrap().1;
-        assert_eq!(token, Token::IntLiteral(Integer::U32(2147483648)));
+        assert_eq!(token, Token::IntLiteral(Integer::new(IntegerType::UnsignedInt, 2147483648)));
         assert_eq!(input.next(), None);
     }
 
+    #[test]
     fn long_long_decimal_integer_literal() {
         let source = "22ll";
         let mut input = Lexer::new("test.c".into(), source);
         let token = input.next().unwrap().unwrap().1;
-        assert_eq!(token, Token::IntLiteral(Integer::I128(22)));
+        assert_eq!(token, Token::IntLiteral(Integer::new(IntegerType::LongLong, 22)));
         assert_eq!(input.next(), None);
     }
 
+    #[test]
     fn unsigned_long_long_decimal_integer_literal() {
         let source = "22llu"; // order of sign and size specifiers don't matter
         let mut input = Lexer::new("test.c".into(), source);
         let token = input.next().unwrap().unwrap().1;
-        assert_eq!(token, Token::IntLiteral(Integer::U128(22)));
+        assert_eq!(token, Token::IntLiteral(Integer::new(IntegerType::UnsignedLongLong, 22)));
         assert_eq!(input.next(), None);
     }
 
@@ -248,7 +203,7 @@ mod test {
         let source = "0x100";
         let mut input = Lexer::new("test.c".into(), source);
         let token = input.next().unwrap().unwrap().1;
-        assert_eq!(token, Token::IntLiteral(Integer::I32(0x100)));
+        assert_eq!(token, Token::IntLiteral(Integer::new(IntegerType::Int, 0x100)));
         assert_eq!(input.next(), None);
     }
 
@@ -257,7 +212,7 @@ mod test {
         let source = "014";
         let mut input = Lexer::new("test.c".into(), source);
         let token = input.next().unwrap().unwrap().1;
-        assert_eq!(token, Token::IntLiteral(Integer::I32(12)));
+        assert_eq!(token, Token::IntLiteral(Integer::new(IntegerType::Int, 12)));
         assert_eq!(input.next(), None);
     }
 
@@ -266,7 +221,35 @@ mod test {
         let source = "0b11110000"; // 0xF0
         let mut input = Lexer::new("test.c".into(),source);
         let token = input.next().unwrap().unwrap().1;
-        assert_eq!(token, Token::IntLiteral(Integer::I32(0xF0)));
+        assert_eq!(token, Token::IntLiteral(Integer::new(IntegerType::Int, 0xF0)));
         assert_eq!(input.next(), None);
     }
-}
\ No newline at end of file
+
+    fn lex_with_model(source: &str, data_model: DataModel) -> Vec<Token<'_>> {
+        let mut lexer = Lexer::new("test.c".into(), source);
+        lexer.set_target(TargetInfo::new(data_model));
+        lexer.map(|token| token.unwrap().1).collect()
+    }
+
+    #[test]
+    fn literal_types_depend_on_the_data_model() {
+        let source = "0x80000000 2147483648 1L 0x80000000L 4294967296L";
+        assert_eq!(lex_with_model(source, DataModel::LP64), vec![
+            Token::IntLiteral(Integer::new(IntegerType::UnsignedInt, 0x80000000)),
+            Token::IntLiteral(Integer::new(IntegerType::Long { bits: 64 }, 2147483648)),
+            Token::IntLiteral(Integer::new(IntegerType::Long { bits: 64 }, 1)),
+            Token::IntLiteral(Integer::new(IntegerType::Long { bits: 64 }, 0x80000000)),
+            Token::IntLiteral(Integer::new(IntegerType::Long { bits: 64 }, 4294967296))
+        ]);
+        // long is 32 bits wide, so values that don't fit in an int or long become long long
+        for &data_model in &[DataModel::LLP64, DataModel::ILP32] {
+            assert_eq!(lex_with_model(source, data_model), vec![
+                Token::IntLiteral(Integer::new(IntegerType::UnsignedInt, 0x80000000)),
+                Token::IntLiteral(Integer::new(IntegerType::LongLong, 2147483648)),
+                Token::IntLiteral(Integer::new(IntegerType::Long { bits: 32 }, 1)),
+                Token::IntLiteral(Integer::new(IntegerType::UnsignedLong { bits: 32 }, 0x80000000)),
+                Token::IntLiteral(Integer::new(IntegerType::LongLong, 4294967296))
+            ]);
+        }
+    }
+}
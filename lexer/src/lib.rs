@@ -1,34 +1,38 @@
+#[cfg(any(test, feature = "reference"))]
 #[macro_use] extern crate nom;
+#[cfg(any(test, feature = "reference"))]
 #[macro_use] extern crate lazy_static;
 
 use std::ffi::OsString;
-use std::collections::HashSet;
 use std::rc::Rc;
 
-use nom::types::CompleteStr;
-
 mod tokens;
 mod error;
 mod integer_literals;
+mod float_literals;
+mod character_constants;
+mod identifiers;
+mod typedef_names;
+mod source_map;
+mod trivia;
+mod scanner;
+#[cfg(any(test, feature = "reference"))]
+mod reference;
 
 #[cfg(test)]
 mod tests;
 
-use self::integer_literals::integer_literal;
-pub use error::InvalidToken;
+use self::scanner::Scan;
+use self::character_constants::{escape_sequence_length, escape_sequence_fits, code_unit_bits};
+use self::identifiers::{universal_character_name, universal_character_name_length};
+pub use error::{LexicalError, LexicalErrorKind};
 pub use self::tokens::Token;
-use ast::Location;
-
-lazy_static! {
-    static ref KEYWORDS: HashSet<&'static str> = {
-        ["goto", "continue", "break", "return", "inline", "typedef",
-        "extern", "static", "auto", "register", "const", "restrict",
-        "volatile", "void", "char", "short", "int", "long", "float",
-        "double", "signed", "unsigned", "sizeof", "struct", "union",
-        "enum", "switch", "else", "case", "default", "while", "for",
-        "do", "if"].iter().cloned().collect()
-    };
-}
+pub use self::typedef_names::TypedefNames;
+pub use self::source_map::{SourceMap, SourceOptions};
+pub use self::trivia::{Trivia, TriviaKind, TriviaToken, TriviaTokens};
+pub use self::character_constants::decode_string_literal;
+pub use self::identifiers::{decode_identifier, is_identifier_start, is_identifier_continue, preprocessing_identifier_length};
+use ast::{Location, TargetInfo};
 
 pub type Spanned<Token, Loc, Error> = Result<(Loc, Token, Loc), Error>;
 
@@ -43,48 +47,177 @@ pub struct Lexer<'input> {
     line: usize,
 
     /// The offset from the beginning of the file to the beginning of the current line.
-    column: usize
+    column: usize,
+
+    /// Used to tell typedef names apart from other identifiers, shared with the parser
+    typedef_names: TypedefNames,
+
+    /// Maps locations back to the physical source, when the source has gone through translation phases 1 and 2
+    source_map: Option<&'input SourceMap<'input>>,
+
+    /// Whether invalid input is turned into error tokens instead of ending the token stream
+    recover_errors: bool,
+
+    /// Recognizes the token at the start of the remaining input
+    scan: fn(&str, TargetInfo) -> Scan<'_>,
+
+    /// Determines the types of integer constants
+    target: TargetInfo
 }
 
 impl<'input> Lexer<'input> {
     pub fn new(source_filename: OsString, source: &'input str) -> Lexer<'input> {
+        Lexer::with_typedef_names(source_filename, source, TypedefNames::new())
+    }
+
+    /// Creates a lexer that classifies the identifiers declared in `typedef_names` as typedef names
+    pub fn with_typedef_names(source_filename: OsString, source: &'input str, typedef_names: TypedefNames) -> Lexer<'input> {
         Lexer {
             source_filename: Rc::new(source_filename),
             source,
             offset: 0,
             line: 1,
-            column: 1
+            column: 1,
+            typedef_names,
+            source_map: None,
+            recover_errors: false,
+            scan: scanner::scan,
+            target: TargetInfo::default()
         }
     }
 
-    pub fn advance(&mut self) -> Option<Spanned<Token<'input>, Location, InvalidToken>> {
+    /// Creates a lexer for the logical source of `source_map` (after line splicing and trigraph replacement),
+    /// which reports the locations of tokens in the physical source.
+    pub fn with_source_map(source_filename: OsString, source_map: &'input SourceMap<'input>, typedef_names: TypedefNames) -> Lexer<'input> {
+        let mut lexer = Lexer::with_typedef_names(source_filename, source_map.logical(), typedef_names);
+        lexer.source_map = Some(source_map);
+        lexer
+    }
+
+    /// The typedef names used by this lexer, which the parser must declare typedef names in
+    pub fn typedef_names(&self) -> &TypedefNames {
+        &self.typedef_names
+    }
+
+    /// Makes the lexer keep going after invalid input: instead of returning an error, it produces a
+    /// `Token::Error` that covers the invalid input, and resumes at the next whitespace or punctuation.
+    /// This lets tools such as editors report every lexical error in a file at once.
+    pub fn enable_error_recovery(&mut self) {
+        self.recover_errors = true;
+    }
+
+    /// Sets the target that determines the types of integer constants (64 bit Linux by default)
+    pub fn set_target(&mut self, target: TargetInfo) {
+        self.target = target;
+    }
+
+    /// Makes the lexer use the original nom based implementation to recognize tokens, which is much slower
+    /// than the default scanner. Only meant for comparing the two, it requires the `reference` feature.
+    #[cfg(any(test, feature = "reference"))]
+    pub fn with_reference_scanner(mut self) -> Lexer<'input> {
+        self.scan = reference::scan;
+        self
+    }
+
+    /// Turns this lexer into one that keeps the whitespace, comments and line continuations between
+    /// tokens, so that the source can be reconstructed from the tokens. Error recovery is enabled, so
+    /// invalid input becomes error tokens instead of ending the token stream.
+    pub fn with_trivia(self) -> TriviaTokens<'input> {
+        TriviaTokens::new(self)
+    }
+
+    /// The source before translation phases 1 and 2, which token locations refer to
+    pub(crate) fn physical_source(&self) -> &'input str {
+        match self.source_map {
+            Some(map) => map.physical(),
+            None => self.source
+        }
+    }
+
+    /// The location of the end of the file
+    pub fn end_location(&self) -> Location {
+        self.location_at(self.source.len())
+    }
+
+    pub fn advance(&mut self) -> Option<Spanned<Token<'input>, Location, LexicalError>> {
+        match self.next_token() {
+            Some(Err(error)) if self.recover_errors => Some(Ok(self.error_token(error))),
+            result => result
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Spanned<Token<'input>, Location, LexicalError>> {
         if self.offset >= self.source.len() {
             return None;
         }
-        let s = &self.source[self.offset..].trim_left();
-        let new_offset = self.offset + ((self.source.len() - self.offset) - s.len());
-        self.update_location(self.offset, new_offset);
-        self.offset = new_offset;
-        let slice = CompleteStr(&self.source[self.offset..]);
-
-        if slice.len() == 0 {
+        if let Err(err) = self.skip_whitespace_and_comments() {
+            return Some(Err(err));
+        }
+        let s = &self.source[self.offset..];
+        if s.is_empty() {
             return None;
         }
 
         let start = self.location();
-        match token(slice) {
-            Ok((input,token)) => {
-                let token_len = s.len() - input.len();
-                let new_offset = self.offset + token_len;
+        match (self.scan)(s, self.target) {
+            Ok((token, len)) => {
+                let new_offset = self.offset + len;
                 self.update_location(self.offset, new_offset);
                 self.offset = new_offset;
-                return Some(Ok((start, token, self.location())));
+                Some(Ok((start, self.typedef_names.classify(token), self.location())))
             },
-            Err(_err) => {
+            Err(Some(kind)) => Some(Err(self.error(kind, 0, pp_number_length(s)))),
+            Err(None) => {
                 // invalid (or incomplete) token
-                return Some(Err(InvalidToken::from_offset(self.location(), self.source)));
+                let (kind, offset, len) = diagnose(s, self.target);
+                Some(Err(self.error(kind, offset, len)))
             }
-        };
+        }
+    }
+
+    /// Skips over any whitespace and comments preceding the next token.
+    ///
+    /// Comments are treated as whitespace (C99 5.1.1.2, translation phase 3). Block comments don't nest,
+    /// and a block comment that runs until the end of the file is reported as an error. When the source
+    /// hasn't gone through a `SourceMap`, a backslash-newline between tokens is skipped as well.
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), LexicalError> {
+        loop {
+            let rest = &self.source[self.offset..];
+            let trimmed = rest.trim_start();
+            let mut new_offset = self.offset + (rest.len() - trimmed.len());
+
+            if trimmed.starts_with("//") {
+                // line comment, ends at (but doesn't include) the next newline
+                new_offset += trimmed.find('\n').unwrap_or(trimmed.len());
+            }
+            else if trimmed.starts_with("\\\n") || trimmed.starts_with("\\\r\n") {
+                // line continuation, which would have been spliced away in translation phase 2
+                new_offset += trimmed.find('\n').unwrap() + 1;
+            }
+            else if let Some(comment) = trimmed.strip_prefix("/*") {
+                match comment.find("*/") {
+                    Some(end) => new_offset += end + 4,
+                    None => {
+                        self.update_location(self.offset, new_offset);
+                        self.offset = new_offset;
+                        let err = self.error(LexicalErrorKind::UnterminatedComment, 0, 2);
+
+                        // there is nothing left to lex after an unterminated comment
+                        self.update_location(self.offset, self.source.len());
+                        self.offset = self.source.len();
+                        return Err(err);
+                    }
+                }
+            }
+            else {
+                self.update_location(self.offset, new_offset);
+                self.offset = new_offset;
+                return Ok(());
+            }
+
+            self.update_location(self.offset, new_offset);
+            self.offset = new_offset;
+        }
     }
 
     /// Updates the line and column fields by counting newlines that have recently been lexed
@@ -101,13 +234,51 @@ impl<'input> Lexer<'input> {
         }
     }
 
+    /// Skips over the invalid input at the current position, turning it into an error token
+    fn error_token(&mut self, error: LexicalError) -> (Location, Token<'input>, Location) {
+        let len = resynchronize(&self.source[self.offset..]);
+        let start = if len == 0 { error.start.clone() } else { self.location() };
+        let spelling = &self.source[self.offset..self.offset + len];
+        self.update_location(self.offset, self.offset + len);
+        self.offset += len;
+        (start, Token::Error{ spelling, error: Box::new(error) }, self.location())
+    }
+
+    /// Creates an error for the `len` bytes starting `offset` bytes after the current position
+    fn error(&self, kind: LexicalErrorKind, offset: usize, len: usize) -> LexicalError {
+        let start = self.location_at(self.offset + offset);
+        let end = self.location_at(self.offset + offset + len);
+        match self.source_map {
+            Some(map) => LexicalError::new(kind, start, end, map.physical()),
+            None => LexicalError::new(kind, start, end, self.source)
+        }
+    }
+
+    /// The location of the byte at `offset`, which must not come before the current position
+    fn location_at(&self, offset: usize) -> Location {
+        match self.source_map {
+            Some(map) => map.location(&self.source_filename, offset),
+            None => {
+                let skipped = &self.source[self.offset..offset];
+                let (line, column) = match skipped.rfind('\n') {
+                    Some(i) => (self.line + skipped.matches('\n').count(), skipped[i + 1..].chars().count() + 1),
+                    None => (self.line, self.column + skipped.chars().count())
+                };
+                Location::new(self.source_filename.clone(), line, column, offset)
+            }
+        }
+    }
+
     fn location(&self) -> Location {
-        Location::new(self.source_filename.clone(), self.line, self.column, self.offset)
+        match self.source_map {
+            Some(map) => map.location(&self.source_filename, self.offset),
+            None => Location::new(self.source_filename.clone(), self.line, self.column, self.offset)
+        }
     }
 }
 
 impl<'a> Iterator for Lexer<'a> {
-    type Item = Spanned<Token<'a>, Location, InvalidToken>;
+    type Item = Spanned<Token<'a>, Location, LexicalError>;
     
     /// Get the next token from the source file
     fn next(&mut self) -> Option<Self::Item> {
@@ -115,226 +286,99 @@ impl<'a> Iterator for Lexer<'a> {
     }
 }
 
-// a macro to generate a named parser from a string literal to a token
-macro_rules! recognize_tag {
-    ($name: ident, $s: tt, $token: expr) => {
-        named!($name(CompleteStr) -> Token, do_parse!(
-            tag!($s) >>
-            ($token)
-        ));
-    };
+/// Works out why no token could be formed at the start of `s`, returning the kind of error along with
+/// the offset and length (in bytes) of the offending part of the input.
+fn diagnose(s: &str, target: TargetInfo) -> (LexicalErrorKind, usize, usize) {
+    let quoted = s.strip_prefix('L').filter(|rest| rest.starts_with(['"', '\''])).unwrap_or(s);
+    let prefix_len = s.len() - quoted.len();
+    let first = quoted.chars().next().unwrap();
+    match first {
+        '"' | '\'' => {
+            let unterminated = if first == '"' { LexicalErrorKind::UnterminatedString } else { LexicalErrorKind::UnterminatedCharacterConstant };
+            let mut i = 1;
+            while i < quoted.len() {
+                let rest = &quoted[i..];
+                if rest.starts_with(first) {
+                    // a string literal or character constant can only fail to end at its closing quote if it is empty
+                    return (LexicalErrorKind::EmptyCharacterConstant, 0, prefix_len + i + 1);
+                }
+                else if rest.starts_with('\n') {
+                    break;
+                }
+                else if let Some(escaped) = rest.strip_prefix('\\') {
+                    match escape_sequence_length(rest) {
+                        Some(len) if !escape_sequence_fits(&rest[..len], code_unit_bits(prefix_len == 1, target)) => {
+                            return (LexicalErrorKind::InvalidEscapeSequence, prefix_len + i, len);
+                        },
+                        Some(len) => i += len,
+                        None if escaped.starts_with(['u', 'U']) => {
+                            let kind = universal_character_name(rest).expect_err("only invalid universal character names are rejected");
+                            return (kind, prefix_len + i, universal_character_name_length(rest));
+                        },
+                        None => {
+                            let len = 1 + escaped.chars().next().filter(|&c| c != '\n').map_or(0, char::len_utf8);
+                            return (LexicalErrorKind::InvalidEscapeSequence, prefix_len + i, len);
+                        }
+                    }
+                }
+                else {
+                    i += rest.chars().next().unwrap().len_utf8();
+                }
+            }
+            (unterminated, 0, prefix_len + i)
+        },
+        c if c.is_ascii_digit() => (LexicalErrorKind::InvalidIntegerSuffix, 0, pp_number_length(s)),
+        // a universal character name that doesn't start an identifier
+        '\\' if s[1..].starts_with(['u', 'U']) => match universal_character_name(s) {
+            Ok((c, len)) => (LexicalErrorKind::CharacterNotAllowedInIdentifier(c), 0, len),
+            Err(kind) => (kind, 0, universal_character_name_length(s))
+        },
+        c => (LexicalErrorKind::StrayCharacter(c), 0, c.len_utf8())
+    }
 }
 
-macro_rules! recognize_char {
-    ($name: ident, $c: tt, $token: expr) => {
-        named!($name(CompleteStr) -> Token, do_parse!(
-            char!($c) >>
-            ($token)
-        ));
-    };
+/// The length of the invalid input at the start of `s` that is skipped when recovering from an error.
+///
+/// A string literal or character constant is skipped up to its closing quote (or the end of the line),
+/// a number up to the end of the preprocessing number, and anything else up to the next whitespace,
+/// punctuation or quote.
+fn resynchronize(s: &str) -> usize {
+    let quoted = s.strip_prefix('L').filter(|rest| rest.starts_with(['"', '\''])).unwrap_or(s);
+    let prefix_len = s.len() - quoted.len();
+    let mut chars = quoted.char_indices();
+    match chars.next() {
+        None => 0,
+        Some((_, quote)) if quote == '"' || quote == '\'' => {
+            let mut escaped = false;
+            for (i, c) in chars {
+                match c {
+                    '\n' => return prefix_len + i,
+                    '\\' if !escaped => escaped = true,
+                    c if c == quote && !escaped => return prefix_len + i + 1,
+                    _ => escaped = false
+                }
+            }
+            s.len()
+        },
+        Some((_, c)) if c.is_ascii_digit() => pp_number_length(s),
+        Some(_) => {
+            let is_boundary = |c: char| c.is_whitespace() || "[](){}.-+&*~!/%<>=^|?:;,#\"'".contains(c);
+            chars.find(|&(_, c)| is_boundary(c)).map_or(s.len(), |(i, _)| i)
+        }
+    }
 }
 
-named!(token(CompleteStr) -> Token, alt!(
-    punctuation | string_literal | ident | keyword | integer_literal
-));
-
-named!(punctuation(CompleteStr) -> Token, alt!(
-    alt!( /* 3 char long tokens */
-        shl_eq | shr_eq
-    ) |
-    alt!( /* 2 char long tokens */
-        equality | not_equal | less_than_or_equal_to |
-        greater_than_or_equal_to | increment| decrement |
-        shl | shr | and_and | or_or | mult_eq | div_eq |
-        mod_eq | plus_eq | minus_eq | and_eq | xor_eq |
-        or_eq
-    ) |
-    alt!( /* single char tokens */
-        l_paren | r_paren | l_brace | r_brace | l_bracket |
-        r_bracket | semicolon | comma | ellipsis | dot |
-        star | arrow | ampersand | plus | minus | tilde |
-        exclamation | slash | modulo | less_than | greater_than |
-        caret | vertical_bar | colon | question_mark | equal
-    )
-));
-
-named!(keyword(CompleteStr) -> Token, alt!(
-    jump_keywords | inline | typedef |
-    _extern | _static | auto | register | _const | restrict |
-    volatile | sizeof | type_keywords |
-    _if | _else | _switch | case | default | loop_keywords
-));
-
-named!(type_keywords(CompleteStr) -> Token, alt!(
-    void | char | short | int | long | float | double |
-    signed | unsigned | _struct | _union | _enum
-));
-
-named!(jump_keywords(CompleteStr) -> Token, alt!(
-    _continue | _break | _goto | _return
-));
-
-named!(loop_keywords(CompleteStr) -> Token, alt!(
-    _while | _for | _do
-));
-
-/* punctuation */
-recognize_char!(l_paren, '(', Token::LParen);
-recognize_char!(r_paren, ')', Token::RParen);
-recognize_char!(l_brace, '{', Token::LBrace);
-recognize_char!(r_brace, '}', Token::RBrace);
-recognize_char!(l_bracket, '[', Token::LBracket);
-recognize_char!(r_bracket, ']', Token::RBracket);
-recognize_char!(semicolon, ';', Token::Semicolon);
-recognize_char!(comma, ',', Token::Comma);
-recognize_tag!(ellipsis, "...", Token::Ellipsis);
-recognize_char!(dot, '.', Token::Dot);
-recognize_char!(star, '*', Token::Star);
-recognize_tag!(arrow, "->", Token::Arrow);
-recognize_tag!(increment, "++", Token::Increment);
-recognize_tag!(decrement, "--", Token::Decrement);
-recognize_char!(ampersand, '&', Token::Ampersand);
-recognize_char!(plus, '+', Token::Plus);
-recognize_char!(minus, '-', Token::Minus);
-recognize_char!(tilde, '~', Token::Tilde);
-recognize_char!(exclamation, '!', Token::Exclamation);
-recognize_char!(slash, '/', Token::Slash);
-recognize_char!(modulo, '%', Token::Modulo);
-recognize_char!(caret, '^', Token::Caret);
-recognize_char!(vertical_bar, '|', Token::VerticalBar);
-recognize_char!(colon, ':', Token::Colon);
-recognize_char!(question_mark, '?', Token::Question);
-recognize_tag!(shl, "<<", Token::Shl);
-recognize_tag!(shr, ">>", Token::Shr);
-recognize_char!(less_than, '<', Token::LessThan);
-recognize_char!(greater_than, '>', Token::GreaterThan);
-recognize_tag!(less_than_or_equal_to, "<=", Token::LessThanOrEqualTo);
-recognize_tag!(greater_than_or_equal_to, ">=", Token::GreaterThanOrEqualTo);
-recognize_tag!(equality, "==", Token::Equality);
-recognize_tag!(not_equal, "!=", Token::NotEqual);
-recognize_tag!(and_and, "&&", Token::AndAnd);
-recognize_tag!(or_or, "||", Token::OrOr);
-
-/* assignment operators */
-recognize_char!(equal, '=', Token::Equal);
-recognize_tag!(mult_eq, "*=", Token::MultEq);
-recognize_tag!(div_eq, "/=", Token::DivEq);
-recognize_tag!(mod_eq, "%=", Token::ModEq);
-recognize_tag!(plus_eq, "+=", Token::PlusEq);
-recognize_tag!(minus_eq, "-=", Token::MinusEq);
-recognize_tag!(shl_eq, "<<=", Token::ShlEq);
-recognize_tag!(shr_eq, ">>=", Token::ShrEq);
-recognize_tag!(and_eq, "&=", Token::AndEq);
-recognize_tag!(xor_eq, "^=", Token::XorEq);
-recognize_tag!(or_eq, "|=", Token::OrEq);
-
-/* jump keywords */
-recognize_tag!(_goto, "goto", Token::Goto);
-recognize_tag!(_continue, "continue", Token::Continue);
-recognize_tag!(_break, "break", Token::Break);
-recognize_tag!(_return, "return", Token::Return);
-
-recognize_tag!(_if, "if", Token::If);
-recognize_tag!(_else, "else", Token::Else);
-recognize_tag!(_switch, "switch", Token::Switch);
-
-recognize_tag!(case, "case", Token::Case);
-recognize_tag!(default, "default", Token::Default);
-
-/* loop keywords */
-recognize_tag!(_while, "while", Token::While);
-recognize_tag!(_do, "do", Token::Do);
-recognize_tag!(_for, "for", Token::For);
-
-/* function specifier */
-recognize_tag!(inline, "inline", Token::Inline);
-
-/* storage class specifiers */
-recognize_tag!(typedef, "typedef", Token::Typedef);
-recognize_tag!(_extern, "extern", Token::Extern);
-recognize_tag!(_static, "static", Token::Static);
-recognize_tag!(auto, "auto", Token::Auto);
-recognize_tag!(register, "register", Token::Register);
-
-/* type qualifier */
-recognize_tag!(_const, "const", Token::Const);
-recognize_tag!(restrict, "restrict", Token::Restrict);
-recognize_tag!(volatile, "volatile", Token::Volatile);
-
-/* struct or union */
-recognize_tag!(_struct, "struct", Token::Struct);
-recognize_tag!(_union, "union", Token::Union);
-
-recognize_tag!(_enum, "enum", Token::Enum);
-
-/* type specifier */
-recognize_tag!(void, "void", Token::Void);
-recognize_tag!(char, "char", Token::Char);
-recognize_tag!(short, "short", Token::Short);
-recognize_tag!(int, "int", Token::Int);
-recognize_tag!(long, "long", Token::Long);
-recognize_tag!(float, "float", Token::Float);
-recognize_tag!(double, "double", Token::Double);
-recognize_tag!(signed, "signed", Token::Signed);
-recognize_tag!(unsigned, "unsigned", Token::Unsigned);
-
-// misc
-recognize_tag!(sizeof, "sizeof", Token::SizeOf);
-
-/* the identifier can not be a reserved word */
-named!(ident(CompleteStr) -> Token, do_parse!(
-    peek!(alt!(nom::alpha | tag!("_"))) >>
-    ident: verify!(take_while1!(|c: char| c.is_alphanumeric() || c == '_'), |s: CompleteStr| !is_keyword(&s)) >>
-    (Token::Identifier(&ident))
-));
-
-fn is_keyword<'a>(s: &'a str) -> bool {
-    KEYWORDS.contains(s)
+/// The length of the preprocessing number (C99 6.4.8) at the start of `s`
+fn pp_number_length(s: &str) -> usize {
+    let mut len = 0;
+    let mut previous = ' ';
+    for c in s.chars() {
+        let exponent_sign = (c == '+' || c == '-') && "eEpP".contains(previous);
+        if !(c.is_alphanumeric() || c == '_' || c == '.' || exponent_sign) {
+            break;
+        }
+        len += c.len_utf8();
+        previous = c;
+    }
+    len.max(1)
 }
-
-named!(string_literal(CompleteStr) -> Token, do_parse!(
-    wide: opt!(char!('L')) >>
-    char!('"') >>
-    s: recognize!(many0!(s_char)) >>
-    char!('"') >>
-    (Token::StringLiteral{ wide: wide.is_some(), contents: &s })
-));
-
-named!(s_char(CompleteStr) -> CompleteStr, alt!(
-        recognize!(none_of!("\"\\\n")) |
-        escape_sequence
-));
-
-named!(escape_sequence(CompleteStr) -> CompleteStr, alt!(
-    /* simple escape sequence */
-    alt!(
-        tag!("\\'") |
-        tag!("\\\"") |
-        tag!("\\?") |
-        tag!("\\a") |
-        tag!("\\b") |
-        tag!("\\f") |
-        tag!("\\n") |
-        tag!("\\r") |
-        tag!("\\t") |
-        tag!("\\v")
-    ) |
-    /* octal escape sequence */
-    recognize!(do_parse!(
-        tag!("\\") >>
-        take_while1!(|c: char| c.is_digit(8)) >>
-        ()
-    )) |
-    /* hexadecimal escape sequence */
-    recognize!(do_parse!(
-        tag!("\\x") >>
-        take_while1!(|c: char| c.is_digit(16)) >>
-        ()
-    )) |
-    /* universal character name */
-    recognize!(do_parse!(
-        tag!("\\u") >>
-        take_while1!(|c: char| c.is_digit(16)) >>
-        ()
-    ))
-));
@@ -0,0 +1,537 @@
+//! The original lexer, built from nom parsers. It has been replaced by the hand-written scanner in
+//! `scanner`, and is kept to check that both produce the same tokens (see `Lexer::with_reference_scanner`).
+//! It is only built for tests and with the `reference` feature, so nom isn't a dependency of the lexer otherwise.
+
+use std::collections::HashSet;
+
+use nom::IResult;
+use nom::types::CompleteStr;
+
+use nom::{Context, ErrorKind};
+
+use ast::{Symbol, FloatType, TargetInfo};
+
+use crate::{Token, LexicalErrorKind};
+use crate::scanner::Scan;
+use crate::integer_literals::{self, Base, ConstIntSign, ConstIntSize};
+use crate::float_literals;
+use crate::character_constants::{character_value, code_unit_bits, escape_sequence_fits};
+use crate::identifiers::{is_identifier_start, is_identifier_continue, decode_identifier};
+
+lazy_static! {
+    static ref KEYWORDS: HashSet<&'static str> = {
+        ["goto", "continue", "break", "return", "inline", "typedef",
+        "extern", "static", "auto", "register", "const", "restrict",
+        "volatile", "void", "char", "short", "int", "long", "float",
+        "double", "signed", "unsigned", "sizeof", "struct", "union",
+        "enum", "switch", "else", "case", "default", "while", "for",
+        "do", "if", "_Bool", "_Complex", "_Imaginary", "_Alignas", "_Alignof",
+        "_Atomic", "_Generic", "_Noreturn", "_Static_assert", "_Thread_local"].iter().cloned().collect()
+    };
+}
+
+/// Recognizes the token at the start of `s`
+pub(crate) fn scan(s: &str, target: TargetInfo) -> Scan<'_> {
+    match token(CompleteStr(s), target) {
+        Ok((rest, token)) => Ok((token, s.len() - rest.len())),
+        Err(nom::Err::Failure(nom::Context::Code(_, nom::ErrorKind::Custom(code)))) => Err(Some(match code {
+            FLOAT_OUT_OF_RANGE => LexicalErrorKind::FloatConstantOutOfRange,
+            INVALID_INTEGER_SUFFIX => LexicalErrorKind::InvalidIntegerSuffix,
+            INTEGER_TOO_LARGE => LexicalErrorKind::IntegerLiteralTooLarge,
+            _ => unreachable!("unknown lexer error code {}", code)
+        })),
+        Err(_) => Err(None)
+    }
+}
+
+// a macro to generate a named parser from a string literal to a token
+macro_rules! recognize_tag {
+    ($name: ident, $s: tt, $token: expr) => {
+        named!($name(CompleteStr) -> Token, do_parse!(
+            tag!($s) >>
+            ($token)
+        ));
+    };
+}
+
+macro_rules! recognize_char {
+    ($name: ident, $c: tt, $token: expr) => {
+        named!($name(CompleteStr) -> Token, do_parse!(
+            char!($c) >>
+            ($token)
+        ));
+    };
+}
+
+fn token(input: CompleteStr, target: TargetInfo) -> IResult<CompleteStr, Token, u32> {
+    alt!(input,
+        float_literal | punctuation | call!(string_literal, target) | call!(character_constant, target) | ident | keyword | call!(integer_literal, target)
+    )
+}
+
+named!(punctuation(CompleteStr) -> Token, alt!(
+    alt!( /* 3 char long tokens */
+        shl_eq | shr_eq
+    ) |
+    alt!( /* 2 char long tokens */
+        equality | not_equal | less_than_or_equal_to |
+        greater_than_or_equal_to | increment| decrement |
+        shl | shr | and_and | or_or | mult_eq | div_eq |
+        mod_eq | plus_eq | minus_eq | and_eq | xor_eq |
+        or_eq | digraphs
+    ) |
+    alt!( /* single char tokens */
+        l_paren | r_paren | l_brace | r_brace | l_bracket |
+        r_bracket | semicolon | comma | ellipsis | dot |
+        star | arrow | ampersand | plus | minus | tilde |
+        exclamation | slash | modulo | less_than | greater_than |
+        caret | vertical_bar | colon | question_mark | equal
+    )
+));
+
+/* alternative spellings of brackets and braces (C99 6.4.6p3) */
+named!(digraphs(CompleteStr) -> Token, alt!(
+    l_bracket_digraph | r_bracket_digraph | l_brace_digraph | r_brace_digraph
+));
+
+named!(keyword(CompleteStr) -> Token, alt!(
+    jump_keywords | inline | typedef |
+    _extern | _static | auto | register | _const | restrict |
+    volatile | sizeof | type_keywords |
+    _if | _else | _switch | case | default | loop_keywords |
+    c11_keywords
+));
+
+named!(c11_keywords(CompleteStr) -> Token, alt!(
+    _bool | complex | imaginary | alignas | alignof | atomic |
+    generic | noreturn | static_assert | thread_local
+));
+
+named!(type_keywords(CompleteStr) -> Token, alt!(
+    void | char | short | int | long | float | double |
+    signed | unsigned | _struct | _union | _enum
+));
+
+named!(jump_keywords(CompleteStr) -> Token, alt!(
+    _continue | _break | _goto | _return
+));
+
+named!(loop_keywords(CompleteStr) -> Token, alt!(
+    _while | _for | _do
+));
+
+/* punctuation */
+recognize_char!(l_paren, '(', Token::LParen);
+recognize_char!(r_paren, ')', Token::RParen);
+recognize_char!(l_brace, '{', Token::LBrace);
+recognize_char!(r_brace, '}', Token::RBrace);
+recognize_char!(l_bracket, '[', Token::LBracket);
+recognize_char!(r_bracket, ']', Token::RBracket);
+recognize_char!(semicolon, ';', Token::Semicolon);
+recognize_char!(comma, ',', Token::Comma);
+recognize_tag!(ellipsis, "...", Token::Ellipsis);
+recognize_char!(dot, '.', Token::Dot);
+recognize_char!(star, '*', Token::Star);
+recognize_tag!(arrow, "->", Token::Arrow);
+recognize_tag!(increment, "++", Token::Increment);
+recognize_tag!(decrement, "--", Token::Decrement);
+recognize_char!(ampersand, '&', Token::Ampersand);
+recognize_char!(plus, '+', Token::Plus);
+recognize_char!(minus, '-', Token::Minus);
+recognize_char!(tilde, '~', Token::Tilde);
+recognize_char!(exclamation, '!', Token::Exclamation);
+recognize_char!(slash, '/', Token::Slash);
+recognize_char!(modulo, '%', Token::Modulo);
+recognize_char!(caret, '^', Token::Caret);
+recognize_char!(vertical_bar, '|', Token::VerticalBar);
+recognize_char!(colon, ':', Token::Colon);
+recognize_char!(question_mark, '?', Token::Question);
+recognize_tag!(shl, "<<", Token::Shl);
+recognize_tag!(shr, ">>", Token::Shr);
+recognize_char!(less_than, '<', Token::LessThan);
+recognize_char!(greater_than, '>', Token::GreaterThan);
+recognize_tag!(less_than_or_equal_to, "<=", Token::LessThanOrEqualTo);
+recognize_tag!(greater_than_or_equal_to, ">=", Token::GreaterThanOrEqualTo);
+recognize_tag!(equality, "==", Token::Equality);
+recognize_tag!(not_equal, "!=", Token::NotEqual);
+recognize_tag!(l_bracket_digraph, "<:", Token::LBracket);
+recognize_tag!(r_bracket_digraph, ":>", Token::RBracket);
+recognize_tag!(l_brace_digraph, "<%", Token::LBrace);
+recognize_tag!(r_brace_digraph, "%>", Token::RBrace);
+recognize_tag!(and_and, "&&", Token::AndAnd);
+recognize_tag!(or_or, "||", Token::OrOr);
+
+/* assignment operators */
+recognize_char!(equal, '=', Token::Equal);
+recognize_tag!(mult_eq, "*=", Token::MultEq);
+recognize_tag!(div_eq, "/=", Token::DivEq);
+recognize_tag!(mod_eq, "%=", Token::ModEq);
+recognize_tag!(plus_eq, "+=", Token::PlusEq);
+recognize_tag!(minus_eq, "-=", Token::MinusEq);
+recognize_tag!(shl_eq, "<<=", Token::ShlEq);
+recognize_tag!(shr_eq, ">>=", Token::ShrEq);
+recognize_tag!(and_eq, "&=", Token::AndEq);
+recognize_tag!(xor_eq, "^=", Token::XorEq);
+recognize_tag!(or_eq, "|=", Token::OrEq);
+
+/* jump keywords */
+recognize_tag!(_goto, "goto", Token::Goto);
+recognize_tag!(_continue, "continue", Token::Continue);
+recognize_tag!(_break, "break", Token::Break);
+recognize_tag!(_return, "return", Token::Return);
+
+recognize_tag!(_if, "if", Token::If);
+recognize_tag!(_else, "else", Token::Else);
+recognize_tag!(_switch, "switch", Token::Switch);
+
+recognize_tag!(case, "case", Token::Case);
+recognize_tag!(default, "default", Token::Default);
+
+/* loop keywords */
+recognize_tag!(_while, "while", Token::While);
+recognize_tag!(_do, "do", Token::Do);
+recognize_tag!(_for, "for", Token::For);
+
+/* function specifier */
+recognize_tag!(inline, "inline", Token::Inline);
+
+/* storage class specifiers */
+recognize_tag!(typedef, "typedef", Token::Typedef);
+recognize_tag!(_extern, "extern", Token::Extern);
+recognize_tag!(_static, "static", Token::Static);
+recognize_tag!(auto, "auto", Token::Auto);
+recognize_tag!(register, "register", Token::Register);
+
+/* type qualifier */
+recognize_tag!(_const, "const", Token::Const);
+recognize_tag!(restrict, "restrict", Token::Restrict);
+recognize_tag!(volatile, "volatile", Token::Volatile);
+
+/* struct or union */
+recognize_tag!(_struct, "struct", Token::Struct);
+recognize_tag!(_union, "union", Token::Union);
+
+recognize_tag!(_enum, "enum", Token::Enum);
+
+/* type specifier */
+recognize_tag!(void, "void", Token::Void);
+recognize_tag!(char, "char", Token::Char);
+recognize_tag!(short, "short", Token::Short);
+recognize_tag!(int, "int", Token::Int);
+recognize_tag!(long, "long", Token::Long);
+recognize_tag!(float, "float", Token::Float);
+recognize_tag!(double, "double", Token::Double);
+recognize_tag!(signed, "signed", Token::Signed);
+recognize_tag!(unsigned, "unsigned", Token::Unsigned);
+
+// misc
+recognize_tag!(sizeof, "sizeof", Token::SizeOf);
+
+/* C99 and C11 keywords, which are reserved identifiers in earlier versions */
+recognize_tag!(_bool, "_Bool", Token::Bool);
+recognize_tag!(complex, "_Complex", Token::Complex);
+recognize_tag!(imaginary, "_Imaginary", Token::Imaginary);
+recognize_tag!(alignas, "_Alignas", Token::Alignas);
+recognize_tag!(alignof, "_Alignof", Token::Alignof);
+recognize_tag!(atomic, "_Atomic", Token::Atomic);
+recognize_tag!(generic, "_Generic", Token::Generic);
+recognize_tag!(noreturn, "_Noreturn", Token::Noreturn);
+recognize_tag!(static_assert, "_Static_assert", Token::StaticAssert);
+recognize_tag!(thread_local, "_Thread_local", Token::ThreadLocal);
+
+/* the identifier can not be a reserved word */
+named!(ident(CompleteStr) -> Token, do_parse!(
+    ident: verify!(recognize!(pair!(
+        verify!(identifier_character, is_identifier_start),
+        many0!(verify!(identifier_character, is_identifier_continue))
+    )), |s: CompleteStr| !is_keyword(&s)) >>
+    (Token::Identifier(Symbol::intern(&decode_identifier(&ident))))
+));
+
+/* a character of an identifier, which may be spelled as a universal character name */
+named!(identifier_character(CompleteStr) -> char, alt!(universal_character_name | nom::anychar));
+
+named!(universal_character_name(CompleteStr) -> char, alt!(
+    map_opt!(preceded!(tag!("\\u"), take_while_m_n!(4, 4, |c: char| c.is_ascii_hexdigit())), ucn_character) |
+    map_opt!(preceded!(tag!("\\U"), take_while_m_n!(8, 8, |c: char| c.is_ascii_hexdigit())), ucn_character)
+));
+
+/// The character named by the digits of a universal character name, which may not be a surrogate or (with
+/// a few exceptions) in the basic character set
+fn ucn_character(digits: CompleteStr) -> Option<char> {
+    let c = std::char::from_u32(u32::from_str_radix(&digits, 16).ok()?)?;
+    if c as u32 >= 0xA0 || c == '$' || c == '@' || c == '`' { Some(c) } else { None }
+}
+
+fn is_keyword<'a>(s: &'a str) -> bool {
+    KEYWORDS.contains(s)
+}
+
+fn string_literal(input: CompleteStr, target: TargetInfo) -> IResult<CompleteStr, Token, u32> {
+    let (rest, (wide, contents)) = string_literal_contents(input)?;
+    if !escape_sequences_fit(contents, wide, target) {
+        return Err(nom::Err::Error(Context::Code(input, ErrorKind::Verify)));
+    }
+    Ok((rest, Token::StringLiteral{ wide, contents: contents.0 }))
+}
+
+named!(string_literal_contents(CompleteStr) -> (bool, CompleteStr), do_parse!(
+    wide: opt!(char!('L')) >>
+    char!('"') >>
+    s: recognize!(many0!(s_char)) >>
+    char!('"') >>
+    ((wide.is_some(), s))
+));
+
+named!(s_char(CompleteStr) -> CompleteStr, alt!(
+        recognize!(none_of!("\"\\\n")) |
+        escape_sequence
+));
+
+named!(escape_sequence(CompleteStr) -> CompleteStr, alt!(
+    /* simple escape sequence */
+    alt!(
+        tag!("\\\\") |
+        tag!("\\'") |
+        tag!("\\\"") |
+        tag!("\\?") |
+        tag!("\\a") |
+        tag!("\\b") |
+        tag!("\\f") |
+        tag!("\\n") |
+        tag!("\\r") |
+        tag!("\\t") |
+        tag!("\\v")
+    ) |
+    /* octal escape sequence (at most 3 digits) */
+    recognize!(do_parse!(
+        tag!("\\") >>
+        take_while_m_n!(1, 3, |c: char| c.is_digit(8)) >>
+        ()
+    )) |
+    /* hexadecimal escape sequence */
+    recognize!(do_parse!(
+        tag!("\\x") >>
+        take_while1!(|c: char| c.is_digit(16)) >>
+        ()
+    )) |
+    /* universal character name */
+    recognize!(universal_character_name)
+));
+
+/// Recognizes a (possibly wide) character constant such as `'a'`, `'\n'`, `'\x41'` or `L'x'`.
+fn character_constant(input: CompleteStr, target: TargetInfo) -> IResult<CompleteStr, Token, u32> {
+    let (rest, (wide, contents)) = character_constant_contents(input)?;
+    if !escape_sequences_fit(contents, wide, target) {
+        return Err(nom::Err::Error(Context::Code(input, ErrorKind::Verify)));
+    }
+    let value = character_value(&contents, wide, target);
+    Ok((rest, Token::CharLiteral{ wide, contents: contents.0, value }))
+}
+
+named!(character_constant_contents(CompleteStr) -> (bool, CompleteStr), do_parse!(
+    wide: opt!(char!('L')) >>
+    char!('\'') >>
+    s: recognize!(many1!(c_char)) >>
+    char!('\'') >>
+    ((wide.is_some(), s))
+));
+
+named!(c_char(CompleteStr) -> CompleteStr, alt!(
+        recognize!(none_of!("'\\\n")) |
+        escape_sequence
+));
+
+/// Whether the octal and hexadecimal escape sequences in the (valid) contents of a literal fit in its code units
+fn escape_sequences_fit(contents: CompleteStr, wide: bool, target: TargetInfo) -> bool {
+    let bits = code_unit_bits(wide, target);
+    match many0!(contents, alt!(escape_sequence | recognize!(nom::anychar))) {
+        Ok((_, characters)) => characters.iter().all(|c| !c.starts_with('\\') || escape_sequence_fits(c, bits)),
+        Err(_) => false
+    }
+}
+
+/// The error code reported (as a `nom::ErrorKind::Custom`) for integer constants followed by an invalid suffix
+const INVALID_INTEGER_SUFFIX: u32 = 2;
+
+/// The error code reported (as a `nom::ErrorKind::Custom`) for integer constants that don't fit in any integer type
+const INTEGER_TOO_LARGE: u32 = 3;
+
+fn integer_literal(input: CompleteStr, target: TargetInfo) -> IResult<CompleteStr, Token, u32> {
+    let start = input;
+    let (input, (literal, base)) = literal(input)?;
+    let (input, (sign, size)) = integer_suffix(input)?;
+    if input.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        // the rest of the preprocessing number can't be part of the integer constant (ex: `10uu` or `123abc`)
+        return Err(nom::Err::Failure(Context::Code(start, ErrorKind::Custom(INVALID_INTEGER_SUFFIX))));
+    }
+    let sign = match sign {
+        Some(_) => ConstIntSign::Unsigned,
+        None => ConstIntSign::Unspecified
+    };
+    let size = match size.map(|v| v.0) {
+        Some("ll") | Some("LL") => ConstIntSize::LongLong,
+        Some("l") | Some("L") => ConstIntSize::Long,
+        _ => ConstIntSize::Unspecified
+    };
+    match integer_literals::convert(&literal, base, sign, size, target) {
+        Some(value) => Ok((input, Token::IntLiteral(value))),
+        None => Err(nom::Err::Failure(Context::Code(start, ErrorKind::Custom(INTEGER_TOO_LARGE))))
+    }
+}
+
+named!(literal(CompleteStr) -> (CompleteStr, Base), alt!(
+    hex_integer_literal | octal_integer_literal |
+    binary_integer_literal | decimal_integer_literal
+));
+
+named!(hex_integer_literal(CompleteStr) -> (CompleteStr, Base), do_parse!(
+    alt!(tag!("0x") | tag!("0X")) >>
+    literal: take_while1!(|c: char| c.is_digit(16)) >>
+    (literal, Base::Hex)
+));
+
+named!(decimal_integer_literal(CompleteStr) -> (CompleteStr, Base), do_parse!(
+    literal: take_while1!(|c: char| c.is_digit(10)) >>
+    ((literal, Base::Decimal))
+));
+
+named!(octal_integer_literal(CompleteStr) -> (CompleteStr, Base), do_parse!(
+    char!('0') >>
+    literal: take_while1!(|c: char| c.is_digit(8)) >>
+    (literal, Base::Octal)
+));
+
+// not part of c99 (or c11 for that matter),
+// but accepted by gcc as an extension
+named!(binary_integer_literal(CompleteStr) -> (CompleteStr, Base), do_parse!(
+    alt!(tag!("0b") | tag!("0B")) >>
+    literal: take_while1!(|c: char| c.is_digit(2)) >>
+    (literal, Base::Binary)
+));
+
+/// The first element in the returned tuple is the unsigned_suffix, and the
+/// second element is the size suffix (long or long long).
+named!(integer_suffix(CompleteStr) -> (Option<CompleteStr>,Option<CompleteStr>), alt!(
+    // sign before size
+    do_parse!(
+        sign: unsigned_suffix >>
+        size: opt!(alt!(long_long_suffix | long_suffix)) >>
+        (Some(sign), size)
+    ) |
+    // size before sign
+    do_parse!(
+        size: opt!(alt!(long_long_suffix | long_suffix)) >>
+        sign: opt!(unsigned_suffix) >>
+        (sign, size)
+    )
+));
+
+named!(unsigned_suffix(CompleteStr) -> CompleteStr, alt!(tag!("u") | tag!("U")));
+named!(long_suffix(CompleteStr) -> CompleteStr, alt!(tag!("l") | tag!("L")));
+named!(long_long_suffix(CompleteStr) -> CompleteStr, alt!(tag!("ll") | tag!("LL")));
+
+/// The error code reported (as a `nom::ErrorKind::Custom`) for floating constants that are
+/// too large to be represented by their type.
+const FLOAT_OUT_OF_RANGE: u32 = 1;
+
+fn float_literal(input: CompleteStr) -> IResult<CompleteStr, Token, u32> {
+    let start = input;
+    let (input, (digits, radix, exponent)) = alt!(input, hex_floating_constant | decimal_floating_constant)?;
+    let (input, suffix) = opt!(input, one_of!("fFlL"))?;
+    let type_ = match suffix {
+        Some('f') | Some('F') => FloatType::Float,
+        Some('l') | Some('L') => FloatType::LongDouble,
+        _ => FloatType::Double
+    };
+    match float_literals::convert(&digits, radix, exponent, type_) {
+        Some(value) => Ok((input, Token::FloatLiteral(value))),
+        None => Err(nom::Err::Failure(Context::Code(start, ErrorKind::Custom(FLOAT_OUT_OF_RANGE))))
+    }
+}
+
+// The first element of the returned tuple holds the significant digits (with the decimal point removed),
+// the second is the radix, and the third is the exponent adjusted for the digits after the decimal point.
+named!(decimal_floating_constant(CompleteStr) -> (String, u32, i64), alt!(
+    do_parse!(
+        fraction: fractional_constant >>
+        exponent: opt!(exponent_part) >>
+        (join_digits(fraction, exponent.unwrap_or(0), 10))
+    ) |
+    do_parse!(
+        whole: digit_sequence >>
+        exponent: exponent_part >>
+        (whole.to_string(), 10, exponent)
+    )
+));
+
+named!(hex_floating_constant(CompleteStr) -> (String, u32, i64), do_parse!(
+    alt!(tag!("0x") | tag!("0X")) >>
+    fraction: alt!(
+        hex_fractional_constant |
+        map!(hex_digit_sequence, |whole| (whole, CompleteStr("")))
+    ) >>
+    exponent: binary_exponent_part >>
+    (join_digits(fraction, exponent, 16))
+));
+
+/// Removes the decimal point between the whole and fractional part of a constant, adjusting the exponent
+/// to compensate. Hexadecimal constants have a binary exponent, so each of their fractional digits is worth 4.
+fn join_digits((whole, fraction): (CompleteStr, CompleteStr), exponent: i64, radix: u32) -> (String, u32, i64) {
+    let digit_exponent = if radix == 16 { 4 } else { 1 };
+    let digits = format!("{}{}", whole, fraction);
+    (digits, radix, exponent.saturating_sub(digit_exponent * fraction.len() as i64))
+}
+
+named!(fractional_constant(CompleteStr) -> (CompleteStr, CompleteStr), alt!(
+    do_parse!(
+        whole: opt!(digit_sequence) >>
+        char!('.') >>
+        fraction: digit_sequence >>
+        (whole.unwrap_or(CompleteStr("")), fraction)
+    ) |
+    do_parse!(
+        whole: digit_sequence >>
+        char!('.') >>
+        (whole, CompleteStr(""))
+    )
+));
+
+named!(hex_fractional_constant(CompleteStr) -> (CompleteStr, CompleteStr), alt!(
+    do_parse!(
+        whole: opt!(hex_digit_sequence) >>
+        char!('.') >>
+        fraction: hex_digit_sequence >>
+        (whole.unwrap_or(CompleteStr("")), fraction)
+    ) |
+    do_parse!(
+        whole: hex_digit_sequence >>
+        char!('.') >>
+        (whole, CompleteStr(""))
+    )
+));
+
+named!(exponent_part(CompleteStr) -> i64, do_parse!(
+    one_of!("eE") >>
+    exponent: signed_exponent >>
+    (exponent)
+));
+
+named!(binary_exponent_part(CompleteStr) -> i64, do_parse!(
+    one_of!("pP") >>
+    exponent: signed_exponent >>
+    (exponent)
+));
+
+named!(signed_exponent(CompleteStr) -> i64, do_parse!(
+    sign: opt!(one_of!("+-")) >>
+    digits: digit_sequence >>
+    ({
+        // exponents this large are far outside the range of every floating type, so saturating is harmless
+        let value = digits.chars().fold(0i64, |acc, c| acc.saturating_mul(10).saturating_add(i64::from(c.to_digit(10).unwrap())));
+        if sign == Some('-') { -value } else { value }
+    })
+));
+
+named!(digit_sequence(CompleteStr) -> CompleteStr, take_while1!(|c: char| c.is_ascii_digit()));
+named!(hex_digit_sequence(CompleteStr) -> CompleteStr, take_while1!(|c: char| c.is_ascii_hexdigit()));
@@ -0,0 +1,219 @@
+//! A hand-written scanner that recognizes the token at the start of the input.
+//!
+//! The first byte of the input selects what kind of token to scan through a table of character classes,
+//! and keywords are told apart from identifiers with a perfect hash, so each token is only scanned once.
+
+use ast::{Symbol, TargetInfo};
+
+use crate::{Token, LexicalErrorKind};
+use crate::integer_literals::scan_integer;
+use crate::float_literals::scan_float;
+use crate::character_constants::{scan_character_constant, scan_string_literal};
+use crate::identifiers::{identifier_length, decode_identifier};
+
+/// The token at the start of the input and its length in bytes, or the reason why no token could be formed.
+/// The reason is `None` when the input doesn't start with anything that looks like a token, which the lexer
+/// diagnoses on its own.
+pub(crate) type Scan<'a> = Result<(Token<'a>, usize), Option<LexicalErrorKind>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    /// Characters that can't start a token, and non-ascii characters (which may start an identifier)
+    Other,
+    /// Letters and underscores
+    Identifier,
+    Digit,
+    Dot,
+    Quote,
+    Punctuator,
+    /// A backslash, which may start a universal character name
+    Backslash
+}
+
+/// The class of each byte, for dispatching on the first byte of a token
+const CLASSES: [Class; 256] = classes();
+
+const fn classes() -> [Class; 256] {
+    let mut classes = [Class::Other; 256];
+    let mut c = 0;
+    while c < 128 {
+        let byte = c as u8;
+        classes[c] = match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => Class::Identifier,
+            b'0'..=b'9' => Class::Digit,
+            b'.' => Class::Dot,
+            b'"' | b'\'' => Class::Quote,
+            b'\\' => Class::Backslash,
+            b'(' | b')' | b'{' | b'}' | b'[' | b']' | b';' | b',' | b'*' | b'-' | b'+' | b'&' | b'~' |
+            b'!' | b'/' | b'%' | b'<' | b'>' | b'=' | b'^' | b'|' | b'?' | b':' => Class::Punctuator,
+            _ => Class::Other
+        };
+        c += 1;
+    }
+    classes
+}
+
+const KEYWORDS: &[(&str, Token<'static>)] = &[
+    ("goto", Token::Goto), ("continue", Token::Continue), ("break", Token::Break), ("return", Token::Return),
+    ("inline", Token::Inline), ("typedef", Token::Typedef), ("extern", Token::Extern), ("static", Token::Static),
+    ("auto", Token::Auto), ("register", Token::Register), ("const", Token::Const), ("restrict", Token::Restrict),
+    ("volatile", Token::Volatile), ("void", Token::Void), ("char", Token::Char), ("short", Token::Short),
+    ("int", Token::Int), ("long", Token::Long), ("float", Token::Float), ("double", Token::Double),
+    ("signed", Token::Signed), ("unsigned", Token::Unsigned), ("sizeof", Token::SizeOf), ("struct", Token::Struct),
+    ("union", Token::Union), ("enum", Token::Enum), ("switch", Token::Switch), ("else", Token::Else),
+    ("case", Token::Case), ("default", Token::Default), ("while", Token::While), ("for", Token::For),
+    ("do", Token::Do), ("if", Token::If), ("_Bool", Token::Bool), ("_Complex", Token::Complex),
+    ("_Imaginary", Token::Imaginary), ("_Alignas", Token::Alignas), ("_Alignof", Token::Alignof),
+    ("_Atomic", Token::Atomic), ("_Generic", Token::Generic), ("_Noreturn", Token::Noreturn),
+    ("_Static_assert", Token::StaticAssert), ("_Thread_local", Token::ThreadLocal)
+];
+
+/// The longest keyword, `_Static_assert`
+const MAX_KEYWORD_LENGTH: usize = 14;
+
+/// The index in `KEYWORDS` of the keyword with each hash, or `u8::MAX` if no keyword has that hash
+static KEYWORD_TABLE: [u8; 128] = keyword_table(KEYWORDS);
+
+/// A perfect hash of the keywords, computed from the first two bytes, the last byte, and the length.
+/// The multiplier was found by trying random values until every keyword had a different hash.
+const fn keyword_hash(s: &[u8]) -> usize {
+    let key = s[0] as u32 | (s[1] as u32) << 8 | (s[s.len() - 1] as u32) << 16 | (s.len() as u32) << 24;
+    (key.wrapping_mul(0x0b94_af3b) >> 25) as usize
+}
+
+const fn keyword_table(keywords: &[(&str, Token<'static>)]) -> [u8; 128] {
+    let mut table = [u8::MAX; 128];
+    let mut i = 0;
+    while i < keywords.len() {
+        let hash = keyword_hash(keywords[i].0.as_bytes());
+        assert!(table[hash] == u8::MAX, "two keywords have the same hash");
+        table[hash] = i as u8;
+        i += 1;
+    }
+    table
+}
+
+fn keyword(s: &str) -> Option<Token<'static>> {
+    if s.len() < 2 || s.len() > MAX_KEYWORD_LENGTH {
+        return None;
+    }
+    let (name, token) = KEYWORDS.get(KEYWORD_TABLE[keyword_hash(s.as_bytes())] as usize)?;
+    if *name == s { Some(token.clone()) } else { None }
+}
+
+/// Recognizes the token at the start of `s`, which must not be empty. The types of integer constants and
+/// wide character constants depend on the target.
+pub(crate) fn scan(s: &str, target: TargetInfo) -> Scan<'_> {
+    let bytes = s.as_bytes();
+    match CLASSES[bytes[0] as usize] {
+        Class::Digit => number(s, target),
+        Class::Dot if bytes.get(1).is_some_and(u8::is_ascii_digit) => number(s, target),
+        Class::Quote => quoted(s, false, target).ok_or(None),
+        Class::Identifier if bytes[0] == b'L' && matches!(bytes.get(1), Some(b'"') | Some(b'\'')) => {
+            // an invalid wide literal leaves `L` as an identifier
+            Ok(quoted(s, true, target).unwrap_or_else(|| identifier(s)))
+        },
+        Class::Identifier => Ok(identifier(s)),
+        Class::Dot | Class::Punctuator => punctuator(bytes).ok_or(None),
+        Class::Other | Class::Backslash => match identifier_length(s) {
+            0 => Err(None),
+            _ => Ok(identifier(s))
+        }
+    }
+}
+
+fn number(s: &str, target: TargetInfo) -> Scan<'_> {
+    scan_float(s).unwrap_or_else(|| scan_integer(s, target)).map_err(Some)
+}
+
+/// Scans a string literal or character constant, which starts with `L` if it is wide
+fn quoted(s: &str, wide: bool, target: TargetInfo) -> Option<(Token<'_>, usize)> {
+    let quote = s.as_bytes()[wide as usize];
+    if quote == b'"' {
+        scan_string_literal(s, wide, target)
+    }
+    else {
+        scan_character_constant(s, wide, target)
+    }
+}
+
+/// Scans an identifier or keyword, which starts with a letter, an underscore, or an extended character
+fn identifier(s: &str) -> (Token<'_>, usize) {
+    let bytes = s.as_bytes();
+    let mut len = 0;
+    while len < bytes.len() && matches!(CLASSES[bytes[len] as usize], Class::Identifier | Class::Digit) {
+        len += 1;
+    }
+    if bytes.get(len).is_some_and(|&byte| !byte.is_ascii() || byte == b'\\') {
+        // identifiers with extended characters or universal character names are rare enough to not need a fast path
+        len = identifier_length(s);
+    }
+    let name = &s[..len];
+    (keyword(name).unwrap_or_else(|| Token::Identifier(Symbol::intern(&decode_identifier(name)))), len)
+}
+
+/// Scans a punctuator, taking the longest one that the input starts with
+fn punctuator(bytes: &[u8]) -> Option<(Token<'static>, usize)> {
+    let at = |i: usize| bytes.get(i).copied().unwrap_or(0);
+    let token = match (bytes[0], at(1)) {
+        (b'<', b'<') if at(2) == b'=' => return Some((Token::ShlEq, 3)),
+        (b'>', b'>') if at(2) == b'=' => return Some((Token::ShrEq, 3)),
+        (b'.', b'.') if at(2) == b'.' => return Some((Token::Ellipsis, 3)),
+        (b'=', b'=') => Token::Equality,
+        (b'!', b'=') => Token::NotEqual,
+        (b'<', b'=') => Token::LessThanOrEqualTo,
+        (b'>', b'=') => Token::GreaterThanOrEqualTo,
+        (b'+', b'+') => Token::Increment,
+        (b'-', b'-') => Token::Decrement,
+        (b'-', b'>') => Token::Arrow,
+        (b'<', b'<') => Token::Shl,
+        (b'>', b'>') => Token::Shr,
+        (b'&', b'&') => Token::AndAnd,
+        (b'|', b'|') => Token::OrOr,
+        (b'*', b'=') => Token::MultEq,
+        (b'/', b'=') => Token::DivEq,
+        (b'%', b'=') => Token::ModEq,
+        (b'+', b'=') => Token::PlusEq,
+        (b'-', b'=') => Token::MinusEq,
+        (b'&', b'=') => Token::AndEq,
+        (b'^', b'=') => Token::XorEq,
+        (b'|', b'=') => Token::OrEq,
+        /* digraphs (C99 6.4.6p3) */
+        (b'<', b':') => Token::LBracket,
+        (b':', b'>') => Token::RBracket,
+        (b'<', b'%') => Token::LBrace,
+        (b'%', b'>') => Token::RBrace,
+        (c, _) => return single_character_punctuator(c).map(|token| (token, 1))
+    };
+    Some((token, 2))
+}
+
+fn single_character_punctuator(c: u8) -> Option<Token<'static>> {
+    Some(match c {
+        b'(' => Token::LParen,
+        b')' => Token::RParen,
+        b'{' => Token::LBrace,
+        b'}' => Token::RBrace,
+        b'[' => Token::LBracket,
+        b']' => Token::RBracket,
+        b';' => Token::Semicolon,
+        b',' => Token::Comma,
+        b'.' => Token::Dot,
+        b'*' => Token::Star,
+        b'&' => Token::Ampersand,
+        b'+' => Token::Plus,
+        b'-' => Token::Minus,
+        b'~' => Token::Tilde,
+        b'!' => Token::Exclamation,
+        b'/' => Token::Slash,
+        b'%' => Token::Modulo,
+        b'^' => Token::Caret,
+        b'|' => Token::VerticalBar,
+        b':' => Token::Colon,
+        b'?' => Token::Question,
+        b'<' => Token::LessThan,
+        b'>' => Token::GreaterThan,
+        b'=' => Token::Equal,
+        _ => return None
+    })
+}
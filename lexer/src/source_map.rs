@@ -0,0 +1,138 @@
+use std::ffi::OsString;
+use std::rc::Rc;
+
+use ast::Location;
+
+/// Options for translation phase 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SourceOptions {
+    /// Replace trigraph sequences such as `??(` with the character they stand for. Trigraphs are part
+    /// of the standard, but most compilers ignore them unless they are asked not to (ex: `-trigraphs`).
+    pub trigraphs: bool
+}
+
+/// The logical source of a file, after translation phases 1 and 2 (C99 5.1.1.2): trigraph sequences are
+/// replaced (if enabled), and lines ending in a backslash are spliced together with the next line.
+/// Every byte offset of the logical source can be mapped back to the physical source file.
+#[derive(Debug, Clone)]
+pub struct SourceMap<'a> {
+    physical: &'a str,
+    logical: String,
+    /// the physical offset of every byte of the logical source, plus one entry for the end of the file
+    offsets: Vec<usize>,
+    /// the physical offset of the start of each line
+    line_starts: Vec<usize>
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(physical: &'a str, options: SourceOptions) -> SourceMap<'a> {
+        let mut logical = String::with_capacity(physical.len());
+        let mut offsets = Vec::with_capacity(physical.len() + 1);
+        let mut i = 0;
+        while i < physical.len() {
+            let (c, len) = source_character(&physical[i..], options);
+            if c == '\\' {
+                let rest = &physical[i + len..];
+                let newline = if rest.starts_with('\n') { 1 } else if rest.starts_with("\r\n") { 2 } else { 0 };
+                if newline > 0 {
+                    i += len + newline;
+                    continue;
+                }
+            }
+            logical.push(c);
+            offsets.extend(std::iter::repeat_n(i, c.len_utf8()));
+            i += len;
+        }
+        offsets.push(physical.len());
+
+        let line_starts = std::iter::once(0)
+            .chain(physical.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        SourceMap { physical, logical, offsets, line_starts }
+    }
+
+    pub fn physical(&self) -> &'a str {
+        self.physical
+    }
+
+    pub fn logical(&self) -> &str {
+        &self.logical
+    }
+
+    /// The offset in the physical source of the character at `logical_offset` in the logical source
+    pub fn physical_offset(&self, logical_offset: usize) -> usize {
+        self.offsets[logical_offset]
+    }
+
+    /// The physical location of the character at `logical_offset` in the logical source
+    pub fn location(&self, filename: &Rc<OsString>, logical_offset: usize) -> Location {
+        let offset = self.physical_offset(logical_offset);
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1
+        };
+        let column = self.physical[self.line_starts[line]..offset].chars().count() + 1;
+        Location::new(filename.clone(), line + 1, column, offset)
+    }
+}
+
+/// Returns the source character at the start of `s` and its length in bytes, which is 3 for trigraphs
+fn source_character(s: &str, options: SourceOptions) -> (char, usize) {
+    if options.trigraphs && s.starts_with("??") {
+        let replacement = match s.as_bytes().get(2) {
+            Some(b'=') => Some('#'),
+            Some(b'(') => Some('['),
+            Some(b'/') => Some('\\'),
+            Some(b')') => Some(']'),
+            Some(b'\'') => Some('^'),
+            Some(b'<') => Some('{'),
+            Some(b'!') => Some('|'),
+            Some(b'>') => Some('}'),
+            Some(b'-') => Some('~'),
+            _ => None
+        };
+        if let Some(c) = replacement {
+            return (c, 3);
+        }
+    }
+    let c = s.chars().next().unwrap();
+    (c, c.len_utf8())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn location(map: &SourceMap, logical_offset: usize) -> (usize, usize, usize) {
+        let location = map.location(&Rc::new(OsString::from("test.c")), logical_offset);
+        (location.line, location.column, location.byte_offset)
+    }
+
+    #[test]
+    fn line_splices() {
+        let map = SourceMap::new("in\\\nt a\\\r\n;\nb", SourceOptions::default());
+        assert_eq!(map.logical(), "int a;\nb");
+        assert_eq!(location(&map, 2), (2, 1, 4));
+        assert_eq!(location(&map, 5), (3, 1, 10));
+        assert_eq!(location(&map, 7), (4, 1, 12));
+        assert_eq!(location(&map, 8), (4, 2, 13));
+    }
+
+    #[test]
+    fn trigraphs() {
+        let source = "??=define A(x) x??(0??) ??/\n??-1 ???!";
+        assert_eq!(SourceMap::new(source, SourceOptions::default()).logical(), source);
+        let map = SourceMap::new(source, SourceOptions { trigraphs: true });
+        assert_eq!(map.logical(), "#define A(x) x[0] ~1 ?|");
+        assert_eq!(location(&map, 1), (1, 4, 3));
+        assert_eq!(location(&map, map.logical().find('~').unwrap()), (2, 1, 28));
+    }
+
+    #[test]
+    fn non_ascii_characters() {
+        let map = SourceMap::new("é\\\né", SourceOptions::default());
+        assert_eq!(map.logical(), "éé");
+        assert_eq!(location(&map, 2), (2, 1, 4));
+    }
+}
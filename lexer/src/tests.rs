@@ -1,6 +1,7 @@
 use std::ffi::OsString;
 
-use super::{Lexer, Token};
+use ast::{DataModel, Location, TargetInfo};
+use super::{Lexer, Token, LexicalErrorKind, SourceMap, SourceOptions, TypedefNames, decode_string_literal};
 
 macro_rules! lex_token_test {
     ($name: ident, $source: tt, $token: expr) => {
@@ -60,6 +61,10 @@ lex_token_test!(shl_eq, "<<=", Token::ShlEq);
 lex_token_test!(shr_eq, ">>=", Token::ShrEq);
 lex_token_test!(and_eq, "&=", Token::AndEq);
 lex_token_test!(or_eq, "|=", Token::OrEq);
+lex_token_test!(l_bracket_digraph, "<:", Token::LBracket);
+lex_token_test!(r_bracket_digraph, ":>", Token::RBracket);
+lex_token_test!(l_brace_digraph, "<%", Token::LBrace);
+lex_token_test!(r_brace_digraph, "%>", Token::RBrace);
 
 // keywords
 lex_token_test!(_goto, "goto", Token::Goto);
@@ -95,15 +100,288 @@ lex_token_test!(double, "double", Token::Double);
 lex_token_test!(signed, "signed", Token::Signed);
 lex_token_test!(unsigned, "unsigned", Token::Unsigned);
 lex_token_test!(sizeof, "sizeof", Token::SizeOf);
+lex_token_test!(_bool, "_Bool", Token::Bool);
+lex_token_test!(complex, "_Complex", Token::Complex);
+lex_token_test!(imaginary, "_Imaginary", Token::Imaginary);
+lex_token_test!(alignas, "_Alignas", Token::Alignas);
+lex_token_test!(alignof, "_Alignof", Token::Alignof);
+lex_token_test!(atomic, "_Atomic", Token::Atomic);
+lex_token_test!(generic, "_Generic", Token::Generic);
+lex_token_test!(noreturn, "_Noreturn", Token::Noreturn);
+lex_token_test!(static_assert, "_Static_assert", Token::StaticAssert);
+lex_token_test!(thread_local, "_Thread_local", Token::ThreadLocal);
 
 // identifiers
-lex_token_test!(ident1, "a", Token::Identifier("a"));
-lex_token_test!(ident_begins_with_keyword1, "return_addr", Token::Identifier("return_addr"));
-lex_token_test!(ident_begins_with_keyword2, "external", Token::Identifier("external"));
+lex_token_test!(ident1, "a", Token::Identifier("a".into()));
+lex_token_test!(ident_begins_with_keyword1, "return_addr", Token::Identifier("return_addr".into()));
+lex_token_test!(ident_begins_with_keyword2, "external", Token::Identifier("external".into()));
+lex_token_test!(ident_begins_with_keyword3, "_Bool_", Token::Identifier("_Bool_".into()));
+lex_token_test!(ident_reserved, "_Static", Token::Identifier("_Static".into()));
 
 // string literals
 lex_token_test!(string_literal, r#""hello, world!""#, Token::StringLiteral{ wide: false, contents: "hello, world!"});
 lex_token_test!(string_literal_simple_escape, r#""hello world!\r\n""#, Token::StringLiteral{ wide: false, contents: r#"hello world!\r\n"# });
 lex_token_test!(string_literal_ucs, r#""\u2699""#, Token::StringLiteral{ wide: false, contents: r#"\u2699"# });
 lex_token_test!(string_literal_escaped_quote, r#""\"""#, Token::StringLiteral{ wide: false, contents: r#"\""# });
-lex_token_test!(wide_string_literal, r#"L"hello world!""#, Token::StringLiteral{ wide: true, contents: "hello world!" });
\ No newline at end of file
+lex_token_test!(wide_string_literal, r#"L"hello world!""#, Token::StringLiteral{ wide: true, contents: "hello world!" });
+// comments
+lex_token_test!(skip_line_comment, "// comment\n(", Token::LParen);
+lex_token_test!(skip_block_comment, "/* comment */(", Token::LParen);
+lex_token_test!(skip_multiline_block_comment, "/* a\n * b\n */ (", Token::LParen);
+lex_token_test!(skip_consecutive_comments, "/* a */ // b\n/**/ // c\n(", Token::LParen);
+lex_token_test!(block_comments_dont_nest, "/* /* */ (", Token::LParen);
+lex_token_test!(line_comment_inside_block_comment, "/* // */ (", Token::LParen);
+lex_token_test!(comment_delimiters_inside_string_literal, r#""/* // */""#, Token::StringLiteral{ wide: false, contents: "/* // */" });
+
+#[test]
+fn location_after_block_comment() {
+    let mut lexer = Lexer::new(OsString::from("test.c"), "/* a\n * b */ x /* c */ y");
+    let (start, token, _) = lexer.next().unwrap().unwrap();
+    assert_eq!(token, Token::Identifier("x".into()));
+    assert_eq!((start.line, start.column, start.byte_offset), (2, 9, 13));
+    let (start, token, _) = lexer.next().unwrap().unwrap();
+    assert_eq!(token, Token::Identifier("y".into()));
+    assert_eq!((start.line, start.column, start.byte_offset), (2, 19, 23));
+    assert_eq!(lexer.next(), None);
+}
+
+#[test]
+fn trailing_line_comment() {
+    let mut lexer = Lexer::new(OsString::from("test.c"), "x // no newline at end of file");
+    assert_eq!(lexer.next().unwrap().unwrap().1, Token::Identifier("x".into()));
+    assert_eq!(lexer.next(), None);
+}
+
+#[test]
+fn unterminated_block_comment() {
+    let mut lexer = Lexer::new(OsString::from("test.c"), "x\n  /* never closed\n");
+    assert_eq!(lexer.next().unwrap().unwrap().1, Token::Identifier("x".into()));
+    let err = lexer.next().unwrap().unwrap_err();
+    assert_eq!(err.kind, LexicalErrorKind::UnterminatedComment);
+    assert_eq!((err.start.line, err.start.column, err.end.line, err.end.column), (2, 3, 2, 5));
+    assert_eq!(lexer.next(), None);
+}
+lex_token_test!(string_literal_escaped_backslash, r#""C:\\""#, Token::StringLiteral{ wide: false, contents: r#"C:\\"# });
+
+#[test]
+fn spliced_tokens_have_physical_locations() {
+    let map = SourceMap::new("int ma\\\nin ??/\n= 1;", SourceOptions { trigraphs: true });
+    let lexer = Lexer::with_source_map(OsString::from("test.c"), &map, TypedefNames::new());
+    let tokens: Vec<_> = lexer.map(|token| token.unwrap()).map(|(start, token, end)| (token, start.line, start.column, end.line, end.column)).collect();
+    assert_eq!(tokens, vec![
+        (Token::Int, 1, 1, 1, 4),
+        (Token::Identifier("main".into()), 1, 5, 2, 3),
+        (Token::Equal, 3, 1, 3, 2),
+        (Token::IntLiteral(ast::Integer::new(ast::IntegerType::Int, 1)), 3, 3, 3, 4),
+        (Token::Semicolon, 3, 4, 3, 5)
+    ]);
+}
+
+fn lex_error(source: &str) -> (LexicalErrorKind, (usize, usize), (usize, usize)) {
+    let mut lexer = Lexer::new(OsString::from("test.c"), source);
+    let err = lexer.find_map(|token| token.err()).unwrap();
+    (err.kind, (err.start.line, err.start.column), (err.end.line, err.end.column))
+}
+
+#[test]
+fn invalid_integer_suffix() {
+    assert_eq!(lex_error("x = 10uu;"), (LexicalErrorKind::InvalidIntegerSuffix, (1, 5), (1, 9)));
+    assert_eq!(lex_error("123abc"), (LexicalErrorKind::InvalidIntegerSuffix, (1, 1), (1, 7)));
+    assert_eq!(lex_error("0x"), (LexicalErrorKind::InvalidIntegerSuffix, (1, 1), (1, 3)));
+}
+
+#[test]
+fn integer_literal_too_large() {
+    assert_eq!(lex_error("\n  0x1000000000000000000000000000000000"), (LexicalErrorKind::IntegerLiteralTooLarge, (2, 3), (2, 39)));
+    assert_eq!(lex_error("170141183460469231731687303715884105728"), (LexicalErrorKind::IntegerLiteralTooLarge, (1, 1), (1, 40)));
+}
+
+#[test]
+fn unterminated_string_and_character_constant() {
+    assert_eq!(lex_error("s = \"abc\nx"), (LexicalErrorKind::UnterminatedString, (1, 5), (1, 9)));
+    assert_eq!(lex_error("\"a\\\"bc\n"), (LexicalErrorKind::UnterminatedString, (1, 1), (1, 7)));
+    assert_eq!(lex_error("c = 'a"), (LexicalErrorKind::UnterminatedCharacterConstant, (1, 5), (1, 7)));
+    assert_eq!(lex_error("c = ''"), (LexicalErrorKind::EmptyCharacterConstant, (1, 5), (1, 7)));
+}
+
+#[test]
+fn invalid_escape_sequence() {
+    assert_eq!(lex_error(r#""a\qb""#), (LexicalErrorKind::InvalidEscapeSequence, (1, 3), (1, 5)));
+    assert_eq!(lex_error(r#"'\x'"#), (LexicalErrorKind::InvalidEscapeSequence, (1, 2), (1, 4)));
+
+    // octal and hexadecimal escape sequences must fit in a char, or in a wchar_t in wide literals
+    assert_eq!(lex_error(r#"'\x100'"#), (LexicalErrorKind::InvalidEscapeSequence, (1, 2), (1, 7)));
+    assert_eq!(lex_error(r#""a\777""#), (LexicalErrorKind::InvalidEscapeSequence, (1, 3), (1, 7)));
+    assert_eq!(lex_error(r#"L"\x100000000""#), (LexicalErrorKind::InvalidEscapeSequence, (1, 3), (1, 14)));
+    let mut lexer = Lexer::new(OsString::from("test.c"), r#"L'\x100' L'\x10000'"#);
+    lexer.set_target(TargetInfo::new(DataModel::LLP64));
+    assert!(lexer.next().unwrap().is_ok());
+    assert!(lexer.any(|result| result.is_err()));
+}
+
+#[test]
+fn stray_character() {
+    assert_eq!(lex_error("int @x;"), (LexicalErrorKind::StrayCharacter('@'), (1, 5), (1, 6)));
+    assert_eq!(lex_error("a\n  `"), (LexicalErrorKind::StrayCharacter('`'), (2, 3), (2, 4)));
+}
+
+fn lex(source: &str) -> Vec<Token<'_>> {
+    Lexer::new(OsString::from("test.c"), source).map(|token| token.unwrap().1).collect()
+}
+
+#[test]
+fn extended_identifiers() {
+    let tokens = lex(r"caf\u00e9 café \U000065E5本 x٣ int\u00e9");
+    let names: Vec<_> = tokens.iter().map(|token| match token {
+        Token::Identifier(name) => name.as_str(),
+        other => panic!("expected an identifier, found {:?}", other)
+    }).collect();
+    assert_eq!(names, vec!["café", "café", "日本", "x٣", "inté"]);
+    // characters outside of Annex D end the identifier
+    assert_eq!(lex_error("a€"), (LexicalErrorKind::StrayCharacter('€'), (1, 2), (1, 3)));
+}
+
+#[test]
+fn universal_character_name_errors() {
+    assert_eq!(lex_error(r"x = a\u12;"), (LexicalErrorKind::IncompleteUniversalCharacterName, (1, 6), (1, 10)));
+    assert_eq!(lex_error(r"\U0000e9"), (LexicalErrorKind::IncompleteUniversalCharacterName, (1, 1), (1, 9)));
+    assert_eq!(lex_error(r"a\uD800"), (LexicalErrorKind::InvalidUniversalCharacterName(0xD800), (1, 2), (1, 8)));
+    assert_eq!(lex_error(r"a\U00110000"), (LexicalErrorKind::InvalidUniversalCharacterName(0x110000), (1, 2), (1, 12)));
+    assert_eq!(lex_error(r"a\u0041"), (LexicalErrorKind::InvalidUniversalCharacterName(0x41), (1, 2), (1, 8)));
+    assert_eq!(lex_error(r"a\u20AC"), (LexicalErrorKind::CharacterNotAllowedInIdentifier('€'), (1, 2), (1, 8)));
+    assert_eq!(lex_error(r"\u0660"), (LexicalErrorKind::CharacterNotAllowedInIdentifier('٠'), (1, 1), (1, 7)));
+    assert_eq!(lex_error(r#""a\u00e""#), (LexicalErrorKind::IncompleteUniversalCharacterName, (1, 3), (1, 8)));
+    assert_eq!(lex_error(r#"'\udfff'"#), (LexicalErrorKind::InvalidUniversalCharacterName(0xDFFF), (1, 2), (1, 8)));
+    // exactly 4 digits, the rest of the hex digits are ordinary characters
+    assert_eq!(lex(r#""\u00e9e""#), vec![Token::StringLiteral { wide: false, contents: r"\u00e9e" }]);
+    assert_eq!(decode_string_literal(r"\u00e9e", true), vec![0xE9, 0x65]);
+}
+
+#[test]
+fn universal_character_name_error_messages() {
+    let message = |source| Lexer::new(OsString::from("test.c"), source).find_map(|token| token.err()).unwrap().to_string();
+    assert_eq!(message(r"a\u12"), r"test.c:1:2: error: incomplete universal character name, \u must be followed by 4 hex digits and \U by 8");
+    assert_eq!(message(r"a\uD800"), r"test.c:1:2: error: universal character name \uD800 is a surrogate code point");
+    assert_eq!(message(r"a\u0041"), r"test.c:1:2: error: universal character name \u0041 names a character of the basic character set");
+    assert_eq!(message(r"a\U00110000"), r"test.c:1:2: error: universal character name \U00110000 is outside the range of unicode");
+    assert_eq!(message(r"a\u20ac"), "test.c:1:2: error: U+20AC ('€') is not allowed in an identifier");
+    assert_eq!(message(r"\u0660"), "test.c:1:1: error: U+0660 ('٠') is not allowed at the start of an identifier");
+}
+
+#[test]
+fn lexical_error_messages() {
+    let mut lexer = Lexer::new(OsString::from("test.c"), "int x;\nint y = 1 @ 2;\n");
+    let err = lexer.find_map(|token| token.err()).unwrap();
+    assert_eq!(err.to_string(), "test.c:2:11: error: stray '@' in program");
+    assert_eq!(err.line_text, "int y = 1 @ 2;");
+}
+
+#[test]
+fn spliced_error_spans_are_physical() {
+    let map = SourceMap::new("x = 1\\\n0uu;", SourceOptions::default());
+    let mut lexer = Lexer::with_source_map(OsString::from("test.c"), &map, TypedefNames::new());
+    let err = lexer.find_map(|token| token.err()).unwrap();
+    assert_eq!(err.kind, LexicalErrorKind::InvalidIntegerSuffix);
+    assert_eq!((err.start.line, err.start.column, err.end.line, err.end.column), (1, 5, 2, 4));
+    assert_eq!(err.line_text, "x = 1\\");
+}
+
+fn recovered_tokens(source: &str) -> Vec<String> {
+    let mut lexer = Lexer::new(OsString::from("test.c"), source);
+    lexer.enable_error_recovery();
+    lexer.map(|token| match token.unwrap().1 {
+        Token::Error{ spelling, error } => format!("<{:?} {}>", error.kind, spelling),
+        token => token.to_string()
+    }).collect()
+}
+
+#[test]
+fn errors_end_the_token_stream_without_recovery() {
+    let mut lexer = Lexer::new(OsString::from("test.c"), "a @ b");
+    assert_eq!(lexer.next().unwrap().unwrap().1, Token::Identifier("a".into()));
+    assert!(lexer.next().unwrap().is_err());
+    assert!(lexer.next().unwrap().is_err());
+}
+
+#[test]
+fn error_recovery_resynchronizes_at_whitespace_and_punctuation() {
+    assert_eq!(recovered_tokens("int a@b = 1; $x; c"), vec![
+        "int", "a", "<StrayCharacter('@') @b>", "=", "1", ";", "<StrayCharacter('$') $x>", ";", "c"
+    ]);
+    assert_eq!(recovered_tokens("x = 10uu+1;"), vec!["x", "=", "<InvalidIntegerSuffix 10uu>", "+", "1", ";"]);
+}
+
+#[test]
+fn error_recovery_skips_invalid_literals() {
+    assert_eq!(recovered_tokens(r#"f("a\qb", '\', "c");"#), vec![
+        "f", "(", r#"<InvalidEscapeSequence "a\qb">"#, ",", r#"<UnterminatedCharacterConstant '\', "c");>"#
+    ]);
+    assert_eq!(recovered_tokens("s = \"abc\nx;"), vec!["s", "=", "<UnterminatedString \"abc>", "x", ";"]);
+    assert_eq!(recovered_tokens("a /* b"), vec!["a", "<UnterminatedComment >"]);
+}
+
+#[test]
+fn error_token_locations() {
+    let mut lexer = Lexer::new(OsString::from("test.c"), "int x;\n  y = \"a\\qb\";");
+    lexer.enable_error_recovery();
+    let (start, token, end) = lexer.nth(5).unwrap().unwrap();
+    match token {
+        Token::Error{ spelling, error } => {
+            assert_eq!(spelling, "\"a\\qb\"");
+            assert_eq!((error.start.line, error.start.column, error.end.column), (2, 9, 11));
+        },
+        token => panic!("expected an error token, found {}", token)
+    }
+    assert_eq!((start.line, start.column, end.line, end.column), (2, 7, 2, 13));
+}
+
+fn recovered(mut lexer: Lexer) -> Vec<(Location, Token, Location)> {
+    lexer.enable_error_recovery();
+    lexer.map(|token| token.unwrap()).collect()
+}
+
+/// Lexes `source` with both the scanner and the original nom based implementation
+fn assert_same_tokens(source: &str) {
+    let scanned = recovered(Lexer::new(OsString::from("test.c"), source));
+    let reference = recovered(Lexer::new(OsString::from("test.c"), source).with_reference_scanner());
+    assert_eq!(scanned, reference, "{:?}", source);
+}
+
+#[test]
+fn scanner_matches_reference_implementation() {
+    let sources = [
+        "int main(int argc, char **argv) { return argv[argc - 1] != 0; }",
+        "a<<=b>>=c...d.e->f++g--h<=i>=j==k!=l&&m||n*=o/=p%=q+=r-=s&=t^=u|=v<:w:><%x%>?y:z;~!",
+        "double do_ int integer inline _Bool _Static_assert _Thread_localx L Lx L'a' L\"b\" L'' L\"\\q\"",
+        "é x_é ǅ 𝔘 € $ @ ` # \\",
+        "0 07 08 09 0x 0x1f 0X1F 0b101 0b2 0B1 1u 1U 1l 1L 1ll 1LL 1ul 1lu 1uLL 1LLu 1lL 1ull 1llu 1uu 1abc 0xfffffffffffffffffffffffffffffffff",
+        "1. .5 1.5 1e5 1e 1e+ 1e-5 1.e5 1.5e 1.0f 1.0F 1.0l 1.0L 1.0fx 0x1p3 0x1.8p-3 0x.8p1 0x1.p1 0x1.8 0x1p 1e309 1..2 1.2.3",
+        "'a' '\\n' '\\x41' '\\101' '\\1011' '\\u00e9' 'ab' '\\'' '\\\"' '\"' ''' '\\q' '\n'",
+        "\"\" \"a\\\"b\" \"\\\\\" \"'\" \"\\x\" \"\\u\" \"tab\\t\" \"unterminated\nx",
+        "/* comment */ a // line\n b /* unterminated",
+        "caf\\u00e9 \\u00e9t\\U000000e9 a\\u0024 a\\u12 \\u0660a a\\u0660 \\uD800 \\U00110000 \"\\u00e9\\U0001F600\" '\\u0041' \"\\U00e9\"",
+    ];
+    for source in sources.iter() {
+        assert_same_tokens(source);
+    }
+}
+
+#[test]
+fn scanner_matches_reference_implementation_on_random_input() {
+    let fragments = [
+        "int", "in", "x", "_", "L", "é", "0", "1", "7", "9", "0x", "0b", "f", "e", "p", "u", "l", "L", ".", "+", "-",
+        "<", ">", "=", "%", ":", "&", "|", "!", "*", "/", "'", "\"", "\\", "\\x", "\\u", "\\U", "00e9", "0024", "d800", "€", "٣", "\\n", " ", "\n", "@", "#", "?",
+        "(", ")", "{", "}", "[", "]", ";", ",", "^", "~", "do", "double", "_Bool"
+    ];
+    // a small linear congruential generator, so that failures can be reproduced
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = || {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        (state >> 33) as usize
+    };
+    for _ in 0..2000 {
+        let len = next() % 12 + 1;
+        let source: String = (0..len).map(|_| fragments[next() % fragments.len()]).collect();
+        assert_same_tokens(&source);
+    }
+}
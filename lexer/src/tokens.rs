@@ -1,4 +1,6 @@
-use ast::Integer;
+use ast::{Integer, Float, Symbol};
+
+use crate::error::LexicalError;
 
 use std::fmt;
 use std::fmt::{Display, Formatter};
@@ -92,22 +94,39 @@ pub enum Token<'a> {
     SizeOf,
     Bool,
     Complex,
+    Imaginary,
+    Alignas,
+    Alignof,
+    Atomic,
+    Generic,
+    Noreturn,
+    StaticAssert,
+    ThreadLocal,
 
     /* identifiers */
-    Identifier(&'a str),
+    Identifier(Symbol),
 
-    TypedefType(&'a str),
+    TypedefType(Symbol),
 
     /* An integer literal, can be in decimal, hex (0x), octal (0), or binary (0b) */
     IntLiteral(Integer),
 
     /* a floating point literal, consists of an optional whole number part, followed by '.', and
-       then an optional fraction part, followed by an optional exponent part, 
-       followed by an optional suffix (one of ['f', 'l', 'F', 'L'])
+       then an optional fraction part, followed by an optional exponent part,
+       followed by an optional suffix (one of ['f', 'l', 'F', 'L']).
+       Hexadecimal floating point literals (0x1.8p3) are also supported.
     */
-    FloatLiteral(&'a str),
+    FloatLiteral(Float),
+
+    /* a character constant, such as 'a', '\n', or L'x'. contents holds the source text between the quotes,
+       and value holds the value of the constant (an int, or a wchar_t for wide character constants) */
+    CharLiteral{ wide: bool, contents: &'a str, value: Integer },
 
-    StringLiteral{ wide: bool, contents: &'a str }
+    StringLiteral{ wide: bool, contents: &'a str },
+
+    /* input that doesn't form a valid token, only produced by lexers that recover from errors.
+       spelling holds the input that was skipped, and error describes what is wrong with it */
+    Error{ spelling: &'a str, error: Box<LexicalError> }
 }
 
 
@@ -197,10 +216,26 @@ impl<'a> Display for Token<'a> {
             SizeOf => "sizeof",
             Bool => "_Bool",
             Complex => "_Complex",
-            Identifier(ident) => ident,
-            TypedefType(name) => name,
+            Imaginary => "_Imaginary",
+            Alignas => "_Alignas",
+            Alignof => "_Alignof",
+            Atomic => "_Atomic",
+            Generic => "_Generic",
+            Noreturn => "_Noreturn",
+            StaticAssert => "_Static_assert",
+            ThreadLocal => "_Thread_local",
+            Identifier(ident) => ident.as_str(),
+            TypedefType(name) => name.as_str(),
             IntLiteral(i) => return i.fmt(f),
-            FloatLiteral(s) => s,
+            FloatLiteral(v) => return v.fmt(f),
+            CharLiteral{ wide, contents, .. } => {
+                if *wide {
+                    return write!(f, "L'{}'", contents);
+                }
+                else {
+                    return write!(f, "'{}'", contents);
+                }
+            },
             StringLiteral{ wide, contents } => {
                 if *wide {
                     return write!(f, "L\"{}\"", contents);
@@ -208,7 +243,8 @@ impl<'a> Display for Token<'a> {
                 else {
                     return write!(f, "\"{}\"", contents);
                 }
-            }
+            },
+            Error{ spelling, .. } => spelling
         };
         write!(f, "{}", s)
     }
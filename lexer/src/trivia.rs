@@ -0,0 +1,272 @@
+use ast::Location;
+
+use crate::{Lexer, Token};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TriviaKind {
+    /// Spaces, tabs, and other whitespace characters, except for newlines
+    Whitespace,
+    /// A `\n` or `\r\n` line ending
+    Newline,
+    /// A `// ...` comment, not including the newline that ends it
+    LineComment,
+    /// A `/* ... */` comment
+    BlockComment,
+    /// A backslash followed by a newline, which splices two physical lines together (translation phase 2)
+    LineContinuation
+}
+
+/// Text between tokens that doesn't affect the meaning of the program
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Trivia<'a> {
+    pub kind: TriviaKind,
+    pub text: &'a str,
+    /// The byte range of the trivia in the physical source
+    pub start: usize,
+    pub end: usize
+}
+
+/// A token along with the trivia that surrounds it, such that concatenating the text of the leading trivia,
+/// the token, and the trailing trivia of every token in a file gives back the original source.
+///
+/// The trailing trivia of a token is everything after it up to (but not including) the end of its line,
+/// any other trivia belongs to the next token.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TriviaToken<'a> {
+    pub leading: Vec<Trivia<'a>>,
+    /// The token, or `None` for the end of the file, which carries any trivia after the last token
+    pub token: Option<Token<'a>>,
+    /// The physical text of the token, including any line continuations (or trigraphs) inside of it
+    pub text: &'a str,
+    pub start: Location,
+    pub end: Location,
+    pub trailing: Vec<Trivia<'a>>
+}
+
+impl<'a> TriviaToken<'a> {
+    /// Writes the text of this token and its trivia to `out`
+    pub fn write_to(&self, out: &mut String) {
+        for trivia in &self.leading {
+            out.push_str(trivia.text);
+        }
+        out.push_str(self.text);
+        for trivia in &self.trailing {
+            out.push_str(trivia.text);
+        }
+    }
+}
+
+/// Produces the tokens of a lexer along with their trivia, see `Lexer::with_trivia`
+pub struct TriviaTokens<'input> {
+    lexer: Lexer<'input>,
+    physical: &'input str,
+    /// The next token to produce, which is only complete once the token after it is known
+    pending: Option<TriviaToken<'input>>,
+    started: bool
+}
+
+impl<'input> TriviaTokens<'input> {
+    pub(crate) fn new(mut lexer: Lexer<'input>) -> TriviaTokens<'input> {
+        lexer.enable_error_recovery();
+        let physical = lexer.physical_source();
+        TriviaTokens {
+            lexer,
+            physical,
+            pending: None,
+            started: false
+        }
+    }
+
+    /// Lexes the token after `previous_end` (a physical offset), without any trailing trivia
+    fn lex(&mut self, previous_end: usize) -> TriviaToken<'input> {
+        let (start, token, end) = match self.lexer.next() {
+            Some(token) => {
+                let (start, token, end) = token.expect("lexer errors are turned into error tokens");
+                (start, Some(token), end)
+            },
+            None => {
+                let end = self.lexer.end_location();
+                (end.clone(), None, end)
+            }
+        };
+        TriviaToken {
+            leading: split_trivia(self.physical, previous_end, start.byte_offset),
+            token,
+            text: &self.physical[start.byte_offset..end.byte_offset],
+            start,
+            end,
+            trailing: Vec::new()
+        }
+    }
+}
+
+impl<'input> Iterator for TriviaTokens<'input> {
+    type Item = TriviaToken<'input>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            self.pending = Some(self.lex(0));
+        }
+        let mut current = self.pending.take()?;
+        if current.token.is_none() {
+            return Some(current);
+        }
+
+        let mut next = self.lex(current.end.byte_offset);
+        // trivia up to the end of the line trails the current token, the rest leads the next one
+        let line_end = next.leading.iter().position(|trivia| trivia.kind == TriviaKind::Newline).unwrap_or(next.leading.len());
+        let leading = next.leading.split_off(line_end);
+        current.trailing = std::mem::replace(&mut next.leading, leading);
+        self.pending = Some(next);
+        Some(current)
+    }
+}
+
+/// Splits the physical source between `start` and `end`, which contains no tokens, into trivia
+fn split_trivia(physical: &str, start: usize, end: usize) -> Vec<Trivia<'_>> {
+    let mut trivia = Vec::new();
+    let mut offset = start;
+    while offset < end {
+        let rest = &physical[offset..end];
+        let (kind, len) = if rest.starts_with("//") {
+            (TriviaKind::LineComment, line_comment_length(rest))
+        }
+        else if let Some(body) = rest.strip_prefix("/*") {
+            // the end of the comment can't overlap its start, as in `/*/`
+            (TriviaKind::BlockComment, body.find("*/").map_or(rest.len(), |i| i + 4))
+        }
+        else if let Some(len) = newline_length(rest) {
+            (TriviaKind::Newline, len)
+        }
+        else if let Some(len) = rest.strip_prefix('\\').and_then(newline_length) {
+            (TriviaKind::LineContinuation, len + 1)
+        }
+        else if let Some(len) = rest.strip_prefix("??/").and_then(newline_length) {
+            (TriviaKind::LineContinuation, len + 3)
+        }
+        else {
+            let len = rest.find(|c: char| !c.is_whitespace() || c == '\n' || c == '\r').unwrap_or(rest.len());
+            // a lone carriage return (or any other stray character) is kept as whitespace
+            (TriviaKind::Whitespace, len.max(rest.chars().next().unwrap().len_utf8()))
+        };
+        trivia.push(Trivia { kind, text: &rest[..len], start: offset, end: offset + len });
+        offset += len;
+    }
+    trivia
+}
+
+/// The length of the newline at the start of `s`, if there is one
+fn newline_length(s: &str) -> Option<usize> {
+    if s.starts_with('\n') {
+        Some(1)
+    }
+    else if s.starts_with("\r\n") {
+        Some(2)
+    }
+    else {
+        None
+    }
+}
+
+/// The length of the line comment at the start of `s`, which continues onto the next line if its line ends
+/// in a line continuation
+fn line_comment_length(s: &str) -> usize {
+    let mut offset = 0;
+    while let Some(i) = s[offset..].find('\n') {
+        let line = s[offset..offset + i].trim_end_matches('\r');
+        if !line.ends_with('\\') && !line.ends_with("??/") {
+            return offset + line.len();
+        }
+        offset += i + 1;
+    }
+    s.len()
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use crate::{Lexer, Token, TypedefNames, SourceMap, SourceOptions};
+    use super::*;
+
+    fn round_trip(tokens: Vec<TriviaToken>) -> String {
+        let mut text = String::new();
+        for token in &tokens {
+            token.write_to(&mut text);
+        }
+        text
+    }
+
+    fn trivia_tokens(source: &str) -> Vec<TriviaToken<'_>> {
+        Lexer::new(OsString::from("test.c"), source).with_trivia().collect()
+    }
+
+    #[test]
+    fn leading_and_trailing_trivia() {
+        let tokens = trivia_tokens("/* header */\nint x; // x\n\n  y\t");
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[0].token, Some(Token::Int));
+        let kinds = |trivia: &[Trivia]| trivia.iter().map(|trivia| trivia.kind).collect::<Vec<_>>();
+        assert_eq!(kinds(&tokens[0].leading), vec![TriviaKind::BlockComment, TriviaKind::Newline]);
+        assert_eq!(kinds(&tokens[0].trailing), vec![TriviaKind::Whitespace]);
+        assert_eq!(kinds(&tokens[2].trailing), vec![TriviaKind::Whitespace, TriviaKind::LineComment]);
+        assert_eq!(tokens[2].trailing[1].text, "// x");
+        assert_eq!((tokens[2].trailing[1].start, tokens[2].trailing[1].end), (20, 24));
+        assert_eq!(kinds(&tokens[3].leading), vec![TriviaKind::Newline, TriviaKind::Newline, TriviaKind::Whitespace]);
+        assert_eq!(tokens[3].token, Some(Token::Identifier("y".into())));
+        assert_eq!(kinds(&tokens[3].trailing), vec![TriviaKind::Whitespace]);
+        assert_eq!(tokens[4].token, None);
+        assert!(tokens[4].leading.is_empty());
+    }
+
+    #[test]
+    fn round_trips_are_byte_identical() {
+        let sources = [
+            "",
+            "  \n// only a comment\r\n",
+            "int main(void) {\r\n\treturn 0; /* done */\r\n}\r\n",
+            "x = a\\\n  + b; // a comment \\\ncontinued\ny",
+            "a @ 10uu \"unterminated\n/* unterminated comment",
+        ];
+        for (i, source) in sources.iter().enumerate() {
+            let tokens = trivia_tokens(source);
+            if i + 1 < sources.len() {
+                assert!(tokens.iter().all(|token| !matches!(token.token, Some(Token::Error{..}))), "error token in {:?}", source);
+            }
+            assert_eq!(&round_trip(tokens), source);
+        }
+    }
+
+    #[test]
+    fn block_comments() {
+        let tokens = trivia_tokens("/*/ x */ a /**/ b");
+        let comments: Vec<_> = tokens.iter().flat_map(|token| token.leading.iter().chain(&token.trailing))
+            .filter(|trivia| trivia.kind == TriviaKind::BlockComment)
+            .map(|trivia| trivia.text)
+            .collect();
+        assert_eq!(comments, vec!["/*/ x */", "/**/"]);
+        assert_eq!(tokens[0].token, Some(Token::Identifier("a".into())));
+        assert_eq!(tokens[1].token, Some(Token::Identifier("b".into())));
+    }
+
+    #[test]
+    fn line_continuations_without_source_map() {
+        let tokens = trivia_tokens("x = a\\\n + b;");
+        assert_eq!(tokens[2].token, Some(Token::Identifier("a".into())));
+        let kinds: Vec<_> = tokens[2].trailing.iter().chain(&tokens[3].leading).map(|trivia| trivia.kind).collect();
+        assert_eq!(kinds, vec![TriviaKind::LineContinuation, TriviaKind::Whitespace]);
+    }
+
+    #[test]
+    fn line_continuations() {
+        let source = "int ma\\\nin \\\r\n ??/\n;";
+        let map = SourceMap::new(source, SourceOptions { trigraphs: true });
+        let tokens: Vec<_> = Lexer::with_source_map(OsString::from("test.c"), &map, TypedefNames::new()).with_trivia().collect();
+        assert_eq!(tokens[1].token, Some(Token::Identifier("main".into())));
+        assert_eq!(tokens[1].text, "ma\\\nin");
+        let kinds: Vec<_> = tokens[1].trailing.iter().chain(&tokens[2].leading).map(|trivia| trivia.kind).collect();
+        assert_eq!(kinds, vec![TriviaKind::Whitespace, TriviaKind::LineContinuation, TriviaKind::Whitespace, TriviaKind::LineContinuation]);
+        assert_eq!(round_trip(tokens), source);
+    }
+}
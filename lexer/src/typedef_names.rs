@@ -0,0 +1,200 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use ast::Symbol;
+
+use crate::tokens::Token;
+
+/// The typedef names that are in scope.
+///
+/// A typedef name can't be told apart from any other identifier without knowing the declarations
+/// that precede it (ex: `T * x;` is a declaration if `T` is a typedef name, and a multiplication otherwise).
+/// The parser declares identifiers as it reduces declarations, and the lexer classifies identifiers as it
+/// produces them, so both share the same `TypedefNames` (cloning it gives another handle to the same scopes).
+#[derive(Debug, Clone)]
+pub struct TypedefNames {
+    inner: Rc<RefCell<Scopes>>
+}
+
+#[derive(Debug)]
+struct Scopes {
+    /// The scopes that are open, the first one being the file scope
+    scopes: Vec<Scope>,
+
+    /// Whether each of the declarations that are being parsed declares typedef names
+    declarations: Vec<bool>
+}
+
+#[derive(Debug)]
+struct Scope {
+    /// The identifiers declared in the scope. The value is true for typedef names, and false for ordinary
+    /// identifiers (which hide any typedef name with the same name from an outer scope).
+    names: HashMap<Symbol, bool>,
+
+    /// How many declarations were being parsed when the scope was entered
+    declarations: usize
+}
+
+impl Scope {
+    fn new(declarations: usize) -> Scope {
+        Scope {
+            names: HashMap::new(),
+            declarations
+        }
+    }
+}
+
+impl TypedefNames {
+    pub fn new() -> TypedefNames {
+        TypedefNames {
+            inner: Rc::new(RefCell::new(Scopes {
+                scopes: vec![Scope::new(0)],
+                declarations: Vec::new()
+            }))
+        }
+    }
+
+    pub fn enter_scope(&self) {
+        let mut inner = self.inner.borrow_mut();
+        let declarations = inner.declarations.len();
+        inner.scopes.push(Scope::new(declarations));
+    }
+
+    /// Leaves the innermost block scope, the file scope is never left
+    pub fn leave_scope(&self) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.scopes.len() > 1 {
+            inner.scopes.pop();
+        }
+    }
+
+    /// Starts a declaration, whose declarators are typedef names if `is_typedef` is true
+    pub fn begin_declaration(&self, is_typedef: bool) {
+        self.inner.borrow_mut().declarations.push(is_typedef);
+    }
+
+    pub fn end_declaration(&self) {
+        self.inner.borrow_mut().declarations.pop();
+    }
+
+    /// Abandons the declarations that were started in the innermost scope and haven't ended, which is
+    /// what happens to them when the parser skips over a syntax error.
+    pub fn abandon_declarations(&self) {
+        let mut inner = self.inner.borrow_mut();
+        let declarations = inner.scopes.last().unwrap().declarations;
+        inner.declarations.truncate(declarations);
+    }
+
+    /// Declares an identifier in the innermost scope, as a typedef name if the current declaration has the
+    /// typedef storage class specifier, or as an ordinary identifier (hiding any typedef name from an outer scope).
+    pub fn declare(&self, name: Symbol) {
+        let is_typedef = self.inner.borrow().declarations.last().cloned().unwrap_or(false);
+        self.declare_as(name, is_typedef);
+    }
+
+    /// Declares an ordinary identifier, such as an enumeration constant, in the innermost scope
+    pub fn declare_ordinary_identifier(&self, name: Symbol) {
+        self.declare_as(name, false);
+    }
+
+    fn declare_as(&self, name: Symbol, is_typedef: bool) {
+        self.inner.borrow_mut().scopes.last_mut().unwrap().names.insert(name, is_typedef);
+    }
+
+    pub fn is_typedef_name(&self, name: Symbol) -> bool {
+        self.inner.borrow().scopes.iter().rev()
+            .find_map(|scope| scope.names.get(&name))
+            .cloned()
+            .unwrap_or(false)
+    }
+
+    /// Turns an identifier into a typedef name if it has been declared as one
+    pub fn classify<'a>(&self, token: Token<'a>) -> Token<'a> {
+        match token {
+            Token::Identifier(name) if self.is_typedef_name(name) => Token::TypedefType(name),
+            token => token
+        }
+    }
+}
+
+impl Default for TypedefNames {
+    fn default() -> Self {
+        TypedefNames::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn typedef_name() {
+        let names = TypedefNames::new();
+        names.begin_declaration(true);
+        names.declare("T".into());
+        names.end_declaration();
+        assert!(names.is_typedef_name("T".into()));
+        assert!(!names.is_typedef_name("U".into()));
+        assert_eq!(names.classify(Token::Identifier("T".into())), Token::TypedefType("T".into()));
+        assert_eq!(names.classify(Token::Identifier("U".into())), Token::Identifier("U".into()));
+    }
+
+    #[test]
+    fn block_scope() {
+        let names = TypedefNames::new();
+        names.enter_scope();
+        names.begin_declaration(true);
+        names.declare("T".into());
+        names.end_declaration();
+        assert!(names.is_typedef_name("T".into()));
+        names.leave_scope();
+        assert!(!names.is_typedef_name("T".into()));
+    }
+
+    #[test]
+    fn ordinary_identifier_hides_typedef_name() {
+        let names = TypedefNames::new();
+        names.begin_declaration(true);
+        names.declare("T".into());
+        names.end_declaration();
+
+        names.enter_scope();
+        names.begin_declaration(false);
+        names.declare("T".into());
+        names.end_declaration();
+        assert!(!names.is_typedef_name("T".into()));
+
+        names.leave_scope();
+        assert!(names.is_typedef_name("T".into()));
+    }
+
+    #[test]
+    fn nested_declarations() {
+        let names = TypedefNames::new();
+        names.begin_declaration(true);
+        names.begin_declaration(false);
+        names.declare("a".into());
+        names.end_declaration();
+        names.declare("T".into());
+        names.end_declaration();
+        assert!(!names.is_typedef_name("a".into()));
+        assert!(names.is_typedef_name("T".into()));
+    }
+
+    #[test]
+    fn abandoned_declarations() {
+        let names = TypedefNames::new();
+        names.begin_declaration(true);
+        names.enter_scope();
+        names.begin_declaration(false);
+        names.begin_declaration(false);
+        names.abandon_declarations();
+        names.leave_scope();
+        names.declare("T".into());
+        assert!(names.is_typedef_name("T".into()));
+        names.abandon_declarations();
+        names.declare("U".into());
+        assert!(!names.is_typedef_name("U".into()));
+    }
+}
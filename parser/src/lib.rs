@@ -3,8 +3,110 @@
 extern crate ast;
 extern crate lexer;
 extern crate types;
+extern crate errors;
+
+use lalrpop_util::ParseError;
+
+use ast::{Location, TranslationUnit};
+use lexer::{Lexer, Token, LexicalError, Spanned, TypedefNames};
+use errors::{CompilationError, UnexpectedEndOfFile};
 
 lalrpop_mod!(grammar);
 
+#[cfg(test)]
+mod tests;
+
 pub use self::grammar::TranslationUnitParser as Parser;
-pub use self::grammar::DeclarationParser;
\ No newline at end of file
+pub use self::grammar::DeclarationParser;
+
+/// Parses a translation unit, declaring the typedef names it contains in the lexer's `TypedefNames` so that
+/// the lexer can tell them apart from other identifiers. Fails with the first syntax error.
+pub fn parse_translation_unit(lexer: Lexer) -> Result<TranslationUnit, CompilationError> {
+    let typedef_names = lexer.typedef_names().clone();
+    let end = lexer.end_location();
+    parse_tokens(lexer, &typedef_names, &end)
+}
+
+/// Parses a translation unit, and keeps going after syntax errors: the declaration, statement or block that
+/// contains an error is skipped up to the `;` or `}` that ends it. Returns the declarations that were parsed
+/// along with every syntax error, in the order they appear in the source. An error that the parser can't recover
+/// from before the end of the file (ex: the end of the file in the middle of a block) abandons the external
+/// declaration that contains it, and so does a lexical error, which also ends the file.
+pub fn parse_translation_unit_with_recovery<'a>(lexer: Lexer<'a>) -> (TranslationUnit, Vec<CompilationError<'a>>) {
+    let typedef_names = lexer.typedef_names().clone();
+    let end = lexer.end_location();
+    parse_tokens_with_recovery(lexer, &typedef_names, &end)
+}
+
+/// Parses a translation unit from any source of tokens, such as `preprocessor::Preprocessed::lexer_tokens`. The
+/// tokens must classify identifiers with `typedef_names`, which the parser declares typedef names in, and `end` is
+/// the location of the end of the input. Fails with the first syntax error.
+pub fn parse_tokens<'a, I>(tokens: I, typedef_names: &TypedefNames, end: &Location) -> Result<TranslationUnit, CompilationError<'a>>
+    where I: IntoIterator<Item = Spanned<Token<'a>, Location, LexicalError>>
+{
+    let mut errors = Vec::new();
+    let result = Parser::new().parse(typedef_names, &mut errors, tokens);
+    match errors.into_iter().next() {
+        Some(recovered) => Err(syntax_error(recovered.error, end)),
+        None => result.map_err(|error| syntax_error(error, end))
+    }
+}
+
+/// Parses a translation unit from any source of tokens like `parse_tokens`, and recovers from syntax errors
+/// like `parse_translation_unit_with_recovery`. A lexical error ends the input, but the declarations before it
+/// are kept.
+pub fn parse_tokens_with_recovery<'a, I>(tokens: I, typedef_names: &TypedefNames, end: &Location) -> (TranslationUnit, Vec<CompilationError<'a>>)
+    where I: IntoIterator<Item = Spanned<Token<'a>, Location, LexicalError>>
+{
+    let mut tokens = UntilLexicalError { tokens: tokens.into_iter(), error: None };
+    let mut recovered = Vec::new();
+    let result = Parser::new().parse(typedef_names, &mut recovered, &mut tokens);
+    let mut errors: Vec<CompilationError> = recovered.into_iter().map(|recovered| syntax_error(recovered.error, end)).collect();
+    let translation_unit = match result {
+        Ok(translation_unit) => translation_unit,
+        // the top level rule recovers from every syntax error, this is only a precaution
+        Err(error) => {
+            errors.push(syntax_error(error, end));
+            Vec::new()
+        }
+    };
+    if let Some(error) = tokens.error {
+        // the parser reports the end of the input in the middle of a declaration, which is really the lexical error
+        if let Some(CompilationError::UnexpectedEndOfFile(_)) = errors.last() {
+            errors.pop();
+        }
+        errors.push(syntax_error(ParseError::User { error }, end));
+    }
+    (translation_unit, errors)
+}
+
+/// Ends a stream of tokens at its first lexical error, which the parser can't recover from, and keeps the error
+struct UntilLexicalError<'a, I: Iterator<Item = Spanned<Token<'a>, Location, LexicalError>>> {
+    tokens: I,
+    error: Option<LexicalError>
+}
+
+impl<'a, I: Iterator<Item = Spanned<Token<'a>, Location, LexicalError>>> Iterator for UntilLexicalError<'a, I> {
+    type Item = (Location, Token<'a>, Location);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+        match self.tokens.next()? {
+            Ok(token) => Some(token),
+            Err(error) => {
+                self.error = Some(error);
+                None
+            }
+        }
+    }
+}
+
+/// Gives the errors at the end of the file (which the parser reports without a location) the location `end`
+fn syntax_error<'a>(error: ParseError<Location, Token<'a>, LexicalError>, end: &Location) -> CompilationError<'a> {
+    match error {
+        ParseError::UnrecognizedToken { token: None, expected } => UnexpectedEndOfFile::new(end.clone(), expected).into(),
+        error => error.into()
+    }
+}
@@ -0,0 +1,873 @@
+use std::ffi::OsString;
+
+use lalrpop_util::ParseError;
+
+use ast::*;
+use lexer::{Lexer, Token};
+
+use super::{parse_tokens, parse_translation_unit, parse_translation_unit_with_recovery};
+
+fn parse(source: &str) -> TranslationUnit {
+    parse_translation_unit(Lexer::new(OsString::from("test.c"), source)).unwrap()
+}
+
+fn parse_fails(source: &str) -> bool {
+    parse_translation_unit(Lexer::new(OsString::from("test.c"), source)).is_err()
+}
+
+fn declaration(unit: &TranslationUnit, index: usize) -> &Declaration {
+    match unit[index].value {
+        ExternalDeclaration::Declaration(ref declaration) => declaration,
+        ref other => panic!("expected a declaration, found {:?}", other)
+    }
+}
+
+fn function_body(unit: &TranslationUnit, index: usize) -> &CompoundStatement {
+    match unit[index].value {
+        ExternalDeclaration::FunctionDefinition(ref function) => &function.compound_statement,
+        ref other => panic!("expected a function definition, found {:?}", other)
+    }
+}
+
+fn is_declaration(item: &Node<BlockItem>) -> bool {
+    match item.value {
+        BlockItem::Declaration(_) | BlockItem::StaticAssert(_) => true,
+        BlockItem::Statement(_) => false
+    }
+}
+
+fn type_specifiers(declaration: &Declaration) -> Vec<TypeSpecifier> {
+    declaration.declaration_specifiers.value.iter().filter_map(|specifier| match specifier {
+        DeclarationSpecifier::TypeSpecifier(type_specifier) => Some(type_specifier.value.clone()),
+        _ => None
+    }).collect()
+}
+
+fn declared_identifiers(declaration: &Declaration) -> Vec<Symbol> {
+    declaration.init_declarator_list.iter()
+        .filter_map(|init_declarator| init_declarator.declarator.identifier())
+        .collect()
+}
+
+#[test]
+fn typedef_name_as_type_specifier() {
+    let unit = parse("typedef unsigned long size_t; size_t x, *y;");
+    let declaration = declaration(&unit, 1);
+    assert_eq!(type_specifiers(declaration), vec![TypeSpecifier::Typedef("size_t".into())]);
+    assert_eq!(declared_identifiers(declaration), vec!["x", "y"]);
+}
+
+#[test]
+fn typedef_name_with_qualifiers() {
+    let unit = parse("typedef struct FILE FILE; static const FILE * volatile f;");
+    let declaration = declaration(&unit, 1);
+    assert_eq!(type_specifiers(declaration), vec![TypeSpecifier::Typedef("FILE".into())]);
+    assert_eq!(declared_identifiers(declaration), vec!["f"]);
+}
+
+#[test]
+fn multiple_typedef_names_in_one_declaration() {
+    let unit = parse("typedef int A, *B; A a; B b;");
+    assert_eq!(type_specifiers(declaration(&unit, 1)), vec![TypeSpecifier::Typedef("A".into())]);
+    assert_eq!(type_specifiers(declaration(&unit, 2)), vec![TypeSpecifier::Typedef("B".into())]);
+}
+
+#[test]
+fn struct_tag_with_the_same_name_as_a_typedef() {
+    let unit = parse("typedef struct node node; struct node { node *next; }; node n;");
+    assert_eq!(unit.len(), 3);
+    assert_eq!(type_specifiers(declaration(&unit, 2)), vec![TypeSpecifier::Typedef("node".into())]);
+}
+
+#[test]
+fn declaration_or_expression() {
+    let unit = parse("typedef int T; int f(void) { T * x; }");
+    assert!(is_declaration(&function_body(&unit, 1)[0]));
+
+    let unit = parse("int T, x; int f(void) { T * x; }");
+    assert!(!is_declaration(&function_body(&unit, 1)[0]));
+}
+
+#[test]
+fn typedef_name_in_parameters_casts_and_sizeof() {
+    let unit = parse("typedef int T; T f(T a, T *); int x = sizeof(T); int y = (T) 1; int z = sizeof(T *);");
+    assert_eq!(unit.len(), 5);
+}
+
+#[test]
+fn block_scope_typedef() {
+    let unit = parse("void f(void) { typedef int T; T x; } void g(void) { T * x; }");
+    assert!(is_declaration(&function_body(&unit, 0)[1]));
+    assert!(!is_declaration(&function_body(&unit, 1)[0]));
+}
+
+#[test]
+fn ordinary_identifier_hides_typedef_name() {
+    let unit = parse("typedef int T; void f(void) { int T; T = 1; } T y;");
+    assert!(!is_declaration(&function_body(&unit, 1)[1]));
+    // T is a typedef name again after the block
+    assert_eq!(type_specifiers(declaration(&unit, 2)), vec![TypeSpecifier::Typedef("T".into())]);
+}
+
+#[test]
+fn parameter_hides_typedef_name() {
+    let unit = parse("typedef int T; void f(int T) { T = 1; } T y;");
+    assert!(!is_declaration(&function_body(&unit, 1)[0]));
+    // the parameters are only in scope in the body
+    assert_eq!(type_specifiers(declaration(&unit, 2)), vec![TypeSpecifier::Typedef("T".into())]);
+
+    let unit = parse("typedef int T; int (*g(long T))(void) { T * 2; return 0; } void (h)(char T) { T * 2; }");
+    assert!(!is_declaration(&function_body(&unit, 1)[0]));
+    assert!(!is_declaration(&function_body(&unit, 2)[0]));
+}
+
+#[test]
+fn typedef_name_redeclared_after_type_specifier() {
+    let unit = parse("typedef int T; void f(void) { unsigned T; T = 1; }");
+    let body = function_body(&unit, 1);
+    match body[0].value {
+        BlockItem::Declaration(ref declaration) => {
+            assert_eq!(type_specifiers(declaration), vec![TypeSpecifier::Unsigned]);
+            assert_eq!(declared_identifiers(declaration), vec!["T"]);
+        },
+        ref other => panic!("expected a declaration, found {:?}", other)
+    }
+    assert!(!is_declaration(&body[1]));
+}
+
+#[test]
+fn enumeration_constant_hides_typedef_name() {
+    let unit = parse("typedef int T; void f(void) { enum { T }; int x = T; }");
+    assert_eq!(function_body(&unit, 1).len(), 2);
+}
+
+#[test]
+fn member_named_like_a_typedef() {
+    let unit = parse("typedef int len; struct s { len len; }; int f(struct s *p) { return p->len; }");
+    assert_eq!(unit.len(), 3);
+}
+
+#[test]
+fn typedef_name_cannot_be_combined_with_other_type_specifiers() {
+    // `unsigned T x` would declare T, and then find an unexpected identifier
+    assert!(parse_fails("typedef int T; unsigned T x;"));
+}
+
+#[test]
+fn bool_type_specifier() {
+    let unit = parse("_Bool flag; int _Boolean;");
+    assert_eq!(type_specifiers(declaration(&unit, 0)), vec![TypeSpecifier::Bool]);
+    let mut env = types::TypeEnvironment::new();
+    match resolve_type(declaration(&unit, 0), &mut env) {
+        types::Type::Integer(types::IntegerType::Bool) => (),
+        other => panic!("expected _Bool, found {}", other)
+    }
+}
+
+#[test]
+fn anonymous_union_member() {
+    let unit = parse("struct s { int tag; union { int i; float f; }; };");
+    let declaration = declaration(&unit, 0);
+    let mut env = types::TypeEnvironment::new();
+    let type_ = types::Type::make_type(&declaration.declaration_specifiers, &mut env).unwrap();
+    let id = match type_.type_ {
+        types::Type::Struct(id) => id,
+        other => panic!("expected a struct, found {}", other)
+    };
+    let s = env.registry().get_struct(id).unwrap();
+    assert_eq!(s.offset_of("f".into()), Some(4));
+    assert_eq!(s.layout(), types::Layout::new(8, 4));
+}
+
+fn resolve_type(declaration: &Declaration, env: &mut types::TypeEnvironment) -> types::Type {
+    types::Type::make_type(&declaration.declaration_specifiers, env).unwrap().type_
+}
+
+#[test]
+fn enumerators_in_source_order() {
+    let unit = parse("enum e { A = 5, B, C = B * 2 + (A < B), D = -1, E };");
+    let mut env = types::TypeEnvironment::new();
+    let id = match resolve_type(declaration(&unit, 0), &mut env) {
+        types::Type::Enum(id) => id,
+        other => panic!("expected an enum, found {}", other)
+    };
+    let e = env.registry().get_enum(id).unwrap();
+    let values: Vec<i128> = e.variants().iter().map(|(_, value)| *value).collect();
+    assert_eq!(values, vec![5, 6, 13, -1, 0]);
+    assert_eq!(e.integer_type(), types::IntegerType::Int);
+    assert_eq!(env.lookup_constant("C".into()), Some(13));
+    assert!(env.diagnostics().warnings().is_empty());
+}
+
+#[test]
+fn enumerator_values_in_later_declarations() {
+    let unit = parse("enum { A = 1, B = A }; struct s { unsigned x : B + 2; };");
+    let mut env = types::TypeEnvironment::new();
+    resolve_type(declaration(&unit, 0), &mut env);
+    assert_eq!(env.diagnostics().warnings().len(), 1);
+    let id = match resolve_type(declaration(&unit, 1), &mut env) {
+        types::Type::Struct(id) => id,
+        other => panic!("expected a struct, found {}", other)
+    };
+    match env.registry().get_struct(id).unwrap().field("x".into()) {
+        Some(types::Field::BitField { bits, .. }) => assert_eq!(*bits, 3),
+        other => panic!("expected a bit field, found {:?}", other)
+    }
+}
+
+#[test]
+fn enumerator_value_must_be_constant() {
+    let unit = parse("int x; enum { A = x };");
+    let mut env = types::TypeEnvironment::new();
+    assert!(types::Type::make_type(&declaration(&unit, 1).declaration_specifiers, &mut env).is_err());
+}
+
+/// Evaluates the initializer of the last declaration, after resolving every declaration before it
+fn evaluate(source: &str, env: &mut types::TypeEnvironment) -> Option<Integer> {
+    let unit = parse(source);
+    for index in 0..unit.len() - 1 {
+        resolve_type(declaration(&unit, index), env);
+    }
+    let last = declaration(&unit, unit.len() - 1);
+    let location = (last.declaration_specifiers.start.clone(), last.declaration_specifiers.end.clone());
+    match last.init_declarator_list[0].initializer {
+        Some(Initializer::AssignmentExpression(AssignmentExpression::ConditionalExpression(ref expression))) => {
+            types::constant_expression::evaluate(expression, &location, env).ok()
+        },
+        ref other => panic!("expected an expression, found {:?}", other)
+    }
+}
+
+fn constant(source: &str) -> Integer {
+    evaluate(source, &mut types::TypeEnvironment::new()).unwrap()
+}
+
+#[test]
+fn constant_expression_types() {
+    assert_eq!(constant("int x = 'a' + (char)1;"), Integer::new(IntegerType::Int, 98));
+    assert_eq!(constant("int x = 1u - 2;"), Integer::new(IntegerType::UnsignedInt, 0xffff_ffff));
+    assert_eq!(constant("int x = -1 < 0u;"), Integer::new(IntegerType::Int, 0));
+    assert_eq!(constant("int x = (unsigned char)300;"), Integer::new(IntegerType::UnsignedChar, 44));
+    assert_eq!(constant("int x = (signed char)200 >> 1;"), Integer::new(IntegerType::Int, -28));
+    assert_eq!(constant("int x = (short)-2.75;"), Integer::new(IntegerType::Short, -2));
+    assert_eq!(constant("int x = (_Bool)0.5 + (_Bool)-3;"), Integer::new(IntegerType::Int, 2));
+    assert_eq!(constant("int x = 1 ? 2 : 3l;"), Integer::new(IntegerType::Long { bits: 64 }, 2));
+    assert_eq!(constant("int x = ~0ul >> 60;"), Integer::new(IntegerType::UnsignedLong { bits: 64 }, 15));
+}
+
+#[test]
+fn constant_expression_sizeof() {
+    assert_eq!(constant("int x = sizeof(int) * 2;"), Integer::new(IntegerType::UnsignedLong { bits: 64 }, 8));
+    assert_eq!(constant("int x = sizeof 1.0f + sizeof(char *);"), Integer::new(IntegerType::UnsignedLong { bits: 64 }, 12));
+    assert_eq!(constant("int x = sizeof \"abc\";"), Integer::new(IntegerType::UnsignedLong { bits: 64 }, 4));
+    assert_eq!(constant("int x = sizeof(struct { char c; int i; }[3]);"), Integer::new(IntegerType::UnsignedLong { bits: 64 }, 24));
+    // the operand of sizeof isn't evaluated
+    assert_eq!(constant("int x = sizeof(1 / 0);"), Integer::new(IntegerType::UnsignedLong { bits: 64 }, 4));
+    assert!(evaluate("struct s; int x = sizeof(struct s);", &mut types::TypeEnvironment::new()).is_none());
+}
+
+#[test]
+fn wide_string_literal_size() {
+    let size = |data_model: DataModel| {
+        let mut env = types::TypeEnvironment::with_target(TargetInfo::new(data_model));
+        evaluate("int x = sizeof L\"ab\";", &mut env).unwrap().value
+    };
+    // wchar_t is int on LP64, long on ILP32 and unsigned short on LLP64
+    assert_eq!(size(DataModel::LP64), 12);
+    assert_eq!(size(DataModel::ILP32), 12);
+    assert_eq!(size(DataModel::LLP64), 6);
+}
+
+#[test]
+fn constant_expression_enumerators() {
+    assert_eq!(constant("enum { A = 3, B }; int x = B * (A == 3);"), Integer::new(IntegerType::Int, 4));
+    assert_eq!(constant("enum e { A = 0x100000000 }; int x = A;"), Integer::new(IntegerType::Long { bits: 64 }, 0x1_0000_0000));
+    assert_eq!(constant("enum e { A, B }; int x = (enum e)-1;"), Integer::new(IntegerType::UnsignedInt, 0xffff_ffff));
+}
+
+#[test]
+fn integer_types_are_not_merged_by_width() {
+    // long and long long are both 64 bits wide, but long long has the higher rank
+    assert_eq!(constant("int x = 1l + 1ll;"), Integer::new(IntegerType::LongLong, 2));
+    assert_eq!(constant("int x = (char)1 + (signed char)1;"), Integer::new(IntegerType::Int, 2));
+    assert_eq!(constant("int x = 1u + 1l;"), Integer::new(IntegerType::Long { bits: 64 }, 2));
+
+    // long can't represent every unsigned int on ILP32, so both are converted to unsigned long
+    let mut env = types::TypeEnvironment::with_target(TargetInfo::new(DataModel::ILP32));
+    assert_eq!(evaluate("int x = 1u + (long)1;", &mut env), Some(Integer::new(IntegerType::UnsignedLong { bits: 32 }, 2)));
+    assert_eq!(evaluate("int x = (long)1 + 1;", &mut env), Some(Integer::new(IntegerType::Long { bits: 32 }, 2)));
+
+    assert_eq!(IntegerType::LongLong.to_string(), "long long int");
+    assert_eq!(IntegerType::SignedChar.to_string(), "signed char");
+}
+
+#[test]
+fn constant_expression_diagnostics() {
+    let mut env = types::TypeEnvironment::new();
+    assert!(evaluate("int x = 1 / 0;", &mut env).is_none());
+    assert!(evaluate("int x = 1 % (2 - 2);", &mut env).is_none());
+    assert!(evaluate("int x = 1 << 32;", &mut env).is_none());
+    assert!(evaluate("int x = (float)1;", &mut env).is_none());
+    assert!(evaluate("int y; int x = y;", &mut env).is_none());
+    assert!(env.diagnostics().warnings().is_empty());
+
+    // operands that aren't evaluated don't cause errors or warnings
+    assert_eq!(evaluate("int x = 0 && 1 / 0;", &mut env), Some(Integer::new(IntegerType::Int, 0)));
+    assert_eq!(evaluate("int x = 1 || 2147483647 + 1;", &mut env), Some(Integer::new(IntegerType::Int, 1)));
+    assert_eq!(evaluate("int x = 1 ? 2 : 1 / 0;", &mut env), Some(Integer::new(IntegerType::Int, 2)));
+    assert!(env.diagnostics().warnings().is_empty());
+
+    assert_eq!(evaluate("int x = 2147483647 + 1;", &mut env), Some(Integer::new(IntegerType::Int, i32::MIN.into())));
+    assert_eq!(evaluate("int x = -(-2147483647 - 1);", &mut env), Some(Integer::new(IntegerType::Int, i32::MIN.into())));
+    assert_eq!(evaluate("int x = 0xffffffffu + 1;", &mut env), Some(Integer::new(IntegerType::UnsignedInt, 0)));
+    assert_eq!(env.diagnostics().warnings().len(), 2);
+}
+
+#[test]
+fn array_sizes() {
+    let unit = parse("enum { N = 4 }; int a[N + 1], b[], c[*], d[-1];");
+    let mut env = types::TypeEnvironment::new();
+    resolve_type(declaration(&unit, 0), &mut env);
+    let declaration = declaration(&unit, 1);
+    let base = types::QualifiedType::from_declaration_specifier_list(&declaration.declaration_specifiers, &mut env).unwrap();
+    let location = (declaration.declaration_specifiers.start.clone(), declaration.declaration_specifiers.end.clone());
+    let resolve = |index: usize, env: &mut types::TypeEnvironment| {
+        use types::type_resolution::ResolveDeclarator;
+        declaration.init_declarator_list[index].declarator.resolve(base.clone(), &location, env)
+    };
+
+    let (a, _) = resolve(0, &mut env).unwrap();
+    assert_eq!(format!("{}", a), "array of 5 int");
+    assert_eq!(a.type_.layout(env.registry()), Some(types::Layout::new(20, 4)));
+    let (b, _) = resolve(1, &mut env).unwrap();
+    assert_eq!(format!("{}", b), "array of int");
+    let (c, _) = resolve(2, &mut env).unwrap();
+    assert_eq!(format!("{}", c), "variable length array of int");
+    assert!(resolve(3, &mut env).is_err());
+
+    // only sizes with operands that aren't constant make variable length arrays, other errors are reported
+    let check = |source: &str| types::declarations::check_translation_unit(&parse(source), &mut types::TypeEnvironment::new()).map_err(|error| error.to_string());
+    assert_eq!(check("void f(int n) { int v[n * 2], w[(int) (n * 1.5)]; }"), Ok(()));
+    assert_eq!(check("int a[1 / 0];"), Err("test.c:1:1: error: invalid constant expression: division by zero".to_string()));
+    assert_eq!(check("int a[1 << 40];"), Err("test.c:1:1: error: invalid constant expression: shift amount out of range".to_string()));
+}
+
+#[test]
+fn enumerators_are_ordinary_identifiers() {
+    let unit = parse("enum { A }; enum e { B = 2 }; enum { A };");
+    let mut env = types::TypeEnvironment::new();
+    resolve_type(declaration(&unit, 0), &mut env);
+    resolve_type(declaration(&unit, 1), &mut env);
+    assert!(types::Type::make_type(&declaration(&unit, 2).declaration_specifiers, &mut env).is_err());
+
+    // an enumeration constant can be hidden by a declaration in an inner scope
+    let location = (Location::default(), Location::default());
+    let int = types::QualifiedType::new(types::TypeQualifiers::default(), types::Type::Integer(types::IntegerType::Int));
+    env.enter_scope(types::ScopeType::Block);
+    env.symbols_mut().insert_variable("B".into(), int, types::StorageClass::Auto, location).unwrap();
+    assert_eq!(env.lookup_constant("B".into()), None);
+    env.leave_scope().unwrap();
+    assert_eq!(env.lookup_constant("B".into()), Some(2));
+}
+
+/// Resolves the declarations of a translation unit in order, and checks its static assertions
+fn check_static_asserts(source: &str) -> Result<(), String> {
+    let mut env = types::TypeEnvironment::new();
+    types::declarations::check_translation_unit(&parse(source), &mut env).map_err(|error| error.to_string())
+}
+
+#[test]
+fn static_asserts() {
+    let unit = parse("_Static_assert(1, \"file\");\nvoid f(void) { _Static_assert(2, \"block\"); }\nstruct s { int a; _Static_assert(3, \"member\"); };");
+    match unit[0].value {
+        ExternalDeclaration::StaticAssert(ref assertion) => assert_eq!(assertion.message, "file"),
+        ref other => panic!("expected a static assertion, found {:?}", other)
+    }
+    match function_body(&unit, 1)[0].value {
+        BlockItem::StaticAssert(ref assertion) => assert_eq!(assertion.message, "block"),
+        ref other => panic!("expected a static assertion, found {:?}", other)
+    }
+    match type_specifiers(declaration(&unit, 2))[0] {
+        TypeSpecifier::StructOrUnionSpecifier(StructOrUnionSpecifier::Complete { ref declaration_list, .. }) => match declaration_list[1] {
+            StructDeclaration::StaticAssert(ref assertion) => assert_eq!(assertion.value.message, "member"),
+            ref other => panic!("expected a static assertion, found {:?}", other)
+        },
+        ref other => panic!("expected a struct definition, found {:?}", other)
+    }
+    assert!(parse_fails("_Static_assert(1);"));
+    assert!(parse_fails("_Static_assert(1, 2);"));
+}
+
+#[test]
+fn static_assert_evaluation() {
+    assert_eq!(check_static_asserts("struct s { int a; char b; }; _Static_assert(sizeof(struct s) == 8, \"size\");"), Ok(()));
+    assert_eq!(check_static_asserts("enum { N = 3 }; _Static_assert(N > 2 && N < 4, \"N\");"), Ok(()));
+    assert_eq!(check_static_asserts("_Static_assert(sizeof(long) == 4, \"long is \" \"32 bits\");"),
+               Err("test.c:1:1: error: static assertion failed: \"long is 32 bits\"".to_string()));
+    assert!(check_static_asserts("int x; _Static_assert(x, \"not constant\");").is_err());
+    assert_eq!(check_static_asserts("struct s { int a; _Static_assert(sizeof(int) == 2, \"int\"); };"),
+               Err("test.c:1:19: error: static assertion failed: \"int\"".to_string()));
+    assert_eq!(check_static_asserts("typedef struct { long a; char b; } foo_t; _Static_assert(sizeof(foo_t) == 16, \"foo_t\");"), Ok(()));
+    assert_eq!(check_static_asserts("typedef struct { int a; } foo_t; typedef foo_t bar_t[2]; _Static_assert(sizeof(bar_t) == 4, \"bar_t\");"),
+               Err("test.c:1:58: error: static assertion failed: \"bar_t\"".to_string()));
+
+    // assertions at block scope see the declarations of the blocks that contain them
+    assert_eq!(check_static_asserts("void f(int x) { typedef char c[2]; _Static_assert(sizeof(c) == 2 && sizeof x == 4, \"c\"); }"), Ok(()));
+    assert_eq!(check_static_asserts("void f(void) { for (int i = 0; i < 2; i++) { long l; _Static_assert(sizeof l == 4, \"l\"); } }"),
+               Err("test.c:1:54: error: static assertion failed: \"l\"".to_string()));
+    assert_eq!(check_static_asserts("typedef int t; void f(void) { if (1) { typedef char t; _Static_assert(sizeof(t) == 1, \"t\"); } _Static_assert(sizeof(t) == 1, \"outer t\"); }"),
+               Err("test.c:1:95: error: static assertion failed: \"outer t\"".to_string()));
+}
+
+/// Declares the typedef names or variables of a declaration
+fn declare(declaration: &Declaration, env: &mut types::TypeEnvironment) -> Result<(), String> {
+    types::declarations::declare(declaration, env).map_err(|error| error.to_string())
+}
+
+/// Declares the typedef names and variables of every declaration but the last, and evaluates the initializer
+/// of the last one
+fn evaluate_with_variables(source: &str) -> Result<i128, String> {
+    let unit = parse(source);
+    let mut env = types::TypeEnvironment::new();
+    for index in 0..unit.len() - 1 {
+        declare(declaration(&unit, index), &mut env)?;
+    }
+    let last = declaration(&unit, unit.len() - 1);
+    let location = (last.declaration_specifiers.start.clone(), last.declaration_specifiers.end.clone());
+    match last.init_declarator_list[0].initializer {
+        Some(Initializer::AssignmentExpression(AssignmentExpression::ConditionalExpression(ref expression))) => {
+            types::constant_expression::evaluate(expression, &location, &mut env)
+                .map(|value| value.value)
+                .map_err(|error| error.to_string())
+        },
+        ref other => panic!("expected an expression, found {:?}", other)
+    }
+}
+
+#[test]
+fn sizeof_operands_are_not_evaluated() {
+    assert_eq!(evaluate_with_variables("int a[10]; int n = sizeof(a) / sizeof(a[0]);"), Ok(10));
+    assert_eq!(evaluate_with_variables("char m[2][3]; int n = sizeof m[1] + sizeof m[1][2] + sizeof *m;"), Ok(7));
+    assert_eq!(evaluate_with_variables("int *p; int n = sizeof *p + sizeof p[1];"), Ok(8));
+    assert_eq!(evaluate_with_variables("struct v { char c; double x; } v; int n = sizeof v.x + sizeof(v.c);"), Ok(9));
+    assert_eq!(evaluate_with_variables("struct v { char c; double x; } *p; int n = sizeof p->x + sizeof *p;"), Ok(24));
+    assert_eq!(evaluate_with_variables("union u { char c[5]; int i; } u[2]; int n = sizeof u[1].c;"), Ok(5));
+    assert_eq!(evaluate_with_variables("long f(void); int n = sizeof f() + sizeof (*f)();"), Ok(16));
+    assert_eq!(evaluate_with_variables("struct v { double x; } *p; int n = _Generic(p->x, double: 1, default: 2);"), Ok(1));
+
+    assert_eq!(evaluate_with_variables("struct v { int x; } v; int n = sizeof v.y;"),
+               Err("test.c:1:24: error: invalid constant expression: no member named 'y'".to_string()));
+    assert!(evaluate_with_variables("int i; int n = sizeof *i;").is_err());
+    assert!(evaluate_with_variables("int i; int n = sizeof i.x;").is_err());
+}
+
+#[test]
+fn typedef_names_in_types() {
+    assert_eq!(evaluate_with_variables("typedef int T; struct s { T x; char c; }; int n = sizeof(struct s);"), Ok(8));
+    assert_eq!(evaluate_with_variables("typedef unsigned char byte; int n = (byte)300;"), Ok(44));
+    assert_eq!(evaluate_with_variables("typedef int T; int n = sizeof(T) + sizeof(T *);"), Ok(12));
+    assert_eq!(evaluate_with_variables("typedef char A[3]; typedef A B; int n = sizeof(B);"), Ok(3));
+
+    // the qualifiers of the typedef and the declaration are combined
+    let unit = parse("typedef const int C; volatile C c;");
+    let mut env = types::TypeEnvironment::new();
+    declare(declaration(&unit, 0), &mut env).unwrap();
+    declare(declaration(&unit, 1), &mut env).unwrap();
+    match env.symbols().lookup_identifier("c".into()).unwrap().kind {
+        types::OrdinaryIdentifier::Variable(ref type_, _) => assert_eq!(type_.to_string(), "const volatile int"),
+        ref other => panic!("expected a variable, found {:?}", other)
+    }
+
+    // the grammar doesn't allow other type specifiers with a typedef name, but the syntax tree does
+    let specifier = |specifier| DeclarationSpecifier::TypeSpecifier(Node::new(specifier, Location::default(), Location::default()));
+    let specifiers = Node::new(vec![specifier(TypeSpecifier::Unsigned), specifier(TypeSpecifier::Typedef("C".into()))], Location::default(), Location::default());
+    assert!(types::Type::make_type(&specifiers, &mut env).unwrap_err().to_string().contains("incompatible"));
+
+    // the lexer knows that T is a typedef name, but it hasn't been declared
+    let unit = parse("typedef int T; T x;");
+    assert_eq!(declare(declaration(&unit, 1), &mut types::TypeEnvironment::new()),
+               Err("test.c:1:16: error: unknown type name 'T'".to_string()));
+}
+
+#[test]
+fn generic_selection_by_type() {
+    assert_eq!(evaluate_with_variables("int x = _Generic(1, long: 1, int: 2, default: 3);"), Ok(2));
+    assert_eq!(evaluate_with_variables("int x = _Generic(1L, long: 1, int: 2, default: 3);"), Ok(1));
+    assert_eq!(evaluate_with_variables("int x = _Generic(1.0f, float: 1, double: 2);"), Ok(1));
+    assert_eq!(evaluate_with_variables("int x = _Generic('a', char: 1, int: 2);"), Ok(2));
+    assert_eq!(evaluate_with_variables("int x = _Generic((unsigned char)1, char: 1, unsigned char: 2);"), Ok(2));
+    assert_eq!(evaluate_with_variables("int x = _Generic(1, long: 1, default: 2);"), Ok(2));
+    assert_eq!(evaluate_with_variables("int x = sizeof(_Generic(1, int: 1.0, default: 'a'));"), Ok(8));
+    assert_eq!(evaluate_with_variables("int x = _Generic(1, int: _Generic(2u, unsigned: 3, int: 4), default: 5);"), Ok(3));
+
+    // types of the same width are still distinct types
+    assert_eq!(evaluate_with_variables("int x = _Generic(1L, long: 1, long long: 2);"), Ok(1));
+    assert_eq!(evaluate_with_variables("int x = _Generic(1LL, long: 1, long long: 2);"), Ok(2));
+    assert_eq!(evaluate_with_variables("int x = _Generic((char)1, char: 1, signed char: 2, default: 3);"), Ok(1));
+    assert_eq!(evaluate_with_variables("int x = _Generic((signed char)1, char: 1, signed char: 2, default: 3);"), Ok(2));
+    assert_eq!(evaluate_with_variables("typedef unsigned long size_t; int x = _Generic(sizeof(int), size_t: 1, default: 2);"), Ok(1));
+}
+
+#[test]
+fn generic_selection_lvalue_conversion() {
+    assert_eq!(evaluate_with_variables("const int c; int x = _Generic(c, const int: 1, int: 2);"), Ok(2));
+    assert_eq!(evaluate_with_variables("char s[4]; int x = _Generic(s, char *: 1, char (*)[4]: 2);"), Ok(1));
+    assert_eq!(evaluate_with_variables("const char *p; int x = _Generic(p, char *: 1, const char *: 2);"), Ok(2));
+    assert_eq!(evaluate_with_variables("int x = _Generic(\"abc\", char *: 1, default: 2);"), Ok(1));
+    assert_eq!(evaluate_with_variables("int f(void); int x = _Generic(f, int (*)(void): 1, default: 2);"), Ok(1));
+    assert_eq!(evaluate_with_variables("double d; int x = _Generic((d), float: 1, double: 2);"), Ok(2));
+}
+
+#[test]
+fn generic_selection_errors() {
+    assert_eq!(evaluate_with_variables("int x = _Generic(1, long: 1, char *: 2);"),
+               Err("test.c:1:1: error: controlling expression type 'int' is not compatible with any generic association type".to_string()));
+    assert_eq!(evaluate_with_variables("int x = _Generic(1, int: 1, signed: 2);"),
+               Err("test.c:1:1: error: type 'int' in generic association is compatible with previously specified type 'int'".to_string()));
+    assert_eq!(evaluate_with_variables("int x = _Generic(1, long: 1, long int: 2);"),
+               Err("test.c:1:1: error: type 'long int' in generic association is compatible with previously specified type 'long int'".to_string()));
+    assert_eq!(evaluate_with_variables("typedef int T; int x = _Generic(1, T: 1, int: 2);"),
+               Err("test.c:1:16: error: type 'int' in generic association is compatible with previously specified type 'int'".to_string()));
+    assert_eq!(evaluate_with_variables("int x = _Generic(1, int[]: 1, int[3]: 2, default: 3);"),
+               Err("test.c:1:1: error: type 'array of 3 int' in generic association is compatible with previously specified type 'array of int'".to_string()));
+    assert_eq!(evaluate_with_variables("int x = _Generic(1, default: 1, int: 2, default: 3);"),
+               Err("test.c:1:1: error: duplicate default association in generic selection".to_string()));
+    assert!(parse_fails("int x = _Generic(1);"));
+    assert!(parse_fails("int x = _Generic(1, 2: 3);"));
+}
+
+#[test]
+fn adjacent_string_literals() {
+    let unit = parse(r#"char *s = "ab" "\x1" "2";"#);
+    assert!(format!("{:?}", unit).contains(r#"StringLiteral { wide: false, contents: ["ab", "\\x1", "2"], value: [97, 98, 1, 50] }"#));
+    let unit = parse(r#"int *s = "é" L"\u20AC";"#);
+    assert!(format!("{:?}", unit).contains(r#"StringLiteral { wide: true, contents: ["é", "\\u20AC"], value: [233, 8364] }"#));
+
+    assert_eq!(constant(r#"int x = sizeof("ab" "\x1" "2");"#), Integer::new(IntegerType::UnsignedLong { bits: 64 }, 5));
+    assert_eq!(constant(r#"int x = sizeof("é" L"\u20AC");"#), Integer::new(IntegerType::UnsignedLong { bits: 64 }, 12));
+}
+
+#[test]
+fn integer_and_pointer_widths_depend_on_the_target() {
+    let unit = parse("long long a; long b; struct s { char c; long l; char *p; }; enum { N = sizeof(long) };");
+    let integer_type = |env: &mut types::TypeEnvironment, index| match resolve_type(declaration(&unit, index), env) {
+        types::Type::Integer(integer_type) => integer_type,
+        other => panic!("expected an integer, found {}", other)
+    };
+    let struct_layout = |env: &mut types::TypeEnvironment| match resolve_type(declaration(&unit, 2), env) {
+        types::Type::Struct(id) => env.registry().get_struct(id).unwrap().layout(),
+        other => panic!("expected a struct, found {}", other)
+    };
+
+    let mut env = types::TypeEnvironment::new();
+    assert_eq!(integer_type(&mut env, 0), types::IntegerType::LongLong);
+    assert_eq!(integer_type(&mut env, 1), types::IntegerType::Long { bits: 64 });
+    assert_eq!(struct_layout(&mut env), types::Layout::new(24, 8));
+    resolve_type(declaration(&unit, 3), &mut env);
+    assert_eq!(env.lookup_constant("N".into()), Some(8));
+
+    let mut env = types::TypeEnvironment::with_target(TargetInfo::new(DataModel::ILP32));
+    assert_eq!(integer_type(&mut env, 0), types::IntegerType::LongLong);
+    assert_eq!(integer_type(&mut env, 1), types::IntegerType::Long { bits: 32 });
+    assert_eq!(struct_layout(&mut env), types::Layout::new(12, 4));
+    resolve_type(declaration(&unit, 3), &mut env);
+    assert_eq!(env.lookup_constant("N".into()), Some(4));
+
+    let mut env = types::TypeEnvironment::with_target(TargetInfo::new(DataModel::LLP64));
+    assert_eq!(integer_type(&mut env, 1), types::IntegerType::Long { bits: 32 });
+    assert_eq!(struct_layout(&mut env), types::Layout::new(16, 8));
+}
+
+#[test]
+fn parse_preprocessed_tokens() {
+    let output = preprocessor::Preprocessor::new()
+        .preprocess_str("test.c", "#define TYPE(name) typedef long name;\nTYPE(T)\nT x;")
+        .unwrap();
+    let typedef_names = lexer::TypedefNames::new();
+    let end = output.tokens.last().unwrap().location.clone();
+    let unit = parse_tokens(output.lexer_tokens(typedef_names.clone()), &typedef_names, &end).unwrap();
+    assert_eq!(declared_identifiers(declaration(&unit, 0)), vec!["T"]);
+    assert_eq!(type_specifiers(declaration(&unit, 1)), vec![TypeSpecifier::Typedef("T".into())]);
+
+    let output = preprocessor::Preprocessor::new().preprocess_str("test.c", "int x").unwrap();
+    let typedef_names = lexer::TypedefNames::new();
+    let end = output.tokens.last().unwrap().location.clone();
+    assert!(parse_tokens(output.lexer_tokens(typedef_names.clone()), &typedef_names, &end).is_err());
+}
+
+#[test]
+fn floating_and_long_long_layouts_depend_on_the_target() {
+    let unit = parse("struct s { char c; long long l; double d; long double x; };");
+    let layout = |data_model: DataModel| {
+        let mut env = types::TypeEnvironment::with_target(TargetInfo::new(data_model));
+        match resolve_type(declaration(&unit, 0), &mut env) {
+            types::Type::Struct(id) => {
+                let s = env.registry().get_struct(id).unwrap();
+                let offsets = ["l", "d", "x"].iter().map(|name| match s.field((*name).into()) {
+                    Some(types::Field::Field { offset, .. }) => *offset,
+                    other => panic!("expected a field, found {:?}", other)
+                }).collect::<Vec<_>>();
+                (offsets, s.layout())
+            },
+            other => panic!("expected a struct, found {}", other)
+        }
+    };
+    assert_eq!(layout(DataModel::LP64), (vec![8, 16, 32], types::Layout::new(48, 16)));
+    assert_eq!(layout(DataModel::ILP32), (vec![4, 12, 20], types::Layout::new(32, 4)));
+    assert_eq!(layout(DataModel::LLP64), (vec![8, 16, 24], types::Layout::new(32, 8)));
+}
+
+#[test]
+fn universal_character_names_in_identifiers() {
+    let unit = parse("typedef int caf\\u00e9; café x; int \\u00e9t\\u00e9 = x;");
+    assert_eq!(declared_identifiers(declaration(&unit, 0)), vec!["café"]);
+    assert_eq!(type_specifiers(declaration(&unit, 1)), vec![TypeSpecifier::Typedef("café".into())]);
+    assert_eq!(declared_identifiers(declaration(&unit, 2)), vec!["été"]);
+}
+
+fn constant_value(expression: &ConditionalExpression) -> Option<i128> {
+    let location = (Location::default(), Location::default());
+    let value = types::constant_expression::evaluate(expression, &location, &mut types::TypeEnvironment::new()).ok()?;
+    Some(value.value)
+}
+
+/// Spells out the structure of an initializer, with constant expressions replaced by their values
+/// and anything else by `?`, e.g. `{.a = 1, [2] = {3, ?}}`
+fn initializer_shape(initializer: &Initializer) -> String {
+    match initializer {
+        Initializer::AssignmentExpression(AssignmentExpression::ConditionalExpression(expression)) => {
+            constant_value(expression).map(|value| value.to_string()).unwrap_or_else(|| "?".to_string())
+        },
+        Initializer::AssignmentExpression(_) => "?".to_string(),
+        Initializer::InitializerList(list) => {
+            let items: Vec<String> = list.iter().map(|item| {
+                let designation: String = item.designator.iter().flatten().map(|designator| match designator {
+                    Designator::Index(index) => format!("[{}]", constant_value(&index.0).unwrap()),
+                    Designator::Field(name) => format!(".{}", name)
+                }).collect();
+                match designation.as_str() {
+                    "" => initializer_shape(&item.initializer),
+                    _ => format!("{} = {}", designation, initializer_shape(&item.initializer))
+                }
+            }).collect();
+            format!("{{{}}}", items.join(", "))
+        }
+    }
+}
+
+/// The shape of the initializer of the first declarator of the last declaration
+fn initializer(source: &str) -> String {
+    let unit = parse(source);
+    match declaration(&unit, unit.len() - 1).init_declarator_list[0].initializer {
+        Some(ref initializer) => initializer_shape(initializer),
+        None => panic!("expected an initializer")
+    }
+}
+
+#[test]
+fn initializer_lists_keep_every_item() {
+    assert_eq!(initializer("int x = 1;"), "1");
+    assert_eq!(initializer("int a[1] = {7};"), "{7}");
+    assert_eq!(initializer("int a[] = {1, 2, 3};"), "{1, 2, 3}");
+    assert_eq!(initializer("int x; int *a[] = {&x, 0, 1 + 1};"), "{?, 0, 2}");
+}
+
+#[test]
+fn designated_initializers() {
+    assert_eq!(initializer("struct p { int x, y; } p = {.y = 2, .x = 1};"), "{.y = 2, .x = 1}");
+    assert_eq!(initializer("int a[8] = {[4] = 1, 2, [1 + 1] = 3};"), "{[4] = 1, 2, [2] = 3}");
+    assert_eq!(initializer("struct { int a[3]; } s = {.a[2] = 5, .a = {1}};"), "{.a[2] = 5, .a = {1}}");
+    assert_eq!(initializer("struct { struct { int x; } in[2]; } s = {.in[1].x = 9};"), "{.in[1].x = 9}");
+    // a designator can name a member that is spelled like a typedef name
+    assert_eq!(initializer("typedef int T; struct { int T; } s = {.T = 1};"), "{.T = 1}");
+    assert!(parse_fails("int a[2] = {[0] 1};"));
+    assert!(parse_fails("int a[2] = {[] = 1};"));
+}
+
+#[test]
+fn nested_initializer_lists() {
+    assert_eq!(initializer("int m[2][2] = {{1, 2}, {3, 4}};"), "{{1, 2}, {3, 4}}");
+    assert_eq!(initializer("int m[2][2] = {{1}, 2, {{3}}};"), "{{1}, 2, {{3}}}");
+    assert_eq!(initializer("struct { int a; struct { int b, c; } s; } x = {1, {.c = 3, 2}};"), "{1, {.c = 3, 2}}");
+    assert_eq!(initializer("int x = {4};"), "{4}");
+}
+
+#[test]
+fn initializer_list_trailing_commas() {
+    assert_eq!(initializer("int a[] = {1,};"), "{1}");
+    assert_eq!(initializer("int a[] = {1, 2,};"), "{1, 2}");
+    assert_eq!(initializer("int m[][2] = {{1,}, {2, 3,},};"), "{{1}, {2, 3}}");
+    assert_eq!(initializer("int a[] = {[1] = 1,};"), "{[1] = 1}");
+    assert!(parse_fails("int a[] = {};"));
+    assert!(parse_fails("int a[] = {,};"));
+    assert!(parse_fails("int a[] = {1,,};"));
+    assert!(parse_fails("int a[] = {1, 2,,};"));
+}
+
+#[test]
+fn compound_literals_keep_every_item() {
+    let unit = parse("int *p = (int[]){1, 2, 3,}; struct s { int x, y; }; struct s q = (struct s){.x = 1, .y = 2};");
+    let debug = format!("{:?}", unit);
+    assert_eq!(debug.matches("TypeInitializerList").count(), 2);
+    assert_eq!(debug.matches("InitializerListItem").count(), 5);
+    assert!(debug.contains("Field(\"x\")"));
+    assert!(debug.contains("Field(\"y\")"));
+}
+
+/// Parses a file that contains syntax errors, and describes each error by the location and spelling of the
+/// unexpected token
+fn parse_with_errors(source: &str) -> (TranslationUnit, Vec<String>) {
+    let (unit, errors) = parse_translation_unit_with_recovery(Lexer::new(OsString::from("test.c"), source));
+    let errors = errors.into_iter().map(|error| match error {
+        errors::CompilationError::ParseError(ParseError::UnrecognizedToken { token: Some((location, token, _)), .. }) => {
+            format!("{}:{} {}", location.line, location.column, token)
+        },
+        errors::CompilationError::UnexpectedEndOfFile(_) => "end of file".to_string(),
+        errors::CompilationError::ParseError(ParseError::User { error }) => {
+            format!("{}:{} {:?}", error.start.line, error.start.column, error.kind)
+        },
+        other => panic!("expected an unrecognized token, found {}", other)
+    }).collect();
+    (unit, errors)
+}
+
+#[test]
+fn recover_from_errors_in_external_declarations() {
+    let (unit, errors) = parse_with_errors("int a = ;\nint b;\nint c d, e;\nlong = 1; int f;");
+    assert_eq!(errors, vec!["1:9 ;", "3:7 d", "4:6 ="]);
+    assert_eq!(unit.len(), 2);
+    assert_eq!(declared_identifiers(declaration(&unit, 0)), vec!["b"]);
+    assert_eq!(declared_identifiers(declaration(&unit, 1)), vec!["f"]);
+}
+
+#[test]
+fn recover_from_errors_in_statements() {
+    let source = "int f(int x) {\n    x = (1 + ;\n    if (x) { x = * ; x++; }\n    return x;\n}\nint g;";
+    let (unit, errors) = parse_with_errors(source);
+    assert_eq!(errors, vec!["2:14 ;", "3:20 ;"]);
+    assert_eq!(unit.len(), 2);
+    let body = function_body(&unit, 0);
+    assert_eq!(body.len(), 2);
+    match body[0].value {
+        BlockItem::Statement(ref statement) => match **statement {
+            Statement::Selection(SelectionStatement::If { ref body, .. }) => match body.value {
+                Statement::Compound(ref block) => assert_eq!(block.len(), 1),
+                ref other => panic!("expected a block, found {:?}", other)
+            },
+            ref other => panic!("expected an if statement, found {:?}", other)
+        },
+        ref other => panic!("expected a statement, found {:?}", other)
+    }
+    assert_eq!(declared_identifiers(declaration(&unit, 1)), vec!["g"]);
+}
+
+#[test]
+fn recover_at_the_end_of_a_block() {
+    let (unit, errors) = parse_with_errors("void f(void) { int a; g(a, 2 } void h(void) { { ( } return; } int i;");
+    assert_eq!(errors, vec!["1:30 }", "1:51 }"]);
+    assert_eq!(unit.len(), 3);
+    assert_eq!(function_body(&unit, 0).len(), 1);
+    assert_eq!(function_body(&unit, 1).len(), 2);
+}
+
+#[test]
+fn recover_from_errors_in_struct_members() {
+    let (unit, errors) = parse_with_errors("struct s { int a b; int c; int 1; }; struct s v;");
+    assert_eq!(errors, vec!["1:18 b", "1:32 1"]);
+    assert_eq!(unit.len(), 2);
+    let mut env = types::TypeEnvironment::new();
+    match resolve_type(declaration(&unit, 0), &mut env) {
+        types::Type::Struct(id) => {
+            let s = env.registry().get_struct(id).unwrap();
+            assert!(s.field("a".into()).is_none());
+            assert!(s.field("c".into()).is_some());
+        },
+        other => panic!("expected a struct, found {}", other)
+    }
+}
+
+#[test]
+fn typedef_names_after_recovering() {
+    // the declaration of `x` is abandoned, but `T` is still a typedef name in the blocks that follow
+    let source = "typedef int T; void f(void) { T x = ; { T y; } T z; } typedef int U, V W; T a; U b;";
+    let (unit, errors) = parse_with_errors(source);
+    assert_eq!(errors, vec!["1:37 ;", "1:72 W"]);
+    assert_eq!(unit.len(), 4);
+    let body = function_body(&unit, 1);
+    assert_eq!(body.len(), 2);
+    assert!(body.iter().all(|item| match item.value {
+        BlockItem::Declaration(_) | BlockItem::StaticAssert(_) => true,
+        BlockItem::Statement(ref statement) => match **statement {
+            Statement::Compound(ref block) => block.iter().all(is_declaration),
+            _ => false
+        }
+    }));
+    assert_eq!(type_specifiers(declaration(&unit, 2)), vec![TypeSpecifier::Typedef("T".into())]);
+    // `U` was declared before the error, `V` wasn't
+    assert_eq!(type_specifiers(declaration(&unit, 3)), vec![TypeSpecifier::Typedef("U".into())]);
+}
+
+#[test]
+fn unrecoverable_syntax_errors() {
+    // the declarations before the external declaration that contains the error are kept
+    let (unit, errors) = parse_with_errors("int a; int f(void) { int x = 1; return x;");
+    assert_eq!(unit.len(), 1);
+    assert_eq!(errors, vec!["end of file"]);
+
+    let (unit, errors) = parse_with_errors("int a = 1 +");
+    assert!(unit.is_empty());
+    assert_eq!(errors, vec!["end of file"]);
+
+    // a lexical error ends the input, in the middle of a declaration or between two of them
+    let (unit, errors) = parse_with_errors("int a; int f(void) { int b = 1abc; } int c;");
+    assert_eq!(unit.len(), 1);
+    assert_eq!(declared_identifiers(declaration(&unit, 0)), vec!["a"]);
+    assert_eq!(errors, vec!["1:30 InvalidIntegerSuffix"]);
+
+    let (unit, errors) = parse_with_errors("int a; int b;\n@ int c;");
+    assert_eq!(unit.len(), 2);
+    assert_eq!(errors, vec!["2:1 StrayCharacter('@')"]);
+
+    // without recovery the first syntax error is reported
+    for source in ["int a = ; int b c;", "int a = ; int f(void) { int b;"].iter() {
+        match parse_translation_unit(Lexer::new(OsString::from("test.c"), source)) {
+            Err(errors::CompilationError::ParseError(ParseError::UnrecognizedToken { token: Some((location, Token::Semicolon, _)), .. })) => {
+                assert_eq!(location.column, 9)
+            },
+            other => panic!("expected an unrecognized token, found {:?}", other)
+        }
+    }
+}
+
+fn syntax_error(source: &str) -> String {
+    match parse_translation_unit(Lexer::new(OsString::from("test.c"), source)) {
+        Err(error) => error.to_string(),
+        Ok(_) => panic!("expected a syntax error in {:?}", source)
+    }
+}
+
+#[test]
+fn syntax_error_messages() {
+    assert_eq!(syntax_error("int x y;"), "test.c:1:7: error: expected ';' after declaration, found identifier 'y'");
+    assert_eq!(syntax_error("int x = 1\nint y;"), "test.c:2:1: error: expected ';' after expression, found 'int'");
+    assert_eq!(syntax_error("int x = 'a' \"s\";"), "test.c:1:13: error: expected ';' after expression, found string literal \"s\"");
+    assert_eq!(syntax_error("int x = ;"), "test.c:1:9: error: expected expression or '{', found ';'");
+    assert_eq!(syntax_error("int f(int a int b);"), "test.c:1:13: error: expected ')' or ',', found 'int'");
+    assert_eq!(syntax_error("int;;"), "test.c:1:5: error: expected declaration, found ';'");
+    assert_eq!(syntax_error("int f(void) { if x; }"), "test.c:1:18: error: expected '(', found identifier 'x'");
+    assert_eq!(syntax_error("int f(void) { goto 1; }"), "test.c:1:20: error: expected identifier, found constant '1'");
+    assert_eq!(syntax_error("void f(void) { while (1) }"), "test.c:1:26: error: expected statement, found '}'");
+}
+
+#[test]
+fn syntax_error_hints() {
+    assert_eq!(syntax_error("void f(int x) { x = 1 }"),
+               "test.c:1:23: error: expected ';' after expression, found '}'\nnote: a ';' is probably missing before the '}'");
+    assert_eq!(syntax_error("int f(void) { return }"),
+               "test.c:1:22: error: expected expression or ';', found '}'\nnote: a ';' is probably missing before the '}'");
+    assert_eq!(syntax_error("foo x;"),
+               "test.c:1:1: error: expected declaration, found identifier 'foo'\nnote: unknown type name 'foo'");
+    assert_eq!(syntax_error("void f(void) { int x;\n  x = 1;"),
+               "test.c:2:9: error: expected statement, declaration or '}', found end of file\nnote: a '}' is probably missing at the end of the file");
+}
+
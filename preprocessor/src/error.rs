@@ -0,0 +1,98 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use ast::Location;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreprocessorErrorKind {
+    UnterminatedComment,
+    FileNotFound(String),
+    /// A file could not be read, holds the name of the file and the reason
+    UnreadableFile(String, String),
+    IncludeDepthExceeded,
+    /// `#include` wasn't followed by `"file"` or `<file>`
+    InvalidInclude,
+    /// The message of an `#error` directive
+    ErrorDirective(String),
+    UnknownDirective(String),
+    /// A directive that must be followed by a macro name wasn't
+    MissingMacroName(String),
+    InvalidMacroParameterList,
+    DuplicateMacroParameter(String),
+    /// A macro was redefined with a different definition, holds the location of the previous definition
+    MacroRedefinition(String, Location),
+    /// `defined` can't be defined or undefined as a macro
+    InvalidMacroName(String),
+    /// `#` in a function-like macro must be followed by a parameter
+    StringizeWithoutParameter,
+    /// `##` can't appear at either end of a replacement list
+    PasteAtEdge,
+    /// Pasting two tokens together didn't give a valid preprocessing token
+    InvalidPaste(String, String),
+    UnterminatedArgumentList(String),
+    WrongArgumentCount { name: String, expected: usize, given: usize },
+    /// An `#if` (or `#ifdef`, `#ifndef`) without a matching `#endif`
+    UnterminatedConditional,
+    /// An `#elif`, `#else` or `#endif` without a matching `#if`, or following an `#else`
+    UnbalancedConditional(String),
+    /// An `#elif` or `#else` following an `#else`
+    ConditionalAfterElse(String),
+    InvalidExpression(String),
+    DivisionByZero,
+    InvalidLineDirective,
+    InvalidPragmaOperator
+}
+
+/// An error encountered while preprocessing a translation unit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreprocessorError {
+    pub kind: PreprocessorErrorKind,
+    pub location: Location
+}
+
+impl PreprocessorError {
+    pub fn new(kind: PreprocessorErrorKind, location: Location) -> PreprocessorError {
+        PreprocessorError {
+            kind,
+            location
+        }
+    }
+}
+
+impl Display for PreprocessorError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use PreprocessorErrorKind::*;
+        write!(f, "{}: error: ", self.location)?;
+        match &self.kind {
+            UnterminatedComment => write!(f, "unterminated comment"),
+            FileNotFound(name) => write!(f, "{}: no such file or directory", name),
+            UnreadableFile(name, reason) => write!(f, "{}: {}", name, reason),
+            IncludeDepthExceeded => write!(f, "#include nested too deeply"),
+            InvalidInclude => write!(f, "#include expects \"FILENAME\" or <FILENAME>"),
+            ErrorDirective(message) => write!(f, "#error {}", message),
+            UnknownDirective(name) => write!(f, "invalid preprocessing directive #{}", name),
+            MissingMacroName(directive) => write!(f, "no macro name given in #{} directive", directive),
+            InvalidMacroParameterList => write!(f, "invalid macro parameter list"),
+            DuplicateMacroParameter(name) => write!(f, "duplicate macro parameter '{}'", name),
+            MacroRedefinition(name, previous) => write!(f, "'{}' redefined\nprevious definition at {}", name, previous),
+            InvalidMacroName(name) => write!(f, "'{}' cannot be used as a macro name", name),
+            StringizeWithoutParameter => write!(f, "'#' is not followed by a macro parameter"),
+            PasteAtEdge => write!(f, "'##' cannot appear at either end of a macro expansion"),
+            InvalidPaste(lhs, rhs) => write!(f, "pasting \"{}\" and \"{}\" does not give a valid preprocessing token", lhs, rhs),
+            UnterminatedArgumentList(name) => write!(f, "unterminated argument list invoking macro '{}'", name),
+            WrongArgumentCount { name, expected, given } => {
+                write!(f, "macro '{}' requires {} arguments, but {} given", name, expected, given)
+            },
+            UnterminatedConditional => write!(f, "unterminated conditional directive"),
+            UnbalancedConditional(directive) => write!(f, "#{} without #if", directive),
+            ConditionalAfterElse(directive) => write!(f, "#{} after #else", directive),
+            InvalidExpression(reason) => write!(f, "invalid preprocessor expression: {}", reason),
+            DivisionByZero => write!(f, "division by zero in #if"),
+            InvalidLineDirective => write!(f, "#line directive requires a positive integer argument"),
+            InvalidPragmaOperator => write!(f, "_Pragma takes a parenthesized string literal")
+        }
+    }
+}
+
+impl Error for PreprocessorError {}
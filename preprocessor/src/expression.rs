@@ -0,0 +1,267 @@
+use ast::{Integer, Location, TargetInfo};
+use lexer::Token;
+
+use crate::error::{PreprocessorError, PreprocessorErrorKind};
+use crate::tokens::{PPToken, PPTokenKind};
+
+/// In `#if` expressions every integer acts as if it had the type `intmax_t` or `uintmax_t` (C99 6.10.1p4)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    Signed(i64),
+    Unsigned(u64)
+}
+
+impl Value {
+    fn is_true(self) -> bool {
+        match self {
+            Value::Signed(v) => v != 0,
+            Value::Unsigned(v) => v != 0
+        }
+    }
+
+    fn as_u64(self) -> u64 {
+        match self {
+            Value::Signed(v) => v as u64,
+            Value::Unsigned(v) => v
+        }
+    }
+
+    fn from_bool(b: bool) -> Value {
+        Value::Signed(b as i64)
+    }
+
+    fn from_integer(i: Integer) -> Value {
+        if i.type_.is_signed() {
+            Value::Signed(i.value as i64)
+        }
+        else {
+            Value::Unsigned(i.value as u64)
+        }
+    }
+}
+
+/// Evaluates the controlling expression of an `#if` or `#elif` directive.
+///
+/// `defined` operators and macros must have been replaced already, any identifier that remains evaluates to 0.
+/// The values of character constants depend on the target.
+pub fn evaluate(tokens: &[PPToken], location: &Location, target: TargetInfo) -> Result<bool, PreprocessorError> {
+    let mut parser = ExpressionParser { tokens, pos: 0, location, target };
+    if tokens.is_empty() {
+        return Err(parser.error("expected an expression"));
+    }
+    let value = parser.expression(true)?;
+    match tokens.get(parser.pos) {
+        Some(token) => Err(parser.error(&format!("unexpected token '{}'", token))),
+        None => Ok(value.is_true())
+    }
+}
+
+struct ExpressionParser<'a> {
+    tokens: &'a [PPToken],
+    pos: usize,
+    location: &'a Location,
+    target: TargetInfo
+}
+
+/// Binary operators from the highest to the lowest precedence (C99 6.5.5 to 6.5.14)
+const PRECEDENCE: &[&[&str]] = &[
+    &["*", "/", "%"],
+    &["+", "-"],
+    &["<<", ">>"],
+    &["<", ">", "<=", ">="],
+    &["==", "!="],
+    &["&"],
+    &["^"],
+    &["|"],
+    &["&&"],
+    &["||"]
+];
+
+impl<'a> ExpressionParser<'a> {
+    fn error(&self, reason: &str) -> PreprocessorError {
+        let location = self.tokens.get(self.pos)
+            .map(|token| token.location.clone())
+            .unwrap_or_else(|| self.location.clone());
+        PreprocessorError::new(PreprocessorErrorKind::InvalidExpression(reason.to_string()), location)
+    }
+
+    fn peek_punctuator(&self, spelling: &str) -> bool {
+        self.tokens.get(self.pos).is_some_and(|token| token.is_punctuator(spelling))
+    }
+
+    fn expect(&mut self, spelling: &str) -> Result<(), PreprocessorError> {
+        if self.peek_punctuator(spelling) {
+            self.pos += 1;
+            Ok(())
+        }
+        else {
+            Err(self.error(&format!("expected '{}'", spelling)))
+        }
+    }
+
+    /// `evaluate` is false for the operands that are not evaluated because of short-circuiting, which can't
+    /// cause errors such as a division by zero.
+    fn expression(&mut self, evaluate: bool) -> Result<Value, PreprocessorError> {
+        let mut value = self.conditional(evaluate)?;
+        while self.peek_punctuator(",") {
+            self.pos += 1;
+            value = self.conditional(evaluate)?;
+        }
+        Ok(value)
+    }
+
+    fn conditional(&mut self, evaluate: bool) -> Result<Value, PreprocessorError> {
+        let condition = self.binary(PRECEDENCE.len() - 1, evaluate)?;
+        if !self.peek_punctuator("?") {
+            return Ok(condition);
+        }
+        self.pos += 1;
+        let lhs = self.expression(evaluate && condition.is_true())?;
+        self.expect(":")?;
+        let rhs = self.conditional(evaluate && !condition.is_true())?;
+        let value = if condition.is_true() { lhs } else { rhs };
+        Ok(match (lhs, rhs) {
+            (Value::Signed(_), Value::Signed(_)) => value,
+            _ => Value::Unsigned(value.as_u64())
+        })
+    }
+
+    fn binary(&mut self, level: usize, evaluate: bool) -> Result<Value, PreprocessorError> {
+        let operand = |parser: &mut Self, evaluate| {
+            if level == 0 { parser.unary(evaluate) } else { parser.binary(level - 1, evaluate) }
+        };
+
+        let mut lhs = operand(self, evaluate)?;
+        loop {
+            let op = match self.tokens.get(self.pos) {
+                Some(token) if token.kind == PPTokenKind::Punctuator && PRECEDENCE[level].contains(&&*token.spelling) => token,
+                _ => return Ok(lhs)
+            };
+            self.pos += 1;
+            lhs = match &*op.spelling {
+                "&&" => {
+                    let rhs = operand(self, evaluate && lhs.is_true())?;
+                    Value::from_bool(lhs.is_true() && rhs.is_true())
+                },
+                "||" => {
+                    let rhs = operand(self, evaluate && !lhs.is_true())?;
+                    Value::from_bool(lhs.is_true() || rhs.is_true())
+                },
+                spelling => {
+                    let rhs = operand(self, evaluate)?;
+                    if evaluate {
+                        apply(spelling, lhs, rhs).ok_or_else(|| {
+                            PreprocessorError::new(PreprocessorErrorKind::DivisionByZero, op.location.clone())
+                        })?
+                    }
+                    else {
+                        apply(spelling, lhs, rhs).unwrap_or(Value::Signed(0))
+                    }
+                }
+            };
+        }
+    }
+
+    fn unary(&mut self, evaluate: bool) -> Result<Value, PreprocessorError> {
+        let token = match self.tokens.get(self.pos) {
+            Some(token) => token,
+            None => return Err(self.error("expected an expression"))
+        };
+        self.pos += 1;
+        match token.kind {
+            PPTokenKind::Punctuator => match &*token.spelling {
+                "(" => {
+                    let value = self.expression(evaluate)?;
+                    self.expect(")")?;
+                    Ok(value)
+                },
+                "+" => self.unary(evaluate),
+                "-" => Ok(match self.unary(evaluate)? {
+                    Value::Signed(v) => Value::Signed(v.wrapping_neg()),
+                    Value::Unsigned(v) => Value::Unsigned(v.wrapping_neg())
+                }),
+                "~" => Ok(match self.unary(evaluate)? {
+                    Value::Signed(v) => Value::Signed(!v),
+                    Value::Unsigned(v) => Value::Unsigned(!v)
+                }),
+                "!" => Ok(Value::from_bool(!self.unary(evaluate)?.is_true())),
+                _ => {
+                    self.pos -= 1;
+                    Err(self.error(&format!("unexpected token '{}'", token)))
+                }
+            },
+            PPTokenKind::Identifier => Ok(Value::Signed(0)),
+            PPTokenKind::Number | PPTokenKind::CharacterConstant => match token.to_token(self.target) {
+                Ok((_, Token::IntLiteral(value), _)) => Ok(Value::from_integer(value)),
+                Ok((_, Token::CharLiteral { value, .. }, _)) => Ok(Value::from_integer(value)),
+                Ok((_, Token::FloatLiteral(_), _)) => {
+                    self.pos -= 1;
+                    Err(self.error("floating constant in preprocessor expression"))
+                },
+                _ => {
+                    self.pos -= 1;
+                    Err(self.error(&format!("invalid constant '{}'", token)))
+                }
+            },
+            _ => {
+                self.pos -= 1;
+                Err(self.error(&format!("unexpected token '{}'", token)))
+            }
+        }
+    }
+}
+
+/// Applies a binary operator after the usual arithmetic conversions, returns `None` for a division by zero
+fn apply(op: &str, lhs: Value, rhs: Value) -> Option<Value> {
+    // the result of a shift has the type of its left operand
+    if op == "<<" || op == ">>" {
+        let amount = (rhs.as_u64() & 63) as u32;
+        return Some(match (lhs, op) {
+            (Value::Signed(v), "<<") => Value::Signed(v.wrapping_shl(amount)),
+            (Value::Signed(v), _) => Value::Signed(v.wrapping_shr(amount)),
+            (Value::Unsigned(v), "<<") => Value::Unsigned(v.wrapping_shl(amount)),
+            (Value::Unsigned(v), _) => Value::Unsigned(v.wrapping_shr(amount))
+        });
+    }
+
+    Some(match (lhs, rhs) {
+        (Value::Signed(a), Value::Signed(b)) => match op {
+            "*" => Value::Signed(a.wrapping_mul(b)),
+            "/" if b == 0 => return None,
+            "/" => Value::Signed(a.wrapping_div(b)),
+            "%" if b == 0 => return None,
+            "%" => Value::Signed(a.wrapping_rem(b)),
+            "+" => Value::Signed(a.wrapping_add(b)),
+            "-" => Value::Signed(a.wrapping_sub(b)),
+            "&" => Value::Signed(a & b),
+            "^" => Value::Signed(a ^ b),
+            "|" => Value::Signed(a | b),
+            _ => Value::from_bool(compare(op, a, b))
+        },
+        _ => {
+            let (a, b) = (lhs.as_u64(), rhs.as_u64());
+            match op {
+                "*" => Value::Unsigned(a.wrapping_mul(b)),
+                "/" => Value::Unsigned(a.checked_div(b)?),
+                "%" => Value::Unsigned(a.checked_rem(b)?),
+                "+" => Value::Unsigned(a.wrapping_add(b)),
+                "-" => Value::Unsigned(a.wrapping_sub(b)),
+                "&" => Value::Unsigned(a & b),
+                "^" => Value::Unsigned(a ^ b),
+                "|" => Value::Unsigned(a | b),
+                _ => Value::from_bool(compare(op, a, b))
+            }
+        }
+    })
+}
+
+fn compare<T: PartialOrd>(op: &str, a: T, b: T) -> bool {
+    match op {
+        "<" => a < b,
+        ">" => a > b,
+        "<=" => a <= b,
+        ">=" => a >= b,
+        "==" => a == b,
+        _ => a != b
+    }
+}
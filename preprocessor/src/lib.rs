@@ -0,0 +1,845 @@
+//! A C99 preprocessor (translation phases 1 to 4).
+//!
+//! The preprocessor turns a source file into a sequence of preprocessing tokens, with every directive
+//! executed and every macro expanded. The tokens can then be converted into `lexer::Token`s and handed
+//! to the parser with `Preprocessed::lexer_tokens` and `parser::parse_tokens`.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::OsString;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ast::{Location, TargetInfo};
+use lexer::{LexicalError, Spanned, Token, TypedefNames, SourceOptions};
+
+mod error;
+mod expression;
+mod macros;
+mod tokens;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::error::{PreprocessorError, PreprocessorErrorKind};
+pub use self::macros::{Macro, MacroKind};
+pub use self::tokens::{PPToken, PPTokenKind};
+use self::tokens::{tokenize, tokenize_fragment};
+
+/// The maximum nesting depth of `#include` directives, which stops files that include themselves
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// A `#pragma` directive (or `_Pragma` operator) that the preprocessor doesn't handle itself
+#[derive(Debug, Clone)]
+pub struct Pragma {
+    pub tokens: Vec<PPToken>,
+    pub location: Location
+}
+
+/// The output of the preprocessor
+#[derive(Debug, Clone)]
+pub struct Preprocessed {
+    pub tokens: Vec<PPToken>,
+    pub pragmas: Vec<Pragma>,
+    /// Every file that was included, in the order they were included
+    pub included_files: Vec<PathBuf>,
+    /// The target that the tokens are converted for
+    pub target: TargetInfo
+}
+
+impl Preprocessed {
+    /// Converts the preprocessing tokens into tokens, in the format expected by the parser.
+    ///
+    /// Identifiers are classified as they are consumed, so `typedef_names` must be the one that the parser
+    /// declares typedef names in.
+    pub fn lexer_tokens(&self, typedef_names: TypedefNames) -> impl Iterator<Item = Spanned<Token<'_>, Location, LexicalError>> {
+        self.tokens.iter().map(move |token| {
+            token.to_token(self.target).map(|(start, token, end)| (start, typedef_names.classify(token), end))
+        })
+    }
+}
+
+/// An `#if` section that is open in a file
+struct Conditional {
+    location: Location,
+    /// Whether one of the groups of the section was already included
+    taken: bool,
+    seen_else: bool
+}
+
+/// A file on the include stack
+struct SourceFile {
+    path: PathBuf,
+    tokens: Vec<PPToken>,
+    pos: usize,
+    conditionals: Vec<Conditional>,
+
+    /// Set by `#line`, the difference between the presumed line number and the physical one
+    line_delta: isize,
+    /// Set by `#line`, the presumed name of the file
+    presumed_filename: Option<Rc<OsString>>
+}
+
+impl SourceFile {
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn take(&mut self) -> PPToken {
+        let mut token = self.tokens[self.pos].clone();
+        self.pos += 1;
+        if self.line_delta != 0 {
+            token.location.line = (token.location.line as isize + self.line_delta) as usize;
+        }
+        if let Some(ref filename) = self.presumed_filename {
+            token.location.filename = filename.clone();
+        }
+        token
+    }
+}
+
+pub struct Preprocessor {
+    macros: HashMap<Rc<str>, Rc<Macro>>,
+    include_paths: Vec<PathBuf>,
+    system_include_paths: Vec<PathBuf>,
+
+    files: Vec<SourceFile>,
+
+    /// Tokens that are read before the ones from the current file, which are the results of macro expansions
+    pending: VecDeque<PPToken>,
+    /// When set, the end of `pending` is the end of the input. Used to expand macro arguments on their own.
+    isolated: bool,
+
+    /// Files that contained `#pragma once`
+    once: HashSet<PathBuf>,
+    pragmas: Vec<Pragma>,
+    included_files: Vec<PathBuf>,
+
+    date: String,
+    time: String,
+
+    source_options: SourceOptions,
+    target: TargetInfo
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        Preprocessor::new()
+    }
+}
+
+impl Preprocessor {
+    pub fn new() -> Preprocessor {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let (year, month, day) = civil_from_days((now / 86400) as i64);
+        let seconds = now % 86400;
+
+        let mut preprocessor = Preprocessor {
+            macros: HashMap::new(),
+            include_paths: Vec::new(),
+            system_include_paths: Vec::new(),
+            files: Vec::new(),
+            pending: VecDeque::new(),
+            isolated: false,
+            once: HashSet::new(),
+            pragmas: Vec::new(),
+            included_files: Vec::new(),
+            date: format!("\"{} {:2} {}\"", MONTHS[month as usize - 1], day, year),
+            time: format!("\"{:02}:{:02}:{:02}\"", seconds / 3600, seconds / 60 % 60, seconds % 60),
+            source_options: SourceOptions::default(),
+            target: TargetInfo::default()
+        };
+        for &(name, value) in &[("__STDC__", "1"), ("__STDC_VERSION__", "199901L"), ("__STDC_HOSTED__", "1")] {
+            preprocessor.define(name, value).unwrap();
+        }
+        preprocessor
+    }
+
+    /// Adds a directory that is searched by both `#include "file"` and `#include <file>` (like `-I`).
+    pub fn add_include_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.include_paths.push(path.into());
+    }
+
+    /// Adds a directory that is searched after all the other include paths (like `-isystem`).
+    pub fn add_system_include_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.system_include_paths.push(path.into());
+    }
+
+    /// Replaces trigraph sequences in every file that is preprocessed (like `-trigraphs`).
+    pub fn enable_trigraphs(&mut self) {
+        self.source_options.trigraphs = true;
+    }
+
+    /// Sets the target that determines the types of integer and character constants, both in `#if` expressions
+    /// and in the tokens handed to the parser (64 bit Linux by default).
+    pub fn set_target(&mut self, target: TargetInfo) {
+        self.target = target;
+    }
+
+    /// Defines a macro, like `-D name=value`. `name` can have a parameter list, ex: `define("MAX(a, b)", "...")`.
+    pub fn define(&mut self, name: &str, value: &str) -> Result<(), PreprocessorError> {
+        let line = tokenize(Rc::new(OsString::from("<command line>")), &format!("{} {}", name, value), self.source_options)?;
+        let location = line.first().map(|token| token.location.clone()).unwrap_or_default();
+        self.define_macro(line, &location)
+    }
+
+    pub fn undefine(&mut self, name: &str) {
+        self.macros.remove(name);
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.macros.contains_key(name)
+    }
+
+    pub fn preprocess_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Preprocessed, PreprocessorError> {
+        let path = path.as_ref();
+        let location = Location::new(Rc::new(path.as_os_str().to_os_string()), 0, 0, 0);
+        let source = read_file(path, location)?;
+        self.preprocess_str(path, &source)
+    }
+
+    /// Preprocesses `source` as if it was the contents of the file `filename`.
+    pub fn preprocess_str<P: AsRef<Path>>(&mut self, filename: P, source: &str) -> Result<Preprocessed, PreprocessorError> {
+        let result = self.push_file(filename.as_ref().to_path_buf(), source).and_then(|_| {
+            let mut tokens = Vec::new();
+            while let Some(token) = self.next_token()? {
+                tokens.push(token);
+            }
+            Ok(tokens)
+        });
+
+        self.files.clear();
+        self.pending.clear();
+        self.isolated = false;
+        let pragmas = mem::take(&mut self.pragmas);
+        let included_files = mem::take(&mut self.included_files);
+
+        Ok(Preprocessed {
+            tokens: result?,
+            pragmas,
+            included_files,
+            target: self.target
+        })
+    }
+
+    fn push_file(&mut self, path: PathBuf, source: &str) -> Result<(), PreprocessorError> {
+        let tokens = tokenize(Rc::new(path.as_os_str().to_os_string()), source, self.source_options)?;
+        self.files.push(SourceFile {
+            path,
+            tokens,
+            pos: 0,
+            conditionals: Vec::new(),
+            line_delta: 0,
+            presumed_filename: None
+        });
+        Ok(())
+    }
+
+    /// The next token from the files on the include stack, without executing directives
+    fn next_raw_file_token(&mut self) -> Result<Option<PPToken>, PreprocessorError> {
+        loop {
+            let file = match self.files.last_mut() {
+                Some(file) => file,
+                None => return Ok(None)
+            };
+            if !file.at_end() {
+                return Ok(Some(file.take()));
+            }
+            let file = self.files.pop().unwrap();
+            if let Some(conditional) = file.conditionals.into_iter().next() {
+                return Err(PreprocessorError::new(PreprocessorErrorKind::UnterminatedConditional, conditional.location));
+            }
+        }
+    }
+
+    /// The next token from the files on the include stack, after executing any directives
+    fn next_file_token(&mut self) -> Result<Option<PPToken>, PreprocessorError> {
+        loop {
+            match self.next_raw_file_token()? {
+                Some(ref token) if token.at_line_start && token.is_punctuator("#") => self.directive(token)?,
+                token => return Ok(token)
+            }
+        }
+    }
+
+    fn next_unexpanded(&mut self) -> Result<Option<PPToken>, PreprocessorError> {
+        if let Some(token) = self.pending.pop_front() {
+            return Ok(Some(token));
+        }
+        if self.isolated {
+            return Ok(None);
+        }
+        self.next_file_token()
+    }
+
+    /// The next fully macro-expanded token
+    fn next_token(&mut self) -> Result<Option<PPToken>, PreprocessorError> {
+        loop {
+            let token = match self.next_unexpanded()? {
+                Some(token) => token,
+                None => return Ok(None)
+            };
+            if token.kind != PPTokenKind::Identifier || token.hide_set.contains(&token.spelling) {
+                return Ok(Some(token));
+            }
+
+            if let Some(mac) = self.macros.get(&token.spelling).cloned() {
+                if !self.expand_macro(&mac, &token)? {
+                    return Ok(Some(token));
+                }
+                continue;
+            }
+
+            match &*token.spelling {
+                "__FILE__" => {
+                    let filename = token.location.expansion_location().filename.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\"");
+                    return Ok(Some(builtin(&token, PPTokenKind::StringLiteral, &format!("\"{}\"", filename))));
+                },
+                "__LINE__" => {
+                    let line = token.location.expansion_location().line;
+                    return Ok(Some(builtin(&token, PPTokenKind::Number, &line.to_string())));
+                },
+                "__DATE__" => return Ok(Some(builtin(&token, PPTokenKind::StringLiteral, &self.date))),
+                "__TIME__" => return Ok(Some(builtin(&token, PPTokenKind::StringLiteral, &self.time))),
+                "_Pragma" => self.pragma_operator(&token)?,
+                _ => return Ok(Some(token))
+            }
+        }
+    }
+
+    /// Expands a macro invocation and pushes the result back onto the input so that it's rescanned (C99 6.10.3.4).
+    /// Returns false if the name of a function-like macro isn't followed by an argument list, in which case
+    /// it isn't an invocation.
+    fn expand_macro(&mut self, mac: &Macro, name: &PPToken) -> Result<bool, PreprocessorError> {
+        let (args, mut hide_set) = match mac.kind {
+            MacroKind::Object => (Vec::new(), name.hide_set.to_vec()),
+            MacroKind::Function { .. } => {
+                match self.next_unexpanded()? {
+                    Some(ref token) if token.is_punctuator("(") => (),
+                    Some(token) => {
+                        self.pending.push_front(token);
+                        return Ok(false);
+                    },
+                    None => return Ok(false)
+                }
+                let (args, rparen) = self.collect_arguments(mac, name)?;
+                let hide_set = name.hide_set.iter().filter(|name| rparen.hide_set.contains(name)).cloned().collect();
+                (args, hide_set)
+            }
+        };
+        hide_set.push(mac.name.clone());
+        let hide_set = Rc::new(hide_set);
+
+        let mut expansion = mac.substitute(&args, &name.location, |arg| self.expand_isolated(arg))?;
+        for token in expansion.iter_mut() {
+            token.at_line_start = false;
+            token.hide_set = if token.hide_set.is_empty() {
+                hide_set.clone()
+            }
+            else {
+                let mut union = token.hide_set.to_vec();
+                union.extend(hide_set.iter().filter(|name| !token.hide_set.contains(name)).cloned());
+                Rc::new(union)
+            };
+        }
+        if let Some(first) = expansion.first_mut() {
+            first.leading_space = name.leading_space;
+            first.at_line_start = name.at_line_start;
+        }
+
+        for token in expansion.into_iter().rev() {
+            self.pending.push_front(token);
+        }
+        Ok(true)
+    }
+
+    /// Reads the arguments of a function-like macro invocation, up to and including the closing parenthesis.
+    fn collect_arguments(&mut self, mac: &Macro, name: &PPToken) -> Result<(Vec<Vec<PPToken>>, PPToken), PreprocessorError> {
+        let param_count = mac.params().len();
+        let mut args = vec![Vec::new()];
+        let mut depth = 0;
+        let rparen = loop {
+            let mut token = match self.next_unexpanded()? {
+                Some(token) => token,
+                None => return Err(PreprocessorError::new(
+                    PreprocessorErrorKind::UnterminatedArgumentList(mac.name.to_string()),
+                    name.location.clone()
+                ))
+            };
+            // newlines in an argument list are just whitespace
+            if token.at_line_start {
+                token.at_line_start = false;
+                token.leading_space = true;
+            }
+            if depth == 0 {
+                if token.is_punctuator(")") {
+                    break token;
+                }
+                // the variable arguments include the commas that separate them
+                if token.is_punctuator(",") && !(mac.is_variadic() && args.len() == param_count) {
+                    args.push(Vec::new());
+                    continue;
+                }
+            }
+            if token.is_punctuator("(") {
+                depth += 1;
+            }
+            else if token.is_punctuator(")") {
+                depth -= 1;
+            }
+            args.last_mut().unwrap().push(token);
+        };
+
+        if param_count == 0 && args.len() == 1 && args[0].is_empty() {
+            args.clear();
+        }
+        if mac.is_variadic() && args.len() + 1 == param_count {
+            args.push(Vec::new());
+        }
+        if args.len() != param_count {
+            return Err(PreprocessorError::new(
+                PreprocessorErrorKind::WrongArgumentCount { name: mac.name.to_string(), expected: param_count, given: args.len() },
+                name.location.clone()
+            ));
+        }
+        Ok((args, rparen))
+    }
+
+    /// Fully macro-expands a sequence of tokens on its own, without reading anything that follows it.
+    fn expand_isolated(&mut self, tokens: &[PPToken]) -> Result<Vec<PPToken>, PreprocessorError> {
+        let pending = mem::replace(&mut self.pending, tokens.iter().cloned().collect());
+        let isolated = mem::replace(&mut self.isolated, true);
+        let mut expanded = Vec::new();
+        let result = loop {
+            match self.next_token() {
+                Ok(Some(token)) => expanded.push(token),
+                Ok(None) => break Ok(expanded),
+                Err(err) => break Err(err)
+            }
+        };
+        self.pending = pending;
+        self.isolated = isolated;
+        result
+    }
+
+    /// The tokens of the rest of the current line of the current file
+    fn directive_line(&mut self) -> Vec<PPToken> {
+        let mut line = Vec::new();
+        if let Some(file) = self.files.last_mut() {
+            while !file.at_end() && !file.tokens[file.pos].at_line_start {
+                line.push(file.take());
+            }
+        }
+        line
+    }
+
+    fn directive(&mut self, hash: &PPToken) -> Result<(), PreprocessorError> {
+        let mut line = self.directive_line();
+        if line.is_empty() {
+            // the null directive
+            return Ok(());
+        }
+        let name = line.remove(0);
+        let location = &hash.location;
+        match &*name.spelling {
+            _ if name.kind == PPTokenKind::Number => {
+                // GNU line markers (# 42 "file.c") are equivalent to #line
+                line.insert(0, name);
+                self.line_directive(line, location)
+            },
+            _ if name.kind != PPTokenKind::Identifier => {
+                Err(PreprocessorError::new(PreprocessorErrorKind::UnknownDirective(name.spelling.to_string()), name.location))
+            },
+            "define" => self.define_macro(line, location),
+            "undef" => {
+                let name = macro_name(&line, "undef", location)?;
+                self.macros.remove(&name.spelling);
+                Ok(())
+            },
+            "include" => self.include_directive(line, location),
+            "if" => {
+                let condition = self.evaluate_condition(line, location)?;
+                self.push_conditional(location, condition);
+                Ok(())
+            },
+            "ifdef" | "ifndef" => {
+                let defined = self.macros.contains_key(&macro_name(&line, &name.spelling, location)?.spelling);
+                self.push_conditional(location, defined == (&*name.spelling == "ifdef"));
+                Ok(())
+            },
+            "elif" => {
+                let taken = self.current_conditional("elif", location)?.taken;
+                if taken || !self.evaluate_condition(line, location)? {
+                    self.skip_group();
+                }
+                else {
+                    self.current_conditional("elif", location)?.taken = true;
+                }
+                Ok(())
+            },
+            "else" => {
+                let conditional = self.current_conditional("else", location)?;
+                let taken = conditional.taken;
+                conditional.seen_else = true;
+                conditional.taken = true;
+                if taken {
+                    self.skip_group();
+                }
+                Ok(())
+            },
+            "endif" => {
+                let file = self.files.last_mut().unwrap();
+                match file.conditionals.pop() {
+                    Some(_) => Ok(()),
+                    None => Err(PreprocessorError::new(PreprocessorErrorKind::UnbalancedConditional("endif".to_string()), location.clone()))
+                }
+            },
+            "line" => {
+                let line = self.expand_isolated(&line)?;
+                self.line_directive(line, location)
+            },
+            "error" => {
+                Err(PreprocessorError::new(PreprocessorErrorKind::ErrorDirective(spell(&line)), location.clone()))
+            },
+            "pragma" => {
+                self.pragma(line, location);
+                Ok(())
+            },
+            _ => Err(PreprocessorError::new(PreprocessorErrorKind::UnknownDirective(name.spelling.to_string()), name.location))
+        }
+    }
+
+    fn define_macro(&mut self, line: Vec<PPToken>, location: &Location) -> Result<(), PreprocessorError> {
+        let name = macro_name(&line, "define", location)?.clone();
+        let invalid_params = |token: Option<&PPToken>| {
+            let location = token.map_or(&name.location, |token| &token.location).clone();
+            PreprocessorError::new(PreprocessorErrorKind::InvalidMacroParameterList, location)
+        };
+
+        let mut i = 1;
+        // a function-like macro's parameter list must follow its name without any whitespace in between
+        let kind = match line.get(1) {
+            Some(token) if token.is_punctuator("(") && !token.leading_space => {
+                let mut params: Vec<Rc<str>> = Vec::new();
+                let mut variadic = false;
+                i = 2;
+                if line.get(i).is_some_and(|token| token.is_punctuator(")")) {
+                    i += 1;
+                }
+                else {
+                    loop {
+                        match line.get(i) {
+                            Some(token) if token.is_punctuator("...") => {
+                                variadic = true;
+                                params.push("__VA_ARGS__".into());
+                                i += 1;
+                                match line.get(i) {
+                                    Some(token) if token.is_punctuator(")") => break i += 1,
+                                    token => return Err(invalid_params(token))
+                                }
+                            },
+                            Some(token) if token.kind == PPTokenKind::Identifier && &*token.spelling != "__VA_ARGS__" => {
+                                if params.contains(&token.spelling) {
+                                    return Err(PreprocessorError::new(
+                                        PreprocessorErrorKind::DuplicateMacroParameter(token.spelling.to_string()),
+                                        token.location.clone()
+                                    ));
+                                }
+                                params.push(token.spelling.clone());
+                                i += 1;
+                                match line.get(i) {
+                                    Some(token) if token.is_punctuator(",") => i += 1,
+                                    Some(token) if token.is_punctuator(")") => break i += 1,
+                                    token => return Err(invalid_params(token))
+                                }
+                            },
+                            token => return Err(invalid_params(token))
+                        }
+                    }
+                }
+                MacroKind::Function { params, variadic }
+            },
+            _ => MacroKind::Object
+        };
+
+        let mut body = line[i..].to_vec();
+        if let Some(first) = body.first_mut() {
+            first.leading_space = false;
+        }
+        for (i, token) in body.iter().enumerate() {
+            if token.is_punctuator("##") && (i == 0 || i == body.len() - 1) {
+                return Err(PreprocessorError::new(PreprocessorErrorKind::PasteAtEdge, token.location.clone()));
+            }
+        }
+        let mac = Macro { name: name.spelling.clone(), kind, body, location: name.location.clone() };
+        if mac.kind != MacroKind::Object {
+            for (i, token) in mac.body.iter().enumerate() {
+                let operand = mac.body.get(i + 1).filter(|next| mac.params().contains(&next.spelling));
+                if token.is_punctuator("#") && operand.is_none() {
+                    return Err(PreprocessorError::new(PreprocessorErrorKind::StringizeWithoutParameter, token.location.clone()));
+                }
+            }
+        }
+
+        if let Some(previous) = self.macros.get(&mac.name) {
+            if !previous.is_identical_to(&mac) {
+                return Err(PreprocessorError::new(
+                    PreprocessorErrorKind::MacroRedefinition(mac.name.to_string(), previous.location.clone()),
+                    name.location
+                ));
+            }
+        }
+        self.macros.insert(mac.name.clone(), Rc::new(mac));
+        Ok(())
+    }
+
+    fn include_directive(&mut self, line: Vec<PPToken>, location: &Location) -> Result<(), PreprocessorError> {
+        if self.files.len() >= MAX_INCLUDE_DEPTH {
+            return Err(PreprocessorError::new(PreprocessorErrorKind::IncludeDepthExceeded, location.clone()));
+        }
+
+        // if the directive doesn't match either form, it is macro-expanded first (C99 6.10.2p4)
+        let (name, quoted) = match header_name(&line) {
+            Some(header) => header,
+            None => header_name(&self.expand_isolated(&line)?)
+                .ok_or_else(|| PreprocessorError::new(PreprocessorErrorKind::InvalidInclude, location.clone()))?
+        };
+
+        let path = self.find_include(&name, quoted)
+            .ok_or_else(|| PreprocessorError::new(PreprocessorErrorKind::FileNotFound(name), location.clone()))?;
+        if self.once.contains(&canonicalize(&path)) {
+            return Ok(());
+        }
+        let source = read_file(&path, location.clone())?;
+        self.included_files.push(path.clone());
+        self.push_file(path, &source)
+    }
+
+    /// `#include "file"` searches the directory of the current file first, then the include paths.
+    /// `#include <file>` only searches the include paths.
+    fn find_include(&self, name: &str, quoted: bool) -> Option<PathBuf> {
+        let path = Path::new(name);
+        if path.is_absolute() {
+            return Some(path.to_path_buf()).filter(|path| path.is_file());
+        }
+        let current_dir = self.files.last()
+            .and_then(|file| file.path.parent())
+            .map(Path::to_path_buf)
+            .filter(|_| quoted);
+        current_dir.iter()
+            .chain(self.include_paths.iter())
+            .chain(self.system_include_paths.iter())
+            .map(|dir| dir.join(path))
+            .find(|path| path.is_file())
+    }
+
+    fn line_directive(&mut self, line: Vec<PPToken>, location: &Location) -> Result<(), PreprocessorError> {
+        let invalid = || PreprocessorError::new(PreprocessorErrorKind::InvalidLineDirective, location.clone());
+        let number: isize = match line.first() {
+            Some(token) if token.kind == PPTokenKind::Number && token.spelling.bytes().all(|c| c.is_ascii_digit()) => {
+                token.spelling.parse().map_err(|_| invalid())?
+            },
+            _ => return Err(invalid())
+        };
+        let filename = match line.get(1) {
+            Some(token) if token.kind == PPTokenKind::StringLiteral && token.spelling.starts_with('"') => {
+                Some(token.spelling[1..token.spelling.len() - 1].to_string())
+            },
+            // line markers can be followed by flags
+            Some(token) if token.kind == PPTokenKind::Number => None,
+            Some(_) => return Err(invalid()),
+            None => None
+        };
+
+        let file = self.files.last_mut().unwrap();
+        let physical_line = location.line as isize - file.line_delta;
+        file.line_delta = number - (physical_line + 1);
+        if let Some(filename) = filename {
+            file.presumed_filename = Some(Rc::new(OsString::from(filename)));
+        }
+        Ok(())
+    }
+
+    fn pragma(&mut self, tokens: Vec<PPToken>, location: &Location) {
+        if tokens.len() == 1 && tokens[0].is_identifier("once") {
+            if let Some(file) = self.files.last() {
+                self.once.insert(canonicalize(&file.path));
+            }
+        }
+        else if !tokens.is_empty() {
+            self.pragmas.push(Pragma { tokens, location: location.clone() });
+        }
+    }
+
+    /// `_Pragma ( string-literal )` (C99 6.10.9)
+    fn pragma_operator(&mut self, keyword: &PPToken) -> Result<(), PreprocessorError> {
+        let invalid = || PreprocessorError::new(PreprocessorErrorKind::InvalidPragmaOperator, keyword.location.clone());
+        if !self.next_unexpanded()?.is_some_and(|token| token.is_punctuator("(")) {
+            return Err(invalid());
+        }
+        let string = match self.next_unexpanded()? {
+            Some(ref token) if token.kind == PPTokenKind::StringLiteral => {
+                let contents = token.spelling.trim_start_matches('L');
+                contents[1..contents.len() - 1].replace("\\\"", "\"").replace("\\\\", "\\")
+            },
+            _ => return Err(invalid())
+        };
+        if !self.next_unexpanded()?.is_some_and(|token| token.is_punctuator(")")) {
+            return Err(invalid());
+        }
+        let tokens = tokenize_fragment(&string, &keyword.location);
+        self.pragma(tokens, &keyword.location);
+        Ok(())
+    }
+
+    fn evaluate_condition(&mut self, line: Vec<PPToken>, location: &Location) -> Result<bool, PreprocessorError> {
+        // the defined operator is evaluated before macro replacement (C99 6.10.1p4)
+        let mut tokens = Vec::with_capacity(line.len());
+        let mut i = 0;
+        while i < line.len() {
+            if !line[i].is_identifier("defined") {
+                tokens.push(line[i].clone());
+                i += 1;
+                continue;
+            }
+            let parenthesized = line.get(i + 1).is_some_and(|token| token.is_punctuator("("));
+            let name = if parenthesized { line.get(i + 2) } else { line.get(i + 1) };
+            let name = match name {
+                Some(name) if name.kind == PPTokenKind::Identifier => name,
+                _ => return Err(PreprocessorError::new(
+                    PreprocessorErrorKind::InvalidExpression("'defined' must be followed by a macro name".to_string()),
+                    line[i].location.clone()
+                ))
+            };
+            if parenthesized && !line.get(i + 3).is_some_and(|token| token.is_punctuator(")")) {
+                return Err(PreprocessorError::new(
+                    PreprocessorErrorKind::InvalidExpression("missing ')' after 'defined'".to_string()),
+                    name.location.clone()
+                ));
+            }
+            let value = if self.macros.contains_key(&name.spelling) { "1" } else { "0" };
+            tokens.push(PPToken::new(PPTokenKind::Number, value, line[i].location.clone()));
+            i += if parenthesized { 4 } else { 2 };
+        }
+
+        let tokens = self.expand_isolated(&tokens)?;
+        expression::evaluate(&tokens, location, self.target)
+    }
+
+    fn push_conditional(&mut self, location: &Location, condition: bool) {
+        self.files.last_mut().unwrap().conditionals.push(Conditional {
+            location: location.clone(),
+            taken: condition,
+            seen_else: false
+        });
+        if !condition {
+            self.skip_group();
+        }
+    }
+
+    fn current_conditional(&mut self, directive: &str, location: &Location) -> Result<&mut Conditional, PreprocessorError> {
+        match self.files.last_mut().unwrap().conditionals.last_mut() {
+            Some(conditional) if conditional.seen_else => Err(PreprocessorError::new(
+                PreprocessorErrorKind::ConditionalAfterElse(directive.to_string()),
+                location.clone()
+            )),
+            Some(conditional) => Ok(conditional),
+            None => Err(PreprocessorError::new(
+                PreprocessorErrorKind::UnbalancedConditional(directive.to_string()),
+                location.clone()
+            ))
+        }
+    }
+
+    /// Skips the rest of a group whose condition is false, up to the `#elif`, `#else` or `#endif` that ends it.
+    fn skip_group(&mut self) {
+        let file = self.files.last_mut().unwrap();
+        let mut depth = 0;
+        while !file.at_end() {
+            let token = &file.tokens[file.pos];
+            let directive = file.tokens.get(file.pos + 1)
+                .filter(|name| token.at_line_start && token.is_punctuator("#") && !name.at_line_start);
+            if let Some(name) = directive {
+                match &*name.spelling {
+                    "if" | "ifdef" | "ifndef" => depth += 1,
+                    "elif" | "else" | "endif" if depth == 0 => return,
+                    "endif" => depth -= 1,
+                    _ => ()
+                }
+            }
+            file.pos += 1;
+        }
+    }
+}
+
+/// The macro name that must follow `#define`, `#undef`, `#ifdef` and `#ifndef`
+fn macro_name<'a>(line: &'a [PPToken], directive: &str, location: &Location) -> Result<&'a PPToken, PreprocessorError> {
+    match line.first() {
+        Some(name) if name.is_identifier("defined") => Err(PreprocessorError::new(
+            PreprocessorErrorKind::InvalidMacroName(name.spelling.to_string()),
+            name.location.clone()
+        )),
+        Some(name) if name.kind == PPTokenKind::Identifier => Ok(name),
+        _ => Err(PreprocessorError::new(PreprocessorErrorKind::MissingMacroName(directive.to_string()), location.clone()))
+    }
+}
+
+/// The file name of an `#include` directive, and whether it was written with quotes (`"file"` rather than `<file>`)
+fn header_name(line: &[PPToken]) -> Option<(String, bool)> {
+    let first = line.first()?;
+    if first.kind == PPTokenKind::StringLiteral && first.spelling.starts_with('"') {
+        return Some((first.spelling[1..first.spelling.len() - 1].to_string(), true));
+    }
+    if first.is_punctuator("<") {
+        let end = line.iter().position(|token| token.is_punctuator(">"))?;
+        return Some((spell(&line[1..end]), false));
+    }
+    None
+}
+
+/// The spelling of a sequence of tokens, with a single space wherever there was whitespace between them
+fn spell(tokens: &[PPToken]) -> String {
+    let mut s = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 && token.leading_space {
+            s.push(' ');
+        }
+        s.push_str(&token.spelling);
+    }
+    s
+}
+
+fn builtin(token: &PPToken, kind: PPTokenKind, spelling: &str) -> PPToken {
+    let mut builtin = PPToken::new(kind, spelling, token.location.clone());
+    builtin.leading_space = token.leading_space;
+    builtin.at_line_start = token.at_line_start;
+    builtin
+}
+
+fn read_file(path: &Path, location: Location) -> Result<String, PreprocessorError> {
+    fs::read_to_string(path).map_err(|err| {
+        PreprocessorError::new(PreprocessorErrorKind::UnreadableFile(path.to_string_lossy().into_owned(), err.to_string()), location)
+    })
+}
+
+fn canonicalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Converts a number of days since 1970-01-01 to a (year, month, day) date in the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
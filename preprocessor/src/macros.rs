@@ -0,0 +1,203 @@
+use std::rc::Rc;
+
+use ast::Location;
+
+use crate::error::{PreprocessorError, PreprocessorErrorKind};
+use crate::tokens::{PPToken, PPTokenKind, tokenize_fragment};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroKind {
+    Object,
+    /// A function-like macro. For variadic macros the last parameter is `__VA_ARGS__`.
+    Function { params: Vec<Rc<str>>, variadic: bool }
+}
+
+/// A macro defined with `#define` (or on the command line)
+#[derive(Debug, Clone)]
+pub struct Macro {
+    pub name: Rc<str>,
+    pub kind: MacroKind,
+    /// The replacement list
+    pub body: Vec<PPToken>,
+    /// Where the macro was defined
+    pub location: Location
+}
+
+impl Macro {
+    /// Two definitions of the same macro are allowed only if they are identical: same kind, same
+    /// parameters, and the same replacement list with the same whitespace separation (C99 6.10.3p2).
+    pub fn is_identical_to(&self, other: &Macro) -> bool {
+        self.kind == other.kind
+            && self.body.len() == other.body.len()
+            && self.body.iter().zip(other.body.iter()).enumerate().all(|(i, (a, b))| {
+                a.kind == b.kind && a.spelling == b.spelling && (i == 0 || a.leading_space == b.leading_space)
+            })
+    }
+
+    pub fn params(&self) -> &[Rc<str>] {
+        match self.kind {
+            MacroKind::Object => &[],
+            MacroKind::Function { ref params, .. } => params
+        }
+    }
+
+    pub fn is_variadic(&self) -> bool {
+        match self.kind {
+            MacroKind::Object => false,
+            MacroKind::Function { variadic, .. } => variadic
+        }
+    }
+
+    fn param_index(&self, token: &PPToken) -> Option<usize> {
+        if token.kind != PPTokenKind::Identifier {
+            return None;
+        }
+        self.params().iter().position(|param| *param == token.spelling)
+    }
+
+    /// Replaces the parameters in the replacement list with the arguments of an invocation, and applies
+    /// the `#` and `##` operators (C99 6.10.3.1 to 6.10.3.3).
+    ///
+    /// `expand` fully macro-expands an argument. It is only called for parameters that aren't an operand of
+    /// `#` or `##`, and at most once per argument. The returned tokens are not rescanned yet, and the
+    /// tokens that come from the replacement list are marked as expanded from `invocation`.
+    pub fn substitute<F>(&self, args: &[Vec<PPToken>], invocation: &Location, mut expand: F) -> Result<Vec<PPToken>, PreprocessorError>
+        where F: FnMut(&[PPToken]) -> Result<Vec<PPToken>, PreprocessorError>
+    {
+        let expanded_from = Some(Rc::new(invocation.clone()));
+        let from_body = |token: &PPToken| {
+            let mut token = token.clone();
+            token.location.expanded_from = expanded_from.clone();
+            token
+        };
+
+        let mut expanded_args: Vec<Option<Vec<PPToken>>> = vec![None; args.len()];
+        let mut result: Vec<PPToken> = Vec::new();
+        let body = &self.body;
+        let mut i = 0;
+        while i < body.len() {
+            let token = &body[i];
+            let next = body.get(i + 1);
+
+            // # parameter
+            if token.is_punctuator("#") && self.kind != MacroKind::Object {
+                if let Some(index) = next.and_then(|next| self.param_index(next)) {
+                    let mut string = stringize(&args[index], &from_body(token));
+                    string.leading_space = token.leading_space;
+                    result.push(string);
+                    i += 2;
+                    continue;
+                }
+            }
+
+            // , ## __VA_ARGS__ deletes the comma when the variable arguments are empty (a GNU extension)
+            if token.is_punctuator(",") && self.is_variadic()
+                && next.is_some_and(|next| next.is_punctuator("##"))
+                && body.get(i + 2).is_some_and(|token| token.is_identifier("__VA_ARGS__"))
+            {
+                let va_args = args.last().unwrap();
+                if !va_args.is_empty() {
+                    result.push(from_body(token));
+                    result.extend(va_args.iter().cloned());
+                }
+                i += 3;
+                continue;
+            }
+
+            // ## operand
+            if token.is_punctuator("##") {
+                let rhs = &body[i + 1];
+                let mut rhs_tokens = match self.param_index(rhs) {
+                    Some(index) if args[index].is_empty() => vec![placemarker(rhs)],
+                    Some(index) => args[index].clone(),
+                    None => vec![from_body(rhs)]
+                }.into_iter();
+                let lhs = result.pop().unwrap_or_else(|| placemarker(token));
+                result.push(paste(&lhs, &rhs_tokens.next().unwrap())?);
+                result.extend(rhs_tokens);
+                i += 2;
+                continue;
+            }
+
+            if let Some(index) = self.param_index(token) {
+                if next.is_some_and(|next| next.is_punctuator("##")) {
+                    if args[index].is_empty() {
+                        result.push(placemarker(token));
+                    }
+                    else {
+                        result.extend(args[index].iter().cloned());
+                    }
+                }
+                else {
+                    if expanded_args[index].is_none() {
+                        expanded_args[index] = Some(expand(&args[index])?);
+                    }
+                    let start = result.len();
+                    result.extend(expanded_args[index].as_ref().unwrap().iter().cloned());
+                    if let Some(first) = result.get_mut(start) {
+                        first.leading_space = token.leading_space;
+                    }
+                }
+                i += 1;
+                continue;
+            }
+
+            result.push(from_body(token));
+            i += 1;
+        }
+
+        result.retain(|token| token.kind != PPTokenKind::Placemarker);
+        Ok(result)
+    }
+}
+
+fn placemarker(at: &PPToken) -> PPToken {
+    PPToken::new(PPTokenKind::Placemarker, "", at.location.clone())
+}
+
+/// The `#` operator: turns the spelling of an argument into a string literal (C99 6.10.3.2)
+fn stringize(arg: &[PPToken], at: &PPToken) -> PPToken {
+    let mut string = String::from("\"");
+    for (i, token) in arg.iter().enumerate() {
+        if i > 0 && token.leading_space {
+            string.push(' ');
+        }
+        if token.kind == PPTokenKind::StringLiteral || token.kind == PPTokenKind::CharacterConstant {
+            for c in token.spelling.chars() {
+                if c == '"' || c == '\\' {
+                    string.push('\\');
+                }
+                string.push(c);
+            }
+        }
+        else {
+            string.push_str(&token.spelling);
+        }
+    }
+    string.push('"');
+    PPToken::new(PPTokenKind::StringLiteral, &string, at.location.clone())
+}
+
+/// The `##` operator: concatenates two tokens, which must form a single valid preprocessing token (C99 6.10.3.3)
+fn paste(lhs: &PPToken, rhs: &PPToken) -> Result<PPToken, PreprocessorError> {
+    if rhs.kind == PPTokenKind::Placemarker {
+        return Ok(lhs.clone());
+    }
+    if lhs.kind == PPTokenKind::Placemarker {
+        let mut token = rhs.clone();
+        token.leading_space = lhs.leading_space;
+        return Ok(token);
+    }
+
+    let spelling = format!("{}{}", lhs.spelling, rhs.spelling);
+    let mut tokens = tokenize_fragment(&spelling, &lhs.location);
+    if tokens.len() != 1 {
+        return Err(PreprocessorError::new(
+            PreprocessorErrorKind::InvalidPaste(lhs.spelling.to_string(), rhs.spelling.to_string()),
+            lhs.location.clone()
+        ));
+    }
+    let mut token = tokens.remove(0);
+    token.leading_space = lhs.leading_space;
+    Ok(token)
+}
@@ -0,0 +1,542 @@
+use std::fs;
+use std::path::PathBuf;
+
+use lexer::{Token, TypedefNames, LexicalErrorKind};
+
+use super::*;
+
+fn preprocess(source: &str) -> Vec<String> {
+    let output = Preprocessor::new().preprocess_str("test.c", source).unwrap();
+    output.tokens.iter().map(|token| token.spelling.to_string()).collect()
+}
+
+fn preprocess_error(source: &str) -> PreprocessorErrorKind {
+    Preprocessor::new().preprocess_str("test.c", source).unwrap_err().kind
+}
+
+fn spellings(s: &str) -> Vec<String> {
+    s.split_whitespace().map(String::from).collect()
+}
+
+/// A directory in the system's temporary directory that is removed when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("cc-preprocessor-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn file(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn no_directives() {
+    assert_eq!(preprocess("int main() { return 0; }"), spellings("int main ( ) { return 0 ; }"));
+}
+
+#[test]
+fn comments_and_line_splices() {
+    let source = "int /* comment */ a; // comment\nint b\\\n1;";
+    assert_eq!(preprocess(source), spellings("int a ; int b1 ;"));
+}
+
+#[test]
+fn trigraphs_are_opt_in() {
+    let source = "??=define A(x) x??(0??) ??/\n+ 1\nA(a)";
+    assert_eq!(preprocess(source), spellings("? ? = define A ( x ) x ? ? ( 0 ? ? ) ? ? / + 1 A ( a )"));
+
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.enable_trigraphs();
+    let output = preprocessor.preprocess_str("test.c", source).unwrap();
+    let spelled: Vec<String> = output.tokens.iter().map(|token| token.spelling.to_string()).collect();
+    assert_eq!(spelled, spellings("a [ 0 ] + 1"));
+    assert_eq!((output.tokens[0].location.line, output.tokens[0].location.column), (3, 3));
+}
+
+#[test]
+fn digraphs() {
+    let source = "%:define CAT(a, b) a %:%: b\n%:define STR(a) %:a\nCAT(x, y)<:0:> <%%> STR(<:)";
+    assert_eq!(preprocess(source), spellings("xy <: 0 :> <% %> \"<:\""));
+}
+
+#[test]
+fn object_like_macro() {
+    assert_eq!(preprocess("#define N 10\nint a[N];"), spellings("int a [ 10 ] ;"));
+}
+
+#[test]
+fn function_like_macro() {
+    let source = "#define MAX(a, b) ((a) > (b) ? (a) : (b))\nMAX(x, f(y, z))";
+    assert_eq!(preprocess(source), spellings("( ( x ) > ( f ( y , z ) ) ? ( x ) : ( f ( y , z ) ) )"));
+}
+
+#[test]
+fn function_like_macro_without_arguments() {
+    assert_eq!(preprocess("#define F(x) [x]\nF F() (F)(1)"), spellings("F [ ] ( F ) ( 1 )"));
+}
+
+#[test]
+fn macro_arguments_across_lines() {
+    assert_eq!(preprocess("#define F(a, b) a + b\nF(1,\n2)"), spellings("1 + 2"));
+}
+
+#[test]
+fn nested_macros_are_expanded() {
+    assert_eq!(preprocess("#define A B + 1\n#define B 2\nA"), spellings("2 + 1"));
+}
+
+#[test]
+fn recursive_macro_is_not_expanded_again() {
+    assert_eq!(preprocess("#define foo foo + 1\nfoo"), spellings("foo + 1"));
+    assert_eq!(preprocess("#define a b\n#define b a\na b"), spellings("a b"));
+}
+
+#[test]
+fn arguments_are_expanded_before_substitution() {
+    let source = "#define f(x) g(x)\n#define g(x) [x]\n#define N 1\nf(N) f(f(N))";
+    assert_eq!(preprocess(source), spellings("[ 1 ] [ [ 1 ] ]"));
+}
+
+/// The example from C99 6.10.3.5p5
+#[test]
+fn standard_rescanning_example() {
+    let source = "#define x 3
+#define f(a) f(x * (a))
+#undef x
+#define x 2
+#define g f
+#define z z[0]
+#define h g(~
+#define m(a) a(w)
+#define w 0,1
+#define t(a) a
+#define p() int
+#define q(x) x
+#define r(x,y) x ## y
+f(y+1) + f(f(z)) % t(t(g)(0) + t)(1);
+g(x+(3,4)-w) | h 5) & m
+(f)^m(m);
+p() i[q()] = { q(1), r(2,3), r(4,), r(,5), r(,) };";
+    let expected = "f(2 * (y+1)) + f(2 * (f(2 * (z[0])))) % f(2 * (0)) + t(1);
+f(2 * (2+(3,4)-0,1)) | f(2 * (~ 5)) & f(2 * (0,1))^m(0,1);
+int i[] = { 1, 23, 4, 5, };";
+    let expected: Vec<String> = Preprocessor::new().preprocess_str("expected.c", expected).unwrap()
+        .tokens.iter().map(|token| token.spelling.to_string()).collect();
+    assert_eq!(preprocess(source), expected);
+}
+
+#[test]
+fn stringize() {
+    let source = "#define str(x) #x\nstr(  a  +   \"b\\n\"  ) str() str('\\'')";
+    assert_eq!(preprocess(source), vec!["\"a + \\\"b\\\\n\\\"\"", "\"\"", "\"'\\\\''\""]);
+}
+
+#[test]
+fn token_pasting() {
+    let source = "#define cat(a, b) a ## b\n#define x 1\ncat(x, 2) cat(, y) cat(z, ) cat(<, <=) cat(x, x)";
+    assert_eq!(preprocess(source), spellings("x2 y z <<= xx"));
+}
+
+#[test]
+fn pasted_tokens_are_rescanned() {
+    assert_eq!(preprocess("#define ab 42\n#define cat(a, b) a ## b\ncat(a, b)"), spellings("42"));
+}
+
+#[test]
+fn invalid_paste() {
+    assert_eq!(
+        preprocess_error("#define cat(a, b) a ## b\ncat(+, -)"),
+        PreprocessorErrorKind::InvalidPaste("+".to_string(), "-".to_string())
+    );
+}
+
+#[test]
+fn paste_at_edge() {
+    assert_eq!(preprocess_error("#define cat(a) ## a"), PreprocessorErrorKind::PasteAtEdge);
+    assert_eq!(preprocess_error("#define cat(a) a ##"), PreprocessorErrorKind::PasteAtEdge);
+}
+
+#[test]
+fn stringize_without_parameter() {
+    assert_eq!(preprocess_error("#define str(x) #y"), PreprocessorErrorKind::StringizeWithoutParameter);
+    // # isn't an operator in object-like macros
+    assert_eq!(preprocess("#define hash # x\nhash"), spellings("# x"));
+}
+
+#[test]
+fn variadic_macro() {
+    let source = "#define debug(...) fprintf(stderr, __VA_ARGS__)\n#define showlist(...) #__VA_ARGS__\n\
+                  debug(\"%d %d\", x, y) showlist(The first, second, and third items.)";
+    assert_eq!(
+        preprocess(source),
+        vec!["fprintf", "(", "stderr", ",", "\"%d %d\"", ",", "x", ",", "y", ")", "\"The first, second, and third items.\""]
+    );
+}
+
+#[test]
+fn variadic_macro_comma_deletion() {
+    let source = "#define eprintf(format, ...) fprintf(stderr, format, ## __VA_ARGS__)\neprintf(\"a\") eprintf(\"b\", 1)";
+    assert_eq!(
+        preprocess(source),
+        vec!["fprintf", "(", "stderr", ",", "\"a\"", ")", "fprintf", "(", "stderr", ",", "\"b\"", ",", "1", ")"]
+    );
+}
+
+#[test]
+fn wrong_argument_count() {
+    assert_eq!(
+        preprocess_error("#define F(a, b) a\nF(1)"),
+        PreprocessorErrorKind::WrongArgumentCount { name: "F".to_string(), expected: 2, given: 1 }
+    );
+    assert_eq!(
+        preprocess_error("#define F() 0\nF(1)"),
+        PreprocessorErrorKind::WrongArgumentCount { name: "F".to_string(), expected: 0, given: 1 }
+    );
+}
+
+#[test]
+fn unterminated_argument_list() {
+    assert_eq!(
+        preprocess_error("#define F(a) a\nF(1, (2)"),
+        PreprocessorErrorKind::UnterminatedArgumentList("F".to_string())
+    );
+}
+
+#[test]
+fn redefinition() {
+    assert_eq!(preprocess("#define A 1  +  2\n#define A 1 + 2\nA"), spellings("1 + 2"));
+    match preprocess_error("#define A 1 + 2\n#define A 1+2") {
+        PreprocessorErrorKind::MacroRedefinition(name, location) => {
+            assert_eq!(name, "A");
+            assert_eq!((location.line, location.column), (1, 9));
+        },
+        kind => panic!("unexpected error: {:?}", kind)
+    }
+}
+
+#[test]
+fn undef() {
+    assert_eq!(preprocess("#define A 1\nA\n#undef A\nA"), spellings("1 A"));
+    assert_eq!(preprocess_error("#undef"), PreprocessorErrorKind::MissingMacroName("undef".to_string()));
+    assert_eq!(preprocess_error("#define defined 1"), PreprocessorErrorKind::InvalidMacroName("defined".to_string()));
+}
+
+#[test]
+fn invalid_parameter_lists() {
+    assert_eq!(preprocess_error("#define F(a, b"), PreprocessorErrorKind::InvalidMacroParameterList);
+    assert_eq!(preprocess_error("#define F(a, 1) a"), PreprocessorErrorKind::InvalidMacroParameterList);
+    assert_eq!(preprocess_error("#define F(a, a) a"), PreprocessorErrorKind::DuplicateMacroParameter("a".to_string()));
+    // with a space before the parenthesis, it's an object-like macro
+    assert_eq!(preprocess("#define F (a)\nF"), spellings("( a )"));
+}
+
+#[test]
+fn ifdef() {
+    let source = "#define A\n#ifdef A\na\n#else\nnot_a\n#endif\n#ifndef B\nnot_b\n#else\nb\n#endif";
+    assert_eq!(preprocess(source), spellings("a not_b"));
+}
+
+#[test]
+fn if_elif_else() {
+    let source = "#define V 2
+#if V == 1
+one
+#elif V == 2
+two
+#elif V == 3
+three
+#else
+other
+#endif";
+    assert_eq!(preprocess(source), spellings("two"));
+}
+
+#[test]
+fn nested_conditionals_in_skipped_group() {
+    let source = "#if 0\n#if 1\na\n#else\nb\n#endif\n#error not reached\n#elif 1\nc\n#endif";
+    assert_eq!(preprocess(source), spellings("c"));
+}
+
+#[test]
+fn defined_operator() {
+    let source = "#define A\n#if defined A && defined(A) && !defined B\nyes\n#endif";
+    assert_eq!(preprocess(source), spellings("yes"));
+}
+
+#[test]
+fn if_expressions() {
+    let cases = [
+        ("1 + 2 * 3 == 7", true),
+        ("(1 + 2) * 3 == 7", false),
+        ("-1 < 0", true),
+        ("-1 < 0u", false),
+        ("~0u == 0xffffffffffffffff", true),
+        ("1 << 62 > 0", true),
+        ("10 / 3 == 3 && 10 % 3 == 1", true),
+        ("1 ? 2 : 0", true),
+        ("0 ? 1 : 0", false),
+        ("'a' == 97", true),
+        ("UNDEFINED == 0", true),
+        ("0 && 1 / 0", false),
+        ("1 || 1 % 0", true),
+        ("(2, 0)", false),
+        ("0x10 == 16 && 010 == 8", true),
+        ("-1 >> 1 == -1", true)
+    ];
+    for &(expression, expected) in cases.iter() {
+        let source = format!("#if {}\ntrue\n#else\nfalse\n#endif", expression);
+        assert_eq!(preprocess(&source), vec![expected.to_string()], "#if {}", expression);
+    }
+}
+
+#[test]
+fn invalid_if_expressions() {
+    assert_eq!(preprocess_error("#if 1 / 0\n#endif"), PreprocessorErrorKind::DivisionByZero);
+    match preprocess_error("#if 1 +\n#endif") {
+        PreprocessorErrorKind::InvalidExpression(_) => (),
+        kind => panic!("unexpected error: {:?}", kind)
+    }
+    match preprocess_error("#if 1.0\n#endif") {
+        PreprocessorErrorKind::InvalidExpression(_) => (),
+        kind => panic!("unexpected error: {:?}", kind)
+    }
+    match preprocess_error("#if (1\n#endif") {
+        PreprocessorErrorKind::InvalidExpression(_) => (),
+        kind => panic!("unexpected error: {:?}", kind)
+    }
+}
+
+#[test]
+fn unbalanced_conditionals() {
+    assert_eq!(preprocess_error("#if 1\na"), PreprocessorErrorKind::UnterminatedConditional);
+    assert_eq!(preprocess_error("#endif"), PreprocessorErrorKind::UnbalancedConditional("endif".to_string()));
+    assert_eq!(preprocess_error("#else"), PreprocessorErrorKind::UnbalancedConditional("else".to_string()));
+    assert_eq!(
+        preprocess_error("#if 1\n#else\n#else\n#endif"),
+        PreprocessorErrorKind::ConditionalAfterElse("else".to_string())
+    );
+}
+
+#[test]
+fn error_directive() {
+    assert_eq!(
+        preprocess_error("#if 1\n#error something  went wrong\n#endif"),
+        PreprocessorErrorKind::ErrorDirective("something went wrong".to_string())
+    );
+}
+
+#[test]
+fn unknown_directive() {
+    assert_eq!(preprocess_error("#foo"), PreprocessorErrorKind::UnknownDirective("foo".to_string()));
+    // unknown directives are fine in skipped groups, and so is the null directive
+    assert_eq!(preprocess("#if 0\n#foo\n#endif\n#\na"), spellings("a"));
+}
+
+#[test]
+fn line_directive() {
+    let output = Preprocessor::new().preprocess_str("test.c", "a\n#line 100 \"other.c\"\nb __LINE__ __FILE__\nc").unwrap();
+    let locations: Vec<(String, usize)> = output.tokens.iter()
+        .map(|token| (token.location.filename.to_string_lossy().into_owned(), token.location.line))
+        .collect();
+    assert_eq!(locations, vec![
+        ("test.c".to_string(), 1),
+        ("other.c".to_string(), 100),
+        ("other.c".to_string(), 100),
+        ("other.c".to_string(), 100),
+        ("other.c".to_string(), 101)
+    ]);
+    assert_eq!(output.tokens[2].spelling.as_ref(), "100");
+    assert_eq!(output.tokens[3].spelling.as_ref(), "\"other.c\"");
+    assert_eq!(preprocess_error("#line x"), PreprocessorErrorKind::InvalidLineDirective);
+}
+
+#[test]
+fn predefined_macros() {
+    assert_eq!(preprocess("__STDC__ __STDC_VERSION__ __LINE__\n__LINE__"), spellings("1 199901L 1 2"));
+    let output = preprocess("__DATE__ __TIME__");
+    assert_eq!(output[0].len(), 13);
+    assert_eq!(output[1].len(), 10);
+}
+
+#[test]
+fn line_of_multiline_invocation() {
+    assert_eq!(preprocess("#define F(x) __LINE__ x\nF(\n1\n)"), spellings("2 1"));
+}
+
+#[test]
+fn command_line_definitions() {
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.define("DEBUG", "1").unwrap();
+    preprocessor.define("SQUARE(x)", "((x) * (x))").unwrap();
+    assert!(preprocessor.is_defined("DEBUG"));
+    let output = preprocessor.preprocess_str("test.c", "#if DEBUG\nSQUARE(2)\n#endif").unwrap();
+    let spellings: Vec<&str> = output.tokens.iter().map(|token| &*token.spelling).collect();
+    assert_eq!(spellings, vec!["(", "(", "2", ")", "*", "(", "2", ")", ")"]);
+
+    preprocessor.undefine("DEBUG");
+    assert!(!preprocessor.is_defined("DEBUG"));
+    assert!(preprocessor.preprocess_str("test.c", "#if DEBUG\nSQUARE(2)\n#endif").unwrap().tokens.is_empty());
+}
+
+#[test]
+fn pragmas() {
+    let output = Preprocessor::new().preprocess_str("test.c", "#pragma pack(1)\n_Pragma(\"weak \\\"x\\\"\") a").unwrap();
+    let pragmas: Vec<Vec<&str>> = output.pragmas.iter()
+        .map(|pragma| pragma.tokens.iter().map(|token| &*token.spelling).collect())
+        .collect();
+    assert_eq!(pragmas, vec![vec!["pack", "(", "1", ")"], vec!["weak", "\"x\""]]);
+    assert_eq!(output.tokens.len(), 1);
+}
+
+#[test]
+fn include() {
+    let dir = TempDir::new("include");
+    dir.file("include/defs.h", "#define VALUE 42\nint included;\n");
+    dir.file("local.h", "#include <defs.h>\nint local = VALUE;\n");
+    let main = dir.file("main.c", "#include \"local.h\"\nint main;\n");
+
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.add_include_path(dir.0.join("include"));
+    let output = preprocessor.preprocess_file(&main).unwrap();
+    let spellings: Vec<&str> = output.tokens.iter().map(|token| &*token.spelling).collect();
+    assert_eq!(spellings, vec!["int", "included", ";", "int", "local", "=", "42", ";", "int", "main", ";"]);
+    assert_eq!(output.included_files, vec![dir.0.join("local.h"), dir.0.join("include/defs.h")]);
+    assert_eq!(&*output.tokens[0].location.filename, dir.0.join("include/defs.h").as_os_str());
+    assert_eq!(output.tokens[3].location.line, 2);
+}
+
+#[test]
+fn include_guards_and_pragma_once() {
+    let dir = TempDir::new("once");
+    dir.file("guarded.h", "#ifndef GUARDED\n#define GUARDED\nguarded\n#endif\n");
+    dir.file("once.h", "#pragma once\nonce\n");
+    let main = dir.file("main.c", "#include \"guarded.h\"\n#include \"guarded.h\"\n#include \"once.h\"\n#include \"once.h\"\n");
+    let output = Preprocessor::new().preprocess_file(&main).unwrap();
+    let spellings: Vec<&str> = output.tokens.iter().map(|token| &*token.spelling).collect();
+    assert_eq!(spellings, vec!["guarded", "once"]);
+}
+
+#[test]
+fn computed_include() {
+    let dir = TempDir::new("computed");
+    dir.file("header.h", "from_header\n");
+    let main = dir.file("main.c", "#define HEADER \"header.h\"\n#include HEADER\n");
+    let output = Preprocessor::new().preprocess_file(&main).unwrap();
+    assert_eq!(&*output.tokens[0].spelling, "from_header");
+}
+
+#[test]
+fn include_errors() {
+    let dir = TempDir::new("errors");
+    let main = dir.file("main.c", "#include \"missing.h\"\n");
+    assert_eq!(
+        Preprocessor::new().preprocess_file(&main).unwrap_err().kind,
+        PreprocessorErrorKind::FileNotFound("missing.h".to_string())
+    );
+    let recursive = dir.file("recursive.h", "#include \"recursive.h\"\n");
+    assert_eq!(
+        Preprocessor::new().preprocess_file(&recursive).unwrap_err().kind,
+        PreprocessorErrorKind::IncludeDepthExceeded
+    );
+    assert_eq!(preprocess_error("#include foo"), PreprocessorErrorKind::InvalidInclude);
+}
+
+#[test]
+fn expansion_locations() {
+    let output = Preprocessor::new().preprocess_str("test.c", "#define F(x) (x + 1)\nint a = F(b);").unwrap();
+    let paren = &output.tokens[3];
+    assert_eq!(&*paren.spelling, "(");
+    assert_eq!((paren.location.line, paren.location.column), (1, 14));
+    let invocation = paren.location.expanded_from.as_ref().unwrap();
+    assert_eq!((invocation.line, invocation.column), (2, 9));
+    assert_eq!(paren.location.expansion_location(), &**invocation);
+
+    // tokens from the arguments keep the location they were spelled at
+    let b = &output.tokens[4];
+    assert_eq!(&*b.spelling, "b");
+    assert_eq!((b.location.line, b.location.column), (2, 11));
+    assert!(b.location.expanded_from.is_none());
+}
+
+#[test]
+fn nested_expansion_locations() {
+    let output = Preprocessor::new().preprocess_str("test.c", "#define ONE 1\n#define F() ONE\n  F()").unwrap();
+    let one = &output.tokens[0];
+    assert_eq!((one.location.line, one.location.column), (1, 13));
+    let inner = one.location.expanded_from.as_ref().unwrap();
+    assert_eq!((inner.line, inner.column), (2, 13));
+    let outer = one.location.expansion_location();
+    assert_eq!((outer.line, outer.column), (3, 3));
+}
+
+#[test]
+fn lexer_tokens() {
+    let output = Preprocessor::new().preprocess_str("test.c", "#define N 10\nint x = N;").unwrap();
+    let tokens: Vec<Token> = output.lexer_tokens(TypedefNames::new()).map(|token| token.unwrap().1).collect();
+    assert_eq!(tokens, vec![
+        Token::Int,
+        Token::Identifier("x".into()),
+        Token::Equal,
+        Token::IntLiteral(ast::Integer::new(ast::IntegerType::Int, 10)),
+        Token::Semicolon
+    ]);
+
+    let output = Preprocessor::new().preprocess_str("test.c", "int  x = 1abc;").unwrap();
+    let err = output.lexer_tokens(TypedefNames::new()).find_map(|token| token.err()).unwrap();
+    assert_eq!(err.kind, LexicalErrorKind::InvalidIntegerSuffix);
+    assert_eq!((err.start.column, err.end.column), (10, 14));
+
+    let output = Preprocessor::new().preprocess_str("test.c", "#define S \"a\\qb\"\nS").unwrap();
+    let err = output.lexer_tokens(TypedefNames::new()).next().unwrap().unwrap_err();
+    assert_eq!(err.kind, LexicalErrorKind::InvalidEscapeSequence);
+    assert_eq!((err.start.line, err.start.column, err.end.column), (1, 13, 15));
+
+    let typedef_names = TypedefNames::new();
+    typedef_names.begin_declaration(true);
+    typedef_names.declare("T".into());
+    let output = Preprocessor::new().preprocess_str("test.c", "T x").unwrap();
+    let tokens: Vec<Token> = output.lexer_tokens(typedef_names).map(|token| token.unwrap().1).collect();
+    assert_eq!(tokens, vec![Token::TypedefType("T".into()), Token::Identifier("x".into())]);
+}
+
+#[test]
+fn lexer_tokens_for_target() {
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.set_target(ast::TargetInfo::new(ast::DataModel::LLP64));
+    // wchar_t is unsigned on LLP64, so the subtraction wraps around
+    let output = preprocessor.preprocess_str("test.c", "#if L'\\xffff' - 0x10000 > 0\n1l L'a'\n#endif").unwrap();
+    let tokens: Vec<Token> = output.lexer_tokens(TypedefNames::new()).map(|token| token.unwrap().1).collect();
+    assert_eq!(tokens, vec![
+        Token::IntLiteral(ast::Integer::new(ast::IntegerType::Long { bits: 32 }, 1)),
+        Token::CharLiteral { wide: true, contents: "a", value: ast::Integer::new(ast::IntegerType::UnsignedShort, 97) }
+    ]);
+}
+
+#[test]
+fn unterminated_comment() {
+    assert_eq!(preprocess_error("a /* b"), PreprocessorErrorKind::UnterminatedComment);
+}
+
+#[test]
+fn universal_character_names_in_macro_names() {
+    assert_eq!(preprocess("#define caf\\u00e9 1\ncafé caf\\U000000e9"), spellings("1 1"));
+    assert_eq!(preprocess("#define x\\u0660 2\nx٠ x\\u0661"), spellings("2 x١"));
+
+    let output = Preprocessor::new().preprocess_str("test.c", "int a\\u12;").unwrap();
+    let err = output.lexer_tokens(TypedefNames::new()).find_map(|token| token.err()).unwrap();
+    assert_eq!(err.kind, LexicalErrorKind::IncompleteUniversalCharacterName);
+    assert_eq!((err.start.column, err.end.column), (6, 10));
+}
@@ -0,0 +1,289 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+use ast::{Location, TargetInfo};
+use lexer::{Lexer, Token, LexicalError, LexicalErrorKind, SourceMap, SourceOptions};
+use lexer::{decode_identifier, is_identifier_start, is_identifier_continue, preprocessing_identifier_length};
+
+use crate::error::{PreprocessorError, PreprocessorErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PPTokenKind {
+    Identifier,
+    /// A preprocessing number, which is a superset of integer and floating constants (ex: `1.5e+3f`, `0x1p-2`)
+    Number,
+    CharacterConstant,
+    StringLiteral,
+    Punctuator,
+    /// Any other single non-whitespace character (ex: `@`, `$`, or an unmatched `'`)
+    Other,
+    /// Stands in for an empty macro argument that is an operand of `##`, never part of the output
+    Placemarker
+}
+
+/// A preprocessing token (C99 6.4)
+#[derive(Debug, Clone)]
+pub struct PPToken {
+    pub kind: PPTokenKind,
+    pub spelling: Rc<str>,
+    pub location: Location,
+
+    /// Whether the token was preceded by whitespace (or a comment)
+    pub leading_space: bool,
+
+    /// Whether the token is the first one on its line, which is needed to recognize directives
+    pub at_line_start: bool,
+
+    /// Names of the macros that may not be expanded again within this token (the "hide set" of the
+    /// rescanning algorithm described in C99 6.10.3.4)
+    pub(crate) hide_set: Rc<Vec<Rc<str>>>
+}
+
+impl PPToken {
+    /// Creates a token, the universal character names in an identifier are decoded so that every spelling of
+    /// an identifier names the same macro
+    pub fn new(kind: PPTokenKind, spelling: &str, location: Location) -> PPToken {
+        let spelling = if kind == PPTokenKind::Identifier { decode_identifier(spelling) } else { spelling.into() };
+        PPToken {
+            kind,
+            spelling: spelling.into(),
+            location,
+            leading_space: false,
+            at_line_start: false,
+            hide_set: Rc::new(Vec::new())
+        }
+    }
+
+    /// Whether the token is the punctuator `spelling`, digraphs are the same punctuators as the tokens that
+    /// they stand for (C99 6.4.6p3)
+    pub fn is_punctuator(&self, spelling: &str) -> bool {
+        let punctuator = match &*self.spelling {
+            "<:" => "[",
+            ":>" => "]",
+            "<%" => "{",
+            "%>" => "}",
+            "%:" => "#",
+            "%:%:" => "##",
+            punctuator => punctuator
+        };
+        self.kind == PPTokenKind::Punctuator && punctuator == spelling
+    }
+
+    pub fn is_identifier(&self, spelling: &str) -> bool {
+        self.kind == PPTokenKind::Identifier && &*self.spelling == spelling
+    }
+
+    /// Converts this preprocessing token into a token that the parser accepts.
+    ///
+    /// Every preprocessing token must form exactly one token (C99 5.1.1.2 translation phase 7), so
+    /// preprocessing numbers such as `1abc` and stray characters are rejected here. The types of integer
+    /// and wide character constants depend on `target`.
+    pub fn to_token(&self, target: TargetInfo) -> Result<(Location, Token<'_>, Location), LexicalError> {
+        let mut lexer = Lexer::new(OsString::clone(&self.location.filename), &self.spelling);
+        lexer.set_target(target);
+        // locations reported by the lexer are relative to the spelling of this token
+        let relocate = |location: &Location| {
+            let mut relocated = self.location.clone();
+            relocated.column += location.column - 1;
+            relocated.byte_offset += location.byte_offset;
+            relocated
+        };
+        let whole_token = || {
+            let kind = match self.spelling.chars().next() {
+                Some(c) if self.kind == PPTokenKind::Number && c != '.' => LexicalErrorKind::InvalidIntegerSuffix,
+                Some(c) => LexicalErrorKind::StrayCharacter(c),
+                None => LexicalErrorKind::StrayCharacter(' ')
+            };
+            let mut end = self.location.clone();
+            end.column += self.spelling.chars().count();
+            end.byte_offset += self.spelling.len();
+            LexicalError { kind, start: self.location.clone(), end, line_text: self.spelling.to_string() }
+        };
+        let relocate_error = |err: LexicalError| LexicalError {
+            start: relocate(&err.start),
+            end: relocate(&err.end),
+            line_text: self.spelling.to_string(),
+            ..err
+        };
+        match lexer.next() {
+            Some(Ok((_, token, _))) => {
+                match lexer.next() {
+                    // the rest of the token is invalid, such as the incomplete universal character name in `a\u12`
+                    Some(Err(err)) => return Err(relocate_error(err)),
+                    Some(Ok(_)) => return Err(whole_token()),
+                    None => ()
+                }
+                let mut end = self.location.clone();
+                end.column += self.spelling.chars().count();
+                end.byte_offset += self.spelling.len();
+                Ok((self.location.clone(), token, end))
+            },
+            Some(Err(err)) => Err(relocate_error(err)),
+            None => Err(whole_token())
+        }
+    }
+}
+
+impl Display for PPToken {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.spelling)
+    }
+}
+
+/// Punctuators (C99 6.4.6), longest first so that the longest possible match is found first
+const PUNCTUATORS: &[&str] = &[
+    "%:%:", "...", "<<=", ">>=",
+    "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "*=", "/=", "%=", "+=", "-=",
+    "&=", "^=", "|=", "##", "<:", ":>", "<%", "%>", "%:",
+    "[", "]", "(", ")", "{", "}", ".", "&", "*", "+", "-", "~", "!", "/", "%", "<", ">", "^", "|", "?",
+    ":", ";", "=", ",", "#"
+];
+
+/// Splits a source file into preprocessing tokens (C99 5.1.1.2, translation phases 1 to 3).
+///
+/// Comments are replaced by whitespace, lines ending in a backslash are joined with the next one, and
+/// trigraphs are replaced if `options` enables them.
+pub fn tokenize(filename: Rc<OsString>, source: &str, options: SourceOptions) -> Result<Vec<PPToken>, PreprocessorError> {
+    let map = SourceMap::new(source, options);
+    let s = map.logical();
+    let bytes = s.as_bytes();
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut at_line_start = true;
+    let mut leading_space = false;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c == b'\n' {
+            at_line_start = true;
+            leading_space = false;
+            i += 1;
+            continue;
+        }
+        if c == b' ' || c == b'\t' || c == b'\r' || c == 0x0B || c == 0x0C {
+            leading_space = true;
+            i += 1;
+            continue;
+        }
+        if s[i..].starts_with("//") {
+            i += s[i..].find('\n').unwrap_or(s.len() - i);
+            leading_space = true;
+            continue;
+        }
+        if s[i..].starts_with("/*") {
+            match s[i + 2..].find("*/") {
+                Some(end) => i += end + 4,
+                None => return Err(PreprocessorError::new(PreprocessorErrorKind::UnterminatedComment, map.location(&filename, i)))
+            }
+            leading_space = true;
+            continue;
+        }
+
+        let (kind, len) = scan_token(&s[i..]);
+        let mut token = PPToken::new(kind, &s[i..i + len], map.location(&filename, i));
+        token.leading_space = leading_space;
+        token.at_line_start = at_line_start;
+        tokens.push(token);
+
+        i += len;
+        at_line_start = false;
+        leading_space = false;
+    }
+
+    Ok(tokens)
+}
+
+/// Tokenizes a string that doesn't contain any newlines or comments, such as the result of pasting two tokens
+/// together, or the contents of a `_Pragma` operator.
+pub fn tokenize_fragment(s: &str, location: &Location) -> Vec<PPToken> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut leading_space = false;
+    while i < s.len() {
+        if let Some(c) = s[i..].chars().next().filter(|c| c.is_whitespace()) {
+            leading_space = true;
+            i += c.len_utf8();
+            continue;
+        }
+        let (kind, len) = scan_token(&s[i..]);
+        let mut token = PPToken::new(kind, &s[i..i + len], location.clone());
+        token.leading_space = leading_space;
+        tokens.push(token);
+        leading_space = false;
+        i += len;
+    }
+    tokens
+}
+
+/// Returns the kind and length (in bytes) of the preprocessing token at the start of `s`.
+fn scan_token(s: &str) -> (PPTokenKind, usize) {
+    let bytes = s.as_bytes();
+    let c = s.chars().next().unwrap();
+
+    if c == 'L' && (s[1..].starts_with('\'') || s[1..].starts_with('"')) {
+        if let Some(len) = scan_quoted(&s[1..]) {
+            let kind = if bytes[1] == b'\'' { PPTokenKind::CharacterConstant } else { PPTokenKind::StringLiteral };
+            return (kind, len + 1);
+        }
+    }
+    if is_identifier_start(c) || (c == '\\' && s[1..].starts_with(['u', 'U'])) {
+        return (PPTokenKind::Identifier, preprocessing_identifier_length(s));
+    }
+    if c.is_ascii_digit() || (c == '.' && bytes.len() > 1 && bytes[1].is_ascii_digit()) {
+        return (PPTokenKind::Number, scan_number(s));
+    }
+    if c == '\'' || c == '"' {
+        // an unmatched quote is just another character, which is harmless inside skipped groups
+        return match scan_quoted(s) {
+            Some(len) => (if c == '\'' { PPTokenKind::CharacterConstant } else { PPTokenKind::StringLiteral }, len),
+            None => (PPTokenKind::Other, 1)
+        };
+    }
+    for punctuator in PUNCTUATORS {
+        if s.starts_with(punctuator) {
+            return (PPTokenKind::Punctuator, punctuator.len());
+        }
+    }
+    (PPTokenKind::Other, c.len_utf8())
+}
+
+/// pp-number: an optional '.', a digit, and then any sequence of identifier characters, '.', and
+/// exponent signs ("e+", "e-", "p+", "p-", ...)
+fn scan_number(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let exponent_sign = (c == b'+' || c == b'-') && matches!(bytes[i - 1], b'e' | b'E' | b'p' | b'P');
+        if exponent_sign || c == b'.' || c == b'_' || c.is_ascii_alphanumeric() {
+            i += 1;
+        }
+        else if c >= 0x80 && s[i..].starts_with(is_identifier_continue) {
+            i += s[i..].chars().next().unwrap().len_utf8();
+        }
+        else {
+            break;
+        }
+    }
+    i
+}
+
+/// Returns the length of the character constant or string literal at the start of `s`, or `None`
+/// if it isn't terminated before the end of the line.
+fn scan_quoted(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let quote = bytes[0];
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if i + 1 < bytes.len() && bytes[i + 1] != b'\n' => i += 2,
+            b'\n' => return None,
+            c if c == quote => return Some(i + 1),
+            _ => i += 1
+        }
+    }
+    None
+}
@@ -0,0 +1,639 @@
+use ast::*;
+use errors::{CompilationError, InvalidConstantExpression, IntegerOverflow, StaticAssertionFailed};
+
+use crate::{Field, IntegerType, OrdinaryIdentifier, QualifiedType, Type, TypeEnvironment, TypeQualifiers};
+use crate::{enums, generic_selection};
+
+/// Evaluates an integer constant expression (C99 6.6), the result has the type of the expression.
+///
+/// The operands are converted as they would be at runtime (integer promotions and the usual arithmetic
+/// conversions), unsigned arithmetic wraps around, and signed overflow is reported as a warning.
+/// Operands that aren't evaluated because of `&&`, `||` and `?:` can't cause any diagnostics.
+pub fn evaluate<'a>(expression: &ConditionalExpression, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<Integer, CompilationError<'a>> {
+    let value = Evaluator { location, env }.conditional(expression, true)?;
+    Ok(value.to_integer())
+}
+
+/// Evaluates the expression of a static assertion (C11 6.7.10), which fails if it compares equal to 0.
+///
+/// Assertions in struct declarations are checked when the struct is resolved, the ones at file and block
+/// scope by `declarations::check_translation_unit`.
+pub fn check_static_assert<'a>(assertion: &StaticAssert, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<(), CompilationError<'a>> {
+    if evaluate(&assertion.expression.0, location, env)?.value == 0 {
+        return Err(StaticAssertionFailed::new(location.clone(), assertion.message.clone()).into());
+    }
+    Ok(())
+}
+
+/// An integer of some type, stored as its two's complement representation sign extended to 128 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Value {
+    type_: IntegerType,
+    bits: u128
+}
+
+impl Value {
+    /// Converts `bits` to `type_`, wrapping around values that it can't represent
+    fn new(type_: IntegerType, bits: u128) -> Value {
+        let width = type_.bits() as u32;
+        let bits = if type_ == IntegerType::Bool {
+            (bits != 0) as u128
+        }
+        else if type_.is_signed() {
+            // sign extend
+            (((bits << (128 - width)) as i128) >> (128 - width)) as u128
+        }
+        else {
+            bits & ((1u128 << width) - 1)
+        };
+        Value { type_, bits }
+    }
+
+    fn from_bool(value: bool) -> Value {
+        Value::new(IntegerType::Int, value as u128)
+    }
+
+    fn from_integer(value: Integer) -> Value {
+        Value::new(value.type_, value.value as u128)
+    }
+
+    fn to_integer(self) -> Integer {
+        let value = if self.type_.is_signed() { self.signed() } else { self.bits as i128 };
+        Integer::new(self.type_, value)
+    }
+
+    fn convert(self, type_: IntegerType) -> Value {
+        Value::new(type_, self.bits)
+    }
+
+    fn promote(self) -> Value {
+        self.convert(self.type_.promote())
+    }
+
+    fn signed(self) -> i128 {
+        self.bits as i128
+    }
+
+    fn is_true(self) -> bool {
+        self.bits != 0
+    }
+
+    /// Compares two values of the same type
+    fn less_than(self, other: Value) -> bool {
+        if self.type_.is_signed() { self.signed() < other.signed() } else { self.bits < other.bits }
+    }
+
+    /// The type of an enumeration constant is int, or the type of the enum for values that don't fit
+    fn enumeration_constant(value: i128, target: TargetInfo) -> Value {
+        let wide = enums::wide_type(target);
+        let type_ = if value >= i32::MIN.into() && value <= i32::MAX.into() {
+            IntegerType::Int
+        }
+        else if value >= i64::MIN.into() && value <= i64::MAX.into() {
+            wide
+        }
+        else {
+            wide.to_unsigned()
+        };
+        Value::new(type_, value as u128)
+    }
+
+    /// The result of a signed operation, or `None` if it can't be represented in `type_`
+    fn checked_signed(type_: IntegerType, result: Option<i128>) -> Option<Value> {
+        let result = result?;
+        let value = Value::new(type_, result as u128);
+        if value.signed() == result { Some(value) } else { None }
+    }
+}
+
+struct Evaluator<'e> {
+    location: &'e (Location, Location),
+    env: &'e mut TypeEnvironment
+}
+
+impl<'e> Evaluator<'e> {
+    fn error<'a>(&self, reason: &str) -> CompilationError<'a> {
+        InvalidConstantExpression::new(self.location.clone(), reason.to_string()).into()
+    }
+
+    /// An operand or operator that can only be evaluated at runtime
+    fn non_constant<'a>(&self, reason: &str) -> CompilationError<'a> {
+        InvalidConstantExpression::non_constant_operand(self.location.clone(), reason.to_string()).into()
+    }
+
+    /// Reports a signed overflow (which is undefined at runtime) and wraps the result around
+    fn overflow(&mut self, evaluate: bool, type_: IntegerType, wrapped: u128) -> Value {
+        if evaluate {
+            self.env.diagnostics_mut().warn(IntegerOverflow::new(self.location.clone()));
+        }
+        Value::new(type_, wrapped)
+    }
+
+    fn expression<'a>(&mut self, expression: &Expression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression.as_slice() {
+            [assignment] => self.assignment(assignment, evaluate),
+            _ => Err(self.non_constant("comma operator"))
+        }
+    }
+
+    fn conditional<'a>(&mut self, expression: &ConditionalExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression {
+            ConditionalExpression::LogicalOrExpression(e) => self.logical_or(e, evaluate),
+            ConditionalExpression::ConditionalExpression { condition, true_expr, false_expr } => {
+                let condition = self.logical_or(condition, evaluate)?.is_true();
+                let lhs = self.expression(true_expr, evaluate && condition)?;
+                let rhs = self.conditional(false_expr, evaluate && !condition)?;
+                let type_ = lhs.type_.common_type(&rhs.type_);
+                Ok(if condition { lhs } else { rhs }.convert(type_))
+            }
+        }
+    }
+
+    fn logical_or<'a>(&mut self, expression: &LogicalOrExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression {
+            LogicalOrExpression::LogicalAndExpression(e) => self.logical_and(e, evaluate),
+            LogicalOrExpression::LogicalOr((lhs, rhs)) => {
+                let lhs = self.logical_or(lhs, evaluate)?.is_true();
+                let rhs = self.logical_and(rhs, evaluate && !lhs)?.is_true();
+                Ok(Value::from_bool(lhs || rhs))
+            }
+        }
+    }
+
+    fn logical_and<'a>(&mut self, expression: &LogicalAndExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression {
+            LogicalAndExpression::OrExpression(e) => self.or(e, evaluate),
+            LogicalAndExpression::LogicalAnd((lhs, rhs)) => {
+                let lhs = self.logical_and(lhs, evaluate)?.is_true();
+                let rhs = self.or(rhs, evaluate && lhs)?.is_true();
+                Ok(Value::from_bool(lhs && rhs))
+            }
+        }
+    }
+
+    fn or<'a>(&mut self, expression: &OrExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression {
+            OrExpression::XorExpression(e) => self.xor(e, evaluate),
+            OrExpression::Or((lhs, rhs)) => {
+                let (lhs, rhs) = (self.or(lhs, evaluate)?, self.xor(rhs, evaluate)?);
+                self.arithmetic(BinaryOperator::Or, lhs, rhs, evaluate)
+            }
+        }
+    }
+
+    fn xor<'a>(&mut self, expression: &XorExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression {
+            XorExpression::AndExpression(e) => self.and(e, evaluate),
+            XorExpression::Xor((lhs, rhs)) => {
+                let (lhs, rhs) = (self.xor(lhs, evaluate)?, self.and(rhs, evaluate)?);
+                self.arithmetic(BinaryOperator::Xor, lhs, rhs, evaluate)
+            }
+        }
+    }
+
+    fn and<'a>(&mut self, expression: &AndExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression {
+            AndExpression::EqualityExpression(e) => self.equality(e, evaluate),
+            AndExpression::And((lhs, rhs)) => {
+                let (lhs, rhs) = (self.and(lhs, evaluate)?, self.equality(rhs, evaluate)?);
+                self.arithmetic(BinaryOperator::And, lhs, rhs, evaluate)
+            }
+        }
+    }
+
+    fn equality<'a>(&mut self, expression: &EqualityExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        let (lhs, rhs, equals) = match expression {
+            EqualityExpression::RelationalExpression(e) => return self.relational(e, evaluate),
+            EqualityExpression::Equals((lhs, rhs)) => (self.equality(lhs, evaluate)?, self.relational(rhs, evaluate)?, true),
+            EqualityExpression::NotEquals((lhs, rhs)) => (self.equality(lhs, evaluate)?, self.relational(rhs, evaluate)?, false)
+        };
+        let type_ = lhs.type_.common_type(&rhs.type_);
+        Ok(Value::from_bool((lhs.convert(type_) == rhs.convert(type_)) == equals))
+    }
+
+    fn relational<'a>(&mut self, expression: &RelationalExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        let (lhs, rhs, result): (Value, Value, fn(Value, Value) -> bool) = match expression {
+            RelationalExpression::ShiftExpression(e) => return self.shift(e, evaluate),
+            RelationalExpression::LessThan((lhs, rhs)) => {
+                (self.relational(lhs, evaluate)?, self.shift(rhs, evaluate)?, |a, b| a.less_than(b))
+            },
+            RelationalExpression::GreaterThan((lhs, rhs)) => {
+                (self.relational(lhs, evaluate)?, self.shift(rhs, evaluate)?, |a, b| b.less_than(a))
+            },
+            RelationalExpression::LessThanOrEqualTo((lhs, rhs)) => {
+                (self.relational(lhs, evaluate)?, self.shift(rhs, evaluate)?, |a, b| !b.less_than(a))
+            },
+            RelationalExpression::GreaterThanOrEqualTo((lhs, rhs)) => {
+                (self.relational(lhs, evaluate)?, self.shift(rhs, evaluate)?, |a, b| !a.less_than(b))
+            }
+        };
+        let type_ = lhs.type_.common_type(&rhs.type_);
+        Ok(Value::from_bool(result(lhs.convert(type_), rhs.convert(type_))))
+    }
+
+    fn shift<'a>(&mut self, expression: &ShiftExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        let (lhs, rhs, left) = match expression {
+            ShiftExpression::AdditiveExpression(e) => return self.additive(e, evaluate),
+            ShiftExpression::Shl((lhs, rhs)) => (self.shift(lhs, evaluate)?, self.additive(rhs, evaluate)?, true),
+            ShiftExpression::Shr((lhs, rhs)) => (self.shift(lhs, evaluate)?, self.additive(rhs, evaluate)?, false)
+        };
+
+        // the result has the type of the promoted left operand
+        let (lhs, rhs) = (lhs.promote(), rhs.promote());
+        let width = lhs.type_.bits() as i128;
+        let amount = if rhs.type_.is_signed() { rhs.signed() } else { rhs.bits.min(u128::MAX >> 1) as i128 };
+        if amount < 0 || amount >= width {
+            return if evaluate { Err(self.error("shift amount out of range")) } else { Ok(Value::new(lhs.type_, 0)) };
+        }
+
+        let amount = amount as u32;
+        if !left {
+            // an arithmetic shift for signed values, which are sign extended
+            let bits = if lhs.type_.is_signed() { (lhs.signed() >> amount) as u128 } else { lhs.bits >> amount };
+            return Ok(Value::new(lhs.type_, bits));
+        }
+        let wrapped = lhs.bits << amount;
+        if lhs.type_.is_signed() {
+            let result = lhs.signed().checked_mul(1i128 << amount);
+            match Value::checked_signed(lhs.type_, result) {
+                Some(value) if lhs.signed() >= 0 => Ok(value),
+                _ => Ok(self.overflow(evaluate, lhs.type_, wrapped))
+            }
+        }
+        else {
+            Ok(Value::new(lhs.type_, wrapped))
+        }
+    }
+
+    fn additive<'a>(&mut self, expression: &AdditiveExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression {
+            AdditiveExpression::MultiplicativeExpression(e) => self.multiplicative(e, evaluate),
+            AdditiveExpression::Add((lhs, rhs)) => {
+                let (lhs, rhs) = (self.additive(lhs, evaluate)?, self.multiplicative(rhs, evaluate)?);
+                self.arithmetic(BinaryOperator::Add, lhs, rhs, evaluate)
+            },
+            AdditiveExpression::Sub((lhs, rhs)) => {
+                let (lhs, rhs) = (self.additive(lhs, evaluate)?, self.multiplicative(rhs, evaluate)?);
+                self.arithmetic(BinaryOperator::Sub, lhs, rhs, evaluate)
+            }
+        }
+    }
+
+    fn multiplicative<'a>(&mut self, expression: &MultiplicativeExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        let (lhs, rhs, op) = match expression {
+            MultiplicativeExpression::CastExpression(e) => return self.cast(e, evaluate),
+            MultiplicativeExpression::Mult((lhs, rhs)) => (self.multiplicative(lhs, evaluate)?, self.cast(rhs, evaluate)?, BinaryOperator::Mul),
+            MultiplicativeExpression::Div((lhs, rhs)) => (self.multiplicative(lhs, evaluate)?, self.cast(rhs, evaluate)?, BinaryOperator::Div),
+            MultiplicativeExpression::Mod((lhs, rhs)) => (self.multiplicative(lhs, evaluate)?, self.cast(rhs, evaluate)?, BinaryOperator::Mod)
+        };
+        self.arithmetic(op, lhs, rhs, evaluate)
+    }
+
+    /// Applies a binary operator after the usual arithmetic conversions
+    fn arithmetic<'a>(&mut self, op: BinaryOperator, lhs: Value, rhs: Value, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        let type_ = lhs.type_.common_type(&rhs.type_);
+        let (lhs, rhs) = (lhs.convert(type_), rhs.convert(type_));
+
+        if (op == BinaryOperator::Div || op == BinaryOperator::Mod) && rhs.bits == 0 {
+            return if evaluate { Err(self.error("division by zero")) } else { Ok(Value::new(type_, 0)) };
+        }
+
+        let wrapped = match op {
+            BinaryOperator::Add => lhs.bits.wrapping_add(rhs.bits),
+            BinaryOperator::Sub => lhs.bits.wrapping_sub(rhs.bits),
+            BinaryOperator::Mul => lhs.bits.wrapping_mul(rhs.bits),
+            BinaryOperator::Div if type_.is_signed() => lhs.signed().wrapping_div(rhs.signed()) as u128,
+            BinaryOperator::Div => lhs.bits / rhs.bits,
+            BinaryOperator::Mod if type_.is_signed() => lhs.signed().wrapping_rem(rhs.signed()) as u128,
+            BinaryOperator::Mod => lhs.bits % rhs.bits,
+            BinaryOperator::And => lhs.bits & rhs.bits,
+            BinaryOperator::Xor => lhs.bits ^ rhs.bits,
+            BinaryOperator::Or => lhs.bits | rhs.bits
+        };
+        if !type_.is_signed() {
+            return Ok(Value::new(type_, wrapped));
+        }
+
+        let (a, b) = (lhs.signed(), rhs.signed());
+        let result = match op {
+            BinaryOperator::Add => a.checked_add(b),
+            BinaryOperator::Sub => a.checked_sub(b),
+            BinaryOperator::Mul => a.checked_mul(b),
+            BinaryOperator::Div => a.checked_div(b),
+            BinaryOperator::Mod => a.checked_rem(b),
+            BinaryOperator::And | BinaryOperator::Xor | BinaryOperator::Or => return Ok(Value::new(type_, wrapped))
+        };
+        match Value::checked_signed(type_, result) {
+            Some(value) => Ok(value),
+            None => Ok(self.overflow(evaluate, type_, wrapped))
+        }
+    }
+
+    fn cast<'a>(&mut self, expression: &CastExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression {
+            CastExpression::UnaryExpression(e) => self.unary(e, evaluate),
+            CastExpression::Cast { type_name, cast_expression } => {
+                let type_ = match self.resolve_type_name(type_name)? {
+                    Type::Integer(type_) => type_,
+                    Type::Enum(id) => match self.env.registry().get_enum(id) {
+                        Some(e) => e.integer_type(),
+                        None => return Err(self.error("cast to an incomplete type"))
+                    },
+                    _ => return Err(self.error("cast to a non integer type"))
+                };
+                // floating constants are allowed as the immediate operand of a cast (C99 6.6p6)
+                if let Some(float) = float_operand(cast_expression) {
+                    return self.convert_float(float, type_, evaluate);
+                }
+                Ok(self.cast(cast_expression, evaluate)?.convert(type_))
+            }
+        }
+    }
+
+    /// Converts a floating constant to an integer type, truncating it toward zero
+    fn convert_float<'a>(&mut self, (negative, float): (bool, Float), type_: IntegerType, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        if type_ == IntegerType::Bool {
+            return Ok(Value::new(type_, (float.mantissa != 0) as u128));
+        }
+        let magnitude = if float.exponent >= 0 {
+            u128::from(float.mantissa).checked_shl(float.exponent as u32).filter(|v| v >> float.exponent == u128::from(float.mantissa))
+        }
+        else if float.exponent > -64 {
+            Some(u128::from(float.mantissa >> -float.exponent))
+        }
+        else {
+            Some(0)
+        };
+        let value = magnitude.filter(|m| *m <= i128::MAX as u128).map(|m| if negative { -(m as i128) } else { m as i128 });
+        let in_range = |value: i128| {
+            if type_.is_signed() {
+                Value::checked_signed(type_, Some(value)).is_some()
+            }
+            else {
+                value >= 0 && Value::new(type_, value as u128).bits == value as u128
+            }
+        };
+        match value {
+            Some(value) if in_range(value) => Ok(Value::new(type_, value as u128)),
+            _ if evaluate => Err(self.error("floating constant out of range of the integer type")),
+            _ => Ok(Value::new(type_, 0))
+        }
+    }
+
+    fn resolve_type_name<'a>(&mut self, type_name: &TypeName) -> Result<Type, CompilationError<'a>> {
+        Ok(QualifiedType::from_type_name(type_name, self.location, self.env)?.type_)
+    }
+
+    /// The expression that a generic selection selects, the controlling expression isn't evaluated
+    fn generic_selection<'s, 'a>(&mut self, controlling: &AssignmentExpression, associations: &'s [GenericAssociation]) -> Result<&'s AssignmentExpression, CompilationError<'a>> {
+        let controlling = QualifiedType::new(TypeQualifiers::default(), self.type_of_assignment(controlling)?);
+        generic_selection::select(&controlling, associations, self.location, self.env)
+    }
+
+    fn assignment<'a>(&mut self, expression: &AssignmentExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression {
+            AssignmentExpression::ConditionalExpression(conditional) => self.conditional(conditional, evaluate),
+            AssignmentExpression::Assignment { .. } => Err(self.non_constant("assignment"))
+        }
+    }
+
+    fn unary<'a>(&mut self, expression: &UnaryExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression {
+            UnaryExpression::PostfixExpression(e) => self.postfix(e, evaluate),
+            UnaryExpression::UnaryOperator((op, operand)) => {
+                if matches!(op, UnaryOperator::AddressOf | UnaryOperator::Indirection) {
+                    return Err(self.non_constant("pointer operation"));
+                }
+                let operand = self.cast(operand, evaluate)?;
+                match op {
+                    UnaryOperator::Plus => Ok(operand.promote()),
+                    UnaryOperator::Minus => {
+                        let zero = Value::new(IntegerType::Int, 0);
+                        self.arithmetic(BinaryOperator::Sub, zero.convert(operand.type_.promote()), operand, evaluate)
+                    },
+                    UnaryOperator::BitwiseNot => {
+                        let operand = operand.promote();
+                        Ok(Value::new(operand.type_, !operand.bits))
+                    },
+                    UnaryOperator::LogicalNot => Ok(Value::from_bool(!operand.is_true())),
+                    UnaryOperator::AddressOf | UnaryOperator::Indirection => unreachable!()
+                }
+            },
+            UnaryExpression::Increment(_) | UnaryExpression::Decrement(_) => Err(self.non_constant("increment or decrement")),
+            UnaryExpression::SizeOfType(type_name) => {
+                let type_ = self.resolve_type_name(type_name)?;
+                self.size_of(&type_)
+            },
+            UnaryExpression::SizeOfExpr(operand) => {
+                let type_ = self.type_of(operand)?;
+                self.size_of(&type_)
+            }
+        }
+    }
+
+    /// `sizeof` has the type size_t, which is as wide as a pointer
+    fn size_of<'a>(&mut self, type_: &Type) -> Result<Value, CompilationError<'a>> {
+        match type_.layout(self.env.registry()) {
+            Some(layout) => Ok(Value::new(IntegerType::size_type(self.env.target()), layout.size as u128)),
+            None => Err(self.error("sizeof an incomplete type"))
+        }
+    }
+
+    /// The type of an expression that isn't evaluated (the operand of `sizeof`, or a controlling expression)
+    fn type_of_assignment<'a>(&mut self, expression: &AssignmentExpression) -> Result<Type, CompilationError<'a>> {
+        match unary_operand(expression) {
+            Some(unary) => self.type_of(unary),
+            None => Ok(Type::Integer(self.assignment(expression, false)?.type_))
+        }
+    }
+
+    /// The type of the operand of `sizeof`, which isn't evaluated
+    fn type_of<'a>(&mut self, expression: &UnaryExpression) -> Result<Type, CompilationError<'a>> {
+        match expression {
+            UnaryExpression::PostfixExpression(postfix) => self.type_of_postfix(postfix),
+            UnaryExpression::UnaryOperator((UnaryOperator::Indirection, operand)) => {
+                let pointer = match operand {
+                    CastExpression::UnaryExpression(operand) => self.type_of(operand)?,
+                    CastExpression::Cast { type_name, .. } => self.resolve_type_name(type_name)?
+                };
+                self.dereference(&pointer)
+            },
+            _ => Ok(Type::Integer(self.unary(expression, false)?.type_))
+        }
+    }
+
+    /// The type of a postfix expression, which is found from the type of its operand without evaluating it
+    fn type_of_postfix<'a>(&mut self, expression: &[PostfixExpressionPart]) -> Result<Type, CompilationError<'a>> {
+        let (operator, operand) = match expression.split_last() {
+            Some((PostfixExpressionPart::PrimaryExpression(primary), [])) => return self.type_of_primary(primary),
+            Some((operator, operand)) => (operator, self.type_of_postfix(operand)?),
+            None => unreachable!("postfix expressions have a primary expression")
+        };
+        match operator {
+            PostfixExpressionPart::ArrayAccess(_) => self.dereference(&operand),
+            PostfixExpressionPart::MemberAccess(name) => self.member(&operand, *name),
+            PostfixExpressionPart::PointerMemberAccess(name) => {
+                let operand = self.dereference(&operand)?;
+                self.member(&operand, *name)
+            },
+            PostfixExpressionPart::ArgumentExpressionList(_) => match operand {
+                Type::Function { returns, .. } => Ok(returns.type_),
+                Type::Pointer(inner) => match inner.type_ {
+                    Type::Function { returns, .. } => Ok(returns.type_),
+                    _ => Err(self.error("call of a non-function"))
+                },
+                _ => Err(self.error("call of a non-function"))
+            },
+            PostfixExpressionPart::Increment | PostfixExpressionPart::Decrement => Ok(operand),
+            _ => Err(self.error("postfix operator"))
+        }
+    }
+
+    fn type_of_primary<'a>(&mut self, expression: &PrimaryExpression) -> Result<Type, CompilationError<'a>> {
+        match expression {
+            PrimaryExpression::Identifier(name) => {
+                let declared_type = self.env.symbols().lookup_identifier(*name).and_then(|symbol| match symbol.kind {
+                    OrdinaryIdentifier::Variable(ref type_, _) | OrdinaryIdentifier::Function(ref type_, _) => Some(type_.type_.clone()),
+                    _ => None
+                });
+                if let Some(type_) = declared_type {
+                    return Ok(type_);
+                }
+            },
+            PrimaryExpression::GenericSelection { controlling, associations } => {
+                let selected = self.generic_selection(controlling, associations)?;
+                return self.type_of_assignment(selected);
+            },
+            PrimaryExpression::Parens(inner) => {
+                if let Some(inner) = parenthesized_unary(inner) {
+                    return self.type_of(inner);
+                }
+            },
+            PrimaryExpression::StringLiteral { wide, value, .. } => {
+                // the array includes the terminating null character
+                let element = if *wide { IntegerType::wide_char(self.env.target()) } else { IntegerType::Char };
+                let inner = QualifiedType::new(TypeQualifiers::default(), Type::Integer(element));
+                return Ok(Type::Array { inner: Box::new(inner), size: crate::ArraySize::Constant(value.len() as u64 + 1) });
+            },
+            PrimaryExpression::Constant(Constant::Float(float)) => {
+                return Ok(Type::Float(float.type_));
+            },
+            _ => ()
+        }
+        Ok(Type::Integer(self.primary(expression, false)?.type_))
+    }
+
+    /// The type of the object that a pointer (or an array, which is converted to a pointer) points to
+    fn dereference<'a>(&self, type_: &Type) -> Result<Type, CompilationError<'a>> {
+        match type_ {
+            Type::Pointer(inner) | Type::Array { inner, .. } => Ok(inner.type_.clone()),
+            // a function designator is converted to a pointer, which designates the function again
+            Type::Function { .. } => Ok(type_.clone()),
+            _ => Err(self.error("indirection of a non-pointer"))
+        }
+    }
+
+    /// The type of a member of a struct or union
+    fn member<'a>(&self, type_: &Type, name: Symbol) -> Result<Type, CompilationError<'a>> {
+        let registry = self.env.registry();
+        let field = match type_ {
+            Type::Struct(id) => registry.get_struct(*id).map(|s| s.field(name)),
+            Type::Union(id) => registry.get_union(*id).map(|u| u.field(name)),
+            _ => return Err(self.error(&format!("request for member '{}' in something that isn't a struct or union", name)))
+        };
+        match field {
+            Some(Some(Field::Field { type_, .. })) | Some(Some(Field::BitField { type_, .. })) => Ok(type_.clone()),
+            Some(None) => Err(self.error(&format!("no member named '{}'", name))),
+            None => Err(self.error("member of an incomplete type"))
+        }
+    }
+
+    fn postfix<'a>(&mut self, expression: &PostfixExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression.as_slice() {
+            [PostfixExpressionPart::PrimaryExpression(primary)] => self.primary(primary, evaluate),
+            _ => Err(self.non_constant("postfix operator"))
+        }
+    }
+
+    fn primary<'a>(&mut self, expression: &PrimaryExpression, evaluate: bool) -> Result<Value, CompilationError<'a>> {
+        match expression {
+            PrimaryExpression::Constant(Constant::Integer(value)) => Ok(Value::from_integer(*value)),
+            PrimaryExpression::Constant(Constant::Character { value, .. }) => Ok(Value::from_integer(*value)),
+            PrimaryExpression::Constant(Constant::Float(_)) => Err(self.non_constant("floating constant")),
+            PrimaryExpression::StringLiteral { .. } => Err(self.error("string literal")),
+            PrimaryExpression::Parens(e) => self.expression(e, evaluate),
+            PrimaryExpression::GenericSelection { controlling, associations } => {
+                let selected = self.generic_selection(controlling, associations)?;
+                self.assignment(selected, evaluate)
+            },
+            PrimaryExpression::Identifier(name) => match self.env.lookup_constant(*name) {
+                Some(value) => Ok(Value::enumeration_constant(value, self.env.target())),
+                None => Err(self.non_constant(&format!("'{}' is not a constant", name)))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Xor,
+    Or
+}
+
+/// Returns the unary expression in parentheses, if that is all that the expression consists of
+fn parenthesized_unary(expression: &Expression) -> Option<&UnaryExpression> {
+    match expression.as_slice() {
+        [assignment] => unary_operand(assignment),
+        _ => None
+    }
+}
+
+/// Returns the unary expression that an assignment expression consists of, if it has no operators
+fn unary_operand(expression: &AssignmentExpression) -> Option<&UnaryExpression> {
+    // the expression that a variant with a single operand wraps
+    macro_rules! operand {
+        ($expression:expr, $variant:path) => {
+            match $expression {
+                $variant(ref operand) => &**operand,
+                _ => return None
+            }
+        };
+    }
+
+    let conditional = operand!(*expression, AssignmentExpression::ConditionalExpression);
+    let e = operand!(*conditional, ConditionalExpression::LogicalOrExpression);
+    let e = operand!(*e, LogicalOrExpression::LogicalAndExpression);
+    let e = operand!(*e, LogicalAndExpression::OrExpression);
+    let e = operand!(*e, OrExpression::XorExpression);
+    let e = operand!(*e, XorExpression::AndExpression);
+    let e = operand!(*e, AndExpression::EqualityExpression);
+    let e = operand!(*e, EqualityExpression::RelationalExpression);
+    let e = operand!(*e, RelationalExpression::ShiftExpression);
+    let e = operand!(*e, ShiftExpression::AdditiveExpression);
+    let e = operand!(*e, AdditiveExpression::MultiplicativeExpression);
+    let e = operand!(*e, MultiplicativeExpression::CastExpression);
+    Some(operand!(*e, CastExpression::UnaryExpression))
+}
+
+/// Returns the floating constant that is the operand of a cast (possibly negated and parenthesized)
+fn float_operand(expression: &CastExpression) -> Option<(bool, Float)> {
+    let unary = match expression {
+        CastExpression::UnaryExpression(unary) => unary,
+        CastExpression::Cast { .. } => return None
+    };
+    match **unary {
+        UnaryExpression::UnaryOperator((UnaryOperator::Minus, ref operand)) => float_operand(operand).map(|(negative, f)| (!negative, f)),
+        UnaryExpression::UnaryOperator((UnaryOperator::Plus, ref operand)) => float_operand(operand),
+        UnaryExpression::PostfixExpression(ref postfix) => match postfix.as_slice() {
+            [PostfixExpressionPart::PrimaryExpression(PrimaryExpression::Constant(Constant::Float(float)))] => Some((false, *float)),
+            _ => None
+        },
+        _ => None
+    }
+}
@@ -0,0 +1,129 @@
+//! A pass over the declarations of a translation unit, which declares the identifiers and tags they contain
+//! in the type environment and checks the static assertions at file and block scope (C11 6.7.10).
+
+use ast::{BlockItem, CompoundStatement, Declaration, ExternalDeclaration, FunctionDefinition, IterationStatement,
+          LabeledStatement, Location, Node, SelectionStatement, Statement, TranslationUnit};
+use errors::CompilationError;
+
+use crate::{constant_expression, QualifiedType, ScopeType, StorageClass, Type, TypeEnvironment};
+use crate::type_resolution::{self, ResolveDeclarator};
+
+/// Declares the identifiers of every declaration in a translation unit, including the ones in function bodies,
+/// and checks its static assertions, in the order they appear in the source.
+pub fn check_translation_unit<'a>(unit: &TranslationUnit, env: &mut TypeEnvironment) -> Result<(), CompilationError<'a>> {
+    for declaration in unit.iter() {
+        let location = (declaration.start.clone(), declaration.end.clone());
+        match declaration.value {
+            ExternalDeclaration::Declaration(ref declaration) => declare(declaration, env)?,
+            ExternalDeclaration::StaticAssert(ref assertion) => constant_expression::check_static_assert(assertion, &location, env)?,
+            ExternalDeclaration::FunctionDefinition(ref definition) => function_definition(definition, &location, env)?
+        }
+    }
+    Ok(())
+}
+
+/// Declares the typedef names, variables and functions of a declaration in the innermost scope. Identifiers
+/// declared without a storage class specifier have external linkage at file scope.
+pub fn declare<'a>(declaration: &Declaration, env: &mut TypeEnvironment) -> Result<(), CompilationError<'a>> {
+    let specifiers = &declaration.declaration_specifiers;
+    let location = (specifiers.start.clone(), specifiers.end.clone());
+    let storage_class = match StorageClass::try_from_specifier_list(specifiers)? {
+        StorageClass::Auto if env.symbols().scope_type() == ScopeType::Global => StorageClass::Extern,
+        storage_class => storage_class
+    };
+    let base_type = QualifiedType::from_declaration_specifier_list(specifiers, env)?;
+
+    for init_declarator in declaration.init_declarator_list.iter() {
+        let (qualified_type, name) = init_declarator.declarator.resolve(base_type.clone(), &location, env)?;
+        let name = match name {
+            Some(name) => name,
+            None => continue
+        };
+        match (storage_class, &qualified_type.type_) {
+            (StorageClass::Typedef, _) => env.symbols_mut().insert_typedef(name, qualified_type, location.clone())?,
+            (_, Type::Function { .. }) => env.symbols_mut().insert_function(name, qualified_type, storage_class, location.clone())?,
+            _ => env.symbols_mut().insert_variable(name, qualified_type, storage_class, location.clone())?
+        };
+    }
+    Ok(())
+}
+
+/// Declares a function, and then its parameters and the declarations of its body in the scope of the function
+fn function_definition<'a>(definition: &FunctionDefinition, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<(), CompilationError<'a>> {
+    let specifiers = Node::new(definition.declaration_specifiers.clone(), location.0.clone(), location.1.clone());
+    let storage_class = match StorageClass::try_from_specifier_list(&specifiers)? {
+        StorageClass::Auto => StorageClass::Extern,
+        storage_class => storage_class
+    };
+    let base_type = QualifiedType::from_declaration_specifier_list(&specifiers, env)?;
+    let (qualified_type, name) = definition.declarator.resolve(base_type, location, env)?;
+    if let Some(name) = name {
+        env.symbols_mut().insert_function(name, qualified_type, storage_class, location.clone())?;
+    }
+
+    env.enter_scope(ScopeType::Function);
+    let result = function_body(definition, env);
+    env.leave_scope()?;
+    result
+}
+
+fn function_body<'a>(definition: &FunctionDefinition, env: &mut TypeEnvironment) -> Result<(), CompilationError<'a>> {
+    if let Some(parameters) = definition.declarator.parameter_type_list() {
+        for parameter in parameters.parameter_list.iter() {
+            type_resolution::resolve_parameter_declaration(parameter, env)?;
+        }
+    }
+    for declaration in definition.declaration_list.iter() {
+        declare(&declaration.value, env)?;
+    }
+    block_items(&definition.compound_statement, env)
+}
+
+/// Walks the items of a compound statement in a new block scope
+fn block<'a>(items: &CompoundStatement, env: &mut TypeEnvironment) -> Result<(), CompilationError<'a>> {
+    env.enter_scope(ScopeType::Block);
+    let result = block_items(items, env);
+    env.leave_scope()?;
+    result
+}
+
+fn block_items<'a>(items: &CompoundStatement, env: &mut TypeEnvironment) -> Result<(), CompilationError<'a>> {
+    for item in items.iter() {
+        let location = (item.start.clone(), item.end.clone());
+        match item.value {
+            BlockItem::Declaration(ref declaration) => declare(declaration, env)?,
+            BlockItem::StaticAssert(ref assertion) => constant_expression::check_static_assert(assertion, &location, env)?,
+            BlockItem::Statement(ref statement) => self::statement(statement, env)?
+        }
+    }
+    Ok(())
+}
+
+/// Walks the compound statements and declarations nested in a statement
+fn statement<'a>(statement: &Statement, env: &mut TypeEnvironment) -> Result<(), CompilationError<'a>> {
+    match statement {
+        Statement::Compound(items) => block(items, env),
+        Statement::Labeled(LabeledStatement::Statement { statement: body, .. }) |
+        Statement::Labeled(LabeledStatement::Case { body, .. }) |
+        Statement::Labeled(LabeledStatement::DefaultCase { body }) |
+        Statement::Selection(SelectionStatement::Switch { body, .. }) |
+        Statement::Iteration(IterationStatement::While { body, .. }) |
+        Statement::Iteration(IterationStatement::DoWhile { body, .. }) |
+        Statement::Iteration(IterationStatement::ForA { body, .. }) => self::statement(&body.value, env),
+        Statement::Selection(SelectionStatement::If { body, else_clause, .. }) => {
+            self::statement(&body.value, env)?;
+            match else_clause {
+                Some(else_clause) => self::statement(&else_clause.value, env),
+                None => Ok(())
+            }
+        },
+        Statement::Iteration(IterationStatement::ForB { clause1, body, .. }) => {
+            // the declaration of a for statement is in the scope of the statement (C99 6.8.5p5)
+            env.enter_scope(ScopeType::Block);
+            let result = declare(&clause1.value, env).and_then(|_| self::statement(&body.value, env));
+            env.leave_scope()?;
+            result
+        },
+        Statement::Expression(_) | Statement::Jump(_) => Ok(())
+    }
+}
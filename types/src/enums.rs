@@ -1,121 +1,195 @@
-use std::collections::HashMap;
-use std::rc::Rc;
+use ast::{Location, Symbol, TargetInfo};
+use errors::{CompilationError, Diagnostics, DuplicateEnumeratorValue, EnumVariantRedefinition, EnumeratorValueOutOfRange};
 
-use ast::Location;
-use errors::{CompilationError, EnumVariantRedefinition};
+use crate::{IntegerType, Layout};
+
+/// The signed type of enums and enumeration constants with values that don't fit in an int: the first of
+/// long and long long that is 64 bits wide on the target
+pub(crate) fn wide_type(target: TargetInfo) -> IntegerType {
+    if IntegerType::long(target).bits() == 64 { IntegerType::long(target) } else { IntegerType::LongLong }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct EnumID(i32);
+pub struct EnumID(pub(crate) i32);
 
+#[derive(Debug, Clone)]
 pub struct Enum {
-    name: String,
-    
+    name: Symbol,
+
     id: EnumID,
 
-    /// Maps each enum variant to a value
-    variants: HashMap<String, i32>
+    /// The value of each enum variant, in declaration order
+    variants: Vec<(Symbol, i128)>,
+
+    /// The integer type that is compatible with the enum
+    type_: IntegerType
+}
+
+impl Enum {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn id(&self) -> EnumID {
+        self.id
+    }
+
+    pub fn variants(&self) -> &[(Symbol, i128)] {
+        &self.variants
+    }
+
+    pub fn value_of(&self, name: Symbol) -> Option<i128> {
+        self.variants.iter().find(|(variant, _)| *variant == name).map(|(_, value)| *value)
+    }
+
+    pub fn integer_type(&self) -> IntegerType {
+        self.type_
+    }
+
+    pub fn layout(&self, target: TargetInfo) -> Layout {
+        Layout::of_integer(self.type_, target)
+    }
 }
 
 pub struct EnumBuilder {
-    name: String,
+    name: Symbol,
     id: EnumID,
 
-    /// holds each variant of the current enum.
-    /// 
-    /// variants that don't have a value assigned manually
-    /// will automatically have a value assigned when the
-    /// enum is finalized.
-    variants: HashMap<String, Option<i32>>,
+    /// holds each variant of the current enum, in declaration order.
+    variants: Vec<Variant>
+}
+
+struct Variant {
+    name: Symbol,
+    value: i128,
+    location: (Location, Location)
 }
 
 impl<'a> EnumBuilder {
-    pub fn new(name: Option<String>, id: EnumID) -> EnumBuilder {
+    pub fn new(name: Option<Symbol>, id: EnumID) -> EnumBuilder {
         EnumBuilder {
-            name: name.unwrap_or(format!("anonymous enum #{}", id.0)),
+            name: name.unwrap_or_else(|| Symbol::intern(&format!("anonymous enum #{}", id.0))),
             id,
-            variants: HashMap::new(),
+            variants: Vec::new(),
         }
     }
 
-    pub fn add_variant<T: Into<String>>(&mut self, name: T, value: Option<i32>, location: (Location, Location)) -> Result<(), CompilationError<'a>> {
+    /// Adds a variant, and returns its value. A variant without a value is one greater than the previous
+    /// variant, or 0 if it is the first one (C99 6.7.2.2p3).
+    pub fn add_variant<T: Into<Symbol>>(&mut self, name: T, value: Option<i128>, location: (Location, Location)) -> Result<i128, CompilationError<'a>> {
         let name = name.into();
-        if self.variants.contains_key(&name) {
-            Err(EnumVariantRedefinition::new(name, location).into())
+        if self.variants.iter().any(|variant| variant.name == name) {
+            return Err(EnumVariantRedefinition::new(name.to_string(), location).into());
         }
-        else {
-            self.variants.insert(name, value);
-            Ok(())
+
+        let value = value.unwrap_or_else(|| self.variants.last().map(|variant| variant.value + 1).unwrap_or(0));
+        // the widest types that an enum can be compatible with are long and unsigned long
+        if value < i128::from(i64::MIN) || value > i128::from(u64::MAX) {
+            return Err(EnumeratorValueOutOfRange::new(location, name.to_string()).into());
         }
+        self.variants.push(Variant { name, value, location });
+        Ok(value)
     }
 
-    pub fn build(self) -> Enum {
-        let mut value_counter = 0;
-
-        let mut variants: HashMap<Rc<String>, i32> = HashMap::new();
-        {
-            let mut missing_value = Vec::new();
-            let mut values: HashMap<i32, Rc<String>> = HashMap::new();
-
-            for var in self.variants {
-                let name = Rc::new(var.0);
-                if let Some(value) = var.1 {
-                    if let Some(other) = values.get(&value) {
-                        // TODO: do something sensible with warnings
-                        println!("WARNING: in enum {}, variants '{}' and '{}' share the same value", self.name, name, other);
-                    }
-                    variants.insert(name.clone(), value);
-                    values.insert(value, name);
-                }
-                else {
-                    missing_value.push(name)
-                }
+    /// Finishes the enum, and picks the integer type that it is compatible with: unsigned int if none of
+    /// the values are negative, int otherwise, or the first of long and long long that is 64 bits wide on the
+    /// target for values that don't fit (C99 6.7.2.2p4 leaves the choice to the implementation, this is what
+    /// gcc does).
+    pub fn build(self, target: TargetInfo, diagnostics: &mut Diagnostics) -> Result<Enum, CompilationError<'a>> {
+        for (index, variant) in self.variants.iter().enumerate() {
+            if let Some(previous) = self.variants[..index].iter().find(|previous| previous.value == variant.value) {
+                diagnostics.warn(DuplicateEnumeratorValue::new(variant.location.clone(), self.name.to_string(), variant.name.to_string(), previous.name.to_string()));
             }
+        }
 
-            for name in missing_value {
-                while values.contains_key(&value_counter) {
-                    value_counter += 1; // this will overflow if you have more than 2^31 enum variants!
-                    if value_counter == i32::max_value() {
-                        panic!("too many enum variants")
-                    }
-                }
-                variants.insert(name, value_counter);
-                value_counter += 1;
-            }
+        let min = self.variants.iter().map(|variant| variant.value).min().unwrap_or(0);
+        let max = self.variants.iter().map(|variant| variant.value).max().unwrap_or(0);
+        let wide = wide_type(target);
+        let type_ = if min >= 0 {
+            if max <= i128::from(u32::MAX) { IntegerType::UnsignedInt } else { wide.to_unsigned() }
+        }
+        else if min >= i128::from(i32::MIN) && max <= i128::from(i32::MAX) {
+            IntegerType::Int
         }
+        else if max <= i128::from(i64::MAX) {
+            wide
+        }
+        else {
+            // there is no type that can represent both the smallest and largest value
+            let variant = self.variants.iter().find(|variant| variant.value == max).unwrap();
+            return Err(EnumeratorValueOutOfRange::new(variant.location.clone(), variant.name.to_string()).into());
+        };
 
-        Enum {
+        Ok(Enum {
             name: self.name,
             id: self.id,
-            variants: variants.into_iter().map(|(name, value)| (Rc::try_unwrap(name).unwrap(), value)).collect()
-        }
+            variants: self.variants.into_iter().map(|variant| (variant.name, variant.value)).collect(),
+            type_
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use ast::DataModel;
+
+    fn location() -> (Location, Location) {
+        (Location::default(), Location::default())
+    }
 
     #[test]
     fn enum_1_variant_with_value() {
         let mut builder = EnumBuilder::new(Some("Foo".into()), EnumID(1));
-        builder.add_variant("Bar", Some(1), (Location::default(), Location::default())).unwrap();
-        let result = builder.build();
-        let mut variants = HashMap::new();
-        variants.insert("Bar".into(), 1i32);
-        assert_eq!(result.variants, variants);
+        builder.add_variant("Bar", Some(1), location()).unwrap();
+        let result = builder.build(TargetInfo::default(), &mut Diagnostics::new()).unwrap();
+        assert_eq!(result.variants(), &[("Bar".into(), 1)]);
     }
 
     #[test]
     fn enum_3_variants_mixed_values() {
         let mut builder = EnumBuilder::new(Some("Foo".into()), EnumID(1));
-        builder.add_variant("Bar", None, (Location::default(), Location::default())).unwrap();
-        builder.add_variant("Foo", Some(0), (Location::default(), Location::default())).unwrap();
-        builder.add_variant("Baz", Some(1), (Location::default(), Location::default())).unwrap();
-        let result = builder.build();
-        let mut variants = HashMap::new();
-        variants.insert("Bar".into(), 2i32);
-        variants.insert("Foo".into(), 0i32);
-        variants.insert("Baz".into(), 1i32);
-        assert_eq!(result.variants, variants);
+        builder.add_variant("Bar", None, location()).unwrap();
+        builder.add_variant("Foo", Some(5), location()).unwrap();
+        builder.add_variant("Baz", None, location()).unwrap();
+        let result = builder.build(TargetInfo::default(), &mut Diagnostics::new()).unwrap();
+        assert_eq!(result.variants(), &[("Bar".into(), 0), ("Foo".into(), 5), ("Baz".into(), 6)]);
+    }
+
+    #[test]
+    fn duplicate_values_warn() {
+        let mut diagnostics = Diagnostics::new();
+        let mut builder = EnumBuilder::new(Some("Foo".into()), EnumID(1));
+        builder.add_variant("A", Some(1), location()).unwrap();
+        builder.add_variant("B", Some(0), location()).unwrap();
+        builder.add_variant("C", None, location()).unwrap();
+        assert!(builder.add_variant("A", None, location()).is_err());
+        builder.build(TargetInfo::default(), &mut diagnostics).unwrap();
+        assert_eq!(diagnostics.warnings().len(), 1);
+        assert!(format!("{}", diagnostics.warnings()[0]).contains("'A' and 'C'"));
+    }
+
+    #[test]
+    fn compatible_integer_type() {
+        let build_for = |target: TargetInfo, values: &[i128]| {
+            let mut builder = EnumBuilder::new(None, EnumID(1));
+            for (index, value) in values.iter().enumerate() {
+                builder.add_variant(format!("V{}", index), Some(*value), location())?;
+            }
+            builder.build(target, &mut Diagnostics::new()).map(|e| e.integer_type())
+        };
+        let build = |values: &[i128]| build_for(TargetInfo::default(), values);
+        assert_eq!(build(&[0, 1]).unwrap(), IntegerType::UnsignedInt);
+        assert_eq!(build(&[0xffff_ffff]).unwrap(), IntegerType::UnsignedInt);
+        assert_eq!(build(&[-1, 1]).unwrap(), IntegerType::Int);
+        assert_eq!(build(&[-1, 0xffff_ffff]).unwrap(), IntegerType::Long { bits: 64 });
+        assert_eq!(build(&[0x1_0000_0000]).unwrap(), IntegerType::UnsignedLong { bits: 64 });
+        assert!(build(&[-1, i128::from(u64::MAX)]).is_err());
+        assert!(build(&[i128::from(u64::MAX) + 1]).is_err());
+
+        // long is 32 bits wide on Windows
+        let llp64 = TargetInfo::new(DataModel::LLP64);
+        assert_eq!(build_for(llp64, &[-1, 0xffff_ffff]).unwrap(), IntegerType::LongLong);
+        assert_eq!(build_for(llp64, &[0x1_0000_0000]).unwrap(), IntegerType::UnsignedLongLong);
     }
 }
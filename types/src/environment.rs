@@ -0,0 +1,98 @@
+use ast::{Location, Symbol, TargetInfo};
+use errors::{CompilationError, Diagnostics};
+
+use crate::*;
+
+/// Everything that is needed to turn declaration specifiers into types: the struct, union and enum
+/// definitions, and the symbol table with the tags and enumeration constants that are in scope.
+#[derive(Debug)]
+pub struct TypeEnvironment {
+    registry: TypeRegistry,
+    symbols: SymbolTable,
+    diagnostics: Diagnostics
+}
+
+impl TypeEnvironment {
+    pub fn new() -> TypeEnvironment {
+        TypeEnvironment::with_target(TargetInfo::default())
+    }
+
+    /// Creates an environment for a target other than the default (64 bit Linux)
+    pub fn with_target(target: TargetInfo) -> TypeEnvironment {
+        TypeEnvironment {
+            registry: TypeRegistry::with_target(target),
+            symbols: SymbolTable::new(),
+            diagnostics: Diagnostics::new()
+        }
+    }
+
+    pub fn target(&self) -> TargetInfo {
+        self.registry.target()
+    }
+
+    pub fn registry(&self) -> &TypeRegistry {
+        &self.registry
+    }
+
+    pub fn registry_mut(&mut self) -> &mut TypeRegistry {
+        &mut self.registry
+    }
+
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    pub fn diagnostics_mut(&mut self) -> &mut Diagnostics {
+        &mut self.diagnostics
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    pub fn symbols_mut(&mut self) -> &mut SymbolTable {
+        &mut self.symbols
+    }
+
+    pub fn enter_scope(&mut self, scope_type: ScopeType) {
+        self.symbols.scope_enter(scope_type);
+    }
+
+    /// Leaves the innermost scope, the file scope is never left
+    pub fn leave_scope<'a>(&mut self) -> Result<(), CompilationError<'a>> {
+        self.symbols.scope_leave()
+    }
+
+    /// Looks up a tag, starting from the innermost scope
+    pub fn lookup_tag(&self, name: Symbol) -> Option<Tag> {
+        self.symbols.lookup_tag(name)
+    }
+
+    /// Looks up a tag in the innermost scope only
+    pub fn lookup_tag_in_current_scope(&self, name: Symbol) -> Option<Tag> {
+        self.symbols.lookup_tag_in_current_scope(name)
+    }
+
+    pub fn declare_tag(&mut self, name: Symbol, tag: Tag) {
+        self.symbols.insert_tag(name, tag);
+    }
+
+    /// Looks up the value of an enumeration constant, which can be hidden by any other ordinary
+    /// identifier declared in an inner scope
+    pub fn lookup_constant(&self, name: Symbol) -> Option<i128> {
+        match self.symbols.lookup_identifier(name)?.kind {
+            OrdinaryIdentifier::EnumVariant(value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn declare_constant<'a>(&mut self, name: Symbol, value: i128, location: (Location, Location)) -> Result<(), CompilationError<'a>> {
+        self.symbols.insert_enum_variant(name, value, location).map(|_| ())
+    }
+}
+
+impl Default for TypeEnvironment {
+    fn default() -> Self {
+        TypeEnvironment::new()
+    }
+}
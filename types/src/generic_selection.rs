@@ -0,0 +1,41 @@
+use ast::{AssignmentExpression, GenericAssociation, Location};
+use errors::{CompilationError, CompatibleGenericAssociations, DuplicateGenericDefault, NoMatchingGenericAssociation};
+
+use crate::{QualifiedType, TypeEnvironment};
+
+/// Returns the expression of the association that a generic selection (C11 6.5.1.1) selects: the one whose type is
+/// compatible with the type of the controlling expression after lvalue conversion, or the default association if
+/// there is none. `controlling` is the type of the controlling expression.
+///
+/// No two associations can have compatible types, and there can be at most one default association.
+pub fn select<'s, 'a>(controlling: &QualifiedType, associations: &'s [GenericAssociation], location: &(Location, Location),
+                      env: &mut TypeEnvironment) -> Result<&'s AssignmentExpression, CompilationError<'a>> {
+    let controlling = controlling.lvalue_conversion();
+    let mut types: Vec<QualifiedType> = Vec::new();
+    let mut default = None;
+    let mut selected = None;
+
+    for association in associations {
+        let type_name = match association.type_name {
+            Some(ref type_name) => type_name,
+            None if default.is_some() => return Err(DuplicateGenericDefault::new(location.clone()).into()),
+            None => {
+                default = Some(&association.expression);
+                continue;
+            }
+        };
+        let type_ = QualifiedType::from_type_name(type_name, location, env)?;
+        if let Some(previous) = types.iter().find(|previous| previous.is_compatible_with(&type_)) {
+            return Err(CompatibleGenericAssociations::new(location.clone(), type_.to_string(), previous.to_string()).into());
+        }
+        if type_.is_compatible_with(&controlling) {
+            selected = Some(&association.expression);
+        }
+        types.push(type_);
+    }
+
+    match selected.or(default) {
+        Some(expression) => Ok(expression),
+        None => Err(NoMatchingGenericAssociation::new(location.clone(), controlling.to_string()).into())
+    }
+}
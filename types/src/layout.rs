@@ -0,0 +1,167 @@
+use ast::{DataModel, TargetInfo};
+
+use crate::*;
+
+/// The size and alignment of a complete object type, in bytes. They follow the ABI of the data model of the
+/// target: the System V ABIs for ILP32 (i386) and LP64 (x86-64), and the Windows x64 ABI for LLP64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub size: usize,
+    pub alignment: usize
+}
+
+impl Layout {
+    pub fn new(size: usize, alignment: usize) -> Layout {
+        Layout {
+            size,
+            alignment
+        }
+    }
+
+    /// The layout of an integer type, which is aligned to its size, except for `long long` on ILP32 which is
+    /// only aligned to 4 bytes
+    pub fn of_integer(type_: IntegerType, target: TargetInfo) -> Layout {
+        match type_ {
+            IntegerType::Bool => Layout::new(1, 1),
+            _ => {
+                let size = type_.bits() / 8;
+                Layout::new(size, size.min(max_scalar_alignment(target)))
+            }
+        }
+    }
+
+    /// The layout of a floating type, `long double` is only as precise as `double` on LLP64
+    pub fn of_float(type_: FloatType, target: TargetInfo) -> Layout {
+        match (type_, target.data_model) {
+            (FloatType::Float, _) => Layout::new(4, 4),
+            (FloatType::Double, _) => Layout::new(8, max_scalar_alignment(target)),
+            // x87 extended precision, padded to 12 bytes on ILP32 and 16 bytes on LP64
+            (FloatType::LongDouble, DataModel::ILP32) => Layout::new(12, 4),
+            (FloatType::LongDouble, DataModel::LP64) => Layout::new(16, 16),
+            // the same as double
+            (FloatType::LongDouble, DataModel::LLP64) => Layout::new(8, 8)
+        }
+    }
+}
+
+/// The i386 System V ABI aligns the 8 byte scalar types (`long long` and `double`) to 4 bytes
+fn max_scalar_alignment(target: TargetInfo) -> usize {
+    match target.data_model {
+        DataModel::ILP32 => 4,
+        DataModel::LP64 | DataModel::LLP64 => 8
+    }
+}
+
+/// Rounds `value` up to the next multiple of `alignment`
+pub(crate) fn align_to(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
+
+/// Holds the structs, unions and enums that have been declared, so that the layout of a type can
+/// be computed from the layout of the structs that it contains.
+#[derive(Debug, Default)]
+pub struct TypeRegistry {
+    /// The definition of each struct, union and enum, indexed by id. Those that have been
+    /// declared but not defined yet are incomplete.
+    structs: Vec<Option<Struct>>,
+    unions: Vec<Option<Union>>,
+    enums: Vec<Option<Enum>>,
+    target: TargetInfo
+}
+
+impl TypeRegistry {
+    pub fn new() -> TypeRegistry {
+        TypeRegistry::with_target(TargetInfo::default())
+    }
+
+    pub fn with_target(target: TargetInfo) -> TypeRegistry {
+        TypeRegistry {
+            structs: Vec::new(),
+            unions: Vec::new(),
+            enums: Vec::new(),
+            target
+        }
+    }
+
+    /// The target that determines the widths of integer types and pointers
+    pub fn target(&self) -> TargetInfo {
+        self.target
+    }
+
+    /// Declares a new (incomplete) struct type
+    pub fn declare_struct(&mut self) -> StructID {
+        self.structs.push(None);
+        StructID((self.structs.len() - 1) as i32)
+    }
+
+    /// Completes a struct type that was declared with `declare_struct`
+    pub fn define_struct(&mut self, definition: Struct) {
+        let index = definition.id().0 as usize;
+        self.structs[index] = Some(definition);
+    }
+
+    /// Returns the definition of a struct, or `None` if it is incomplete
+    pub fn get_struct(&self, id: StructID) -> Option<&Struct> {
+        self.structs.get(id.0 as usize).and_then(|definition| definition.as_ref())
+    }
+
+    /// Declares a new (incomplete) union type
+    pub fn declare_union(&mut self) -> UnionID {
+        self.unions.push(None);
+        UnionID((self.unions.len() - 1) as i32)
+    }
+
+    /// Completes a union type that was declared with `declare_union`
+    pub fn define_union(&mut self, definition: Union) {
+        let index = definition.id().0 as usize;
+        self.unions[index] = Some(definition);
+    }
+
+    /// Returns the definition of a union, or `None` if it is incomplete
+    pub fn get_union(&self, id: UnionID) -> Option<&Union> {
+        self.unions.get(id.0 as usize).and_then(|definition| definition.as_ref())
+    }
+
+    /// Declares a new (incomplete) enum type
+    pub fn declare_enum(&mut self) -> EnumID {
+        self.enums.push(None);
+        EnumID((self.enums.len() - 1) as i32)
+    }
+
+    /// Completes an enum type that was declared with `declare_enum`
+    pub fn define_enum(&mut self, definition: Enum) {
+        let index = definition.id().0 as usize;
+        self.enums[index] = Some(definition);
+    }
+
+    /// Returns the definition of an enum, or `None` if it is incomplete
+    pub fn get_enum(&self, id: EnumID) -> Option<&Enum> {
+        self.enums.get(id.0 as usize).and_then(|definition| definition.as_ref())
+    }
+}
+
+impl Type {
+    /// Returns the layout of this type, or `None` if it isn't a complete object type
+    /// (void, functions, incomplete structs, unions or enums and arrays of unknown size).
+    pub fn layout(&self, registry: &TypeRegistry) -> Option<Layout> {
+        match self {
+            Type::Void | Type::Function{ .. } => None,
+            Type::Integer(int_type) => Some(Layout::of_integer(*int_type, registry.target())),
+            Type::Float(float_type) => Some(Layout::of_float(*float_type, registry.target())),
+            Type::Struct(id) => registry.get_struct(*id).map(|s| s.layout()),
+            Type::Union(id) => registry.get_union(*id).map(|u| u.layout()),
+            Type::Enum(id) => registry.get_enum(*id).map(|e| e.layout(registry.target())),
+            Type::Pointer(_) => {
+                let size = registry.target().pointer_bits() / 8;
+                Some(Layout::new(size, size))
+            },
+            Type::Array{ inner, size } => {
+                let inner = inner.type_.layout(registry)?;
+                match size {
+                    ArraySize::Constant(length) => Some(Layout::new(inner.size * *length as usize, inner.alignment)),
+                    ArraySize::Incomplete | ArraySize::Variable(_) => None
+                }
+            }
+        }
+    }
+}
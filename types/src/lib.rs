@@ -1,12 +1,27 @@
 extern crate ast;
 extern crate errors;
 
+mod members;
 mod structs;
+mod unions;
 mod enums;
+mod layout;
+mod environment;
+mod symbol_table;
+pub mod constant_expression;
+mod tag_resolution;
 pub mod type_resolution;
+pub mod generic_selection;
+pub mod declarations;
 
+pub use members::Field;
 pub use structs::*;
+pub use unions::*;
 pub use enums::*;
+pub use layout::{Layout, TypeRegistry};
+pub use environment::TypeEnvironment;
+pub use symbol_table::{SymbolTable, SymbolEntry, SymbolID, LabelID, OrdinaryIdentifier, ScopeType, Tag};
+pub use ast::{FloatType, IntegerType};
 
 use std::convert::From;
 use std::default::Default;
@@ -14,7 +29,7 @@ use std::fmt;
 use std::fmt::{Display, Formatter};
 
 use ast::{Location, Node};
-use errors::{InvalidTypeSpecifierCombination, InvalidStorageClassSpecifierCombination};
+use errors::{CompilationError, InvalidTypeSpecifierCombination, InvalidStorageClassSpecifierCombination, UnknownTypeName};
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub struct TypeQualifiers {
@@ -154,7 +169,7 @@ impl StorageClass {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct QualifiedType {
     pub qualifiers: TypeQualifiers,
     pub type_: Type
@@ -168,7 +183,12 @@ impl QualifiedType {
         }
     }
 
-    pub fn from_declaration_specifier_list(specifier_list: &Node<ast::DeclarationSpecifiers>) -> Result<QualifiedType, InvalidTypeSpecifierCombination> {
+    /// Two qualified types are compatible if they have the same qualifiers and compatible types (C99 6.7.3p9)
+    pub fn is_compatible_with(&self, other: &QualifiedType) -> bool {
+        self.qualifiers == other.qualifiers && self.type_.is_compatible_with(&other.type_)
+    }
+
+    pub fn from_declaration_specifier_list<'a>(specifier_list: &Node<ast::DeclarationSpecifiers>, env: &mut TypeEnvironment) -> Result<QualifiedType, CompilationError<'a>> {
         let qualifiers = TypeQualifiers::from(specifier_list.value.iter().filter_map(|v| {
             match v {
                 ast::DeclarationSpecifier::TypeQualifier(node) => Some(node),
@@ -176,9 +196,45 @@ impl QualifiedType {
             }
         }).map(|v| v.value));
 
-        let type_ = Type::make_type(specifier_list)?;
+        let mut type_ = Type::make_type(specifier_list, env)?;
+        type_.qualifiers.constant |= qualifiers.constant;
+        type_.qualifiers.volatile |= qualifiers.volatile;
+        type_.qualifiers.restrict |= qualifiers.restrict;
+
+        Ok(type_)
+    }
 
-        Ok(QualifiedType::new(qualifiers, type_))
+    /// Specifier qualifier lists (in struct declarations and type names) don't keep track of the location
+    /// of each specifier, so `location` is used for all of them.
+    pub fn from_specifier_qualifier_list<'a>(specifier_list: &ast::SpecifierQualifierList, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<QualifiedType, CompilationError<'a>> {
+        let (start, end) = location.clone();
+        let specifiers = specifier_list.iter().map(|specifier| match specifier {
+            ast::SpecifierQualifier::TypeSpecifier(s) => ast::DeclarationSpecifier::TypeSpecifier(Node::new(s.clone(), start.clone(), end.clone())),
+            ast::SpecifierQualifier::TypeQualifier(q) => ast::DeclarationSpecifier::TypeQualifier(Node::new(*q, start.clone(), end.clone()))
+        }).collect();
+        QualifiedType::from_declaration_specifier_list(&Node::new(specifiers, start.clone(), end.clone()), env)
+    }
+
+    /// The type named by a type name (in casts, `sizeof` and generic associations)
+    pub fn from_type_name<'a>(type_name: &ast::TypeName, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<QualifiedType, CompilationError<'a>> {
+        use crate::type_resolution::ResolveAbstractDeclarator;
+
+        let base = QualifiedType::from_specifier_qualifier_list(&type_name.specifier_qualifier_list, location, env)?;
+        match type_name.abstract_declarator {
+            Some(ref declarator) => declarator.resolve(base, location, env),
+            None => Ok(base)
+        }
+    }
+
+    /// The type of the value of an lvalue of this type (C11 6.3.2.1p2-4): arrays and functions are converted to
+    /// pointers, and the qualifiers of any other type are dropped
+    pub fn lvalue_conversion(&self) -> QualifiedType {
+        let type_ = match self.type_ {
+            Type::Array { ref inner, .. } => Type::Pointer(inner.clone()),
+            Type::Function { .. } => Type::Pointer(Box::new(self.clone())),
+            ref other => other.clone()
+        };
+        QualifiedType::new(TypeQualifiers::default(), type_)
     }
 }
 
@@ -212,7 +268,7 @@ impl<'a, T: Display> Display for DisplayVec<'a, T> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Void,
     Integer(IntegerType),
@@ -221,10 +277,21 @@ pub enum Type {
     Union(UnionID),
     Enum(EnumID),
     Function{ parameters: Vec<QualifiedType>, variadic: bool, returns: Box<QualifiedType> },
-    Array{ inner: Box<QualifiedType>, size: Option<ast::AssignmentExpression> }, // TODO: convert the size into something more useful
+    Array{ inner: Box<QualifiedType>, size: ArraySize },
     Pointer(Box<QualifiedType>)
 }
 
+/// The number of elements in an array
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArraySize {
+    /// The size wasn't specified, e.g. a flexible array member or an array whose size is given by its initializer
+    Incomplete,
+    /// The size is an integer constant expression
+    Constant(u64),
+    /// A variable length array, `None` for the `[*]` of a function prototype
+    Variable(Option<ast::AssignmentExpression>)
+}
+
 impl Display for Type {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         use Type::*;
@@ -243,7 +310,9 @@ impl Display for Type {
                     write!(f, "function taking ({}) and returning {}", DisplayVec(&parameters), returns)
                 }
             },
-            Array { inner, size } => write!(f, "array of {}", inner), // TODO: implement display for size
+            Array { inner, size: ArraySize::Constant(size) } => write!(f, "array of {} {}", size, inner),
+            Array { inner, size: ArraySize::Variable(_) } => write!(f, "variable length array of {}", inner),
+            Array { inner, size: ArraySize::Incomplete } => write!(f, "array of {}", inner),
             Pointer(inner) => write!(f, "pointer to {}", inner)
         }
     }
@@ -252,7 +321,32 @@ impl Display for Type {
 // TODO: handle enum/struct declaration
 // TODO: get enum/struct ids from symbol table
 impl Type {
-    pub fn make_type(specifier_list: &Node<ast::DeclarationSpecifiers>) -> Result<Type, InvalidTypeSpecifierCombination> {
+    /// Whether two types are compatible (C99 6.2.7), which is the case if they are the same type, or if
+    /// one of them is an array of unknown size that is otherwise the same type as the other. Integer types are
+    /// compared by kind, so `long` isn't compatible with `long long` even when they are equally wide.
+    // TODO: the compatible integer type of an enum, and functions without a prototype
+    pub fn is_compatible_with(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Pointer(a), Type::Pointer(b)) => a.is_compatible_with(b),
+            (Type::Array { inner: a, size: a_size }, Type::Array { inner: b, size: b_size }) => {
+                let same_size = match (a_size, b_size) {
+                    (ArraySize::Constant(a_size), ArraySize::Constant(b_size)) => a_size == b_size,
+                    _ => true
+                };
+                same_size && a.is_compatible_with(b)
+            },
+            (Type::Function { parameters: a, variadic: a_variadic, returns: a_returns },
+             Type::Function { parameters: b, variadic: b_variadic, returns: b_returns }) => {
+                a_variadic == b_variadic && a_returns.is_compatible_with(b_returns) && a.len() == b.len()
+                    && a.iter().zip(b.iter()).all(|(a, b)| a.type_.is_compatible_with(&b.type_))
+            },
+            (a, b) => a == b
+        }
+    }
+
+    /// The type named by the type specifiers of a declaration. The type is unqualified, unless it's named by a
+    /// typedef name for a qualified type.
+    pub fn make_type<'a>(specifier_list: &Node<ast::DeclarationSpecifiers>, env: &mut TypeEnvironment) -> Result<QualifiedType, CompilationError<'a>> {
         use ast::TypeSpecifier::*;
 
         let mut void: Option<&ast::TypeSpecifier> = None;
@@ -266,6 +360,9 @@ impl Type {
         let mut unsigned: Option<&ast::TypeSpecifier> = None;
         let mut signed: Option<&ast::TypeSpecifier> = None;
         let mut bool_: Option<&ast::TypeSpecifier> = None;
+        // struct, union, enum or typedef name
+        let mut tagged: Option<&ast::TypeSpecifier> = None;
+        let mut tagged_type: Option<QualifiedType> = None;
 
         macro_rules! check_compatability {
             ( $specifier_list:ident, $item:ident, $specifiers_to_check:expr) => {
@@ -273,7 +370,7 @@ impl Type {
                     if let Some(s) = specifier {
                         let prev: ast::TypeSpecifier = ast::TypeSpecifier::clone(s);
                         let err = InvalidTypeSpecifierCombination::new($specifier_list.clone(), $item.clone(), prev);
-                        return Err(err);
+                        return Err(err.into());
                     }
                 }
             };
@@ -290,28 +387,28 @@ impl Type {
             match item.value {
                 Void => {
                     // can't combine void specifier with any others
-                    check_compatability!(specifier_list, item, &[&void, &char_, &short, &long, &long_long, &int, &float, &double, &unsigned, &signed, &bool_]);
+                    check_compatability!(specifier_list, item, &[&void, &char_, &short, &long, &long_long, &int, &float, &double, &unsigned, &signed, &bool_, &tagged]);
                     void = Some(&item.value);
                 },
                 Char => {
                     // char can only be combined with unsigned and signed specifiers
-                    check_compatability!(specifier_list, item, &[&void, &short, &long, &long_long, &float, &double, &int, &char_]);
+                    check_compatability!(specifier_list, item, &[&void, &short, &long, &long_long, &float, &double, &int, &char_, &tagged]);
                     char_ = Some(&item.value);
                 },
                 Short => {
                     // 'short' can only be combined with itself, 'int', 'unsigned', and 'signed' type specifiers.
-                    check_compatability!(specifier_list, item, &[&void, &char_, &long, &long_long, &float, &double, &bool_]);
+                    check_compatability!(specifier_list, item, &[&void, &char_, &long, &long_long, &float, &double, &bool_, &tagged]);
                     // TODO: warn if multiple short specifiers
                     short = Some(&item.value);
                 },
                 Int => {
                     // 'int' can only be combined with 'short', 'long', 'long long', 'unsigned', and 'signed' type specifiers.
-                    check_compatability!(specifier_list, item, &[&void, &char_, &float, &double, &bool_]);
+                    check_compatability!(specifier_list, item, &[&void, &char_, &float, &double, &bool_, &tagged]);
                     int = Some(&item.value);
                 },
                 Long => {
                     // 'long' can only be combined with 'int', 'unsigned', 'signed', and 'double'.
-                    check_compatability!(specifier_list, item, &[&void, &char_, &short, &long_long, &float, &bool_]);
+                    check_compatability!(specifier_list, item, &[&void, &char_, &short, &long_long, &float, &bool_, &tagged]);
                     if long.is_some() {
                         long = None;
                         // 'long long' is compatible with the same type specifiers as 'long' with the exception of 'double'.
@@ -322,35 +419,66 @@ impl Type {
                         long = Some(&item.value);
                     }
                 },
+                LongLong => {
+                    // not produced by the parser, but treated like 'long long'.
+                    check_compatability!(specifier_list, item, &[&void, &char_, &short, &long, &long_long, &float, &double, &bool_, &tagged]);
+                    long_long = Some(&item.value);
+                },
                 Float => {
                     // 'float' can't be combined with any other type specifiers.
-                    check_compatability!(specifier_list, item, &[&void, &char_, &short, &long, &long_long, &int, &unsigned, &signed, &bool_, &float, &double]);
+                    check_compatability!(specifier_list, item, &[&void, &char_, &short, &long, &long_long, &int, &unsigned, &signed, &bool_, &float, &double, &tagged]);
                     float = Some(&item.value);
                 },
                 Double => {
                     // 'double' can only be combined with 'long'.
-                    check_compatability!(specifier_list, item, &[&void, &char_, &short, &long_long, &int, &unsigned, &signed, &bool_, &float, &double]);
+                    check_compatability!(specifier_list, item, &[&void, &char_, &short, &long_long, &int, &unsigned, &signed, &bool_, &float, &double, &tagged]);
                     double = Some(&item.value);
                 },
                 Unsigned => {
                     // 'unsigned' can be combined with itself and the 'char', 'short', and 'int' type specifiers.
-                    check_compatability!(specifier_list, item, &[&void, &bool_, &signed, &float, &double]);
+                    check_compatability!(specifier_list, item, &[&void, &bool_, &signed, &float, &double, &tagged]);
                     unsigned = Some(&item.value);
                 },
                 Signed => {
                     // 'signed' can be combined with itself, and the 'char', 'short', and 'int' type specifiers.
-                    check_compatability!(specifier_list, item, &[&void, &bool_, &unsigned, &float, &double]);
+                    check_compatability!(specifier_list, item, &[&void, &bool_, &unsigned, &float, &double, &tagged]);
                     signed = Some(&item.value);
                 },
                 Bool => {
                     // 'bool' can't be combined with any other type specifiers.
-                    check_compatability!(specifier_list, item, &[&void, &bool_, &char_, &unsigned, &signed, &int, &short, &long, &long_long, &float, &double]);
+                    check_compatability!(specifier_list, item, &[&void, &bool_, &char_, &unsigned, &signed, &int, &short, &long, &long_long, &float, &double, &tagged]);
                     bool_ = Some(&item.value);
                 },
-                _ => unimplemented!() // structs/enums
+                StructOrUnionSpecifier(ref specifier) => {
+                    // a struct or union specifier can't be combined with any other type specifiers.
+                    check_compatability!(specifier_list, item, &[&void, &bool_, &char_, &unsigned, &signed, &int, &short, &long, &long_long, &float, &double, &tagged]);
+                    tagged = Some(&item.value);
+                    let location = (item.start.clone(), item.end.clone());
+                    tagged_type = Some(QualifiedType::new(TypeQualifiers::default(), tag_resolution::resolve_struct_or_union(specifier, location, env)?));
+                },
+                EnumSpecifier(ref specifier) => {
+                    // an enum specifier can't be combined with any other type specifiers.
+                    check_compatability!(specifier_list, item, &[&void, &bool_, &char_, &unsigned, &signed, &int, &short, &long, &long_long, &float, &double, &tagged]);
+                    tagged = Some(&item.value);
+                    let location = (item.start.clone(), item.end.clone());
+                    tagged_type = Some(QualifiedType::new(TypeQualifiers::default(), tag_resolution::resolve_enum(specifier, location, env)?));
+                },
+                Typedef(name) => {
+                    // a typedef name can't be combined with any other type specifiers.
+                    check_compatability!(specifier_list, item, &[&void, &bool_, &char_, &unsigned, &signed, &int, &short, &long, &long_long, &float, &double, &tagged]);
+                    tagged = Some(&item.value);
+                    tagged_type = match env.symbols().lookup_identifier(name).map(|symbol| &symbol.kind) {
+                        Some(OrdinaryIdentifier::Typedef(type_)) => Some(type_.clone()),
+                        _ => return Err(UnknownTypeName::new((item.start.clone(), item.end.clone()), name.to_string()).into())
+                    };
+                }
             }
         }
 
+        if let Some(tagged_type) = tagged_type {
+            return Ok(tagged_type);
+        }
+
         let t = if void.is_some() {
             Type::Void
         }
@@ -370,118 +498,54 @@ impl Type {
         }
         else if short.is_some() {
             let int_type = if unsigned.is_some() {
-                IntegerType::U16
+                IntegerType::UnsignedShort
             }
             else {
-                IntegerType::I16
+                IntegerType::Short
             };
             Type::Integer(int_type)
         }
         else if char_.is_some() {
             let char_type = if unsigned.is_some() {
-                IntegerType::U8
+                IntegerType::UnsignedChar
+            }
+            else if signed.is_some() {
+                IntegerType::SignedChar
             }
             else {
-                IntegerType::I8
+                IntegerType::Char
             };
             Type::Integer(char_type)
         }
         else if long_long.is_some() {
             let int_type = if unsigned.is_some() {
-                IntegerType::U128
+                IntegerType::UnsignedLongLong
             }
             else {
-                IntegerType::I128
+                IntegerType::LongLong
             };
             Type::Integer(int_type)
         }
         else if long.is_some() {
             let int_type = if unsigned.is_some() {
-                IntegerType::U64
+                IntegerType::unsigned_long(env.target())
             }
             else {
-                IntegerType::I64
+                IntegerType::long(env.target())
             };
             Type::Integer(int_type)
         }
         else {
             let int_type = if unsigned.is_some() {
-                IntegerType::U32
+                IntegerType::UnsignedInt
             }
             else {
-                IntegerType::I32
+                IntegerType::Int
             };
             Type::Integer(int_type)
         };
 
-        Ok(t)
-    }
-}
-
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
-pub enum IntegerType {
-    Bool,
-    U8,
-    I8,
-    U16,
-    I16,
-    U32,
-    I32,
-    U64,
-    I64,
-    U128,
-    I128
-}
-
-impl IntegerType {
-    pub fn bits(&self) -> usize {
-        use IntegerType::*;
-        match self {
-            Bool => 1,
-            U8 | I8 => 8,
-            U16 | I16 => 16,
-            U32 | I32 => 32,
-            U64 | I64 => 64,
-            U128 | I128 => 128
-        }
-    }
-}
-
-impl Display for IntegerType {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        use IntegerType::*;
-        let s = match self {
-            Bool => "_Bool",
-            U8 => "unsigned char",
-            I8 => "char",
-            U16 => "unsigned short int",
-            I16 => "short int",
-            U32 => "unsigned int",
-            I32 => "int",
-            U64 => "unsigned long int",
-            I64 => "long int",
-            U128 => "unsigned long long int",
-            I128 => "long long int"
-        };
-        write!(f, "{}", s)
-    }
-}
-
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
-pub enum FloatType {
-    Float,
-    Double,
-    LongDouble // unsupported for now
-}
-
-impl Display for FloatType {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        use FloatType::*;
-        match self {
-            Float => write!(f, "float"),
-            Double => write!(f, "double"),
-            LongDouble => write!(f, "long double")
-        }
+        Ok(QualifiedType::new(TypeQualifiers::default(), t))
     }
 }
 
@@ -489,12 +553,3 @@ pub enum StructField {
     Bitfield{ size: usize },
     Field { type_: Type }
 }
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct UnionID(i32);
-
-// TODO
-pub struct Union {
-    name: String,
-    id: UnionID
-}
@@ -0,0 +1,152 @@
+use crate::{Type, IntegerType, TypeRegistry};
+use ast::{Location, Symbol};
+use errors::{CompilationError, BitFieldSizeExceedsTypeWidth, DuplicateStructMember, NonIntegralBitfieldType,
+             NamedZeroWidthBitField, IncompleteStructMember};
+
+/// A struct or union member. The offset of a bit field is the offset of the storage unit
+/// that contains it, and `bit_offset` is the position of its least significant bit
+/// within that storage unit.
+#[derive(Debug, Clone)]
+pub enum Field {
+    Field { type_: Type, offset: usize },
+    BitField { type_: Type, offset: usize, bits: usize, bit_offset: usize }
+}
+
+impl Field {
+    pub fn offset(&self) -> usize {
+        match self {
+            Field::Field { offset, .. } | Field::BitField { offset, .. } => *offset
+        }
+    }
+
+    fn moved_by(&self, delta: usize) -> Field {
+        match self.clone() {
+            Field::Field { type_, offset } => Field::Field { type_, offset: offset + delta },
+            Field::BitField { type_, offset, bits, bit_offset } => Field::BitField { type_, offset: offset + delta, bits, bit_offset }
+        }
+    }
+}
+
+/// The members of a struct or union that is being built, in declaration order
+pub(crate) struct MemberList {
+    pub(crate) members: Vec<Member>
+}
+
+pub(crate) struct Member {
+    /// unnamed bit fields are only used for padding, and the members of an anonymous
+    /// struct or union are members of the enclosing struct or union
+    pub(crate) name: Option<Symbol>,
+    pub(crate) location: (Location, Location),
+    pub(crate) kind: MemberKind
+}
+
+pub(crate) enum MemberKind {
+    Field { type_: Type },
+    BitField { type_: IntegerType, bits: usize },
+    Anonymous { type_: Type }
+}
+
+impl MemberList {
+    pub(crate) fn new() -> MemberList {
+        MemberList {
+            members: Vec::new()
+        }
+    }
+
+    fn contains(&self, name: Symbol) -> bool {
+        self.members.iter().any(|member| member.name == Some(name))
+    }
+
+    pub(crate) fn add_field<'a>(&mut self, location: (Location, Location), name: Symbol, type_: Type) -> Result<(), CompilationError<'a>> {
+        if self.contains(name) {
+            // a field with this name already exists
+            Err(DuplicateStructMember::new(location, name.to_string()).into())
+        }
+        else {
+            self.members.push(Member { name: Some(name), location, kind: MemberKind::Field{ type_ } });
+            Ok(())
+        }
+    }
+
+    pub(crate) fn add_bit_field<'a>(&mut self, location: (Location, Location), name: Option<Symbol>, type_: Type, bits: usize) -> Result<(), CompilationError<'a>> {
+        let display_name = name.map(|name| name.to_string()).unwrap_or_default();
+        if let Type::Integer(int_type) = type_ {
+            if bits > int_type.bits() {
+                // bitfield size larger than containing type
+                Err(BitFieldSizeExceedsTypeWidth::new(location, display_name, bits, int_type.bits()).into())
+            }
+            else if bits == 0 && name.is_some() {
+                Err(NamedZeroWidthBitField::new(location, display_name).into())
+            }
+            else if name.is_some_and(|name| self.contains(name)) {
+                // a field with this name already exists
+                Err(DuplicateStructMember::new(location, display_name).into())
+            }
+            else {
+                self.members.push(Member { name, location, kind: MemberKind::BitField{ type_: int_type, bits } });
+                Ok(())
+            }
+        }
+        else {
+            // bitfield type must be an integer type
+            Err(NonIntegralBitfieldType::new(location, display_name).into())
+        }
+    }
+
+    /// Adds an anonymous struct or union, whose members are accessed as if they were members of
+    /// the enclosing struct or union. The member names are checked for duplicates when it's built.
+    pub(crate) fn add_anonymous_member<'a>(&mut self, location: (Location, Location), type_: Type) -> Result<(), CompilationError<'a>> {
+        match type_ {
+            Type::Struct(_) | Type::Union(_) => {
+                self.members.push(Member { name: None, location, kind: MemberKind::Anonymous{ type_ } });
+                Ok(())
+            },
+            _ => Err(IncompleteStructMember::new(location, String::new()).into())
+        }
+    }
+}
+
+/// The fields of a finished struct or union, with the fields of anonymous members flattened into it.
+pub(crate) struct FieldList {
+    pub(crate) fields: Vec<(Symbol, Field)>
+}
+
+impl FieldList {
+    pub(crate) fn new() -> FieldList {
+        FieldList {
+            fields: Vec::new()
+        }
+    }
+
+    pub(crate) fn push<'a>(&mut self, location: &(Location, Location), name: Symbol, field: Field) -> Result<(), CompilationError<'a>> {
+        if self.fields.iter().any(|(other, _)| *other == name) {
+            Err(DuplicateStructMember::new(location.clone(), name.to_string()).into())
+        }
+        else {
+            self.fields.push((name, field));
+            Ok(())
+        }
+    }
+
+    /// Adds the fields of an anonymous struct or union that is placed at `offset`
+    pub(crate) fn push_anonymous<'a>(&mut self, location: &(Location, Location), type_: &Type, offset: usize, registry: &TypeRegistry) -> Result<(), CompilationError<'a>> {
+        let fields = match type_ {
+            Type::Struct(id) => registry.get_struct(*id).map(|s| s.fields()),
+            Type::Union(id) => registry.get_union(*id).map(|u| u.fields()),
+            _ => None
+        };
+        let fields = match fields {
+            Some(fields) => fields,
+            None => return Err(IncompleteStructMember::new(location.clone(), String::new()).into())
+        };
+        for (name, field) in fields {
+            self.push(location, *name, field.moved_by(offset))?;
+        }
+        Ok(())
+    }
+}
+
+/// Looks up a member by name
+pub(crate) fn find_field(fields: &[(Symbol, Field)], name: Symbol) -> Option<&Field> {
+    fields.iter().find(|(field_name, _)| *field_name == name).map(|(_, field)| field)
+}
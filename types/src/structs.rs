@@ -1,81 +1,300 @@
-use std::collections::HashMap;
-
-use crate::{Type, IntegerType};
-use ast::Location;
-use errors::{CompilationError, BitFieldSizeExceedsTypeWidth, DuplicateStructMember, NonIntegralBitfieldType};
+use crate::{Type, ArraySize, Layout, TypeRegistry};
+use crate::layout::align_to;
+use crate::members::{MemberList, MemberKind, FieldList, find_field};
+use crate::Field;
+use ast::{Location, Symbol};
+use errors::{CompilationError, IncompleteStructMember, FlexibleArrayMemberNotAtEnd};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct StructID(i32);
+pub struct StructID(pub(crate) i32);
 
+#[derive(Debug, Clone)]
 pub struct Struct {
-    name: String,
+    name: Symbol,
     id: StructID,
-    fields: HashMap<String, Field>,
-    bytes: usize
+
+    /// The named fields, in declaration order
+    fields: Vec<(Symbol, Field)>,
+    bytes: usize,
+    alignment: usize
 }
 
-pub enum Field {
-    Field { type_: Type, offset: usize },
-    BitField { type_: Type, offset: usize, bits: usize, bit_offset: usize }
+impl Struct {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn id(&self) -> StructID {
+        self.id
+    }
+
+    pub fn fields(&self) -> &[(Symbol, Field)] {
+        &self.fields
+    }
+
+    pub fn field(&self, name: Symbol) -> Option<&Field> {
+        find_field(&self.fields, name)
+    }
+
+    /// `sizeof` the struct, including any trailing padding
+    pub fn size(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    pub fn layout(&self) -> Layout {
+        Layout::new(self.bytes, self.alignment)
+    }
+
+    /// `offsetof` a field, bit fields don't have an offset
+    pub fn offset_of(&self, name: Symbol) -> Option<usize> {
+        match self.field(name)? {
+            Field::Field { offset, .. } => Some(*offset),
+            Field::BitField { .. } => None
+        }
+    }
 }
 
 pub struct StructBuilder {
-    name: String,
+    name: Symbol,
     id: StructID,
-    fields: HashMap<String, StructBuilderField>
-}
-
-enum StructBuilderField {
-    Field { type_: Type },
-    BitField { type_: IntegerType, bits: usize }
+    members: MemberList
 }
 
 impl StructBuilder {
-    pub fn new(name: Option<String>, id: StructID) -> StructBuilder {
+    pub fn new(name: Option<Symbol>, id: StructID) -> StructBuilder {
         StructBuilder {
-            name: name.unwrap_or(format!("anonymous struct #{}", id.0)),
+            name: name.unwrap_or_else(|| Symbol::intern(&format!("anonymous struct #{}", id.0))),
             id,
-            fields: HashMap::new()
+            members: MemberList::new()
         }
     }
 
-    pub fn add_field(&mut self, name: String, type_: Type) -> Result<(), ()> {
-        if self.fields.contains_key(&name) {
-            // a field with this name already exists
-            // TODO: return error
-            Err(())
-        }
-        else {
-            self.fields.insert(name, StructBuilderField::Field{ type_ });
-            Ok(())
-        }
+    pub fn add_field<'a>(&mut self, location: (Location, Location), name: Symbol, type_: Type) -> Result<(), CompilationError<'a>> {
+        self.members.add_field(location, name, type_)
     }
 
-    pub fn add_bit_field(&mut self, location: (Location, Location), name: String, type_: Type, bits: usize) -> Result<(), CompilationError> {
-        if let Type::Integer(int_type) = type_ {
-            if bits <= int_type.bits() {
-                if self.fields.contains_key(&name) {
-                    // a field with this name already exists
-                    // TODO: return error
-                    Err(DuplicateStructMember::new(location, name).into())
-                }
-                else {
-                    self.fields.insert(name, StructBuilderField::BitField{ type_: int_type, bits });
-                    Ok(())
+    /// Adds a bit field, unnamed bit fields only affect the layout of the fields that follow them.
+    pub fn add_bit_field<'a>(&mut self, location: (Location, Location), name: Option<Symbol>, type_: Type, bits: usize) -> Result<(), CompilationError<'a>> {
+        self.members.add_bit_field(location, name, type_, bits)
+    }
+
+    /// Adds an anonymous struct or union member (C11 6.7.2.1p13)
+    pub fn add_anonymous_member<'a>(&mut self, location: (Location, Location), type_: Type) -> Result<(), CompilationError<'a>> {
+        self.members.add_anonymous_member(location, type_)
+    }
+
+    /// Lays out the struct the way the System V x86-64 ABI does: fields are placed in declaration order
+    /// at the next offset that satisfies their alignment, and bit fields are packed into the storage unit
+    /// of their declared type, starting a new unit when a bit field would straddle a unit boundary.
+    /// The struct is as aligned as its most aligned field (unnamed bit fields don't count), and its size is
+    /// rounded up to a multiple of its alignment.
+    ///
+    /// An array of unknown size is only allowed as the last field (a flexible array member), it doesn't
+    /// add to the size of the struct.
+    pub fn build<'a>(self, registry: &TypeRegistry) -> Result<Struct, CompilationError<'a>> {
+        let mut fields = FieldList::new();
+        let mut bits_used: usize = 0;
+        let mut alignment = 1;
+        let member_count = self.members.members.len();
+
+        for (index, member) in self.members.members.into_iter().enumerate() {
+            let name = member.name;
+            let display_name = || name.map(|name| name.to_string()).unwrap_or_default();
+            match member.kind {
+                MemberKind::Field { type_ } | MemberKind::Anonymous { type_ } => {
+                    let layout = match type_ {
+                        Type::Array { ref inner, size: ArraySize::Incomplete } => {
+                            if index + 1 != member_count {
+                                return Err(FlexibleArrayMemberNotAtEnd::new(member.location, display_name()).into());
+                            }
+                            // the flexible array member itself has no size
+                            inner.type_.layout(registry).map(|inner| Layout::new(0, inner.alignment))
+                        },
+                        ref type_ => type_.layout(registry)
+                    };
+                    let layout = match layout {
+                        Some(layout) => layout,
+                        None => return Err(IncompleteStructMember::new(member.location, display_name()).into())
+                    };
+
+                    let offset = align_to(bits_used.div_ceil(8), layout.alignment);
+                    bits_used = (offset + layout.size) * 8;
+                    alignment = alignment.max(layout.alignment);
+                    match name {
+                        Some(name) => fields.push(&member.location, name, Field::Field { type_, offset })?,
+                        None => fields.push_anonymous(&member.location, &type_, offset, registry)?
+                    }
+                },
+                MemberKind::BitField { type_, bits } => {
+                    let unit_bits = Layout::of_integer(type_, registry.target()).alignment * 8;
+                    if bits == 0 {
+                        // a zero width bit field pads to the next storage unit
+                        bits_used = align_to(bits_used, unit_bits);
+                        continue;
+                    }
+                    if bits_used / unit_bits != (bits_used + bits - 1) / unit_bits {
+                        bits_used = align_to(bits_used, unit_bits);
+                    }
+                    let unit_start = bits_used / unit_bits * unit_bits;
+                    let bit_offset = bits_used - unit_start;
+                    bits_used += bits;
+
+                    if let Some(name) = name {
+                        alignment = alignment.max(Layout::of_integer(type_, registry.target()).alignment);
+                        let field = Field::BitField { type_: Type::Integer(type_), offset: unit_start / 8, bits, bit_offset };
+                        fields.push(&member.location, name, field)?;
+                    }
                 }
             }
-            else {
-                // bitfield size larger than containing type
-                Err(BitFieldSizeExceedsTypeWidth::new(location, name, bits, int_type.bits()).into())
-            }
         }
-        else {
-            // bitfield type must be an integer type
-            Err(NonIntegralBitfieldType::new(location, name).into())
+
+        Ok(Struct {
+            name: self.name,
+            id: self.id,
+            fields: fields.fields,
+            bytes: align_to(bits_used.div_ceil(8), alignment),
+            alignment
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{IntegerType, QualifiedType, TypeQualifiers, FloatType};
+
+    fn location() -> (Location, Location) {
+        (Location::default(), Location::default())
+    }
+
+    fn int(int_type: IntegerType) -> Type {
+        Type::Integer(int_type)
+    }
+
+    fn build(registry: &mut TypeRegistry, fields: &[(&str, Type)]) -> Struct {
+        let mut builder = StructBuilder::new(None, registry.declare_struct());
+        for (name, type_) in fields {
+            builder.add_field(location(), Symbol::intern(name), type_.clone()).unwrap();
         }
+        builder.build(registry).unwrap()
+    }
+
+    fn bit_field(builder: &mut StructBuilder, name: Option<&str>, int_type: IntegerType, bits: usize) {
+        builder.add_bit_field(location(), name.map(Symbol::from), int(int_type), bits).unwrap();
+    }
+
+    fn bit_field_position(s: &Struct, name: &str) -> (usize, usize) {
+        match s.field(name.into()).unwrap() {
+            Field::BitField { offset, bit_offset, .. } => (*offset, *bit_offset),
+            _ => panic!("expected a bit field")
+        }
+    }
+
+    #[test]
+    fn padding_between_fields_and_at_the_end() {
+        let mut registry = TypeRegistry::new();
+        let s = build(&mut registry, &[("c", int(IntegerType::Char)), ("i", int(IntegerType::Int)), ("d", int(IntegerType::Char))]);
+        assert_eq!(s.layout(), Layout::new(12, 4));
+        assert_eq!(s.offset_of("c".into()), Some(0));
+        assert_eq!(s.offset_of("i".into()), Some(4));
+        assert_eq!(s.offset_of("d".into()), Some(8));
+        let names: Vec<&str> = s.fields().iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["c", "i", "d"]);
+    }
+
+    #[test]
+    fn nested_struct() {
+        let mut registry = TypeRegistry::new();
+        let inner = build(&mut registry, &[("c", int(IntegerType::Char)), ("d", Type::Float(FloatType::Double))]);
+        assert_eq!(inner.layout(), Layout::new(16, 8));
+        let inner_id = inner.id();
+        registry.define_struct(inner);
+
+        let outer = build(&mut registry, &[("c", int(IntegerType::Char)), ("inner", Type::Struct(inner_id)), ("s", int(IntegerType::Short))]);
+        assert_eq!(outer.offset_of("inner".into()), Some(8));
+        assert_eq!(outer.offset_of("s".into()), Some(24));
+        assert_eq!(outer.layout(), Layout::new(32, 8));
+    }
+
+    #[test]
+    fn incomplete_member() {
+        let mut registry = TypeRegistry::new();
+        let incomplete = registry.declare_struct();
+        let mut builder = StructBuilder::new(None, registry.declare_struct());
+        builder.add_field(location(), "s".into(), Type::Struct(incomplete)).unwrap();
+        assert!(builder.build(&registry).is_err());
+    }
+
+    #[test]
+    fn bit_fields_share_a_storage_unit() {
+        let mut registry = TypeRegistry::new();
+        let mut builder = StructBuilder::new(None, registry.declare_struct());
+        bit_field(&mut builder, Some("a"), IntegerType::Int, 3);
+        bit_field(&mut builder, Some("b"), IntegerType::Int, 5);
+        builder.add_field(location(), "c".into(), int(IntegerType::Char)).unwrap();
+        let s = builder.build(&registry).unwrap();
+        assert_eq!(bit_field_position(&s, "a"), (0, 0));
+        assert_eq!(bit_field_position(&s, "b"), (0, 3));
+        assert_eq!(s.offset_of("c".into()), Some(1));
+        assert_eq!(s.layout(), Layout::new(4, 4));
     }
 
-    pub fn build(self) -> Struct {
-        unimplemented!()
+    #[test]
+    fn bit_field_does_not_straddle_a_storage_unit() {
+        let mut registry = TypeRegistry::new();
+        let mut builder = StructBuilder::new(None, registry.declare_struct());
+        builder.add_field(location(), "a".into(), int(IntegerType::Char)).unwrap();
+        bit_field(&mut builder, Some("b"), IntegerType::Int, 30);
+        bit_field(&mut builder, Some("c"), IntegerType::UnsignedChar, 2);
+        bit_field(&mut builder, Some("d"), IntegerType::UnsignedChar, 5);
+        let s = builder.build(&registry).unwrap();
+        assert_eq!(bit_field_position(&s, "b"), (4, 0));
+        // c fits in the last byte of b's storage unit, d doesn't
+        assert_eq!(bit_field_position(&s, "c"), (7, 6));
+        assert_eq!(bit_field_position(&s, "d"), (8, 0));
+        assert_eq!(s.layout(), Layout::new(12, 4));
+    }
+
+    #[test]
+    fn unnamed_bit_fields() {
+        let mut registry = TypeRegistry::new();
+        let mut builder = StructBuilder::new(None, registry.declare_struct());
+        builder.add_field(location(), "a".into(), int(IntegerType::Char)).unwrap();
+        bit_field(&mut builder, None, IntegerType::Int, 0);
+        builder.add_field(location(), "b".into(), int(IntegerType::Char)).unwrap();
+        bit_field(&mut builder, None, IntegerType::LongLong, 4);
+        let s = builder.build(&registry).unwrap();
+        assert_eq!(s.offset_of("b".into()), Some(4));
+        // unnamed bit fields don't affect the alignment of the struct
+        assert_eq!(s.layout(), Layout::new(6, 1));
+        assert_eq!(s.fields().len(), 2);
+    }
+
+    #[test]
+    fn invalid_bit_fields() {
+        let mut builder = StructBuilder::new(None, TypeRegistry::new().declare_struct());
+        assert!(builder.add_bit_field(location(), Some("a".into()), int(IntegerType::Int), 0).is_err());
+        assert!(builder.add_bit_field(location(), Some("a".into()), int(IntegerType::Char), 9).is_err());
+        assert!(builder.add_bit_field(location(), Some("a".into()), Type::Float(FloatType::Float), 1).is_err());
+        assert!(builder.add_bit_field(location(), Some("a".into()), int(IntegerType::Bool), 1).is_ok());
+        assert!(builder.add_bit_field(location(), Some("a".into()), int(IntegerType::Bool), 1).is_err());
+    }
+
+    #[test]
+    fn flexible_array_member() {
+        let mut registry = TypeRegistry::new();
+        let array = Type::Array { inner: Box::new(QualifiedType::new(TypeQualifiers::default(), Type::Float(FloatType::Double))), size: ArraySize::Incomplete };
+        let s = build(&mut registry, &[("n", int(IntegerType::Int)), ("data", array.clone())]);
+        assert_eq!(s.offset_of("data".into()), Some(8));
+        assert_eq!(s.layout(), Layout::new(8, 8));
+
+        let mut builder = StructBuilder::new(None, registry.declare_struct());
+        builder.add_field(location(), "data".into(), array).unwrap();
+        builder.add_field(location(), "n".into(), int(IntegerType::Int)).unwrap();
+        assert!(builder.build(&registry).is_err());
     }
 }
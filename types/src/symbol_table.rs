@@ -1,121 +1,337 @@
 use std::collections::HashMap;
 
-use types::*;
-use ast::Location;
+use ast::{Location, Symbol};
 use errors::*;
 
+use crate::*;
+
+/// Identifies the declaration that an ordinary identifier refers to. Redeclarations of an identifier
+/// with linkage refer to the same declaration.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct VariableID(i32);
+pub struct SymbolID(usize);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct LabelID(i32);
+pub struct LabelID(usize);
+
+/// The type that a struct, union or enum tag refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tag {
+    Struct(StructID),
+    Union(UnionID),
+    Enum(EnumID)
+}
+
+impl Tag {
+    pub fn to_type(self) -> Type {
+        match self {
+            Tag::Struct(id) => Type::Struct(id),
+            Tag::Union(id) => Type::Union(id),
+            Tag::Enum(id) => Type::Enum(id)
+        }
+    }
+}
+
+/// Variables, functions, typedef names and enumeration constants share a namespace
+#[derive(Debug, Clone)]
+pub enum OrdinaryIdentifier {
+    Variable(QualifiedType, StorageClass),
+    EnumVariant(i128),
+    Function(QualifiedType, StorageClass),
+    Typedef(QualifiedType)
+}
+
+/// The entry of an ordinary identifier in the symbol table
+#[derive(Debug, Clone)]
+pub struct SymbolEntry {
+    pub id: SymbolID,
+    pub kind: OrdinaryIdentifier,
+
+    /// The location of the first declaration
+    pub location: (Location, Location)
+}
 
 /// The symbol table is implemented as a stack of hash maps.
-/// Each lexical scope level is an element on the stack.
+/// Each lexical scope level is an element on the stack, and has a separate namespace for ordinary
+/// identifiers and tags. Labels are kept by the function scope that they belong to (C99 6.2.1p3).
+#[derive(Debug)]
 pub struct SymbolTable {
-    scope_stack: Vec<ScopeLevel>
+    scope_stack: Vec<ScopeLevel>,
+    next_symbol: usize,
+    next_label: usize
 }
 
 impl SymbolTable {
-     /// Creates a new symbol table.
-     /// The scope stack will contain a single empty scope level, the global scope.
+    /// Creates a new symbol table.
+    /// The scope stack will contain a single empty scope level, the global scope.
     pub fn new() -> SymbolTable {
         SymbolTable {
-            scope_stack: vec![ ScopeLevel::new(ScopeType::Global) ]
+            scope_stack: vec![ ScopeLevel::new(ScopeType::Global) ],
+            next_symbol: 0,
+            next_label: 0
         }
     }
 
-    pub fn lookup_identifier(&self, name: &String) -> Option<&(OrdinaryIdentifier, Location)> {
-        for level in (0..self.scope_stack.len()).rev() {
-            let ident = self.scope_stack[level].ordinary_identifiers.get(name);
-            if ident.is_some() {
-                return ident;
-            }
+    pub fn scope_enter(&mut self, scope_type: ScopeType) {
+        self.scope_stack.push(ScopeLevel::new(scope_type));
+    }
+
+    /// Leaves the innermost scope, you can't leave the global scope. Leaving a function scope fails if
+    /// one of its labels was used without being defined.
+    pub fn scope_leave<'a>(&mut self) -> Result<(), CompilationError<'a>> {
+        if self.scope_stack.len() == 1 {
+            return Ok(());
+        }
+        let level = self.scope_stack.pop().unwrap();
+        let undefined = level.labels.into_iter()
+            .filter(|(_, label)| !label.defined)
+            .min_by_key(|(_, label)| label.id.0);
+        match undefined {
+            Some((name, label)) => Err(UndefinedLabel::new(label.location, name.to_string()).into()),
+            None => Ok(())
         }
-        None
     }
 
-    pub fn insert_typedef(&mut self, name: &String, value: QualifiedType, location: Location) -> Result<(), TypedefRedefinitionError> {
-        unimplemented!()
+    pub fn scope_type(&self) -> ScopeType {
+        self.scope_stack.last().unwrap().scope_type
     }
 
-    pub fn insert_variable(&mut self, name: &String, value: Type, location: Location) -> Result<(), VariableRedefinitionError> {
-        if let Some((ident, old_location)) = self.scope_stack.last().unwrap().ordinary_identifiers.get(name) {
-            if let OrdinaryIdentifier::Variable(old_type) = ident {
-                // it's illegal to redeclare a variable inside the same scope level
-                Err(VariableRedefinitionError::new(name.clone(), location, old_location.clone()))
-            }
-            else {
-                // it is illegal to redeclare an identifier as a different kind of symbol within the same scope level
-                //TODO: error
-                panic!()
+    /// Looks up an ordinary identifier, starting from the innermost scope
+    pub fn lookup_identifier(&self, name: Symbol) -> Option<&SymbolEntry> {
+        self.scope_stack.iter().rev().find_map(|level| level.ordinary_identifiers.get(&name))
+    }
+
+    pub fn lookup_identifier_in_current_scope(&self, name: Symbol) -> Option<&SymbolEntry> {
+        self.scope_stack.last().unwrap().ordinary_identifiers.get(&name)
+    }
+
+    pub fn insert_variable<'a>(&mut self, name: Symbol, value: QualifiedType, storage_class: StorageClass, location: (Location, Location)) -> Result<SymbolID, CompilationError<'a>> {
+        self.insert(name, OrdinaryIdentifier::Variable(value, storage_class), location)
+    }
+
+    pub fn insert_function<'a>(&mut self, name: Symbol, value: QualifiedType, storage_class: StorageClass, location: (Location, Location)) -> Result<SymbolID, CompilationError<'a>> {
+        self.insert(name, OrdinaryIdentifier::Function(value, storage_class), location)
+    }
+
+    pub fn insert_typedef<'a>(&mut self, name: Symbol, value: QualifiedType, location: (Location, Location)) -> Result<SymbolID, CompilationError<'a>> {
+        self.insert(name, OrdinaryIdentifier::Typedef(value), location)
+    }
+
+    pub fn insert_enum_variant<'a>(&mut self, name: Symbol, value: i128, location: (Location, Location)) -> Result<SymbolID, CompilationError<'a>> {
+        self.insert(name, OrdinaryIdentifier::EnumVariant(value), location)
+    }
+
+    /// Declares an ordinary identifier in the innermost scope, which hides any declaration of the same
+    /// identifier in an enclosing scope. Within one scope, only identifiers with linkage can be declared
+    /// more than once (with compatible types), and typedef names can be redefined to the same type
+    /// (C11 6.7p3).
+    fn insert<'a>(&mut self, name: Symbol, kind: OrdinaryIdentifier, location: (Location, Location)) -> Result<SymbolID, CompilationError<'a>> {
+        use self::OrdinaryIdentifier::*;
+
+        let scope_type = self.scope_type();
+        let level = self.scope_stack.last_mut().unwrap();
+        let previous = match level.ordinary_identifiers.get_mut(&name) {
+            Some(previous) => previous,
+            None => {
+                let id = SymbolID(self.next_symbol);
+                self.next_symbol += 1;
+                level.ordinary_identifiers.insert(name, SymbolEntry { id, kind, location });
+                return Ok(id);
             }
+        };
+
+        let has_linkage = |storage_class: StorageClass| scope_type == ScopeType::Global || storage_class == StorageClass::Extern;
+        match (&mut previous.kind, kind) {
+            (Typedef(old), Typedef(new)) => {
+                if *old != new {
+                    return Err(TypedefRedefinitionError::new(name.to_string(), location.0, previous.location.0.clone()).into());
+                }
+            },
+            (Variable(old, old_class), Variable(new, new_class)) => {
+                if !has_linkage(*old_class) || !has_linkage(new_class) {
+                    return Err(VariableRedefinitionError::new(name.to_string(), location.0, previous.location.0.clone()).into());
+                }
+                if !old.is_compatible_with(&new) {
+                    return Err(ConflictingTypes::new(location, name.to_string()).into());
+                }
+                // a later declaration can complete the type of an array
+                if let Type::Array { size: ArraySize::Incomplete, .. } = old.type_ {
+                    *old = new;
+                }
+            },
+            (Function(old, _), Function(new, _)) => {
+                if !old.is_compatible_with(&new) {
+                    return Err(ConflictingTypes::new(location, name.to_string()).into());
+                }
+            },
+            (EnumVariant(_), EnumVariant(_)) => {
+                return Err(VariableRedefinitionError::new(name.to_string(), location.0, previous.location.0.clone()).into());
+            },
+            _ => return Err(SymbolKindMismatch::new(location, name.to_string()).into())
         }
-        else {
-            self.scope_stack.last_mut().unwrap().ordinary_identifiers.insert(name.clone(), (OrdinaryIdentifier::Variable(value), location));
-            Ok(())
-        }
+        Ok(previous.id)
     }
 
-    pub fn scope_enter(&mut self, scope_type: ScopeType) {
-        self.scope_stack.push(ScopeLevel::new(scope_type));
+    /// Looks up a tag, starting from the innermost scope
+    pub fn lookup_tag(&self, name: Symbol) -> Option<Tag> {
+        self.scope_stack.iter().rev().find_map(|level| level.tags.get(&name)).cloned()
+    }
+
+    /// Looks up a tag in the innermost scope only
+    pub fn lookup_tag_in_current_scope(&self, name: Symbol) -> Option<Tag> {
+        self.scope_stack.last().unwrap().tags.get(&name).cloned()
+    }
+
+    pub fn insert_tag(&mut self, name: Symbol, tag: Tag) {
+        self.scope_stack.last_mut().unwrap().tags.insert(name, tag);
     }
 
-    pub fn scope_leave(&mut self) {
-        // you can't leave the global scope
-        if self.scope_stack.len() > 1 {
-            self.scope_stack.pop();
+    /// Defines a label in the enclosing function, a label can only be defined once.
+    pub fn define_label<'a>(&mut self, name: Symbol, location: (Location, Location)) -> Result<LabelID, CompilationError<'a>> {
+        let label = self.label(name, location.clone());
+        if label.defined {
+            return Err(LabelRedefinition::new(location, name.to_string()).into());
         }
+        label.defined = true;
+        Ok(label.id)
+    }
+
+    /// Refers to a label of the enclosing function (e.g. in a goto statement), which can be defined
+    /// before or after it is used.
+    pub fn reference_label(&mut self, name: Symbol, location: (Location, Location)) -> LabelID {
+        self.label(name, location).id
+    }
+
+    fn label(&mut self, name: Symbol, location: (Location, Location)) -> &mut Label {
+        let next_label = &mut self.next_label;
+        let function = self.scope_stack.iter_mut().rev()
+            .find(|level| level.scope_type == ScopeType::Function)
+            .expect("labels can only be used inside of a function");
+        function.labels.entry(name).or_insert_with(|| {
+            let id = LabelID(*next_label);
+            *next_label += 1;
+            Label { id, defined: false, location }
+        })
     }
 }
 
-pub struct ScopeLevel {
+impl Default for SymbolTable {
+    fn default() -> Self {
+        SymbolTable::new()
+    }
+}
+
+#[derive(Debug)]
+struct ScopeLevel {
     scope_type: ScopeType,
-    ordinary_identifiers: HashMap<String, (OrdinaryIdentifier, Location)>, // variables, functions, and typedefs share the same namespace
-    tags: HashMap<String, Tag>,
-    labels: HashMap<String, ()>, //TODO
-    structs: HashMap<StructID,Struct>,
-    unions: HashMap<UnionID, Union>,
-    enums: HashMap<EnumID, Enum>
+    ordinary_identifiers: HashMap<Symbol, SymbolEntry>, // variables, functions, typedefs and enum variants share the same namespace
+    tags: HashMap<Symbol, Tag>,
+    labels: HashMap<Symbol, Label>
 }
 
 impl ScopeLevel {
-    pub fn new(scope_type: ScopeType) -> ScopeLevel {
+    fn new(scope_type: ScopeType) -> ScopeLevel {
         ScopeLevel {
             scope_type,
             ordinary_identifiers: HashMap::new(),
             tags: HashMap::new(),
-            labels: HashMap::new(),
-            structs: HashMap::new(),
-            unions: HashMap::new(),
-            enums: HashMap::new()
+            labels: HashMap::new()
         }
     }
+}
 
-    pub fn level_type(&self) -> &ScopeType {
-        &self.scope_type
-    }
+#[derive(Debug)]
+struct Label {
+    id: LabelID,
+    defined: bool,
+
+    /// Where the label was first used or defined
+    location: (Location, Location)
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum ScopeType {
     Global,
+    /// The outermost block of a function definition, which also holds its labels
     Function,
-    Block
+    Block,
+    /// The parameter list of a function declarator that isn't part of a function definition
+    Prototype
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Tag {
-    Struct(StructID),
-    Enum(EnumID),
-    Union(UnionID)
-}
+#[cfg(test)]
+mod test {
+    use super::*;
 
-/// Variable identifiers, functions, and typedefs share a namespace
-#[derive(Debug)]
-pub enum OrdinaryIdentifier {
-    Variable(QualifiedType, StorageClass),
-    EnumVariant(i32),
-    Function(Option<Vec<Type>>, Type),
-    Typedef(QualifiedType)
+    fn location() -> (Location, Location) {
+        (Location::default(), Location::default())
+    }
+
+    fn int() -> QualifiedType {
+        QualifiedType::new(TypeQualifiers::default(), Type::Integer(IntegerType::Int))
+    }
+
+    fn array(size: ArraySize) -> QualifiedType {
+        QualifiedType::new(TypeQualifiers::default(), Type::Array { inner: Box::new(int()), size })
+    }
+
+    #[test]
+    fn shadowing() {
+        let mut table = SymbolTable::new();
+        let outer = table.insert_variable("a".into(), int(), StorageClass::Auto, location()).unwrap();
+        table.scope_enter(ScopeType::Block);
+        assert_eq!(table.lookup_identifier("a".into()).unwrap().id, outer);
+        assert!(table.lookup_identifier_in_current_scope("a".into()).is_none());
+        let inner = table.insert_typedef("a".into(), int(), location()).unwrap();
+        assert_ne!(inner, outer);
+        assert!(matches!(table.lookup_identifier("a".into()).unwrap().kind, OrdinaryIdentifier::Typedef(_)));
+        table.scope_leave().unwrap();
+        assert_eq!(table.lookup_identifier("a".into()).unwrap().id, outer);
+    }
+
+    #[test]
+    fn redeclarations() {
+        let mut table = SymbolTable::new();
+        let a = table.insert_variable("a".into(), array(ArraySize::Incomplete), StorageClass::Extern, location()).unwrap();
+        assert_eq!(table.insert_variable("a".into(), array(ArraySize::Constant(3)), StorageClass::Auto, location()).unwrap(), a);
+        assert!(table.insert_variable("a".into(), array(ArraySize::Constant(4)), StorageClass::Auto, location()).is_err());
+        assert!(table.insert_function("a".into(), int(), StorageClass::Extern, location()).is_err());
+        assert!(table.insert_enum_variant("a".into(), 1, location()).is_err());
+
+        table.insert_typedef("t".into(), int(), location()).unwrap();
+        assert!(table.insert_typedef("t".into(), int(), location()).is_ok());
+        assert!(table.insert_typedef("t".into(), array(ArraySize::Incomplete), location()).is_err());
+
+        // identifiers without linkage can't be redeclared
+        table.scope_enter(ScopeType::Block);
+        table.insert_variable("b".into(), int(), StorageClass::Auto, location()).unwrap();
+        assert!(table.insert_variable("b".into(), int(), StorageClass::Auto, location()).is_err());
+        table.insert_variable("c".into(), int(), StorageClass::Extern, location()).unwrap();
+        assert!(table.insert_variable("c".into(), int(), StorageClass::Extern, location()).is_ok());
+    }
+
+    #[test]
+    fn separate_namespaces() {
+        let mut table = SymbolTable::new();
+        table.insert_variable("s".into(), int(), StorageClass::Auto, location()).unwrap();
+        table.insert_tag("s".into(), Tag::Struct(StructID(0)));
+        table.scope_enter(ScopeType::Function);
+        table.define_label("s".into(), location()).unwrap();
+        assert!(matches!(table.lookup_identifier("s".into()).unwrap().kind, OrdinaryIdentifier::Variable(..)));
+        assert_eq!(table.lookup_tag("s".into()), Some(Tag::Struct(StructID(0))));
+        assert!(table.lookup_tag_in_current_scope("s".into()).is_none());
+        assert!(table.scope_leave().is_ok());
+    }
+
+    #[test]
+    fn labels_have_function_scope() {
+        let mut table = SymbolTable::new();
+        table.scope_enter(ScopeType::Function);
+        table.scope_enter(ScopeType::Block);
+        let forward = table.reference_label("end".into(), location());
+        table.scope_leave().unwrap();
+        assert_eq!(table.define_label("end".into(), location()).unwrap(), forward);
+        assert!(table.define_label("end".into(), location()).is_err());
+        table.reference_label("missing".into(), location());
+        assert!(table.scope_leave().is_err());
+    }
 }
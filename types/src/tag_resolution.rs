@@ -0,0 +1,297 @@
+use ast::{Location, EnumSpecifier, StructOrUnion, StructOrUnionSpecifier, StructDeclaration, StructDeclarator, SpecifierQualifier, TypeSpecifier};
+use errors::{CompilationError, TagKindMismatch, TagRedefinition, InvalidBitFieldWidth};
+
+use crate::*;
+use crate::constant_expression;
+use crate::type_resolution::ResolveDeclarator;
+
+/// A struct or union that is being defined
+enum Builder {
+    Struct(StructBuilder),
+    Union(UnionBuilder)
+}
+
+impl Builder {
+    fn add_field<'a>(&mut self, location: (Location, Location), name: ast::Symbol, type_: Type) -> Result<(), CompilationError<'a>> {
+        match self {
+            Builder::Struct(builder) => builder.add_field(location, name, type_),
+            Builder::Union(builder) => builder.add_field(location, name, type_)
+        }
+    }
+
+    fn add_bit_field<'a>(&mut self, location: (Location, Location), name: Option<ast::Symbol>, type_: Type, bits: usize) -> Result<(), CompilationError<'a>> {
+        match self {
+            Builder::Struct(builder) => builder.add_bit_field(location, name, type_, bits),
+            Builder::Union(builder) => builder.add_bit_field(location, name, type_, bits)
+        }
+    }
+
+    fn add_anonymous_member<'a>(&mut self, location: (Location, Location), type_: Type) -> Result<(), CompilationError<'a>> {
+        match self {
+            Builder::Struct(builder) => builder.add_anonymous_member(location, type_),
+            Builder::Union(builder) => builder.add_anonymous_member(location, type_)
+        }
+    }
+
+    fn define<'a>(self, registry: &mut TypeRegistry) -> Result<(), CompilationError<'a>> {
+        match self {
+            Builder::Struct(builder) => {
+                let definition = builder.build(registry)?;
+                registry.define_struct(definition);
+            },
+            Builder::Union(builder) => {
+                let definition = builder.build(registry)?;
+                registry.define_union(definition);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn declare(kind: StructOrUnion, env: &mut TypeEnvironment) -> Tag {
+    match kind {
+        StructOrUnion::Struct => Tag::Struct(env.registry_mut().declare_struct()),
+        StructOrUnion::Union => Tag::Union(env.registry_mut().declare_union())
+    }
+}
+
+fn matches_kind(kind: StructOrUnion, tag: Tag) -> bool {
+    matches!((kind, tag), (StructOrUnion::Struct, Tag::Struct(_)) | (StructOrUnion::Union, Tag::Union(_)))
+}
+
+fn is_complete(tag: Tag, registry: &TypeRegistry) -> bool {
+    match tag {
+        Tag::Struct(id) => registry.get_struct(id).is_some(),
+        Tag::Union(id) => registry.get_union(id).is_some(),
+        Tag::Enum(_) => true
+    }
+}
+
+/// Returns the tag that a struct, union or enum specifier with a member list defines: a new one
+/// declared in the current scope, or a previous declaration of the tag in the same scope that
+/// hasn't been completed yet.
+fn tag_for_definition<'a>(identifier: &Option<ast::Symbol>, location: &(Location, Location), env: &mut TypeEnvironment,
+                          same_kind: &dyn Fn(Tag) -> bool, declare: &dyn Fn(&mut TypeEnvironment) -> Tag) -> Result<Tag, CompilationError<'a>> {
+    let name = match identifier {
+        Some(name) => *name,
+        None => return Ok(declare(env))
+    };
+    match env.lookup_tag_in_current_scope(name) {
+        Some(tag) if !same_kind(tag) => Err(TagKindMismatch::new(location.clone(), name.to_string()).into()),
+        Some(tag) if is_complete(tag, env.registry()) => Err(TagRedefinition::new(location.clone(), name.to_string()).into()),
+        Some(tag) => Ok(tag),
+        None => {
+            let tag = declare(env);
+            env.declare_tag(name, tag);
+            Ok(tag)
+        }
+    }
+}
+
+/// Returns the type that a tag without a member list refers to: the visible declaration of that tag,
+/// or a new incomplete type if there is none.
+fn tag_for_reference<'a>(identifier: ast::Symbol, location: &(Location, Location), env: &mut TypeEnvironment,
+                         same_kind: &dyn Fn(Tag) -> bool, declare: &dyn Fn(&mut TypeEnvironment) -> Tag) -> Result<Type, CompilationError<'a>> {
+    match env.lookup_tag(identifier) {
+        Some(tag) if same_kind(tag) => Ok(tag.to_type()),
+        Some(_) => Err(TagKindMismatch::new(location.clone(), identifier.to_string()).into()),
+        None => {
+            let tag = declare(env);
+            env.declare_tag(identifier, tag);
+            Ok(tag.to_type())
+        }
+    }
+}
+
+/// Returns the type named by a struct or union specifier, declaring its tag and defining it as needed.
+pub(crate) fn resolve_struct_or_union<'a>(specifier: &StructOrUnionSpecifier, location: (Location, Location), env: &mut TypeEnvironment) -> Result<Type, CompilationError<'a>> {
+    match specifier {
+        StructOrUnionSpecifier::Partial { kind, identifier } => {
+            tag_for_reference(*identifier, &location, env, &|tag| matches_kind(*kind, tag), &|env| declare(*kind, env))
+        },
+        StructOrUnionSpecifier::Complete { kind, identifier, declaration_list } => {
+            let tag = tag_for_definition(identifier, &location, env, &|tag| matches_kind(*kind, tag), &|env| declare(*kind, env))?;
+            let mut builder = match tag {
+                Tag::Struct(id) => Builder::Struct(StructBuilder::new(*identifier, id)),
+                Tag::Union(id) => Builder::Union(UnionBuilder::new(*identifier, id)),
+                Tag::Enum(_) => unreachable!()
+            };
+            for declaration in declaration_list {
+                add_members(&mut builder, declaration, &location, env)?;
+            }
+            builder.define(env.registry_mut())?;
+            Ok(tag.to_type())
+        }
+    }
+}
+
+/// Returns the type named by an enum specifier, declaring its tag and defining it as needed.
+///
+/// Each enumeration constant is in scope right after its enumerator, so the values of the
+/// following enumerators can refer to it.
+pub(crate) fn resolve_enum<'a>(specifier: &EnumSpecifier, location: (Location, Location), env: &mut TypeEnvironment) -> Result<Type, CompilationError<'a>> {
+    let is_enum = |tag| matches!(tag, Tag::Enum(_));
+    let declare_enum = |env: &mut TypeEnvironment| Tag::Enum(env.registry_mut().declare_enum());
+    match specifier {
+        EnumSpecifier::Partial { identifier } => tag_for_reference(*identifier, &location, env, &is_enum, &declare_enum),
+        EnumSpecifier::Complete { identifier, enumerator_list } => {
+            let tag = tag_for_definition(identifier, &location, env, &is_enum, &declare_enum)?;
+            let id = match tag {
+                Tag::Enum(id) => id,
+                _ => unreachable!()
+            };
+
+            let mut builder = EnumBuilder::new(*identifier, id);
+            for enumerator in enumerator_list {
+                let value = match enumerator.value {
+                    Some(ref expression) => Some(constant_expression::evaluate(&expression.0, &location, env)?.value),
+                    None => None
+                };
+                let value = builder.add_variant(enumerator.identifier, value, location.clone())?;
+                env.declare_constant(enumerator.identifier, value, location.clone())?;
+            }
+            let definition = builder.build(env.target(), env.diagnostics_mut())?;
+            env.registry_mut().define_enum(definition);
+            Ok(tag.to_type())
+        }
+    }
+}
+
+/// Adds the members declared by a struct declaration. The ast doesn't keep track of the location of each
+/// member, so the location of the enclosing specifier is used for errors.
+fn add_members<'a>(builder: &mut Builder, declaration: &StructDeclaration, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<(), CompilationError<'a>> {
+    let (specifiers, declarators) = match declaration {
+        StructDeclaration::Members { specification_qualifier_list, struct_declaration_list } => (specification_qualifier_list, struct_declaration_list),
+        StructDeclaration::StaticAssert(assertion) => {
+            let location = (assertion.start.clone(), assertion.end.clone());
+            return constant_expression::check_static_assert(&assertion.value, &location, env);
+        }
+    };
+    let base_type = QualifiedType::from_specifier_qualifier_list(specifiers, location, env)?;
+
+    if declarators.is_empty() {
+        // a struct or union without a tag or declarator is an anonymous member, any other
+        // declaration without a declarator doesn't declare a member
+        let is_anonymous = specifiers.iter().any(|specifier| matches!(specifier,
+            SpecifierQualifier::TypeSpecifier(TypeSpecifier::StructOrUnionSpecifier(StructOrUnionSpecifier::Complete { identifier: None, .. }))));
+        if is_anonymous {
+            builder.add_anonymous_member(location.clone(), base_type.type_)?;
+        }
+        return Ok(());
+    }
+
+    for declarator in declarators.iter() {
+        match declarator {
+            StructDeclarator::Field(declarator) => {
+                let (qualified_type, name) = declarator.resolve(base_type.clone(), location, env)?;
+                let name = name.expect("struct member declarator without an identifier");
+                builder.add_field(location.clone(), name, qualified_type.type_)?;
+            },
+            StructDeclarator::BitField(declarator, width) => {
+                let (qualified_type, name) = match declarator {
+                    Some(declarator) => declarator.resolve(base_type.clone(), location, env)?,
+                    None => (base_type.clone(), None)
+                };
+                let bits = constant_expression::evaluate(&width.0, location, env)?.value;
+                if bits < 0 {
+                    return Err(InvalidBitFieldWidth::new(location.clone(), name.map(|name| name.to_string()).unwrap_or_default()).into());
+                }
+                let bits = bits as usize;
+                builder.add_bit_field(location.clone(), name, qualified_type.type_, bits)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // The parser depends on this crate, so these tests spell out the ast of the declarations by hand.
+    fn type_specifier(specifier: TypeSpecifier) -> SpecifierQualifier {
+        SpecifierQualifier::TypeSpecifier(specifier)
+    }
+
+    fn field(name: &str) -> StructDeclarator {
+        StructDeclarator::Field(ast::Declarator {
+            pointer: None,
+            direct_declarator: vec![ast::DirectDeclaratorPart::Identifier(name.into())]
+        })
+    }
+
+    fn member(specifier: TypeSpecifier, names: &[&str]) -> StructDeclaration {
+        StructDeclaration::new(vec![type_specifier(specifier)], names.iter().map(|name| field(name)).collect())
+    }
+
+    fn complete(kind: StructOrUnion, identifier: Option<&str>, declaration_list: Vec<StructDeclaration>) -> StructOrUnionSpecifier {
+        StructOrUnionSpecifier::Complete { kind, identifier: identifier.map(ast::Symbol::from), declaration_list }
+    }
+
+    fn location() -> (Location, Location) {
+        (Location::default(), Location::default())
+    }
+
+    #[test]
+    fn union_specifier() {
+        let mut env = TypeEnvironment::new();
+        let specifier = complete(StructOrUnion::Union, Some("u"), vec![
+            member(TypeSpecifier::Char, &["c"]),
+            member(TypeSpecifier::Double, &["d"])
+        ]);
+        let id = match resolve_struct_or_union(&specifier, location(), &mut env).unwrap() {
+            Type::Union(id) => id,
+            other => panic!("expected a union, found {}", other)
+        };
+        assert_eq!(env.registry().get_union(id).unwrap().layout(), Layout::new(8, 8));
+
+        // the tag refers to the same union
+        let partial = StructOrUnionSpecifier::Partial { kind: StructOrUnion::Union, identifier: "u".into() };
+        match resolve_struct_or_union(&partial, location(), &mut env).unwrap() {
+            Type::Union(other) => assert_eq!(id, other),
+            other => panic!("expected a union, found {}", other)
+        }
+
+        // but not to a struct
+        let partial = StructOrUnionSpecifier::Partial { kind: StructOrUnion::Struct, identifier: "u".into() };
+        assert!(resolve_struct_or_union(&partial, location(), &mut env).is_err());
+
+        // and can't be defined twice in the same scope
+        assert!(resolve_struct_or_union(&specifier, location(), &mut env).is_err());
+        env.enter_scope(ScopeType::Block);
+        assert!(resolve_struct_or_union(&specifier, location(), &mut env).is_ok());
+    }
+
+    #[test]
+    fn anonymous_union_member() {
+        let mut env = TypeEnvironment::new();
+        let anonymous = complete(StructOrUnion::Union, None, vec![
+            member(TypeSpecifier::Int, &["i"]),
+            member(TypeSpecifier::Float, &["f"])
+        ]);
+        let specifier = complete(StructOrUnion::Struct, Some("s"), vec![
+            member(TypeSpecifier::Char, &["tag"]),
+            StructDeclaration::new(vec![type_specifier(TypeSpecifier::StructOrUnionSpecifier(anonymous))], vec![])
+        ]);
+        let id = match resolve_struct_or_union(&specifier, location(), &mut env).unwrap() {
+            Type::Struct(id) => id,
+            other => panic!("expected a struct, found {}", other)
+        };
+        let s = env.registry().get_struct(id).unwrap();
+        assert_eq!(s.offset_of("i".into()), Some(4));
+        assert_eq!(s.offset_of("f".into()), Some(4));
+        assert_eq!(s.layout(), Layout::new(8, 4));
+    }
+
+    #[test]
+    fn incomplete_until_defined() {
+        let mut env = TypeEnvironment::new();
+        let partial = StructOrUnionSpecifier::Partial { kind: StructOrUnion::Struct, identifier: "s".into() };
+        let declared = resolve_struct_or_union(&partial, location(), &mut env).unwrap();
+        assert!(declared.layout(env.registry()).is_none());
+
+        let specifier = complete(StructOrUnion::Struct, Some("s"), vec![member(TypeSpecifier::Short, &["a", "b"])]);
+        resolve_struct_or_union(&specifier, location(), &mut env).unwrap();
+        assert_eq!(declared.layout(env.registry()), Some(Layout::new(4, 2)));
+    }
+}
@@ -1,23 +1,25 @@
 use ast::{DirectDeclaratorPart, Declarator, Pointer, AssignmentExpression,
-          ParameterTypeList, AbstractDeclarator, ParameterDeclaration};
-use errors::CompilationError;
+          ParameterTypeList, AbstractDeclarator, ParameterDeclaration, Location, Symbol};
+use errors::{CompilationError, InvalidArraySize};
 use crate::*;
 
 enum DeclaratorPartialType {
     Pointer(Pointer),
     Array(Option<AssignmentExpression>),
+    /// `[*]`, a variable length array of unspecified size
+    VLA,
     Function(ParameterTypeList)
 }
 
 pub trait ResolveDeclarator {
-    fn resolve(&self, initial_type: QualifiedType) -> (QualifiedType, Option<String>);
+    fn resolve<'a>(&self, initial_type: QualifiedType, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<(QualifiedType, Option<Symbol>), CompilationError<'a>>;
 }
 
 pub trait ResolveAbstractDeclarator {
-    fn resolve(&self, initial_type: QualifiedType) -> QualifiedType;
+    fn resolve<'a>(&self, initial_type: QualifiedType, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<QualifiedType, CompilationError<'a>>;
 }
 
-fn resolve(initial_type: QualifiedType, identifier: Option<String>, mut stack: Vec<DeclaratorPartialType>) -> (QualifiedType, Option<String>) {
+fn resolve<'a>(initial_type: QualifiedType, identifier: Option<Symbol>, mut stack: Vec<DeclaratorPartialType>, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<(QualifiedType, Option<Symbol>), CompilationError<'a>> {
     let mut t = initial_type;
 
     while let Some(partial_type) = stack.pop() {
@@ -28,34 +30,61 @@ fn resolve(initial_type: QualifiedType, identifier: Option<String>, mut stack: V
                     ptr
                 },
                 DeclaratorPartialType::Array(size) => {
-                    QualifiedType::new(TypeQualifiers::default(), 
+                    let size = resolve_array_size(size, &identifier, location, env)?;
+                    QualifiedType::new(TypeQualifiers::default(),
                               Type::Array{ inner: Box::new(t), size })
                 },
+                DeclaratorPartialType::VLA => {
+                    QualifiedType::new(TypeQualifiers::default(),
+                              Type::Array{ inner: Box::new(t), size: ArraySize::Variable(None) })
+                },
                 DeclaratorPartialType::Function(param_list) => {
-                    let (resolved_param_list, variadic) = resolve_parameter_type_list(param_list).unwrap();
+                    let (resolved_param_list, variadic) = resolve_parameter_type_list(param_list, env)?;
                     QualifiedType::new(TypeQualifiers::default(),
                               Type::Function{ parameters: resolved_param_list, variadic, returns: Box::new(t) })
                 }
             };
         }
 
-        (t, identifier)
+        Ok((t, identifier))
+}
+
+/// An array whose size is an integer constant expression has a constant size, and an array whose size
+/// has operands that aren't constant is a variable length array. Other errors in the size (ex: a division by
+/// zero) are reported.
+fn resolve_array_size<'a>(size: Option<AssignmentExpression>, identifier: &Option<Symbol>, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<ArraySize, CompilationError<'a>> {
+    let expression = match size {
+        Some(AssignmentExpression::ConditionalExpression(ref expression)) => expression,
+        Some(size) => return Ok(ArraySize::Variable(Some(size))),
+        None => return Ok(ArraySize::Incomplete)
+    };
+    match constant_expression::evaluate(expression, location, env) {
+        Ok(length) => {
+            if length.value < 0 {
+                let name = identifier.map(|name| name.to_string()).unwrap_or_else(|| "<unnamed>".to_string());
+                return Err(InvalidArraySize::new(location.clone(), name).into());
+            }
+            Ok(ArraySize::Constant(length.value as u64))
+        },
+        Err(CompilationError::InvalidConstantExpression(ref error)) if error.has_non_constant_operand() => Ok(ArraySize::Variable(size)),
+        Err(error) => Err(error)
+    }
 }
 
 impl ResolveDeclarator for Declarator {
-    fn resolve(&self, initial_type: QualifiedType) -> (QualifiedType, Option<String>) {
+    fn resolve<'a>(&self, initial_type: QualifiedType, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<(QualifiedType, Option<Symbol>), CompilationError<'a>> {
         let mut identifier = None;
         let stack = self.build_stack(&mut identifier);
-        resolve(initial_type, identifier, stack)
+        resolve(initial_type, identifier, stack, location, env)
     }
 }
 
 trait BuildDeclaratorTypeStack {
-    fn build_stack(&self, identifier: &mut Option<String>) -> Vec<DeclaratorPartialType>;
+    fn build_stack(&self, identifier: &mut Option<Symbol>) -> Vec<DeclaratorPartialType>;
 }
 
 impl BuildDeclaratorTypeStack for Declarator {
-    fn build_stack(&self, identifier: &mut Option<String>) -> Vec<DeclaratorPartialType> {
+    fn build_stack(&self, identifier: &mut Option<Symbol>) -> Vec<DeclaratorPartialType> {
         let mut left: Vec<DeclaratorPartialType> = Vec::new();
         let mut right: Vec<DeclaratorPartialType> = Vec::new();
 
@@ -83,7 +112,7 @@ impl BuildDeclaratorTypeStack for Declarator {
             match part {
                 DirectDeclaratorPart::Identifier(ident) => {
                     if identifier.is_none() {
-                        *identifier = Some(ident.clone());
+                        *identifier = Some(*ident);
                     }
                     else {
                         // TODO: properly handle error
@@ -98,7 +127,7 @@ impl BuildDeclaratorTypeStack for Declarator {
                     }
                 },
                 DirectDeclaratorPart::Array(size) => active_stack!(identifier, left, right).push(DeclaratorPartialType::Array(size.clone())),
-                DirectDeclaratorPart::VLA => active_stack!(identifier, left, right).push(DeclaratorPartialType::Array(None)),
+                DirectDeclaratorPart::VLA => active_stack!(identifier, left, right).push(DeclaratorPartialType::VLA),
                 DirectDeclaratorPart::ParameterTypeList(type_list) => active_stack!(identifier, left, right).push(DeclaratorPartialType::Function(type_list.clone())),
                 DirectDeclaratorPart::IdentifierList(ident_list) => { 
                         /* TODO: convert identifier list to type_list (assume implicit int?) */
@@ -126,9 +155,9 @@ impl BuildDeclaratorTypeStack for Declarator {
 }
 
 impl ResolveAbstractDeclarator for AbstractDeclarator {
-    fn resolve(&self, initial_type: QualifiedType) -> QualifiedType {
-        let mut stack = build_abstract_declarator_type_stack(self);
-        resolve(initial_type, None, stack).0
+    fn resolve<'a>(&self, initial_type: QualifiedType, location: &(Location, Location), env: &mut TypeEnvironment) -> Result<QualifiedType, CompilationError<'a>> {
+        let stack = build_abstract_declarator_type_stack(self);
+        Ok(resolve(initial_type, None, stack, location, env)?.0)
     }
 }
 
@@ -163,7 +192,7 @@ fn build_abstract_declarator_type_stack(declarator: &AbstractDeclarator) -> Vec<
                         }
                     },
                     DirectAbstractDeclaratorPart::Array(size) => stack.push(DeclaratorPartialType::Array(size.clone())),
-                    DirectAbstractDeclaratorPart::VLA => stack.push(DeclaratorPartialType::Array(None)),
+                    DirectAbstractDeclaratorPart::VLA => stack.push(DeclaratorPartialType::VLA),
                     DirectAbstractDeclaratorPart::ParameterTypeList(type_list) => {
                         stack.push(DeclaratorPartialType::Function(*type_list.clone()))
                     }
@@ -175,25 +204,34 @@ fn build_abstract_declarator_type_stack(declarator: &AbstractDeclarator) -> Vec<
     }
 }
 
-fn resolve_parameter_type_list<'a>(param_list: ParameterTypeList) -> Result<(Vec<QualifiedType>, bool), CompilationError<'a>> {
-    let mut params = Vec::new();
-    for param in param_list.parameter_list {
-        params.push(resolve_parameter_declaration(&param)?);
-    }
+/// Tags declared in a parameter list have function prototype scope
+fn resolve_parameter_type_list<'a>(param_list: ParameterTypeList, env: &mut TypeEnvironment) -> Result<(Vec<QualifiedType>, bool), CompilationError<'a>> {
+    env.enter_scope(ScopeType::Prototype);
+    let params = param_list.parameter_list.iter()
+        .map(|param| resolve_parameter_declaration(param, env))
+        .collect::<Result<Vec<QualifiedType>, CompilationError>>();
+    env.leave_scope()?;
+    let params = params?;
     Ok((params, param_list.variadic))
 }
 
-fn resolve_parameter_declaration<'a>(declaration: &ParameterDeclaration) -> Result<QualifiedType, CompilationError<'a>> {
+pub(crate) fn resolve_parameter_declaration<'a>(declaration: &ParameterDeclaration, env: &mut TypeEnvironment) -> Result<QualifiedType, CompilationError<'a>> {
     use ast::ParameterDeclarator;
 
-    let base_type = QualifiedType::from_declaration_specifier_list(&declaration.declaration_specifier_list)?;
+    let specifiers = &declaration.declaration_specifier_list;
+    let base_type = QualifiedType::from_declaration_specifier_list(specifiers, env)?;
+    let location = (specifiers.start.clone(), specifiers.end.clone());
 
     match &declaration.declarator {
         ParameterDeclarator::Declarator(declarator) => {
-            Ok(declarator.resolve(base_type).0)
+            let (parameter_type, name) = declarator.resolve(base_type, &location, env)?;
+            if let Some(name) = name {
+                env.symbols_mut().insert_variable(name, parameter_type.clone(), StorageClass::Auto, location)?;
+            }
+            Ok(parameter_type)
         },
         ParameterDeclarator::AbstractDeclarator(Some(declarator)) => {
-            Ok(declarator.resolve(base_type))
+            declarator.resolve(base_type, &location, env)
         },
         ParameterDeclarator::AbstractDeclarator(None) => Ok(base_type)
     }
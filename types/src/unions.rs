@@ -0,0 +1,223 @@
+use crate::{Type, Layout, TypeRegistry};
+use crate::layout::align_to;
+use crate::members::{MemberList, MemberKind, FieldList, find_field};
+use crate::Field;
+use ast::{Location, Symbol};
+use errors::{CompilationError, IncompleteStructMember};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnionID(pub(crate) i32);
+
+#[derive(Debug, Clone)]
+pub struct Union {
+    name: Symbol,
+    id: UnionID,
+
+    /// The named fields, in declaration order. Every field starts at offset 0,
+    /// except for the fields of anonymous structs.
+    fields: Vec<(Symbol, Field)>,
+    bytes: usize,
+    alignment: usize
+}
+
+impl Union {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn id(&self) -> UnionID {
+        self.id
+    }
+
+    pub fn fields(&self) -> &[(Symbol, Field)] {
+        &self.fields
+    }
+
+    pub fn field(&self, name: Symbol) -> Option<&Field> {
+        find_field(&self.fields, name)
+    }
+
+    /// `sizeof` the union, including any trailing padding
+    pub fn size(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    pub fn layout(&self) -> Layout {
+        Layout::new(self.bytes, self.alignment)
+    }
+}
+
+pub struct UnionBuilder {
+    name: Symbol,
+    id: UnionID,
+    members: MemberList
+}
+
+impl UnionBuilder {
+    pub fn new(name: Option<Symbol>, id: UnionID) -> UnionBuilder {
+        UnionBuilder {
+            name: name.unwrap_or_else(|| Symbol::intern(&format!("anonymous union #{}", id.0))),
+            id,
+            members: MemberList::new()
+        }
+    }
+
+    pub fn add_field<'a>(&mut self, location: (Location, Location), name: Symbol, type_: Type) -> Result<(), CompilationError<'a>> {
+        self.members.add_field(location, name, type_)
+    }
+
+    /// Adds a bit field, unnamed bit fields have no effect on the layout of a union.
+    pub fn add_bit_field<'a>(&mut self, location: (Location, Location), name: Option<Symbol>, type_: Type, bits: usize) -> Result<(), CompilationError<'a>> {
+        self.members.add_bit_field(location, name, type_, bits)
+    }
+
+    /// Adds an anonymous struct or union member (C11 6.7.2.1p13)
+    pub fn add_anonymous_member<'a>(&mut self, location: (Location, Location), type_: Type) -> Result<(), CompilationError<'a>> {
+        self.members.add_anonymous_member(location, type_)
+    }
+
+    /// Every member of a union is placed at offset 0. The union is as large as its largest member and as
+    /// aligned as its most aligned member (unnamed bit fields don't count), with its size rounded up to a
+    /// multiple of its alignment.
+    pub fn build<'a>(self, registry: &TypeRegistry) -> Result<Union, CompilationError<'a>> {
+        let mut fields = FieldList::new();
+        let mut bytes = 0;
+        let mut alignment = 1;
+
+        for member in self.members.members {
+            let name = member.name;
+            match member.kind {
+                MemberKind::Field { type_ } | MemberKind::Anonymous { type_ } => {
+                    let layout = match type_.layout(registry) {
+                        Some(layout) => layout,
+                        None => return Err(IncompleteStructMember::new(member.location, name.map(|name| name.to_string()).unwrap_or_default()).into())
+                    };
+                    bytes = bytes.max(layout.size);
+                    alignment = alignment.max(layout.alignment);
+                    match name {
+                        Some(name) => fields.push(&member.location, name, Field::Field { type_, offset: 0 })?,
+                        None => fields.push_anonymous(&member.location, &type_, 0, registry)?
+                    }
+                },
+                MemberKind::BitField { type_, bits } => {
+                    let name = match name {
+                        Some(name) => name,
+                        None => continue
+                    };
+                    let layout = Layout::of_integer(type_, registry.target());
+                    bytes = bytes.max(layout.size);
+                    alignment = alignment.max(layout.alignment);
+                    let field = Field::BitField { type_: Type::Integer(type_), offset: 0, bits, bit_offset: 0 };
+                    fields.push(&member.location, name, field)?;
+                }
+            }
+        }
+
+        Ok(Union {
+            name: self.name,
+            id: self.id,
+            fields: fields.fields,
+            bytes: align_to(bytes, alignment),
+            alignment
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{IntegerType, FloatType, ArraySize, QualifiedType, TypeQualifiers, StructBuilder};
+
+    fn location() -> (Location, Location) {
+        (Location::default(), Location::default())
+    }
+
+    fn int(int_type: IntegerType) -> Type {
+        Type::Integer(int_type)
+    }
+
+    #[test]
+    fn size_of_largest_member_alignment_of_strictest_member() {
+        let registry = TypeRegistry::new();
+        let mut builder = UnionBuilder::new(None, UnionID(0));
+        builder.add_field(location(), "c".into(), int(IntegerType::Char)).unwrap();
+        builder.add_field(location(), "i".into(), int(IntegerType::Int)).unwrap();
+        let bytes = Type::Array {
+            inner: Box::new(QualifiedType::new(TypeQualifiers::default(), int(IntegerType::UnsignedChar))),
+            size: ArraySize::Incomplete
+        };
+        builder.add_field(location(), "s".into(), int(IntegerType::Short)).unwrap();
+        let u = builder.build(&registry).unwrap();
+        assert_eq!(u.layout(), Layout::new(4, 4));
+        assert!(u.fields().iter().all(|(_, field)| field.offset() == 0));
+
+        let mut builder = UnionBuilder::new(None, UnionID(1));
+        builder.add_field(location(), "bytes".into(), bytes).unwrap();
+        assert!(builder.build(&registry).is_err());
+    }
+
+    #[test]
+    fn trailing_padding() {
+        let registry = TypeRegistry::new();
+        let mut builder = UnionBuilder::new(None, UnionID(0));
+        builder.add_field(location(), "d".into(), Type::Float(FloatType::Double)).unwrap();
+        builder.add_bit_field(location(), Some("b".into()), int(IntegerType::UnsignedShort), 9).unwrap();
+        builder.add_bit_field(location(), None, int(IntegerType::LongLong), 3).unwrap();
+        let mut layout = builder.build(&registry).unwrap().layout();
+        assert_eq!(layout, Layout::new(8, 8));
+
+        let mut builder = UnionBuilder::new(None, UnionID(1));
+        builder.add_field(location(), "a".into(), int(IntegerType::Int)).unwrap();
+        builder.add_bit_field(location(), Some("b".into()), int(IntegerType::UnsignedChar), 3).unwrap();
+        builder.add_field(location(), "c".into(), int(IntegerType::Short)).unwrap();
+        layout = builder.build(&registry).unwrap().layout();
+        assert_eq!(layout, Layout::new(4, 4));
+    }
+
+    #[test]
+    fn duplicate_members() {
+        let mut builder = UnionBuilder::new(None, UnionID(0));
+        builder.add_field(location(), "a".into(), int(IntegerType::Int)).unwrap();
+        assert!(builder.add_field(location(), "a".into(), int(IntegerType::Char)).is_err());
+        assert!(builder.add_bit_field(location(), Some("a".into()), int(IntegerType::Char), 1).is_err());
+    }
+
+    #[test]
+    fn anonymous_union_in_struct() {
+        // struct { char tag; union { int i; double d; }; short s; }
+        let mut registry = TypeRegistry::new();
+        let union_id = registry.declare_union();
+        let mut builder = UnionBuilder::new(None, union_id);
+        builder.add_field(location(), "i".into(), int(IntegerType::Int)).unwrap();
+        builder.add_field(location(), "d".into(), Type::Float(FloatType::Double)).unwrap();
+        registry.define_union(builder.build(&registry).unwrap());
+
+        let mut builder = StructBuilder::new(None, registry.declare_struct());
+        builder.add_field(location(), "tag".into(), int(IntegerType::Char)).unwrap();
+        builder.add_anonymous_member(location(), Type::Union(union_id)).unwrap();
+        builder.add_field(location(), "s".into(), int(IntegerType::Short)).unwrap();
+        let s = builder.build(&registry).unwrap();
+        assert_eq!(s.offset_of("i".into()), Some(8));
+        assert_eq!(s.offset_of("d".into()), Some(8));
+        assert_eq!(s.offset_of("s".into()), Some(16));
+        assert_eq!(s.layout(), Layout::new(24, 8));
+    }
+
+    #[test]
+    fn anonymous_member_name_conflict() {
+        let mut registry = TypeRegistry::new();
+        let union_id = registry.declare_union();
+        let mut builder = UnionBuilder::new(None, union_id);
+        builder.add_field(location(), "a".into(), int(IntegerType::Int)).unwrap();
+        registry.define_union(builder.build(&registry).unwrap());
+
+        let mut builder = UnionBuilder::new(None, registry.declare_union());
+        builder.add_field(location(), "a".into(), int(IntegerType::Char)).unwrap();
+        builder.add_anonymous_member(location(), Type::Union(union_id)).unwrap();
+        assert!(builder.build(&registry).is_err());
+    }
+}